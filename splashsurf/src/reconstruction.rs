@@ -7,15 +7,13 @@ use rayon::prelude::*;
 use splashsurf_lib::mesh::{AttributeData, Mesh3d, MeshAttribute, MeshWithData};
 use splashsurf_lib::nalgebra::{Unit, Vector3};
 use splashsurf_lib::sph_interpolation::SphInterpolator;
-use splashsurf_lib::{profile, Aabb3d, Index, Real};
+use splashsurf_lib::{profile, Aabb3d, Index, KernelType, Real, SurfaceExtractionMethod};
 use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::path::PathBuf;
 
 use arguments::*;
 
-// TODO: Detect smallest index type (i.e. check if ok to use i32 as index)
-
 static ARGS_IO: &str = "Input/output";
 static ARGS_BASIC: &str = "Numerical reconstruction parameters";
 static ARGS_ADV: &str = "Advanced parameters";
@@ -61,6 +59,56 @@ pub struct ReconstructSubcommandArgs {
     /// The iso-surface threshold for the density, i.e. the normalized value of the reconstructed density level that indicates the fluid surface (in multiplies of the rest density)
     #[arg(help_heading = ARGS_BASIC, short = 't', long, default_value = "0.6")]
     pub surface_threshold: f64,
+    /// The SPH kernel function used to evaluate the particle densities, the density field and all interpolated quantities
+    #[arg(
+        help_heading = ARGS_BASIC,
+        long,
+        default_value = "cubic-spline",
+        ignore_case = true
+    )]
+    pub kernel: KernelTypeArg,
+    /// The method used to extract the surface mesh from the density field, surface nets result in fewer and better shaped triangles (and enable the output of pure quad meshes with `--generate-quads`)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "marching-cubes",
+        ignore_case = true
+    )]
+    pub surface_extraction: SurfaceExtractionArg,
+    /// Enable anisotropic kernels for the evaluation of the density field that are stretched along the local particle distribution, results in smoother surfaces of thin sheets and flat regions
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub anisotropic_kernel: Switch,
+    /// Maximum ratio between the largest and the smallest principal axis of the anisotropic kernels
+    #[arg(help_heading = ARGS_ADV, long, default_value = "4.0")]
+    pub anisotropy_max: f64,
+    /// Minimum number of neighbors of a particle to use an anisotropic kernel, particles with fewer neighbors keep an isotropic kernel
+    #[arg(help_heading = ARGS_ADV, long, default_value = "25")]
+    pub anisotropy_min_neighbors: usize,
+    /// Weight in the range [0, 1] of the Laplacian smoothing of the anisotropic kernel centers, zero disables the smoothing
+    #[arg(help_heading = ARGS_ADV, long, default_value = "0.9")]
+    pub anisotropy_smoothing_weight: f64,
+
+    /// Name of an integer particle attribute with phase ids (only VTK/VTU input), enables the reconstruction of one closed surface per phase with a cell attribute "phase" in the output mesh (post-processing options are not supported and rejected in this mode)
+    #[arg(help_heading = ARGS_ADV, long, value_name = "NAME")]
+    pub phase_attribute: Option<String>,
+    /// Enable writing the surface of every phase to a separate file "{output_stem}_phase_{id}.{ext}" instead of a single mesh with a phase cell attribute (requires phase-attribute to be specified)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true,
+        requires = "phase_attribute"
+    )]
+    pub separate_phase_files: Switch,
 
     /// Enable the use of double precision for all computations
     #[arg(
@@ -73,6 +121,16 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub double_precision: Switch,
+    /// Index type used for the background grid of the reconstruction, "auto" selects the smallest type that can index all grid points of the respective input file (i32 reduces the memory usage)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "auto",
+        value_name = "auto|i32|i64",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub index_type: IndexTypeArg,
     /// Lower corner of the domain where surface reconstruction should be performed (requires domain-max to be specified)
     #[arg(
         help_heading = ARGS_BASIC,
@@ -94,7 +152,7 @@ pub struct ReconstructSubcommandArgs {
     )]
     pub particle_aabb_max: Option<Vec<f64>>,
 
-    /// Enable multi-threading to process multiple input files in parallel (NOTE: Currently, the subdomain-grid domain decomposition approach and some post-processing functions including interpolation do not have sequential versions and therefore do not work well with this option enabled)
+    /// Enable multi-threading to process multiple input files in parallel (should be combined with `--mt-particles=off` to avoid oversubscription of the CPU cores)
     #[arg(
         help_heading = ARGS_ADV,
         long = "mt-files",
@@ -131,6 +189,22 @@ pub struct ReconstructSubcommandArgs {
     /// Each subdomain will be a cube consisting of this number of MC cube cells along each coordinate axis
     #[arg(help_heading = ARGS_OCTREE, long, default_value="64")]
     pub subdomain_cubes: u32,
+    /// Enable adaptive refinement of the subdomain grid using an octree, the subdomains of the grid are the smallest octree leaves (requires subdomain grid)
+    #[arg(
+        help_heading = ARGS_OCTREE,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub octree: Switch,
+    /// Octree leaves with more particles than this limit are subdivided further
+    #[arg(help_heading = ARGS_OCTREE, long, default_value="50000")]
+    pub octree_max_particles: usize,
+    /// Number of octree levels above the smallest leaves, the largest leaves consist of 2^depth subdomains along each axis
+    #[arg(help_heading = ARGS_OCTREE, long, default_value="3")]
+    pub octree_max_depth: u32,
 
     /// Enable omputing surface normals at the mesh vertices and write them to the output file
     #[arg(
@@ -165,6 +239,16 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub output_raw_normals: Switch,
+    /// Enable computing the mean and Gaussian curvature as well as the principal curvatures and directions at the mesh vertices and write them to the output file (requires a manifold mesh, see --repair-mesh)
+    #[arg(
+        help_heading = ARGS_INTERP,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub output_curvature: Switch,
     /// List of point attribute field names from the input file that should be interpolated to the reconstructed surface. Currently this is only supported for VTK and VTU input files.
     #[arg(help_heading = ARGS_INTERP, long)]
     pub interpolate_attributes: Vec<String>,
@@ -179,6 +263,16 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub mesh_cleanup: Switch,
+    /// Enable repair of non-manifold edges and vertices by duplicating vertices, such that the mesh can always be processed by the half-edge based post-processing steps
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub repair_mesh: Switch,
     /// Enable decimation of some typical bad marching cubes triangle configurations (resulting in "barnacles" after Laplacian smoothing)
     #[arg(
         help_heading = ARGS_POSTPROC,
@@ -199,9 +293,45 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub keep_verts: Switch,
+    /// Enable quadric error metric (QEM) based mesh decimation, collapsing edges until the mesh has at most the given number of triangles
+    #[arg(help_heading = ARGS_POSTPROC, long, value_name = "COUNT")]
+    pub qem_target_triangles: Option<usize>,
+    /// Enable quadric error metric (QEM) based mesh decimation, only collapsing edges with a quadric error (sum of squared distances to the planes of the merged triangles) below the given value
+    #[arg(help_heading = ARGS_POSTPROC, long, value_name = "ERROR")]
+    pub qem_max_error: Option<f64>,
+    /// Maximum allowed angle (in degrees) by which the normal of a triangle may rotate during a QEM edge collapse
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "45")]
+    pub qem_max_normal_angle: f64,
+    /// Maximum allowed factor by which the aspect ratio of a triangle may increase during a QEM edge collapse
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub qem_max_aspect_ratio_change: Option<f64>,
+    /// Enable isotropic remeshing of the mesh (edge splits, collapses, flips and tangential relaxation) towards the given target edge length (in multiplies of the particle radius)
+    #[arg(help_heading = ARGS_POSTPROC, long, value_name = "LENGTH", value_parser = parse_positive_finite)]
+    pub remesh_edge_length: Option<f64>,
+    /// Number of iterations of the isotropic remeshing
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "10")]
+    pub remesh_iters: usize,
+    /// Enable projecting the vertices back onto the original surface during isotropic remeshing
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "on",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub remesh_projection: Switch,
     /// Number of smoothing iterations to run on the reconstructed mesh
     #[arg(help_heading = ARGS_POSTPROC, long)]
     pub mesh_smoothing_iters: Option<usize>,
+    /// Method used for mesh smoothing: plain Laplacian smoothing (shrinks the mesh), Taubin λ/μ smoothing (λ=0.5, μ=-0.53) or HC-Laplacian smoothing (α=0.1, β=0.6), the latter two preserve the volume of the mesh
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "laplacian",
+        ignore_case = true
+    )]
+    pub mesh_smoothing_method: MeshSmoothingMethodArg,
     /// Enable feature weights for mesh smoothing if mesh smoothing enabled. Preserves isolated particles even under strong smoothing.
     #[arg(
         help_heading = ARGS_POSTPROC,
@@ -225,8 +355,21 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub output_smoothing_weights: Switch,
+    /// Number of Loop subdivision steps to run on the mesh after smoothing and before the computation of normals, curvature and interpolated attributes (every step splits each triangle into four triangles, existing point attributes are subdivided linearly)
+    #[arg(help_heading = ARGS_POSTPROC, long, value_name = "N")]
+    pub subdivide: Option<usize>,
+    /// Enable moving the vertices of the subdivided mesh to their positions on the Loop limit surface
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub subdivide_limit: Switch,
 
-    /// Enable trying to convert triangles to quads if they meet quality criteria
+    /// Enable trying to convert triangles to quads if they meet quality criteria (with surface nets, the quads of the surface extraction are used directly if no other post-processing step modifies the mesh)
     #[arg(
         help_heading = ARGS_POSTPROC,
         long,
@@ -276,6 +419,40 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub mesh_aabb_clamp_verts: Switch,
+    /// Enable closing the holes of the mesh after clipping with the mesh AABB to obtain a watertight mesh, the boundary loops on each face of the AABB are triangulated together such that enclosed loops become holes of the caps (only has an effect if mesh-aabb-min/max are specified, caps are planar if combined with mesh-aabb-clamp-verts)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_aabb_cap: Switch,
+
+    /// Remove all connected components of the mesh with an enclosed volume smaller than the given value (e.g. small droplets of spray), applied after clipping with the mesh AABB (the boundaries of clipped components are closed for the volume computation)
+    #[arg(help_heading = ARGS_POSTPROC, long, value_name = "VOLUME")]
+    pub min_component_volume: Option<f64>,
+    /// Enable writing the index of the connected component of every triangle as a cell attribute "component_id" to the output mesh (not supported with generate-quads)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub output_component_ids: Switch,
+    /// Enable writing every connected component of the mesh to a separate triangle mesh file "{output_stem}_component_{id}.{ext}" next to the output mesh
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub split_components: Switch,
 
     /// Enable writing the raw reconstructed mesh before applying any post-processing steps
     #[arg(
@@ -287,6 +464,29 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub output_raw_mesh: Switch,
+    /// Enable writing the density field of the reconstruction to a file next to the output mesh, either as a dense VTK image data file (vti) or as sparse blocks of density values (sdg, see `splashsurf_lib::io::sdg_format`)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|vti|sdg",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub output_density_grid: DensityGridFormatArg,
+    /// Enable writing the signed distance field of the final surface mesh sampled on the marching cubes background grid to a VTK image data file (vti) next to the output mesh, the distance is negative inside of the fluid (requires a closed mesh)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub output_sdf: Switch,
+    /// Width of the narrow band around the surface in which the signed distance field is computed exactly (in multiplies of the particle radius), values outside of the band are clamped to the width. By default, the distance is computed on the full grid.
+    #[arg(help_heading = ARGS_POSTPROC, long, value_name = "WIDTH")]
+    pub sdf_narrow_band: Option<f64>,
 
     /// Enable checking the final mesh for holes and non-manifold edges and vertices
     #[arg(
@@ -328,6 +528,16 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub check_mesh_debug: Switch,
+    /// Enable printing the volume enclosed by the final mesh and its relative error to the rest volume of the particles inside of the particle and mesh AABBs (boundaries of meshes clipped by the mesh AABB are closed)
+    #[arg(
+        help_heading = ARGS_DEBUG,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub check_volume: Switch,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
@@ -345,6 +555,94 @@ impl Switch {
     }
 }
 
+/// SPH kernel functions that can be selected for the reconstruction
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum KernelTypeArg {
+    CubicSpline,
+    WendlandC2,
+    WendlandC4,
+    WendlandC6,
+    Poly6,
+    QuinticSpline,
+}
+
+impl KernelTypeArg {
+    fn into_kernel_type(self) -> KernelType {
+        match self {
+            KernelTypeArg::CubicSpline => KernelType::CubicSpline,
+            KernelTypeArg::WendlandC2 => KernelType::WendlandC2,
+            KernelTypeArg::WendlandC4 => KernelType::WendlandC4,
+            KernelTypeArg::WendlandC6 => KernelType::WendlandC6,
+            KernelTypeArg::Poly6 => KernelType::Poly6,
+            KernelTypeArg::QuinticSpline => KernelType::QuinticSpline,
+        }
+    }
+}
+
+/// Surface extraction methods that can be selected for the reconstruction
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum SurfaceExtractionArg {
+    MarchingCubes,
+    SurfaceNets,
+}
+
+impl SurfaceExtractionArg {
+    fn into_extraction_method(self) -> SurfaceExtractionMethod {
+        match self {
+            SurfaceExtractionArg::MarchingCubes => SurfaceExtractionMethod::MarchingCubes,
+            SurfaceExtractionArg::SurfaceNets => SurfaceExtractionMethod::SurfaceNets,
+        }
+    }
+}
+
+/// File formats that can be selected for the output of the density grid
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum DensityGridFormatArg {
+    Off,
+    Vti,
+    Sdg,
+}
+
+impl DensityGridFormatArg {
+    /// Returns the file extension of the format or `None` if the output is disabled
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            DensityGridFormatArg::Off => None,
+            DensityGridFormatArg::Vti => Some("vti"),
+            DensityGridFormatArg::Sdg => Some("sdg"),
+        }
+    }
+}
+
+/// Index types that can be selected for the reconstruction
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum IndexTypeArg {
+    Auto,
+    I32,
+    I64,
+}
+
+/// Mesh smoothing methods that can be selected for the post-processing
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum MeshSmoothingMethodArg {
+    Laplacian,
+    Taubin,
+    Hc,
+}
+
+/// Parses a floating point argument that has to be positive and finite
+fn parse_positive_finite(value: &str) -> Result<f64, String> {
+    let value: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!(
+            "value has to be positive and finite (got {})",
+            value
+        ))
+    }
+}
+
 /// Executes the `reconstruct` subcommand
 pub fn reconstruct_subcommand(cmd_args: &ReconstructSubcommandArgs) -> Result<(), anyhow::Error> {
     profile!("reconstruct subcommand");
@@ -408,12 +706,15 @@ pub fn reconstruct_subcommand(cmd_args: &ReconstructSubcommandArgs) -> Result<()
 
 /// Conversion and validation of command line arguments
 mod arguments {
-    use super::ReconstructSubcommandArgs;
+    use super::{
+        DensityGridFormatArg, IndexTypeArg, MeshSmoothingMethodArg, ReconstructSubcommandArgs,
+    };
     use crate::io;
     use anyhow::{anyhow, Context};
     use log::info;
     use regex::{escape, Regex};
     use splashsurf_lib::nalgebra::Vector3;
+    use splashsurf_lib::postprocessing::{IsotropicRemeshingParameters, QemDecimationParameters};
     use splashsurf_lib::Aabb3d;
     use std::convert::TryFrom;
     use std::fs;
@@ -425,25 +726,107 @@ mod arguments {
         pub check_mesh_closed: bool,
         pub check_mesh_manifold: bool,
         pub check_mesh_debug: bool,
+        pub check_volume: bool,
         pub mesh_cleanup: bool,
+        pub repair_mesh: bool,
         pub decimate_barnacles: bool,
         pub keep_vertices: bool,
+        pub qem_decimation: Option<QemDecimationParameters<f64>>,
+        pub isotropic_remeshing: Option<IsotropicRemeshingParameters<f64>>,
         pub compute_normals: bool,
         pub sph_normals: bool,
         pub normals_smoothing_iters: Option<usize>,
         pub interpolate_attributes: Vec<String>,
         pub mesh_smoothing_iters: Option<usize>,
+        pub mesh_smoothing_method: MeshSmoothingMethodArg,
         pub mesh_smoothing_weights: bool,
         pub mesh_smoothing_weights_normalization: f64,
+        pub subdivision_steps: Option<usize>,
+        pub subdivision_limit: bool,
         pub generate_quads: bool,
         pub quad_max_edge_diag_ratio: f64,
         pub quad_max_normal_angle: f64,
         pub quad_max_interior_angle: f64,
         pub output_mesh_smoothing_weights: bool,
         pub output_raw_normals: bool,
+        pub output_curvature: bool,
         pub output_raw_mesh: bool,
+        pub output_density_grid: DensityGridFormatArg,
+        pub output_sdf: bool,
+        pub sdf_narrow_band_width: Option<f64>,
         pub mesh_aabb: Option<Aabb3d<f64>>,
         pub mesh_aabb_clamp_vertices: bool,
+        pub mesh_aabb_cap: bool,
+        pub min_component_volume: Option<f64>,
+        pub output_component_ids: bool,
+        pub split_components: bool,
+    }
+
+    impl ReconstructionRunnerPostprocessingArgs {
+        /// Returns the command line flags of all enabled post-processing and output options
+        fn enabled_options(&self) -> Vec<&'static str> {
+            [
+                (self.check_mesh_closed, "--check-mesh-closed"),
+                (self.check_mesh_manifold, "--check-mesh-manifold"),
+                (self.check_mesh_debug, "--check-mesh-debug"),
+                (self.check_volume, "--check-volume"),
+                (self.mesh_cleanup, "--mesh-cleanup"),
+                (self.repair_mesh, "--repair-mesh"),
+                (self.decimate_barnacles, "--decimate-barnacles"),
+                (self.keep_vertices, "--keep-verts"),
+                (
+                    self.qem_decimation.is_some(),
+                    "--qem-target-triangles/--qem-max-error",
+                ),
+                (self.isotropic_remeshing.is_some(), "--remesh-edge-length"),
+                (self.compute_normals, "--normals"),
+                (self.sph_normals, "--sph-normals"),
+                (
+                    self.normals_smoothing_iters.is_some(),
+                    "--normals-smoothing-iters",
+                ),
+                (
+                    !self.interpolate_attributes.is_empty(),
+                    "--interpolate-attributes",
+                ),
+                (
+                    self.mesh_smoothing_iters.is_some(),
+                    "--mesh-smoothing-iters",
+                ),
+                (self.mesh_smoothing_weights, "--mesh-smoothing-weights"),
+                (self.subdivision_steps.is_some(), "--subdivide"),
+                (self.generate_quads, "--generate-quads"),
+                (
+                    self.output_mesh_smoothing_weights,
+                    "--output-smoothing-weights",
+                ),
+                (self.output_raw_normals, "--output-raw-normals"),
+                (self.output_curvature, "--output-curvature"),
+                (self.output_raw_mesh, "--output-raw-mesh"),
+                (
+                    self.output_density_grid != DensityGridFormatArg::Off,
+                    "--output-density-grid",
+                ),
+                (self.output_sdf, "--output-sdf"),
+                (self.mesh_aabb.is_some(), "--mesh-aabb-min/--mesh-aabb-max"),
+                (
+                    self.min_component_volume.is_some(),
+                    "--min-component-volume",
+                ),
+                (self.output_component_ids, "--output-component-ids"),
+                (self.split_components, "--split-components"),
+            ]
+            .into_iter()
+            .filter_map(|(enabled, flag)| enabled.then_some(flag))
+            .collect()
+        }
+    }
+
+    /// Arguments of the reconstruction of one surface per phase
+    pub struct ReconstructionRunnerMultiphaseArgs {
+        /// Name of the particle attribute containing the phase ids
+        pub phase_attribute: String,
+        pub separate_phase_files: bool,
     }
 
     /// All arguments that can be supplied to the surface reconstruction tool converted to useful types
@@ -451,8 +834,11 @@ mod arguments {
         /// Parameters passed directly to the surface reconstruction
         pub params: splashsurf_lib::Parameters<f64>,
         pub use_double_precision: bool,
+        pub index_type: IndexTypeArg,
         pub io_params: io::FormatParameters,
         pub postprocessing: ReconstructionRunnerPostprocessingArgs,
+        /// Enables the multiphase reconstruction if present
+        pub multiphase: Option<ReconstructionRunnerMultiphaseArgs>,
     }
 
     fn try_aabb_from_min_max(
@@ -511,11 +897,41 @@ mod arguments {
                 None
             };
 
+            // The mesh cleanup relies on the structure of marching cubes meshes
+            if args.mesh_cleanup.into_bool()
+                && args.surface_extraction != SurfaceExtractionArg::MarchingCubes
+            {
+                return Err(anyhow!(
+                    "the mesh cleanup (--mesh-cleanup) is only supported for meshes extracted with marching cubes"
+                ));
+            }
+
+            // Quads cannot carry the per-triangle component ids
+            if args.output_component_ids.into_bool() && args.generate_quads.into_bool() {
+                return Err(anyhow!(
+                    "the output of component ids (--output-component-ids) is not supported together with quad generation (--generate-quads)"
+                ));
+            }
+
             // Scale kernel radius and cube size by particle radius
             let compact_support_radius = args.particle_radius * 2.0 * args.smoothing_length;
             let cube_size = args.particle_radius * args.cube_size;
 
-            let spatial_decomposition = if args.subdomain_grid.into_bool() {
+            if args.octree.into_bool() && !args.subdomain_grid.into_bool() {
+                return Err(anyhow!(
+                    "the octree decomposition (--octree) requires the subdomain grid (--subdomain-grid=on)"
+                ));
+            }
+
+            let spatial_decomposition = if args.octree.into_bool() {
+                Some(splashsurf_lib::SpatialDecomposition::Octree(
+                    splashsurf_lib::OctreeDecompositionParameters {
+                        subdomain_num_cubes_per_dim: args.subdomain_cubes,
+                        max_particles_per_subdomain: args.octree_max_particles,
+                        max_depth: args.octree_max_depth,
+                    },
+                ))
+            } else if args.subdomain_grid.into_bool() {
                 Some(splashsurf_lib::SpatialDecomposition::UniformGrid(
                     splashsurf_lib::GridDecompositionParameters {
                         subdomain_num_cubes_per_dim: args.subdomain_cubes,
@@ -526,17 +942,45 @@ mod arguments {
                 None
             };
 
+            let anisotropic_kernel = if args.anisotropic_kernel.into_bool() {
+                if !(0.0..=1.0).contains(&args.anisotropy_smoothing_weight) {
+                    return Err(anyhow!(
+                        "The anisotropy smoothing weight has to be in the range [0, 1], got {}",
+                        args.anisotropy_smoothing_weight
+                    ));
+                }
+                if args.anisotropy_max < 1.0 {
+                    return Err(anyhow!(
+                        "The maximum anisotropy has to be at least 1, got {}",
+                        args.anisotropy_max
+                    ));
+                }
+
+                Some(splashsurf_lib::AnisotropicKernelParameters {
+                    min_neighbors: args.anisotropy_min_neighbors,
+                    max_anisotropy: args.anisotropy_max,
+                    center_smoothing_weight: args.anisotropy_smoothing_weight,
+                })
+            } else {
+                None
+            };
+
             // Assemble all parameters for the surface reconstruction
             let params = splashsurf_lib::Parameters {
                 particle_radius: args.particle_radius,
                 rest_density: args.rest_density,
                 compact_support_radius,
+                kernel_type: args.kernel.into_kernel_type(),
+                extraction_method: args.surface_extraction.into_extraction_method(),
                 cube_size,
                 iso_surface_threshold: args.surface_threshold,
                 particle_aabb,
                 enable_multi_threading: args.parallelize_over_particles.into_bool(),
                 spatial_decomposition,
+                anisotropic_kernel,
                 global_neighborhood_list: args.mesh_smoothing_weights.into_bool(),
+                density_grid: args.output_density_grid != DensityGridFormatArg::Off,
+                solid_boundary: None,
             };
 
             // Optionally initialize thread pool
@@ -544,38 +988,91 @@ mod arguments {
                 splashsurf_lib::initialize_thread_pool(num_threads)?;
             }
 
+            let qem_decimation = (args.qem_target_triangles.is_some()
+                || args.qem_max_error.is_some())
+            .then(|| QemDecimationParameters {
+                target_triangle_count: args.qem_target_triangles,
+                max_error: args.qem_max_error,
+                max_normal_change: Some(args.qem_max_normal_angle.to_radians()),
+                max_aspect_ratio_change: args.qem_max_aspect_ratio_change,
+            });
+
+            let isotropic_remeshing =
+                args.remesh_edge_length
+                    .map(|edge_length| IsotropicRemeshingParameters {
+                        target_edge_length: args.particle_radius * edge_length,
+                        iterations: args.remesh_iters,
+                        project_to_surface: args.remesh_projection.into_bool(),
+                    });
+
             let postprocessing = ReconstructionRunnerPostprocessingArgs {
                 check_mesh_closed: args.check_mesh.into_bool()
                     || args.check_mesh_closed.into_bool(),
                 check_mesh_manifold: args.check_mesh.into_bool()
                     || args.check_mesh_manifold.into_bool(),
                 check_mesh_debug: args.check_mesh_debug.into_bool(),
+                check_volume: args.check_volume.into_bool(),
                 mesh_cleanup: args.mesh_cleanup.into_bool(),
+                repair_mesh: args.repair_mesh.into_bool(),
                 decimate_barnacles: args.decimate_barnacles.into_bool(),
                 keep_vertices: args.keep_verts.into_bool(),
+                qem_decimation,
+                isotropic_remeshing,
                 compute_normals: args.normals.into_bool(),
                 sph_normals: args.sph_normals.into_bool(),
                 normals_smoothing_iters: args.normals_smoothing_iters,
                 interpolate_attributes: args.interpolate_attributes.clone(),
                 mesh_smoothing_iters: args.mesh_smoothing_iters,
+                mesh_smoothing_method: args.mesh_smoothing_method,
                 mesh_smoothing_weights: args.mesh_smoothing_weights.into_bool(),
                 mesh_smoothing_weights_normalization: args.mesh_smoothing_weights_normalization,
+                subdivision_steps: args.subdivide,
+                subdivision_limit: args.subdivide_limit.into_bool(),
                 generate_quads: args.generate_quads.into_bool(),
                 quad_max_edge_diag_ratio: args.quad_max_edge_diag_ratio,
                 quad_max_normal_angle: args.quad_max_normal_angle,
                 quad_max_interior_angle: args.quad_max_interior_angle,
                 output_mesh_smoothing_weights: args.output_smoothing_weights.into_bool(),
                 output_raw_normals: args.output_raw_normals.into_bool(),
+                output_curvature: args.output_curvature.into_bool(),
                 output_raw_mesh: args.output_raw_mesh.into_bool(),
+                output_density_grid: args.output_density_grid,
+                output_sdf: args.output_sdf.into_bool(),
+                sdf_narrow_band_width: args
+                    .sdf_narrow_band
+                    .map(|width| args.particle_radius * width),
                 mesh_aabb,
                 mesh_aabb_clamp_vertices: args.mesh_aabb_clamp_verts.into_bool(),
+                mesh_aabb_cap: args.mesh_aabb_cap.into_bool(),
+                min_component_volume: args.min_component_volume,
+                output_component_ids: args.output_component_ids.into_bool(),
+                split_components: args.split_components.into_bool(),
             };
 
+            if args.phase_attribute.is_some() {
+                let unsupported_options = postprocessing.enabled_options();
+                if !unsupported_options.is_empty() {
+                    return Err(anyhow!(
+                        "The multiphase reconstruction (--phase-attribute) does not support post-processing, but the following options were specified: {}",
+                        unsupported_options.join(", ")
+                    ));
+                }
+            }
+
+            let multiphase = args.phase_attribute.as_ref().map(|phase_attribute| {
+                ReconstructionRunnerMultiphaseArgs {
+                    phase_attribute: phase_attribute.clone(),
+                    separate_phase_files: args.separate_phase_files.into_bool(),
+                }
+            });
+
             Ok(ReconstructionRunnerArgs {
                 params,
                 use_double_precision: args.double_precision.into_bool(),
+                index_type: args.index_type,
                 io_params: io::FormatParameters::default(),
                 postprocessing,
+                multiphase,
             })
         }
     }
@@ -864,41 +1361,34 @@ pub(crate) fn reconstruction_pipeline(
 ) -> Result<(), anyhow::Error> {
     if args.use_double_precision {
         info!("Using double precision (f64) for surface reconstruction.");
-        reconstruction_pipeline_generic::<i64, f64>(
-            paths,
-            &args.params,
-            &args.io_params,
-            &args.postprocessing,
-        )?;
+        reconstruction_pipeline_real::<f64>(paths, &args.params, args)?;
     } else {
         info!("Using single precision (f32) for surface reconstruction.");
-        reconstruction_pipeline_generic::<i64, f32>(
-            paths,
-            &args.params.try_convert().ok_or(anyhow!(
-                "Unable to convert surface reconstruction parameters from f64 to f32."
-            ))?,
-            &args.io_params,
-            &args.postprocessing,
-        )?;
+        let params = args.params.try_convert().ok_or(anyhow!(
+            "Unable to convert surface reconstruction parameters from f64 to f32."
+        ))?;
+        reconstruction_pipeline_real::<f32>(paths, &params, args)?;
     }
 
     Ok(())
 }
 
-/// Wrapper for the reconstruction pipeline: loads input file, runs reconstructions, stores output files
-pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
+/// Loads the input file and calls the reconstruction pipeline with the index type selected for the background grid
+fn reconstruction_pipeline_real<R: Real>(
     paths: &ReconstructionRunnerPaths,
     params: &splashsurf_lib::Parameters<R>,
-    io_params: &io::FormatParameters,
-    postprocessing: &ReconstructionRunnerPostprocessingArgs,
+    args: &ReconstructionRunnerArgs,
 ) -> Result<(), anyhow::Error> {
-    profile!("surface reconstruction");
-
-    // Load particle positions and attributes to interpolate
-    let (particle_positions, attributes) = io::read_particle_positions_with_attributes(
+    // Load particle positions and attributes to interpolate or the phase ids
+    let attribute_names = if let Some(multiphase) = &args.multiphase {
+        std::slice::from_ref(&multiphase.phase_attribute)
+    } else {
+        args.postprocessing.interpolate_attributes.as_slice()
+    };
+    let (particle_positions, attributes) = io::read_particle_positions_with_attributes::<R, _>(
         &paths.input_file,
-        &postprocessing.interpolate_attributes,
-        &io_params.input,
+        attribute_names,
+        &args.io_params.input,
     )
     .with_context(|| {
         format!(
@@ -907,6 +1397,179 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
         )
     })?;
 
+    let use_i64 = match args.index_type {
+        IndexTypeArg::Auto => {
+            let index_range =
+                splashsurf_lib::required_index_range(particle_positions.as_slice(), None, params)
+                    .context("Failed to compute the index range of the background grid")?;
+            !index_range.fits::<i32>()
+        }
+        IndexTypeArg::I32 => false,
+        IndexTypeArg::I64 => true,
+    };
+
+    if use_i64 {
+        info!("Using i64 indices for the background grid.");
+        reconstruction_pipeline_index::<i64, R>(paths, params, args, particle_positions, attributes)
+    } else {
+        info!("Using i32 indices for the background grid.");
+        reconstruction_pipeline_index::<i32, R>(paths, params, args, particle_positions, attributes)
+    }
+}
+
+/// Calls the multiphase or the regular reconstruction pipeline with the given index and real type
+fn reconstruction_pipeline_index<I: Index, R: Real>(
+    paths: &ReconstructionRunnerPaths,
+    params: &splashsurf_lib::Parameters<R>,
+    args: &ReconstructionRunnerArgs,
+    particle_positions: Vec<Vector3<R>>,
+    attributes: Vec<MeshAttribute<R>>,
+) -> Result<(), anyhow::Error> {
+    if let Some(multiphase) = &args.multiphase {
+        multiphase_pipeline_generic::<I, R>(
+            paths,
+            params,
+            &args.io_params,
+            multiphase,
+            particle_positions,
+            attributes,
+        )
+    } else {
+        reconstruction_pipeline_generic::<I, R>(
+            paths,
+            params,
+            &args.io_params,
+            &args.postprocessing,
+            particle_positions,
+            attributes,
+        )
+    }
+}
+
+/// Wrapper for the multiphase reconstruction pipeline: runs reconstruction with the loaded phase ids, stores output files
+pub(crate) fn multiphase_pipeline_generic<I: Index, R: Real>(
+    paths: &ReconstructionRunnerPaths,
+    params: &splashsurf_lib::Parameters<R>,
+    io_params: &io::FormatParameters,
+    multiphase: &ReconstructionRunnerMultiphaseArgs,
+    particle_positions: Vec<Vector3<R>>,
+    attributes: Vec<MeshAttribute<R>>,
+) -> Result<(), anyhow::Error> {
+    profile!("multiphase surface reconstruction");
+
+    let phase_attribute = attributes
+        .into_iter()
+        .find(|attribute| attribute.name == multiphase.phase_attribute)
+        .ok_or_else(|| {
+            anyhow!(
+                "Phase attribute \"{}\" was not found in the input file",
+                multiphase.phase_attribute
+            )
+        })?;
+    let phase_ids = match phase_attribute.data {
+        AttributeData::ScalarU64(phase_ids) => phase_ids,
+        AttributeData::ScalarReal(values) => values
+            .iter()
+            .map(|v| {
+                v.round()
+                    .to_u64()
+                    .filter(|id| R::from_u64(*id) == Some(*v))
+                    .ok_or_else(|| {
+                        anyhow!("Phase attribute contains the non-integer value {:?}", v)
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => {
+            return Err(anyhow!(
+                "Phase attribute \"{}\" has to be a scalar attribute",
+                multiphase.phase_attribute
+            ))
+        }
+    };
+
+    // Perform the surface reconstruction
+    let reconstruction = splashsurf_lib::multiphase::reconstruct_surface_multiphase::<I, R>(
+        particle_positions.as_slice(),
+        phase_ids.as_slice(),
+        params,
+    )?;
+
+    // Store the surface meshes
+    {
+        profile!("write surface mesh to file");
+
+        if multiphase.separate_phase_files {
+            let output_path = paths
+                .output_file
+                .parent()
+                // Add a trailing separator if the parent is non-empty
+                .map(|p| p.join(""))
+                .unwrap_or_else(PathBuf::new);
+
+            for phase in reconstruction.phases() {
+                let output_filename = format!(
+                    "{}_phase_{}.{}",
+                    paths.output_file.file_stem().unwrap().to_string_lossy(),
+                    phase.phase_id,
+                    paths
+                        .output_file
+                        .extension()
+                        .map(|e| e.to_string_lossy())
+                        .unwrap_or_default()
+                );
+                let phase_output_file = output_path.join(output_filename);
+
+                info!(
+                    "Writing surface mesh of phase {} to \"{}\"...",
+                    phase.phase_id,
+                    phase_output_file.display()
+                );
+                io::write_mesh(
+                    &MeshWithData::new(Cow::Borrowed(&phase.mesh)),
+                    &phase_output_file,
+                    &io_params.output,
+                )
+                .with_context(|| {
+                    anyhow!(
+                        "Failed to write output mesh to file \"{}\"",
+                        phase_output_file.display()
+                    )
+                })?;
+            }
+        } else {
+            info!(
+                "Writing surface mesh to \"{}\"...",
+                paths.output_file.display()
+            );
+            io::write_mesh(
+                &reconstruction.to_merged_mesh(),
+                paths.output_file.clone(),
+                &io_params.output,
+            )
+            .with_context(|| {
+                anyhow!(
+                    "Failed to write output mesh to file \"{}\"",
+                    paths.output_file.display()
+                )
+            })?;
+        }
+        info!("Done.");
+    }
+
+    Ok(())
+}
+
+/// Wrapper for the reconstruction pipeline: runs reconstructions with the loaded particles, stores output files
+pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
+    paths: &ReconstructionRunnerPaths,
+    params: &splashsurf_lib::Parameters<R>,
+    io_params: &io::FormatParameters,
+    postprocessing: &ReconstructionRunnerPostprocessingArgs,
+    particle_positions: Vec<Vector3<R>>,
+    attributes: Vec<MeshAttribute<R>>,
+) -> Result<(), anyhow::Error> {
+    profile!("surface reconstruction");
+
     // Perform the surface reconstruction
     let reconstruction =
         splashsurf_lib::reconstruct_surface::<I, R>(particle_positions.as_slice(), params)?;
@@ -914,6 +1577,49 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
     let grid = reconstruction.grid();
     let mut mesh_with_data = MeshWithData::new(Cow::Borrowed(reconstruction.mesh()));
 
+    if let Some(extension) = postprocessing.output_density_grid.extension() {
+        profile!("write density grid to file");
+
+        let density_grid = reconstruction
+            .density_grid()
+            .ok_or_else(|| anyhow!("The reconstruction did not return a density grid"))?;
+
+        let output_path = paths
+            .output_file
+            .parent()
+            // Add a trailing separator if the parent is non-empty
+            .map(|p| p.join(""))
+            .unwrap_or_else(PathBuf::new);
+        let output_filename = format!(
+            "density_{}.{}",
+            paths.output_file.file_stem().unwrap().to_string_lossy(),
+            extension
+        );
+        let density_output_file = output_path.join(output_filename);
+
+        info!(
+            "Writing density grid with {} blocks to \"{}\"...",
+            density_grid.num_blocks(),
+            density_output_file.display()
+        );
+
+        match postprocessing.output_density_grid {
+            DensityGridFormatArg::Vti => {
+                io::vtk_format::density_grid_to_vti(density_grid, &density_output_file)
+            }
+            DensityGridFormatArg::Sdg => {
+                io::sdg_format::density_grid_to_sdg(density_grid, &density_output_file)
+            }
+            DensityGridFormatArg::Off => unreachable!(),
+        }
+        .with_context(|| {
+            anyhow!(
+                "Failed to write density grid to file \"{}\"",
+                density_output_file.display()
+            )
+        })?;
+    }
+
     if postprocessing.output_raw_mesh {
         profile!("write surface mesh to file");
 
@@ -963,6 +1669,22 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
             info!("Post-processing: Cleanup reduced number of vertices to {:.2}% and number of triangles to {:.2}% of original mesh.", (verts_after as f64 / verts_before as f64) * 100.0, (tris_after as f64 / tris_before as f64) * 100.0)
         }
 
+        // Repair non-manifold edges and vertices if requested
+        if postprocessing.repair_mesh {
+            info!("Post-processing: Repairing non-manifold edges and vertices");
+            let verts_before = mesh_with_data.mesh.vertices.len();
+            let (vertex_map, triangle_map) = mesh_with_data.mesh.to_mut().repair_non_manifold();
+            // Copy the attributes to the duplicated vertices and remove those of degenerate triangles
+            mesh_with_data.remap_attributes(&vertex_map, &triangle_map);
+            let verts_after = mesh_with_data.mesh.vertices.len();
+            // The connectivity computed by the cleanup does not contain the duplicated vertices
+            vertex_connectivity = None;
+            info!(
+                "Post-processing: Repair duplicated {} vertices.",
+                verts_after - verts_before
+            );
+        }
+
         // Decimate mesh if requested
         if postprocessing.decimate_barnacles {
             info!("Post-processing: Performing decimation");
@@ -972,6 +1694,43 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
             ));
         }
 
+        // Simplify mesh with quadric error metric if requested
+        if let Some(qem_parameters) = &postprocessing.qem_decimation {
+            info!("Post-processing: Performing QEM decimation");
+            let qem_parameters = qem_parameters
+                .try_convert()
+                .ok_or_else(|| anyhow!("Failed to convert QEM decimation parameters"))?;
+            let tris_before = mesh_with_data.mesh.triangles.len();
+            vertex_connectivity = Some(splashsurf_lib::postprocessing::qem_decimation(
+                mesh_with_data.mesh.to_mut(),
+                &qem_parameters,
+                postprocessing.keep_vertices,
+            ));
+            let tris_after = mesh_with_data.mesh.triangles.len();
+            info!(
+                "Post-processing: QEM decimation reduced number of triangles to {:.2}% of original mesh.",
+                (tris_after as f64 / tris_before as f64) * 100.0
+            );
+        }
+
+        // Remesh mesh if requested
+        if let Some(remeshing_parameters) = &postprocessing.isotropic_remeshing {
+            info!("Post-processing: Performing isotropic remeshing");
+            let remeshing_parameters = remeshing_parameters
+                .try_convert()
+                .ok_or_else(|| anyhow!("Failed to convert isotropic remeshing parameters"))?;
+            let isotropic_remeshing = if params.enable_multi_threading {
+                splashsurf_lib::postprocessing::par_isotropic_remeshing
+            } else {
+                splashsurf_lib::postprocessing::isotropic_remeshing
+            };
+            vertex_connectivity = Some(isotropic_remeshing(
+                mesh_with_data.mesh.to_mut(),
+                &remeshing_parameters,
+                postprocessing.keep_vertices,
+            ));
+        }
+
         // Initialize SPH interpolator if required later
         let interpolator_required = postprocessing.mesh_smoothing_weights
             || postprocessing.sph_normals
@@ -1000,12 +1759,16 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
                 "There has to be one density value per particle"
             );
 
-            Some(SphInterpolator::new(
-                &particle_positions,
-                particle_densities,
-                particle_rest_mass,
-                params.compact_support_radius,
-            ))
+            Some(
+                SphInterpolator::new(
+                    &particle_positions,
+                    particle_densities,
+                    particle_rest_mass,
+                    params.compact_support_radius,
+                )
+                .with_kernel_type(params.kernel_type)
+                .with_multi_threading(params.enable_multi_threading),
+            )
         } else {
             None
         };
@@ -1022,49 +1785,51 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
             profile!("compute smoothing weights");
             info!("Post-processing: Computing smoothing weights...");
 
-            // TODO: Switch between parallel/single threaded
             // TODO: Re-use data from reconstruction?
 
             // Global neighborhood search
             let nl = reconstruction
                 .particle_neighbors()
                 .map(|nl| Cow::Borrowed(nl))
-                .unwrap_or_else(||
-                    {
-                        let search_radius = params.compact_support_radius;
-
-                        let mut domain = Aabb3d::from_points(particle_positions.as_slice());
-                        domain.grow_uniformly(search_radius);
-
-                        let mut nl = Vec::new();
-                        splashsurf_lib::neighborhood_search::neighborhood_search_spatial_hashing_parallel::<I, R>(
-                            &domain,
-                            particle_positions.as_slice(),
-                            search_radius,
-                            &mut nl,
-                        );
-                        assert_eq!(nl.len(), particle_positions.len());
-                        Cow::Owned(nl)
-                    }
-                );
+                .unwrap_or_else(|| {
+                    let search_radius = params.compact_support_radius;
+
+                    let mut domain = Aabb3d::from_points(particle_positions.as_slice());
+                    domain.grow_uniformly(search_radius);
+
+                    let nl = splashsurf_lib::neighborhood_search::search::<I, R>(
+                        &domain,
+                        particle_positions.as_slice(),
+                        search_radius,
+                        params.enable_multi_threading,
+                    );
+                    assert_eq!(nl.len(), particle_positions.len());
+                    Cow::Owned(nl)
+                });
 
             // Compute weighted neighbor count
             let squared_r = params.compact_support_radius * params.compact_support_radius;
-            let weighted_ncounts = nl
-                .par_iter()
-                .enumerate()
-                .map(|(i, nl)| {
-                    nl.iter()
-                        .copied()
-                        .map(|j| {
-                            let dist =
-                                (particle_positions[i] - particle_positions[j]).norm_squared();
-                            let weight = R::one() - (dist / squared_r).clamp(R::zero(), R::one());
-                            return weight;
-                        })
-                        .fold(R::zero(), R::add)
-                })
-                .collect::<Vec<_>>();
+            let weighted_ncount = |(i, nl): (usize, &Vec<usize>)| {
+                nl.iter()
+                    .copied()
+                    .map(|j| {
+                        let dist = (particle_positions[i] - particle_positions[j]).norm_squared();
+                        let weight = R::one() - (dist / squared_r).clamp(R::zero(), R::one());
+                        return weight;
+                    })
+                    .fold(R::zero(), R::add)
+            };
+            let weighted_ncounts = if params.enable_multi_threading {
+                nl.par_iter()
+                    .enumerate()
+                    .map(weighted_ncount)
+                    .collect::<Vec<_>>()
+            } else {
+                nl.iter()
+                    .enumerate()
+                    .map(weighted_ncount)
+                    .collect::<Vec<_>>()
+            };
 
             let vertex_weighted_num_neighbors = {
                 profile!("interpolate weighted neighbor counts");
@@ -1086,14 +1851,24 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
                     ) - offset;
 
                 // Normalize number of neighbors
-                let smoothing_weights = vertex_weighted_num_neighbors
-                    .par_iter()
-                    .copied()
-                    .map(|n| (n - offset).max(R::zero()))
-                    .map(|n| (n / normalization).min(R::one()))
+                let smoothing_weight = |n: R| {
+                    let x = ((n - offset).max(R::zero()) / normalization).min(R::one());
                     // Smooth-Step function
-                    .map(|x| x.powi(5).times(6) - x.powi(4).times(15) + x.powi(3).times(10))
-                    .collect::<Vec<_>>();
+                    x.powi(5).times(6) - x.powi(4).times(15) + x.powi(3).times(10)
+                };
+                let smoothing_weights = if params.enable_multi_threading {
+                    vertex_weighted_num_neighbors
+                        .par_iter()
+                        .copied()
+                        .map(smoothing_weight)
+                        .collect::<Vec<_>>()
+                } else {
+                    vertex_weighted_num_neighbors
+                        .iter()
+                        .copied()
+                        .map(smoothing_weight)
+                        .collect::<Vec<_>>()
+                };
 
                 if postprocessing.output_mesh_smoothing_weights {
                     // Raw distance-weighted number of neighbors value per vertex (can be used to determine normalization value)
@@ -1121,20 +1896,90 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
             profile!("mesh smoothing");
             info!("Post-processing: Smoothing mesh...");
 
-            // TODO: Switch between parallel/single threaded
-
             let smoothing_weights = smoothing_weights
                 .unwrap_or_else(|| vec![R::one(); mesh_with_data.vertices().len()]);
 
-            splashsurf_lib::postprocessing::par_laplacian_smoothing_inplace(
-                mesh_with_data.mesh.to_mut(),
-                vertex_connectivity
-                    .as_ref()
-                    .expect("vertex connectivity is required"),
-                mesh_smoothing_iters,
-                R::one(),
-                &smoothing_weights,
+            let mesh = mesh_with_data.mesh.to_mut();
+            let vertex_connectivity = vertex_connectivity
+                .as_ref()
+                .expect("vertex connectivity is required");
+
+            match postprocessing.mesh_smoothing_method {
+                MeshSmoothingMethodArg::Laplacian => {
+                    let laplacian_smoothing_inplace = if params.enable_multi_threading {
+                        splashsurf_lib::postprocessing::par_laplacian_smoothing_inplace
+                    } else {
+                        splashsurf_lib::postprocessing::laplacian_smoothing_inplace
+                    };
+                    laplacian_smoothing_inplace(
+                        mesh,
+                        vertex_connectivity,
+                        mesh_smoothing_iters,
+                        R::one(),
+                        &smoothing_weights,
+                    );
+                }
+                MeshSmoothingMethodArg::Taubin => {
+                    let taubin_smoothing_inplace = if params.enable_multi_threading {
+                        splashsurf_lib::postprocessing::par_taubin_smoothing_inplace
+                    } else {
+                        splashsurf_lib::postprocessing::taubin_smoothing_inplace
+                    };
+                    taubin_smoothing_inplace(
+                        mesh,
+                        vertex_connectivity,
+                        mesh_smoothing_iters,
+                        R::from_f64(0.5).unwrap(),
+                        R::from_f64(-0.53).unwrap(),
+                        &smoothing_weights,
+                    );
+                }
+                MeshSmoothingMethodArg::Hc => {
+                    let hc_smoothing_inplace = if params.enable_multi_threading {
+                        splashsurf_lib::postprocessing::par_hc_smoothing_inplace
+                    } else {
+                        splashsurf_lib::postprocessing::hc_smoothing_inplace
+                    };
+                    hc_smoothing_inplace(
+                        mesh,
+                        vertex_connectivity,
+                        mesh_smoothing_iters,
+                        R::from_f64(0.1).unwrap(),
+                        R::from_f64(0.6).unwrap(),
+                        &smoothing_weights,
+                    );
+                }
+            }
+        }
+
+        // Subdivide mesh if requested
+        if let Some(subdivision_steps) = postprocessing.subdivision_steps {
+            profile!("subdivide mesh");
+            info!(
+                "Post-processing: Performing {} step(s) of Loop subdivision...",
+                subdivision_steps
+            );
+
+            let subdivided = splashsurf_lib::postprocessing::loop_subdivision_with_data(
+                &mesh_with_data,
+                subdivision_steps,
+                postprocessing.subdivision_limit,
+            );
+            mesh_with_data = MeshWithData {
+                mesh: Cow::Owned(subdivided.mesh),
+                point_attributes: subdivided.point_attributes,
+                cell_attributes: subdivided.cell_attributes,
+            };
+            info!(
+                "Post-processing: Subdivided mesh has {} vertices and {} triangles.",
+                mesh_with_data.mesh.vertices.len(),
+                mesh_with_data.mesh.triangles.len()
             );
+
+            // The connectivity is required for the smoothing of the normals of the subdivided mesh
+            if vertex_connectivity.is_some() {
+                vertex_connectivity = Some(mesh_with_data.mesh.vertex_vertex_connectivity());
+            }
         }
 
         // Add normals to mesh if requested
@@ -1153,8 +1998,13 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
                 bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(sph_normals)
             } else {
                 info!("Using area weighted triangle normals for surface normals");
-                profile!("mesh.par_vertex_normals");
-                let tri_normals = mesh_with_data.mesh.par_vertex_normals();
+                let tri_normals = if params.enable_multi_threading {
+                    profile!("mesh.par_vertex_normals");
+                    mesh_with_data.mesh.par_vertex_normals()
+                } else {
+                    profile!("mesh.vertex_normals");
+                    mesh_with_data.mesh.vertex_normals()
+                };
 
                 // Convert unit vectors to plain vectors
                 bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(tri_normals)
@@ -1165,7 +2015,12 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
                 info!("Post-processing: Smoothing normals...");
 
                 let mut smoothed_normals = normals.clone();
-                splashsurf_lib::postprocessing::par_laplacian_smoothing_normals_inplace(
+                let laplacian_smoothing_normals_inplace = if params.enable_multi_threading {
+                    splashsurf_lib::postprocessing::par_laplacian_smoothing_normals_inplace
+                } else {
+                    splashsurf_lib::postprocessing::laplacian_smoothing_normals_inplace
+                };
+                laplacian_smoothing_normals_inplace(
                     &mut smoothed_normals,
                     vertex_connectivity
                         .as_ref()
@@ -1191,6 +2046,21 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
             }
         }
 
+        // Add curvature to mesh if requested
+        if postprocessing.output_curvature {
+            profile!("compute curvature");
+            info!("Post-processing: Computing surface curvature...");
+
+            let curvature = if params.enable_multi_threading {
+                splashsurf_lib::curvature::par_curvature(&mesh_with_data.mesh)
+            } else {
+                splashsurf_lib::curvature::curvature(&mesh_with_data.mesh)
+            };
+            mesh_with_data
+                .point_attributes
+                .extend(curvature.into_attributes());
+        }
+
         // Interpolate attributes if requested
         if !attributes.is_empty() {
             profile!("interpolate attributes");
@@ -1212,6 +2082,24 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
                             AttributeData::ScalarReal(interpolated_values),
                         ));
                     }
+                    AttributeData::ScalarU64(values) => {
+                        let values = values
+                            .iter()
+                            .map(|v| R::from_u64(*v))
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or_else(|| {
+                                anyhow!("Failed to convert attribute \"{}\"", attribute.name)
+                            })?;
+                        let interpolated_values = interpolator.interpolate_scalar_quantity(
+                            values.as_slice(),
+                            mesh_with_data.vertices(),
+                            true,
+                        );
+                        mesh_with_data.point_attributes.push(MeshAttribute::new(
+                            attribute.name,
+                            AttributeData::ScalarReal(interpolated_values),
+                        ));
+                    }
                     AttributeData::Vector3Real(values) => {
                         let interpolated_values = interpolator.interpolate_vector_quantity(
                             values.as_slice(),
@@ -1223,7 +2111,6 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
                             AttributeData::Vector3Real(interpolated_values),
                         ));
                     }
-                    _ => unimplemented!("Interpolation of this attribute type not implemented"),
                 }
             }
         }
@@ -1245,8 +2132,220 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
         mesh_with_data
     };
 
+    // Close the holes of the mesh created by clipping with the AABB
+    let mesh_with_data = if postprocessing.mesh_aabb.is_some() && postprocessing.mesh_aabb_cap {
+        profile!("cap mesh holes");
+        info!("Post-processing: Closing holes of the clipped mesh...");
+
+        let mesh_aabb = postprocessing
+            .mesh_aabb
+            .as_ref()
+            .and_then(|aabb| aabb.try_convert())
+            .ok_or_else(|| anyhow!("Failed to convert mesh AABB"))?;
+
+        let mut mesh_with_data = mesh_with_data;
+        let num_triangles = mesh_with_data.mesh.triangles.len();
+        let source_triangles = splashsurf_lib::postprocessing::cap_aabb_holes(
+            mesh_with_data.mesh.to_mut(),
+            &mesh_aabb,
+        );
+
+        // The caps do not add vertices, their triangles copy the cell attributes of an adjacent triangle
+        let vertex_map = (0..mesh_with_data.mesh.vertices.len()).collect::<Vec<_>>();
+        let triangle_map = (0..num_triangles)
+            .chain(source_triangles)
+            .collect::<Vec<_>>();
+        mesh_with_data.remap_attributes(&vertex_map, &triangle_map);
+
+        mesh_with_data
+    } else {
+        mesh_with_data
+    };
+
+    // Filter and split the connected components of the mesh
+    let mesh_with_data = if postprocessing.min_component_volume.is_some()
+        || postprocessing.output_component_ids
+        || postprocessing.split_components
+    {
+        profile!("connected components");
+        info!("Post-processing: Computing connected components...");
+
+        let mut components = mesh_with_data.mesh.connected_components();
+        info!(
+            "Post-processing: The mesh consists of {} connected components.",
+            components.len()
+        );
+
+        let mut mesh_with_data = if let Some(min_volume_f64) = postprocessing.min_component_volume {
+            let min_volume = R::from_f64(min_volume_f64)
+                .ok_or_else(|| anyhow!("Failed to convert minimum component volume"))?;
+            let triangles_to_keep =
+                components.filter_triangles(|component| component.volume.abs() >= min_volume);
+            let mesh_with_data =
+                mesh_with_data.keep_cells(&triangles_to_keep, postprocessing.keep_vertices);
+
+            let num_components_before = components.len();
+            components = mesh_with_data.mesh.connected_components();
+            info!(
+                "Post-processing: Removed {} connected components with a volume below {}.",
+                num_components_before - components.len(),
+                min_volume_f64
+            );
+            mesh_with_data
+        } else {
+            mesh_with_data
+        };
+
+        if postprocessing.output_component_ids {
+            mesh_with_data
+                .cell_attributes
+                .push(components.to_cell_attribute("component_id"));
+        }
+
+        if postprocessing.split_components {
+            profile!("write component meshes to file");
+
+            let output_path = paths
+                .output_file
+                .parent()
+                // Add a trailing separator if the parent is non-empty
+                .map(|p| p.join(""))
+                .unwrap_or_else(PathBuf::new);
+            let output_stem = paths.output_file.file_stem().unwrap().to_string_lossy();
+            let output_extension = paths
+                .output_file
+                .extension()
+                .map(|e| e.to_string_lossy())
+                .unwrap_or_default();
+
+            info!(
+                "Writing {} connected components to separate files...",
+                components.len()
+            );
+            for (component_idx, triangles) in
+                components.component_triangles().into_iter().enumerate()
+            {
+                let component_mesh = mesh_with_data.keep_cells(&triangles, false);
+                let component_output_file = output_path.join(format!(
+                    "{}_component_{}.{}",
+                    output_stem, component_idx, output_extension
+                ));
+                io::write_mesh(&component_mesh, &component_output_file, &io_params.output)
+                    .with_context(|| {
+                        anyhow!(
+                            "Failed to write component mesh to file \"{}\"",
+                            component_output_file.display()
+                        )
+                    })?;
+            }
+        }
+
+        mesh_with_data
+    } else {
+        mesh_with_data
+    };
+
+    // Compare the volume enclosed by the mesh to the rest volume of the particles
+    if postprocessing.check_volume {
+        profile!("check volume");
+
+        let mesh_aabb = postprocessing
+            .mesh_aabb
+            .as_ref()
+            .map(|aabb| {
+                aabb.try_convert::<R>()
+                    .ok_or_else(|| anyhow!("Failed to convert mesh AABB"))
+            })
+            .transpose()?;
+        let num_particles = particle_positions
+            .iter()
+            .filter(|p| {
+                params
+                    .particle_aabb
+                    .as_ref()
+                    .map(|aabb| aabb.contains_point(p))
+                    .unwrap_or(true)
+                    && mesh_aabb
+                        .as_ref()
+                        .map(|aabb| aabb.contains_point(p))
+                        .unwrap_or(true)
+            })
+            .count();
+
+        let particle_rest_volume =
+            (4.0 / 3.0) * std::f64::consts::PI * params.particle_radius.to_f64().unwrap().powi(3);
+        let particle_volume = num_particles as f64 * particle_rest_volume;
+        let mesh_volume = mesh_with_data.mesh.enclosed_volume().to_f64().unwrap();
+        let mesh_area = mesh_with_data.mesh.area().to_f64().unwrap();
+        let relative_error = (mesh_volume - particle_volume) / particle_volume;
+
+        info!(
+            "Volume check: Mesh volume: {:.6e}, volume of {} particles: {:.6e}, relative error: {:+.3}% (mesh area: {:.6e})",
+            mesh_volume,
+            num_particles,
+            particle_volume,
+            relative_error * 100.0,
+            mesh_area
+        );
+    }
+
+    // Sample the signed distance field of the final mesh on the background grid
+    if postprocessing.output_sdf {
+        profile!("write signed distance field to file");
+
+        let narrow_band_width = postprocessing
+            .sdf_narrow_band_width
+            .map(|width| R::from_f64(width).unwrap());
+        info!(
+            "Post-processing: Computing signed distance field on grid with {:?} points{}...",
+            grid.points_per_dim(),
+            narrow_band_width
+                .map(|width| format!(" in narrow band of width {}", width))
+                .unwrap_or_default()
+        );
+        let signed_distance_grid = if params.enable_multi_threading {
+            splashsurf_lib::signed_distance::par_signed_distance_grid
+        } else {
+            splashsurf_lib::signed_distance::signed_distance_grid
+        };
+        let sdf = signed_distance_grid(&mesh_with_data.mesh, grid, narrow_band_width);
+
+        let output_path = paths
+            .output_file
+            .parent()
+            // Add a trailing separator if the parent is non-empty
+            .map(|p| p.join(""))
+            .unwrap_or_else(PathBuf::new);
+        let output_filename = format!(
+            "sdf_{}.vti",
+            paths.output_file.file_stem().unwrap().to_string_lossy()
+        );
+        let sdf_output_file = output_path.join(output_filename);
+
+        info!(
+            "Writing signed distance field to \"{}\"...",
+            sdf_output_file.display()
+        );
+        io::vtk_format::signed_distance_grid_to_vti(&sdf, &sdf_output_file).with_context(|| {
+            anyhow!(
+                "Failed to write signed distance field to file \"{}\"",
+                sdf_output_file.display()
+            )
+        })?;
+    }
+
+    // Surface nets directly provide quads if no other post-processing step modified the mesh
+    let surface_nets_quad_mesh = (postprocessing.generate_quads
+        && matches!(mesh_with_data.mesh, Cow::Borrowed(_))
+        && mesh_with_data.cell_attributes.is_empty())
+    .then(|| reconstruction.quad_mesh())
+    .flatten();
+
     // Convert triangles to quads
-    let (tri_mesh, tri_quad_mesh) = if postprocessing.generate_quads {
+    let (tri_mesh, tri_quad_mesh) = if let Some(quad_mesh) = surface_nets_quad_mesh {
+        info!("Post-processing: Using quads of the surface nets extraction...");
+        (None, Some(mesh_with_data.with_mesh(quad_mesh)))
+    } else if postprocessing.generate_quads {
         info!("Post-processing: Convert triangles to quads...");
         let non_squareness_limit = R::from_f64(postprocessing.quad_max_edge_diag_ratio).unwrap();
         let normal_angle_limit_rad =
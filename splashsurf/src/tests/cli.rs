@@ -1,4 +1,6 @@
-use crate::reconstruction::Switch;
+use crate::reconstruction::{
+    IndexTypeArg, KernelTypeArg, MeshSmoothingMethodArg, SurfaceExtractionArg, Switch,
+};
 use crate::Subcommand;
 use std::path::PathBuf;
 
@@ -94,6 +96,431 @@ fn test_main_cli() {
         assert_eq!(rec_args.normals, Switch::Off);
     };
 
+    // Test kernel selection
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.kernel, KernelTypeArg::CubicSpline);
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--kernel=wendland-c2",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.kernel, KernelTypeArg::WendlandC2);
+    };
+
+    // Test surface extraction method selection
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.surface_extraction,
+            SurfaceExtractionArg::MarchingCubes
+        );
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--surface-extraction=surface-nets",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.surface_extraction,
+            SurfaceExtractionArg::SurfaceNets
+        );
+    };
+
+    // Test multiphase flags
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--phase-attribute=phase",
+        "--separate-phase-files=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.phase_attribute.as_deref(), Some("phase"));
+        assert_eq!(rec_args.separate_phase_files, Switch::On);
+    };
+
+    assert_eq!(
+        crate::CommandlineArgs::try_parse_from([
+            "splashsurf",
+            "reconstruct",
+            "test.vtk",
+            "--particle-radius=0.05",
+            "--smoothing-length=3.0",
+            "--cube-size=0.75",
+            "--separate-phase-files=on",
+        ])
+        .expect_err("this command is supposed to fail")
+        .kind(),
+        clap::error::ErrorKind::MissingRequiredArgument
+    );
+
+    // Test connected component flags
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--min-component-volume=0.001",
+        "--output-component-ids=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.min_component_volume, Some(0.001));
+        assert_eq!(rec_args.output_component_ids, Switch::On);
+        assert_eq!(rec_args.split_components, Switch::Off);
+    };
+
+    // Test volume check flag
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--check-volume=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.check_volume, Switch::On);
+    };
+
+    // Test QEM decimation flags
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--qem-target-triangles=100000",
+        "--qem-max-aspect-ratio-change=2.0",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.qem_target_triangles, Some(100000));
+        assert_eq!(rec_args.qem_max_error, None);
+        assert_eq!(rec_args.qem_max_normal_angle, 45.0);
+        assert_eq!(rec_args.qem_max_aspect_ratio_change, Some(2.0));
+    };
+
+    // Test mesh AABB capping flag
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--mesh-aabb-min",
+        "0",
+        "0",
+        "0",
+        "--mesh-aabb-max",
+        "1",
+        "1",
+        "1",
+        "--mesh-aabb-cap=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.mesh_aabb_cap, Switch::On);
+        assert_eq!(rec_args.mesh_aabb_clamp_verts, Switch::Off);
+    };
+
+    // Test curvature output flag
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--output-curvature=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.output_curvature, Switch::On);
+        assert_eq!(rec_args.normals, Switch::Off);
+    };
+
+    // Test subdivision flags
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--subdivide",
+        "2",
+        "--subdivide-limit=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.subdivide, Some(2));
+        assert_eq!(rec_args.subdivide_limit, Switch::On);
+    };
+
+    // Test mesh repair flag
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--repair-mesh=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.repair_mesh, Switch::On);
+        assert_eq!(rec_args.mesh_cleanup, Switch::Off);
+    };
+
+    // Test signed distance field output flags
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--output-sdf=on",
+        "--sdf-narrow-band=4.0",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.output_sdf, Switch::On);
+        assert_eq!(rec_args.sdf_narrow_band, Some(4.0));
+    };
+
+    // Test isotropic remeshing flags
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--remesh-edge-length=1.5",
+        "--remesh-projection=off",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.remesh_edge_length, Some(1.5));
+        assert_eq!(rec_args.remesh_iters, 10);
+        assert_eq!(rec_args.remesh_projection, Switch::Off);
+    };
+
+    // Non-positive or non-finite target edge lengths are rejected
+    for edge_length in ["0.0", "-1.0", "NaN", "inf"] {
+        let edge_length_arg = format!("--remesh-edge-length={}", edge_length);
+        assert!(crate::CommandlineArgs::try_parse_from([
+            "splashsurf",
+            "reconstruct",
+            "test.vtk",
+            "--particle-radius=0.05",
+            "--smoothing-length=3.0",
+            "--cube-size=0.75",
+            edge_length_arg.as_str(),
+        ])
+        .is_err());
+    }
+
+    // Test mesh smoothing method flag
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--mesh-smoothing-iters=10",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.mesh_smoothing_method,
+            MeshSmoothingMethodArg::Laplacian
+        );
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--mesh-smoothing-iters=10",
+        "--mesh-smoothing-method=taubin",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.mesh_smoothing_method,
+            MeshSmoothingMethodArg::Taubin
+        );
+    };
+
+    // Test index type flag
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.index_type, IndexTypeArg::Auto);
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--index-type=i32",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.index_type, IndexTypeArg::I32);
+    };
+
+    assert_eq!(
+        crate::CommandlineArgs::try_parse_from([
+            "splashsurf",
+            "reconstruct",
+            "test.vtk",
+            "--particle-radius=0.05",
+            "--smoothing-length=3.0",
+            "--cube-size=0.75",
+            "--index-type=u16",
+        ])
+        .expect_err("this command is supposed to fail")
+        .kind(),
+        clap::error::ErrorKind::InvalidValue
+    );
+
+    // Test anisotropic kernel flags
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.anisotropic_kernel, Switch::Off);
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--anisotropic-kernel=on",
+        "--anisotropy-max=3.0",
+        "--anisotropy-smoothing-weight=0.5",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.anisotropic_kernel, Switch::On);
+        assert_eq!(rec_args.anisotropy_max, 3.0);
+        assert_eq!(rec_args.anisotropy_min_neighbors, 25);
+        assert_eq!(rec_args.anisotropy_smoothing_weight, 0.5);
+    };
+
+    // Test octree decomposition flags
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--subdomain-grid=on",
+        "--subdomain-cubes=32",
+        "--octree=on",
+        "--octree-max-particles=10000",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.subdomain_grid, Switch::On);
+        assert_eq!(rec_args.subdomain_cubes, 32);
+        assert_eq!(rec_args.octree, Switch::On);
+        assert_eq!(rec_args.octree_max_particles, 10000);
+        assert_eq!(rec_args.octree_max_depth, 3);
+    };
+
     // Test domain min/max: correct values
     if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
         "splashsurf",
@@ -4,8 +4,8 @@ use splashsurf_lib::io::particles_from_file;
 #[allow(dead_code)]
 use splashsurf_lib::io::vtk_format::write_vtk;
 use splashsurf_lib::{
-    reconstruct_surface, reconstruct_surface_inplace, GridDecompositionParameters, Parameters,
-    SpatialDecomposition, SurfaceReconstruction,
+    reconstruct_surface, reconstruct_surface_inplace, GridDecompositionParameters, KernelType,
+    Parameters, SpatialDecomposition, SurfaceExtractionMethod, SurfaceReconstruction,
 };
 use std::time::Duration;
 
@@ -98,12 +98,17 @@ pub fn surface_reconstruction_dam_break(c: &mut Criterion) {
         particle_radius,
         rest_density: 1000.0,
         compact_support_radius,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::default(),
         cube_size,
         iso_surface_threshold: 0.6,
         particle_aabb: None,
         enable_multi_threading: true,
         spatial_decomposition: None,
+        anisotropic_kernel: None,
         global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -157,12 +162,17 @@ pub fn surface_reconstruction_double_dam_break(c: &mut Criterion) {
         particle_radius,
         rest_density: 1000.0,
         compact_support_radius,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::default(),
         cube_size,
         iso_surface_threshold: 0.6,
         particle_aabb: None,
         enable_multi_threading: true,
         spatial_decomposition: None,
+        anisotropic_kernel: None,
         global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -216,12 +226,17 @@ pub fn surface_reconstruction_double_dam_break_inplace(c: &mut Criterion) {
         particle_radius,
         rest_density: 1000.0,
         compact_support_radius,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::default(),
         cube_size,
         iso_surface_threshold: 0.6,
         particle_aabb: None,
         enable_multi_threading: true,
         spatial_decomposition: None,
+        anisotropic_kernel: None,
         global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
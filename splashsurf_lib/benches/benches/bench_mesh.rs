@@ -2,8 +2,8 @@ use criterion::{criterion_group, Criterion};
 use splashsurf_lib::io::particles_from_file;
 use splashsurf_lib::nalgebra::Vector3;
 use splashsurf_lib::{
-    reconstruct_surface, GridDecompositionParameters, Parameters, SpatialDecomposition,
-    SurfaceReconstruction,
+    reconstruct_surface, GridDecompositionParameters, KernelType, Parameters, SpatialDecomposition,
+    SurfaceExtractionMethod, SurfaceReconstruction,
 };
 use std::path::Path;
 use std::time::Duration;
@@ -19,6 +19,8 @@ fn reconstruct_particles<P: AsRef<Path>>(particle_file: P) -> SurfaceReconstruct
         particle_radius,
         rest_density: 1000.0,
         compact_support_radius,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::default(),
         cube_size,
         iso_surface_threshold: 0.6,
         particle_aabb: None,
@@ -26,7 +28,10 @@ fn reconstruct_particles<P: AsRef<Path>>(particle_file: P) -> SurfaceReconstruct
         spatial_decomposition: Some(SpatialDecomposition::UniformGrid(
             GridDecompositionParameters::default(),
         )),
+        anisotropic_kernel: None,
         global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
     };
 
     reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters).unwrap()
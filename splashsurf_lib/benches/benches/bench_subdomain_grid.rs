@@ -2,8 +2,8 @@ use criterion::{criterion_group, Criterion, SamplingMode};
 use nalgebra::Vector3;
 use splashsurf_lib::io::particles_from_file;
 use splashsurf_lib::{
-    reconstruct_surface, GridDecompositionParameters, Parameters, SpatialDecomposition,
-    SurfaceReconstruction,
+    reconstruct_surface, GridDecompositionParameters, KernelType, Parameters, SpatialDecomposition,
+    SurfaceExtractionMethod, SurfaceReconstruction,
 };
 use std::time::Duration;
 
@@ -16,6 +16,8 @@ fn parameters_canyon() -> Parameters<f32> {
         particle_radius,
         rest_density: 1000.0,
         compact_support_radius,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::default(),
         cube_size,
         iso_surface_threshold: 0.6,
         particle_aabb: None,
@@ -26,7 +28,10 @@ fn parameters_canyon() -> Parameters<f32> {
                 ..Default::default()
             },
         )),
+        anisotropic_kernel: None,
         global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
     };
 
     parameters
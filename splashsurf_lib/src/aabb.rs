@@ -217,6 +217,11 @@ where
         self.contains_point(&other.min) || self.contains_point(&other.max)
     }
 
+    /// Checks if the given AABB overlaps with the AABB (including touching boundaries)
+    pub fn intersects_aabb(&self, other: &Self) -> bool {
+        (0..D).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
     /// Checks if the given point is inside of the AABB, the AABB is considered to be half-open to its max coordinate
     pub fn contains_point(&self, point: &SVector<R, D>) -> bool {
         point >= &self.min && point < &self.max
@@ -0,0 +1,359 @@
+//! Anisotropic kernels for the reconstruction of smooth surfaces of thin sheets and flat regions
+//!
+//! This module implements the anisotropic kernels of the paper "Reconstructing surfaces of
+//! particle-based fluids using anisotropic kernels" (Yu, Turk; 2013). For every particle, a weighted
+//! covariance matrix of the positions of its neighbors is computed. Its principal axes are then
+//! used to stretch the kernel of the particle along the directions in which the neighboring
+//! particles are distributed and to compress it in the remaining directions (e.g. orthogonal to a
+//! flat surface). Optionally, the kernel centers are moved towards the weighted mean of the
+//! neighborhood (a single step of Laplacian smoothing) which further reduces bumps on the surface.
+//!
+//! The anisotropic kernel of a particle is given by `W(x) = det(G) P(‖G (x - c)‖)` where `c` is
+//! the kernel center, `G` is a linear transformation and `P` is an isotropic kernel with unit
+//! compact support (see [`AnisotropicKernel`]). In contrast to the paper, the principal axes are
+//! normalized such that the volume of the ellipsoidal support is equal to the volume of the support
+//! of the isotropic kernel. Particles with an isotropic neighborhood or too few neighbors keep
+//! their isotropic kernel.
+//!
+//! Note that the particle densities are always computed with isotropic kernels, only the evaluation
+//! of the density field (i.e. the level-set function for marching cubes) uses the anisotropic kernels.
+
+use crate::neighborhood_search::NeighborhoodList;
+use crate::{profile, Real, RealConvert};
+use nalgebra::{Matrix3, Vector3};
+use numeric_literals::replace_float_literals;
+use rayon::prelude::*;
+
+/// Parameters for the construction of anisotropic kernels
+#[derive(Clone, Debug)]
+pub struct AnisotropicKernelParameters<R: Real> {
+    /// Minimum number of neighbors (including the particle itself) of a particle to use an anisotropic kernel, particles with fewer neighbors keep an isotropic kernel (`N_ε` in the paper)
+    pub min_neighbors: usize,
+    /// Maximum ratio between the largest and the smallest principal axis of the kernel (`k_r` in the paper)
+    pub max_anisotropy: R,
+    /// Weight of the Laplacian smoothing of the kernel centers in the range `[0, 1]`, zero disables the smoothing (`λ` in the paper)
+    pub center_smoothing_weight: R,
+}
+
+impl<R: Real> Default for AnisotropicKernelParameters<R> {
+    /// Returns the parameters recommended by the paper
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn default() -> Self {
+        Self {
+            min_neighbors: 25,
+            max_anisotropy: 4.0,
+            center_smoothing_weight: 0.9,
+        }
+    }
+}
+
+impl<R: Real> AnisotropicKernelParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<AnisotropicKernelParameters<T>> {
+        Some(AnisotropicKernelParameters {
+            min_neighbors: self.min_neighbors,
+            max_anisotropy: self.max_anisotropy.try_convert()?,
+            center_smoothing_weight: self.center_smoothing_weight.try_convert()?,
+        })
+    }
+
+    /// Returns the largest possible distance from a particle to the boundary of its anisotropic kernel support, relative to its isotropic compact support radius
+    ///
+    /// This is the sum of the largest possible shift of the kernel center by the smoothing and the
+    /// longest possible principal axis of the kernel. It can be used to determine margins around
+    /// particles that are guaranteed to contain the entire support of their kernels.
+    pub fn max_support_scale(&self) -> R {
+        let max_anisotropy = self.max_anisotropy.max(R::one());
+        let max_stretch = (max_anisotropy * max_anisotropy).cbrt();
+        self.center_smoothing_weight.max(R::zero()) + max_stretch
+    }
+}
+
+/// The anisotropic kernel of a single particle
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnisotropicKernel<R: Real> {
+    /// Center of the kernel, i.e. the (optionally smoothed) position of the particle
+    pub center: Vector3<R>,
+    /// Transformation `G` from offsets relative to the kernel center into the domain of an isotropic kernel with unit compact support
+    pub transform: Matrix3<R>,
+    /// Determinant of the transformation `G`, i.e. the normalization factor of the kernel
+    pub determinant: R,
+    /// Length of the longest principal axis of the ellipsoidal kernel support (in distance units)
+    pub support_radius: R,
+}
+
+impl<R: Real> AnisotropicKernel<R> {
+    /// Returns an isotropic kernel with the given center and compact support radius
+    pub fn isotropic(center: Vector3<R>, compact_support_radius: R) -> Self {
+        let h_inv = compact_support_radius.recip();
+        Self {
+            center,
+            transform: Matrix3::from_diagonal_element(h_inv),
+            determinant: h_inv * h_inv * h_inv,
+            support_radius: compact_support_radius,
+        }
+    }
+
+    /// Returns the squared norm of the given point relative to the kernel center transformed into the domain of the isotropic kernel with unit compact support
+    ///
+    /// The point is inside of the kernel support if and only if the returned value is less than one.
+    #[inline(always)]
+    pub fn transformed_distance_squared(&self, x: &Vector3<R>) -> R {
+        (self.transform * (x - self.center)).norm_squared()
+    }
+}
+
+/// Computes the anisotropic kernels of all particles based on their neighborhood lists
+///
+/// The neighbors of a particle are weighted in its covariance matrix up to its isotropic compact
+/// support radius. If `compact_support_radii` are provided, they are used per particle, otherwise
+/// the global `compact_support_radius` is used for all particles.
+pub fn compute_anisotropic_kernels<R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_neighbor_lists: &[Vec<usize>],
+    compact_support_radius: R,
+    compact_support_radii: Option<&[R]>,
+    parameters: &AnisotropicKernelParameters<R>,
+    enable_multi_threading: bool,
+    anisotropic_kernels: &mut Vec<AnisotropicKernel<R>>,
+) {
+    profile!("compute_anisotropic_kernels");
+    assert_eq!(particle_positions.len(), particle_neighbor_lists.len());
+
+    let compact_support_radius_of = |i: usize| {
+        compact_support_radii
+            .map(|radii| radii[i])
+            .unwrap_or(compact_support_radius)
+    };
+
+    if enable_multi_threading {
+        particle_positions
+            .par_iter()
+            .with_min_len(8)
+            .enumerate()
+            .map(|(i, _)| {
+                compute_anisotropic_kernel(
+                    i,
+                    particle_positions,
+                    particle_neighbor_lists[i].as_slice(),
+                    compact_support_radius_of(i),
+                    parameters,
+                )
+            })
+            .collect_into_vec(anisotropic_kernels);
+    } else {
+        sequential_compute_anisotropic_kernels_filtered(
+            particle_positions,
+            particle_neighbor_lists,
+            compact_support_radius,
+            compact_support_radii,
+            parameters,
+            anisotropic_kernels,
+            |_| true,
+        );
+    }
+}
+
+/// Computes the anisotropic kernels of all particles accepted by the filter based on their neighborhood lists, sequential implementation
+///
+/// The output vector is resized to the number of particles. Entries of particles that are rejected
+/// by the filter are set to the isotropic kernel of the particle.
+pub fn sequential_compute_anisotropic_kernels_filtered<R: Real, Nl: NeighborhoodList + ?Sized>(
+    particle_positions: &[Vector3<R>],
+    particle_neighbor_lists: &Nl,
+    compact_support_radius: R,
+    compact_support_radii: Option<&[R]>,
+    parameters: &AnisotropicKernelParameters<R>,
+    anisotropic_kernels: &mut Vec<AnisotropicKernel<R>>,
+    filter: impl Fn(usize) -> bool,
+) {
+    profile!("sequential_compute_anisotropic_kernels_filtered");
+
+    anisotropic_kernels.clear();
+    anisotropic_kernels.extend(particle_positions.iter().enumerate().map(|(i, x_i)| {
+        let h_i = compact_support_radii
+            .map(|radii| radii[i])
+            .unwrap_or(compact_support_radius);
+        if filter(i) {
+            compute_anisotropic_kernel(
+                i,
+                particle_positions,
+                particle_neighbor_lists.neighbors(i),
+                h_i,
+                parameters,
+            )
+        } else {
+            AnisotropicKernel::isotropic(*x_i, h_i)
+        }
+    }));
+}
+
+/// Computes the anisotropic kernel of a single particle from its neighborhood
+#[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+fn compute_anisotropic_kernel<R: Real>(
+    particle_i: usize,
+    particle_positions: &[Vector3<R>],
+    neighbors: &[usize],
+    compact_support_radius: R,
+    parameters: &AnisotropicKernelParameters<R>,
+) -> AnisotropicKernel<R> {
+    let h = compact_support_radius;
+    let x_i = particle_positions[particle_i];
+
+    // Isotropic weighting function of the neighbors, w(r) = 1 - (r/h)³
+    let weight = |x_j: &Vector3<R>| {
+        let q = (x_j - x_i).norm() / h;
+        if q < 1.0 {
+            1.0 - q * q * q
+        } else {
+            0.0
+        }
+    };
+
+    // Weighted mean of the neighborhood (including the particle itself)
+    let mut weight_sum = 1.0;
+    let mut mean = x_i;
+    let mut num_neighbors = 1;
+    for x_j in neighbors.iter().map(|&j| &particle_positions[j]) {
+        let w_ij = weight(x_j);
+        if w_ij > 0.0 {
+            weight_sum += w_ij;
+            mean += x_j * w_ij;
+            num_neighbors += 1;
+        }
+    }
+    mean /= weight_sum;
+
+    let smoothing_weight = parameters.center_smoothing_weight.max(0.0).min(1.0);
+    let center = x_i * (1.0 - smoothing_weight) + mean * smoothing_weight;
+
+    if num_neighbors < parameters.min_neighbors {
+        return AnisotropicKernel::isotropic(center, h);
+    }
+
+    // Weighted covariance matrix of the neighborhood
+    let mut covariance = {
+        let dx = x_i - mean;
+        dx * dx.transpose()
+    };
+    for x_j in neighbors.iter().map(|&j| &particle_positions[j]) {
+        let w_ij = weight(x_j);
+        if w_ij > 0.0 {
+            let dx = x_j - mean;
+            covariance += (dx * dx.transpose()) * w_ij;
+        }
+    }
+    covariance /= weight_sum;
+
+    let eigen = covariance.symmetric_eigen();
+    let sigma_max = eigen.eigenvalues.max();
+    if sigma_max <= R::default_epsilon() {
+        return AnisotropicKernel::isotropic(center, h);
+    }
+
+    // Clamp the principal axes to limit the anisotropy and normalize them to preserve the kernel volume
+    let max_anisotropy = parameters.max_anisotropy.max(1.0);
+    let mut sigma = eigen
+        .eigenvalues
+        .map(|sigma_k| sigma_k.max(sigma_max / max_anisotropy));
+    let volume_scale = (sigma.x * sigma.y * sigma.z).cbrt();
+    sigma /= volume_scale;
+
+    // G = 1/h R Σ⁻¹ Rᵀ
+    let rotation = eigen.eigenvectors;
+    let transform = rotation
+        * Matrix3::from_diagonal(&sigma.map(|sigma_k| (sigma_k * h).recip()))
+        * rotation.transpose();
+
+    AnisotropicKernel {
+        center,
+        transform,
+        determinant: (h * h * h).recip(),
+        support_radius: sigma.max() * h,
+    }
+}
+
+#[test]
+fn test_anisotropic_kernel_isotropic_neighborhood() {
+    // A regular lattice results in an isotropic covariance in its interior
+    let n = 7;
+    let spacing = 0.05;
+    let mut particles = Vec::new();
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                particles.push(Vector3::new(i as f64, j as f64, k as f64) * spacing);
+            }
+        }
+    }
+
+    let h = 4.0 * spacing;
+    let mut neighbors = Vec::new();
+    crate::neighborhood_search::neighborhood_search_naive(&particles, h, &mut neighbors);
+
+    let mut kernels = Vec::new();
+    compute_anisotropic_kernels(
+        &particles,
+        &neighbors,
+        h,
+        None,
+        &AnisotropicKernelParameters::default(),
+        false,
+        &mut kernels,
+    );
+
+    let center_idx = (n / 2) * n * n + (n / 2) * n + n / 2;
+    let kernel = &kernels[center_idx];
+    assert!((kernel.center - particles[center_idx]).norm() < 1e-10);
+    assert!((kernel.transform - Matrix3::from_diagonal_element(1.0 / h)).norm() < 1e-6 / h);
+    assert!((kernel.support_radius - h).abs() < 1e-6 * h);
+}
+
+#[test]
+fn test_anisotropic_kernel_flat_neighborhood() {
+    // A single layer of particles in the xy-plane results in kernels that are compressed along the z-axis
+    let n = 9;
+    let spacing = 0.05;
+    let mut particles = Vec::new();
+    for i in 0..n {
+        for j in 0..n {
+            particles.push(Vector3::new(i as f64, j as f64, 0.0) * spacing);
+        }
+    }
+
+    let h = 4.0 * spacing;
+    let mut neighbors = Vec::new();
+    crate::neighborhood_search::neighborhood_search_naive(&particles, h, &mut neighbors);
+
+    let parameters = AnisotropicKernelParameters {
+        min_neighbors: 10,
+        ..Default::default()
+    };
+
+    let mut kernels = Vec::new();
+    compute_anisotropic_kernels(
+        &particles,
+        &neighbors,
+        h,
+        None,
+        &parameters,
+        true,
+        &mut kernels,
+    );
+
+    let center_idx = (n / 2) * n + n / 2;
+    let kernel = &kernels[center_idx];
+
+    // The support is stretched in the plane and compressed orthogonal to it by the maximum anisotropy
+    let extent = |axis: Vector3<f64>| 1.0 / (kernel.transform * axis).norm();
+    let (extent_x, extent_z) = (extent(Vector3::x()), extent(Vector3::z()));
+    assert!((extent_x / extent_z - parameters.max_anisotropy).abs() < 1e-6);
+    assert!((extent_x * extent_x * extent_z - h * h * h).abs() < 1e-6 * h * h * h);
+    assert!(kernel.support_radius <= parameters.max_support_scale() * h);
+
+    // Points on the plane far from the center are only inside of the stretched kernel
+    let x = particles[center_idx] + Vector3::new(1.2 * h, 0.0, 0.0);
+    assert!(kernel.transformed_distance_squared(&x) < 1.0);
+    let z = particles[center_idx] + Vector3::new(0.0, 0.0, 0.8 * h);
+    assert!(kernel.transformed_distance_squared(&z) > 1.0);
+}
@@ -0,0 +1,592 @@
+//! Handling of solid boundaries (e.g. tank walls and rigid obstacles) in the surface reconstruction
+//!
+//! Without any information about solid boundaries, the reconstructed surface closes off with a gap
+//! in front of walls and obstacles: the density of fluid particles close to a boundary is
+//! underestimated and the density field of the fluid decays towards the boundary. A
+//! [`SolidBoundary`] passed to the reconstruction using [`Parameters::solid_boundary`](crate::Parameters::solid_boundary)
+//! fixes both issues:
+//!  - the boundary is described by a volume fraction field `ϕ_b(x)` of the solid, i.e. the kernel
+//!    weighted solid volume inside of the kernel support around `x` (`1` deep inside of the solid
+//!    and `0` outside of the kernel support of the solid),
+//!  - the densities of the fluid particles are corrected by the boundary contributions
+//!    `ρ₀ ϕ_b(x_i)` (similar to "Versatile rigid-fluid coupling for incompressible SPH", Akinci et al. 2012),
+//!  - the volume fraction of the solid is added to the density field `ϕ_f(x)` of the fluid wherever the
+//!    fluid is present, weighted by `min(1, ϕ_f(x) / τ)` where `τ` is the iso-surface threshold.
+//!
+//! As a result, the fluid surface meets the solid flush instead of closing off in front of it.
+//! The surface continues slightly into the solid where it is hidden by the obstacle. The field of
+//! the solid does not create any surfaces far away from the fluid.
+//!
+//! The solid can either be sampled by [`BoundaryParticles`] (which can also be generated from a
+//! triangle mesh with [`BoundaryParticles::from_mesh`]) or described by a [`SignedDistanceFunction`].
+//! The boundary is only evaluated for particles close to the boundary and, with the subdomain-grid
+//! approach, only in subdomains that are close to the boundary.
+
+use crate::density_map::DensityMap;
+use crate::kernel::{DynamicKernel, SymmetricKernel3d};
+use crate::mesh::TriMesh3d;
+use crate::uniform_grid::UniformGrid;
+use crate::{new_map, profile, Aabb3d, Index, MapType, Parameters, Real, RealConvert};
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// A signed distance function describing a solid, negative inside of the solid and positive outside
+///
+/// The function does not have to be exact but it should never overestimate the distance to the
+/// surface of the solid, otherwise regions close to the solid might be skipped. It is implemented
+/// for all closures `Fn(&Vector3<R>) -> R` that are `Send + Sync`.
+pub trait SignedDistanceFunction<R: Real>: Send + Sync {
+    /// Returns the signed distance of the given point to the surface of the solid
+    fn signed_distance(&self, point: &Vector3<R>) -> R;
+}
+
+impl<R: Real, F: Fn(&Vector3<R>) -> R + Send + Sync> SignedDistanceFunction<R> for F {
+    fn signed_distance(&self, point: &Vector3<R>) -> R {
+        self(point)
+    }
+}
+
+/// Particles sampling the surface (or the volume close to the surface) of a solid
+#[derive(Clone, Debug)]
+pub struct BoundaryParticles<R: Real> {
+    /// Positions of the boundary particles
+    positions: Vec<Vector3<R>>,
+    /// Volumes of the boundary particles (computed from the sampling density if not provided)
+    volumes: Option<Vec<R>>,
+}
+
+impl<R: Real> BoundaryParticles<R> {
+    /// Creates boundary particles at the given positions, their volumes are computed during the reconstruction from the local sampling density
+    pub fn new(positions: Vec<Vector3<R>>) -> Self {
+        Self {
+            positions,
+            volumes: None,
+        }
+    }
+
+    /// Samples the given triangle mesh with boundary particles with approximately the given spacing
+    ///
+    /// Every triangle is sampled by a regular lattice in barycentric coordinates that includes its
+    /// vertices and edges. Particles on edges and vertices that are shared by multiple triangles
+    /// are sampled multiple times which is compensated by the computed particle volumes.
+    pub fn from_mesh(mesh: &TriMesh3d<R>, spacing: R) -> Self {
+        assert!(
+            spacing > R::zero(),
+            "spacing of boundary particles has to be positive"
+        );
+
+        let mut positions = Vec::new();
+        for triangle in &mesh.triangles {
+            let [a, b, c] = triangle.map(|v| mesh.vertices[v]);
+            let max_edge_length = (b - a).norm().max((c - b).norm()).max((a - c).norm());
+            let n = (max_edge_length / spacing)
+                .ceil()
+                .to_usize()
+                .expect("number of samples per triangle edge has to fit into usize")
+                .max(1);
+            let n_real = R::from_usize(n).unwrap();
+
+            for i in 0..=n {
+                for j in 0..=(n - i) {
+                    let u = R::from_usize(i).unwrap() / n_real;
+                    let v = R::from_usize(j).unwrap() / n_real;
+                    positions.push(a + (b - a) * u + (c - a) * v);
+                }
+            }
+        }
+
+        Self::new(positions)
+    }
+
+    /// Sets the volume of every boundary particle (has to contain exactly one value per particle)
+    pub fn with_volumes(mut self, volumes: Vec<R>) -> Self {
+        assert_eq!(
+            volumes.len(),
+            self.positions.len(),
+            "number of boundary particle volumes has to match the number of boundary particles"
+        );
+        self.volumes = Some(volumes);
+        self
+    }
+
+    /// Returns the positions of the boundary particles
+    pub fn positions(&self) -> &[Vector3<R>] {
+        self.positions.as_slice()
+    }
+
+    /// Returns the volumes of the boundary particles if they were provided
+    pub fn volumes(&self) -> Option<&[R]> {
+        self.volumes.as_deref()
+    }
+
+    /// Tries to convert the boundary particles from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<BoundaryParticles<T>> {
+        let volumes = match &self.volumes {
+            Some(volumes) => Some(
+                volumes
+                    .iter()
+                    .map(|v| v.try_convert())
+                    .collect::<Option<_>>()?,
+            ),
+            None => None,
+        };
+        Some(BoundaryParticles {
+            positions: self
+                .positions
+                .iter()
+                .map(|p| p.try_convert())
+                .collect::<Option<_>>()?,
+            volumes,
+        })
+    }
+}
+
+/// Description of a solid boundary that is taken into account by the surface reconstruction (see the [module level documentation](self))
+#[derive(Clone)]
+pub enum SolidBoundary<R: Real> {
+    /// The solid is sampled by boundary particles
+    Particles(Arc<BoundaryParticles<R>>),
+    /// The solid is described by a signed distance function
+    SignedDistance(Arc<dyn SignedDistanceFunction<R>>),
+}
+
+impl<R: Real> SolidBoundary<R> {
+    /// Creates a boundary sampled by the given particles
+    pub fn from_particles(particles: BoundaryParticles<R>) -> Self {
+        SolidBoundary::Particles(Arc::new(particles))
+    }
+
+    /// Creates a boundary described by the given signed distance function
+    pub fn from_signed_distance<F: SignedDistanceFunction<R> + 'static>(sdf: F) -> Self {
+        SolidBoundary::SignedDistance(Arc::new(sdf))
+    }
+
+    /// Tries to convert the boundary from one [Real] type to another [Real] type, returns `None` if conversion fails
+    ///
+    /// Signed distance functions are wrapped such that their arguments and results are converted on every evaluation.
+    pub fn try_convert<T: Real>(&self) -> Option<SolidBoundary<T>> {
+        Some(match self {
+            SolidBoundary::Particles(particles) => {
+                SolidBoundary::Particles(Arc::new(particles.try_convert()?))
+            }
+            SolidBoundary::SignedDistance(sdf) => {
+                SolidBoundary::SignedDistance(Arc::new(ConvertedSignedDistance {
+                    sdf: sdf.clone(),
+                    phantom: PhantomData,
+                }))
+            }
+        })
+    }
+}
+
+impl<R: Real> fmt::Debug for SolidBoundary<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolidBoundary::Particles(particles) => f
+                .debug_struct("Particles")
+                .field("num_particles", &particles.positions.len())
+                .field("has_volumes", &particles.volumes.is_some())
+                .finish(),
+            SolidBoundary::SignedDistance(_) => f.debug_struct("SignedDistance").finish(),
+        }
+    }
+}
+
+/// Signed distance function of another [Real] type
+struct ConvertedSignedDistance<R: Real, T: Real> {
+    sdf: Arc<dyn SignedDistanceFunction<R>>,
+    phantom: PhantomData<T>,
+}
+
+impl<R: Real, T: Real> SignedDistanceFunction<T> for ConvertedSignedDistance<R, T> {
+    fn signed_distance(&self, point: &Vector3<T>) -> T {
+        let distance = self.sdf.signed_distance(&point.convert::<R>());
+        (&distance).convert::<T>()
+    }
+}
+
+/// Boundary particles sorted into a spatial hash grid with the compact support radius as cell size
+struct BoundaryParticleGrid<'a, R: Real> {
+    positions: &'a [Vector3<R>],
+    volumes: Vec<R>,
+    cell_size: R,
+    cells: MapType<[i64; 3], Vec<usize>>,
+}
+
+impl<'a, R: Real> BoundaryParticleGrid<'a, R> {
+    fn cell_of(cell_size: R, point: &Vector3<R>) -> [i64; 3] {
+        [0, 1, 2].map(|d| {
+            (point[d] / cell_size)
+                .floor()
+                .to_i64()
+                .expect("particle coordinate has to fit into the spatial hash grid")
+        })
+    }
+
+    /// Calls the closure for every boundary particle in the cells around the given point
+    fn for_each_neighbor_candidate<F: FnMut(usize)>(&self, point: &Vector3<R>, mut f: F) {
+        let [i, j, k] = Self::cell_of(self.cell_size, point);
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    if let Some(particles) = self.cells.get(&[i + di, j + dj, k + dk]) {
+                        particles.iter().copied().for_each(&mut f);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns whether any boundary particle lies in a cell intersecting the given AABB
+    fn any_particle_in(&self, aabb: &Aabb3d<R>) -> bool {
+        let min = Self::cell_of(self.cell_size, aabb.min());
+        let max = Self::cell_of(self.cell_size, aabb.max());
+        let num_cells = (0..3)
+            .map(|d| (max[d] - min[d] + 1) as u64)
+            .fold(1u64, u64::saturating_mul);
+
+        let in_range = |cell: &[i64; 3]| (0..3).all(|d| cell[d] >= min[d] && cell[d] <= max[d]);
+        if num_cells > self.cells.len() as u64 {
+            self.cells.keys().any(in_range)
+        } else {
+            (min[0]..=max[0]).any(|i| {
+                (min[1]..=max[1])
+                    .any(|j| (min[2]..=max[2]).any(|k| self.cells.contains_key(&[i, j, k])))
+            })
+        }
+    }
+}
+
+/// Tabulated volume fraction of a half space inside of the kernel support around a point
+///
+/// The volume fraction is the integral of the kernel over the half space, it only depends on the
+/// signed distance of the point to the plane bounding the half space.
+struct HalfSpaceVolumeFraction<R: Real> {
+    /// Volume fractions at equidistant positive distances from zero to the compact support radius
+    values: Vec<R>,
+    compact_support_radius: R,
+}
+
+impl<R: Real> HalfSpaceVolumeFraction<R> {
+    /// Number of intervals of the table
+    const RESOLUTION: usize = 256;
+
+    fn new(kernel: &DynamicKernel<R>, compact_support_radius: R) -> Self {
+        let n = Self::RESOLUTION;
+        let h = compact_support_radius.to_f64().unwrap();
+        let dz = h / n as f64;
+        let w = |r: f64| kernel.evaluate(R::from_f64(r).unwrap()).to_f64().unwrap();
+
+        // Integral of the kernel over the plane at distance z: 2π ∫_z^h W(r) r dr
+        let mut plane_integrals = vec![0.0; n + 1];
+        for k in (0..n).rev() {
+            let (z0, z1) = (k as f64 * dz, (k + 1) as f64 * dz);
+            plane_integrals[k] =
+                plane_integrals[k + 1] + std::f64::consts::PI * (w(z0) * z0 + w(z1) * z1) * dz;
+        }
+
+        // Integral of the plane integrals over all planes beyond the distance z
+        let mut volume_fractions = vec![0.0; n + 1];
+        for k in (0..n).rev() {
+            volume_fractions[k] =
+                volume_fractions[k + 1] + 0.5 * (plane_integrals[k] + plane_integrals[k + 1]) * dz;
+        }
+
+        // Compensate the discretization error such that the volume fraction on the plane is exactly one half
+        let normalization = 0.5 / volume_fractions[0];
+        Self {
+            values: volume_fractions
+                .into_iter()
+                .map(|f| R::from_f64(f * normalization).unwrap())
+                .collect(),
+            compact_support_radius,
+        }
+    }
+
+    /// Returns the volume fraction of the half space for a point with the given signed distance to the half space
+    fn evaluate(&self, signed_distance: R) -> R {
+        let d = signed_distance.abs() / self.compact_support_radius;
+        let fraction = if d >= R::one() {
+            R::zero()
+        } else {
+            let x = d * R::from_usize(Self::RESOLUTION).unwrap();
+            let k = x.floor().to_usize().unwrap().min(Self::RESOLUTION - 1);
+            let t = x - R::from_usize(k).unwrap();
+            self.values[k] * (R::one() - t) + self.values[k + 1] * t
+        };
+
+        if signed_distance < R::zero() {
+            R::one() - fraction
+        } else {
+            fraction
+        }
+    }
+}
+
+enum BoundaryFieldKind<'a, R: Real> {
+    Particles(BoundaryParticleGrid<'a, R>),
+    SignedDistance(
+        &'a dyn SignedDistanceFunction<R>,
+        HalfSpaceVolumeFraction<R>,
+    ),
+}
+
+/// A solid boundary prepared for the evaluation of its volume fraction field during a reconstruction
+pub(crate) struct BoundaryField<'a, R: Real> {
+    kind: BoundaryFieldKind<'a, R>,
+    kernel: DynamicKernel<R>,
+    compact_support_radius: R,
+}
+
+impl<'a, R: Real> BoundaryField<'a, R> {
+    /// Prepares the boundary for the kernel and compact support radius of the given parameters
+    pub(crate) fn new(boundary: &'a SolidBoundary<R>, parameters: &Parameters<R>) -> Self {
+        profile!("initialize solid boundary");
+
+        let compact_support_radius = parameters.compact_support_radius;
+        let kernel = DynamicKernel::new(parameters.kernel_type, compact_support_radius);
+
+        let kind = match boundary {
+            SolidBoundary::Particles(particles) => {
+                let mut cells = new_map();
+                for (i, p) in particles.positions.iter().enumerate() {
+                    cells
+                        .entry(BoundaryParticleGrid::cell_of(compact_support_radius, p))
+                        .or_insert_with(Vec::new)
+                        .push(i);
+                }
+
+                let mut grid = BoundaryParticleGrid {
+                    positions: particles.positions.as_slice(),
+                    volumes: Vec::new(),
+                    cell_size: compact_support_radius,
+                    cells,
+                };
+
+                grid.volumes = if let Some(volumes) = &particles.volumes {
+                    volumes.clone()
+                } else {
+                    // The volume of a boundary particle is the inverse of its "number density"
+                    let volume = |p: &Vector3<R>| {
+                        let mut number_density = R::zero();
+                        grid.for_each_neighbor_candidate(p, |j| {
+                            let r = (p - grid.positions[j]).norm();
+                            if r < compact_support_radius {
+                                number_density += kernel.evaluate(r);
+                            }
+                        });
+                        R::one() / number_density
+                    };
+
+                    if parameters.enable_multi_threading {
+                        grid.positions.par_iter().map(volume).collect()
+                    } else {
+                        grid.positions.iter().map(volume).collect()
+                    }
+                };
+
+                BoundaryFieldKind::Particles(grid)
+            }
+            SolidBoundary::SignedDistance(sdf) => BoundaryFieldKind::SignedDistance(
+                sdf.as_ref(),
+                HalfSpaceVolumeFraction::new(&kernel, compact_support_radius),
+            ),
+        };
+
+        Self {
+            kind,
+            kernel,
+            compact_support_radius,
+        }
+    }
+
+    /// Evaluates the volume fraction of the solid in the kernel support around the given point
+    pub(crate) fn volume_fraction(&self, point: &Vector3<R>) -> R {
+        match &self.kind {
+            BoundaryFieldKind::Particles(grid) => {
+                let mut volume_fraction = R::zero();
+                grid.for_each_neighbor_candidate(point, |j| {
+                    let r = (point - grid.positions[j]).norm();
+                    if r < self.compact_support_radius {
+                        volume_fraction += grid.volumes[j] * self.kernel.evaluate(r);
+                    }
+                });
+                volume_fraction
+            }
+            BoundaryFieldKind::SignedDistance(sdf, half_space) => {
+                // The solid is locally approximated by a half space
+                half_space.evaluate(sdf.signed_distance(point))
+            }
+        }
+    }
+
+    /// Returns whether the boundary may influence the density field inside of the given AABB
+    pub(crate) fn may_affect(&self, aabb: &Aabb3d<R>) -> bool {
+        let mut aabb = aabb.clone();
+        aabb.grow_uniformly(self.compact_support_radius);
+        match &self.kind {
+            BoundaryFieldKind::Particles(grid) => grid.any_particle_in(&aabb),
+            BoundaryFieldKind::SignedDistance(sdf, _) => {
+                let half_diagonal = aabb.extents().norm() * R::from_f64(0.5).unwrap();
+                sdf.signed_distance(&aabb.centroid()) <= half_diagonal
+            }
+        }
+    }
+
+    /// Adds the boundary contributions to the densities of the given particles
+    pub(crate) fn add_to_particle_densities(
+        &self,
+        particle_positions: &[Vector3<R>],
+        rest_density: R,
+        enable_multi_threading: bool,
+        particle_densities: &mut [R],
+    ) {
+        profile!("add boundary contributions to particle densities");
+
+        let near_boundary =
+            self.particles_near_boundary(particle_positions, enable_multi_threading);
+        let add_contribution = |((p, density), near): ((&Vector3<R>, &mut R), &bool)| {
+            if *near {
+                *density += rest_density * self.volume_fraction(p);
+            }
+        };
+
+        if enable_multi_threading {
+            particle_positions
+                .par_iter()
+                .zip(particle_densities.par_iter_mut())
+                .zip(near_boundary.par_iter())
+                .for_each(add_contribution);
+        } else {
+            particle_positions
+                .iter()
+                .zip(particle_densities.iter_mut())
+                .zip(near_boundary.iter())
+                .for_each(add_contribution);
+        }
+    }
+
+    /// Returns for every particle whether the boundary may be inside of its kernel support
+    ///
+    /// The particles are sorted into cells with the compact support radius as cell size, so that
+    /// the boundary is only tested once per cell instead of being evaluated for every particle.
+    fn particles_near_boundary(
+        &self,
+        particle_positions: &[Vector3<R>],
+        enable_multi_threading: bool,
+    ) -> Vec<bool> {
+        let cell_size = self.compact_support_radius;
+        let mut cells: MapType<[i64; 3], Vec<usize>> = new_map();
+        for (i, p) in particle_positions.iter().enumerate() {
+            cells
+                .entry(BoundaryParticleGrid::cell_of(cell_size, p))
+                .or_default()
+                .push(i);
+        }
+
+        let cells: Vec<_> = cells.into_iter().collect();
+        let is_near_boundary = |(cell, _): &&([i64; 3], Vec<usize>)| {
+            let cell_min = Vector3::from_fn(|d, _| R::from_i64(cell[d]).unwrap() * cell_size);
+            self.may_affect(&Aabb3d::new(
+                cell_min,
+                cell_min + Vector3::repeat(cell_size),
+            ))
+        };
+        let near_cells: Vec<_> = if enable_multi_threading {
+            cells.par_iter().filter(is_near_boundary).collect()
+        } else {
+            cells.iter().filter(is_near_boundary).collect()
+        };
+
+        let mut near_boundary = vec![false; particle_positions.len()];
+        for (_, particles) in near_cells {
+            for &i in particles {
+                near_boundary[i] = true;
+            }
+        }
+        near_boundary
+    }
+
+    /// Returns the value of the density field including the solid at a point with the given value of the density field of the fluid
+    #[inline(always)]
+    pub(crate) fn apply_to_value(
+        &self,
+        fluid_value: R,
+        point: &Vector3<R>,
+        iso_surface_threshold: R,
+    ) -> R {
+        // The solid is only added where the fluid is present to avoid surfaces around the entire boundary
+        if fluid_value <= R::zero() {
+            return fluid_value;
+        }
+        let weight = (fluid_value / iso_surface_threshold).min(R::one());
+        fluid_value + weight * self.volume_fraction(point)
+    }
+
+    /// Adds the solid to the density values of all points of the given (dense) grid
+    pub(crate) fn apply_to_level_set_grid<I: Index>(
+        &self,
+        grid: &UniformGrid<I, R>,
+        iso_surface_threshold: R,
+        levelset_grid: &mut [R],
+    ) {
+        if !self.may_affect(grid.aabb()) {
+            return;
+        }
+
+        for (flat_point_idx, value) in levelset_grid.iter_mut().enumerate() {
+            if *value > R::zero() {
+                let point = grid
+                    .try_unflatten_point_index(I::from_usize(flat_point_idx).unwrap())
+                    .expect("level-set value has to belong to a grid point");
+                *value = self.apply_to_value(
+                    *value,
+                    &grid.point_coordinates(&point),
+                    iso_surface_threshold,
+                );
+            }
+        }
+    }
+
+    /// Adds the solid to all values of the given sparse density map
+    pub(crate) fn apply_to_density_map<I: Index>(
+        &self,
+        grid: &UniformGrid<I, R>,
+        iso_surface_threshold: R,
+        enable_multi_threading: bool,
+        density_map: &mut DensityMap<I, R>,
+    ) {
+        profile!("add solid boundary to density map");
+        if !self.may_affect(grid.aabb()) {
+            return;
+        }
+
+        let apply = |(flat_point_idx, value): (I, R)| {
+            let point = grid
+                .try_unflatten_point_index(flat_point_idx)
+                .expect("density map entry has to belong to a grid point");
+            (
+                flat_point_idx,
+                self.apply_to_value(
+                    value,
+                    &grid.point_coordinates(&point),
+                    iso_surface_threshold,
+                ),
+            )
+        };
+
+        let values = density_map.to_vec();
+        let mut map: MapType<I, R> = new_map();
+        map.reserve(values.len());
+        if enable_multi_threading {
+            let values: Vec<_> = values.into_par_iter().map(apply).collect();
+            map.extend(values);
+        } else {
+            map.extend(values.into_iter().map(apply));
+        }
+        *density_map = DensityMap::from(map);
+    }
+}
@@ -0,0 +1,500 @@
+//! Bounding volume hierarchy (BVH) over the triangles of a triangle mesh for spatial queries
+//!
+//! See [`TriMeshBvh`] for more information.
+
+use crate::mesh::{tri_closest_point_barycentric, TriMesh3d};
+use crate::{profile, Aabb3d, Real};
+use nalgebra::Vector3;
+use num_traits::Bounded;
+use rayon::prelude::*;
+
+/// Maximum number of triangles stored in a leaf node of the BVH
+const MAX_LEAF_SIZE: usize = 4;
+/// Minimum number of triangles of a subtree such that its children are built in parallel
+const PARALLEL_BUILD_THRESHOLD: usize = 4096;
+/// Ratio of the distance to a node and its radius above which the winding number of the node's triangles is approximated
+const WINDING_NUMBER_FAR_FIELD_RATIO: f64 = 2.0;
+
+/// Intersection of a ray with a triangle of the mesh, see [`TriMeshBvh::intersect_ray`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit<R: Real> {
+    /// Index of the intersected triangle
+    pub triangle: usize,
+    /// Ray parameter of the intersection, i.e. the intersection point is `origin + t * direction`
+    pub t: R,
+    /// The intersection point
+    pub point: Vector3<R>,
+    /// Barycentric coordinates of the intersection point with respect to the vertices of the triangle
+    pub barycentric: Vector3<R>,
+}
+
+/// Point on the mesh that is closest to a query point, see [`TriMeshBvh::closest_point`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClosestPoint<R: Real> {
+    /// Index of the triangle containing the closest point
+    pub triangle: usize,
+    /// The closest point on the mesh
+    pub point: Vector3<R>,
+    /// Barycentric coordinates of the closest point with respect to the vertices of the triangle
+    pub barycentric: Vector3<R>,
+    /// Distance between the query point and the closest point
+    pub distance: R,
+}
+
+/// Node of the BVH, the nodes are stored in depth-first order
+#[derive(Clone, Debug)]
+struct BvhNode<R: Real> {
+    /// Bounding box of all triangles of the node
+    aabb: Aabb3d<R>,
+    /// Sum of the area weighted normals of all triangles of the node
+    area_normal: Vector3<R>,
+    /// Area weighted centroid of all triangles of the node
+    center: Vector3<R>,
+    /// Radius of the sphere around the center enclosing all triangles of the node
+    radius: R,
+    kind: BvhNodeKind,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum BvhNodeKind {
+    /// Leaf node referencing a range of the permuted triangle indices
+    Leaf { start: usize, end: usize },
+    /// Inner node, the left child directly follows the node and the right child is located at the given offset
+    Inner { right_offset: usize },
+}
+
+/// Bounding volume hierarchy over the triangles of a [`TriMesh3d`] for ray casting, closest point and inside/outside queries
+///
+/// The BVH is a binary tree of axis-aligned bounding boxes ([`Aabb3d`]) that is built by recursively
+/// splitting the triangles at the median of their centroids along the axis with the largest extent.
+/// It borrows the mesh and has to be rebuilt if the mesh is modified.
+///
+/// Queries are not parallelized internally, the BVH can be shared between threads to run many queries in parallel.
+#[derive(Clone, Debug)]
+pub struct TriMeshBvh<'a, R: Real> {
+    /// The mesh whose triangles are stored in the BVH
+    mesh: &'a TriMesh3d<R>,
+    /// All nodes of the tree in depth-first order, the first node is the root
+    nodes: Vec<BvhNode<R>>,
+    /// Triangle indices permuted such that every leaf references a contiguous range
+    triangle_indices: Vec<usize>,
+}
+
+impl<'a, R: Real> TriMeshBvh<'a, R> {
+    /// Builds a BVH over all triangles of the given mesh
+    pub fn new(mesh: &'a TriMesh3d<R>) -> Self {
+        profile!("TriMeshBvh::new");
+        Self::build(mesh, false)
+    }
+
+    /// Builds a BVH over all triangles of the given mesh (parallelized version)
+    pub fn par_new(mesh: &'a TriMesh3d<R>) -> Self {
+        profile!("TriMeshBvh::par_new");
+        Self::build(mesh, true)
+    }
+
+    fn build(mesh: &'a TriMesh3d<R>, parallel: bool) -> Self {
+        let three = R::from_f64(3.0).unwrap();
+        let centroid = |tri: &[usize; 3]| {
+            (mesh.vertices[tri[0]] + mesh.vertices[tri[1]] + mesh.vertices[tri[2]]) / three
+        };
+        let centroids: Vec<Vector3<R>> = if parallel {
+            mesh.triangles.par_iter().map(centroid).collect()
+        } else {
+            mesh.triangles.iter().map(centroid).collect()
+        };
+
+        let mut triangle_indices = (0..mesh.triangles.len()).collect::<Vec<_>>();
+        let nodes = if triangle_indices.is_empty() {
+            Vec::new()
+        } else {
+            build_subtree(mesh, &centroids, &mut triangle_indices, 0, parallel)
+        };
+
+        Self {
+            mesh,
+            nodes,
+            triangle_indices,
+        }
+    }
+
+    /// Returns the mesh of the BVH
+    pub fn mesh(&self) -> &'a TriMesh3d<R> {
+        self.mesh
+    }
+
+    /// Returns the bounding box of all triangles of the mesh or `None` if the mesh has no triangles
+    pub fn aabb(&self) -> Option<&Aabb3d<R>> {
+        self.nodes.first().map(|root| &root.aabb)
+    }
+
+    /// Returns the closest intersection of a ray with the mesh for ray parameters in the range `[0, max_t]`
+    ///
+    /// The points on the ray are given by `origin + t * direction`, i.e. `t` is the distance to the
+    /// origin if the direction is normalized. Use [`Bounded::max_value`] as
+    /// `max_t` for unbounded rays. Triangles are intersected from both sides.
+    pub fn intersect_ray(
+        &self,
+        origin: &Vector3<R>,
+        direction: &Vector3<R>,
+        max_t: R,
+    ) -> Option<RayHit<R>> {
+        let inv_direction = direction.map(|d| R::one() / d);
+
+        let mut max_t = max_t;
+        let mut closest_hit = None;
+        let mut stack = self.root_stack();
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            if !ray_intersects_aabb(&node.aabb, origin, &inv_direction, max_t) {
+                continue;
+            }
+
+            match node.kind {
+                BvhNodeKind::Leaf { start, end } => {
+                    for &tri in &self.triangle_indices[start..end] {
+                        let [a, b, c] = self.mesh.triangles[tri].map(|v| self.mesh.vertices[v]);
+                        if let Some((t, barycentric)) =
+                            ray_triangle_intersection(origin, direction, &a, &b, &c)
+                        {
+                            if t >= R::zero() && t <= max_t {
+                                max_t = t;
+                                closest_hit = Some(RayHit {
+                                    triangle: tri,
+                                    t,
+                                    point: origin + direction * t,
+                                    barycentric,
+                                });
+                            }
+                        }
+                    }
+                }
+                BvhNodeKind::Inner { right_offset } => {
+                    stack.push(node_idx + right_offset);
+                    stack.push(node_idx + 1);
+                }
+            }
+        }
+
+        closest_hit
+    }
+
+    /// Returns the point on the mesh that is closest to the given point or `None` if the mesh has no triangles
+    pub fn closest_point(&self, point: &Vector3<R>) -> Option<ClosestPoint<R>> {
+        self.closest_point_impl(point, <R as Bounded>::max_value())
+    }
+
+    /// Returns the point on the mesh that is closest to the given point if it is closer than the given maximum distance
+    ///
+    /// Only the parts of the BVH within the maximum distance are traversed, i.e. this query is
+    /// considerably faster than [`closest_point`](Self::closest_point) for points far from the mesh.
+    pub fn closest_point_within(
+        &self,
+        point: &Vector3<R>,
+        max_distance: R,
+    ) -> Option<ClosestPoint<R>> {
+        self.closest_point_impl(point, max_distance * max_distance)
+    }
+
+    /// Returns the signed distance of the given point to the mesh, negative inside and positive outside of the mesh
+    ///
+    /// The distance is exact while the sign is determined by the [`winding_number`](Self::winding_number),
+    /// which is robust for meshes with small holes or other defects. Returns `None` if the mesh has no triangles.
+    pub fn signed_distance(&self, point: &Vector3<R>) -> Option<R> {
+        self.closest_point(point).map(|closest| {
+            if self.is_inside(point) {
+                -closest.distance
+            } else {
+                closest.distance
+            }
+        })
+    }
+
+    fn closest_point_impl(
+        &self,
+        point: &Vector3<R>,
+        max_distance_sq: R,
+    ) -> Option<ClosestPoint<R>> {
+        let mut min_distance_sq = max_distance_sq;
+        let mut closest = None;
+        let mut stack = self.root_stack();
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            if aabb_distance_sq(&node.aabb, point) > min_distance_sq {
+                continue;
+            }
+
+            match node.kind {
+                BvhNodeKind::Leaf { start, end } => {
+                    for &tri in &self.triangle_indices[start..end] {
+                        let [a, b, c] = self.mesh.triangles[tri].map(|v| self.mesh.vertices[v]);
+                        let barycentric = tri_closest_point_barycentric(point, &a, &b, &c);
+                        let candidate = a * barycentric.x + b * barycentric.y + c * barycentric.z;
+                        let distance_sq = (candidate - point).norm_squared();
+                        if distance_sq < min_distance_sq {
+                            min_distance_sq = distance_sq;
+                            closest = Some(ClosestPoint {
+                                triangle: tri,
+                                point: candidate,
+                                barycentric,
+                                distance: R::zero(),
+                            });
+                        }
+                    }
+                }
+                BvhNodeKind::Inner { right_offset } => {
+                    // Visit the closer child first to shrink the search radius early
+                    let left = node_idx + 1;
+                    let right = node_idx + right_offset;
+                    if aabb_distance_sq(&self.nodes[left].aabb, point)
+                        <= aabb_distance_sq(&self.nodes[right].aabb, point)
+                    {
+                        stack.push(right);
+                        stack.push(left);
+                    } else {
+                        stack.push(left);
+                        stack.push(right);
+                    }
+                }
+            }
+        }
+
+        closest.map(|closest| ClosestPoint {
+            distance: min_distance_sq.sqrt(),
+            ..closest
+        })
+    }
+
+    /// Computes the generalized winding number of the mesh at the given point
+    ///
+    /// For closed meshes with outward facing triangles, the winding number is one inside and zero
+    /// outside of the mesh. For meshes with holes, it varies smoothly between these values, see
+    /// Jacobson et al. (2013), "Robust Inside-Outside Segmentation using Generalized Winding Numbers".
+    /// The contribution of triangles far away from the point is approximated by the dipole
+    /// expansion of Barill et al. (2018), "Fast Winding Numbers for Soups and Clouds".
+    pub fn winding_number(&self, point: &Vector3<R>) -> R {
+        let far_field_ratio = R::from_f64(WINDING_NUMBER_FAR_FIELD_RATIO).unwrap();
+
+        let mut solid_angle = R::zero();
+        let mut stack = self.root_stack();
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            let d = node.center - point;
+            let distance = d.norm();
+            if distance > far_field_ratio * node.radius {
+                solid_angle += node.area_normal.dot(&d) / (distance * distance * distance);
+                continue;
+            }
+
+            match node.kind {
+                BvhNodeKind::Leaf { start, end } => {
+                    for &tri in &self.triangle_indices[start..end] {
+                        let [a, b, c] = self.mesh.triangles[tri].map(|v| self.mesh.vertices[v]);
+                        solid_angle += tri_solid_angle(&(a - point), &(b - point), &(c - point));
+                    }
+                }
+                BvhNodeKind::Inner { right_offset } => {
+                    stack.push(node_idx + right_offset);
+                    stack.push(node_idx + 1);
+                }
+            }
+        }
+
+        solid_angle / (R::two_pi() + R::two_pi())
+    }
+
+    /// Returns whether the given point is inside of the mesh, i.e. whether its winding number is larger than one half
+    pub fn is_inside(&self, point: &Vector3<R>) -> bool {
+        self.winding_number(point) > R::from_f64(0.5).unwrap()
+    }
+
+    /// Returns a traversal stack containing the root node if the tree is not empty
+    fn root_stack(&self) -> Vec<usize> {
+        let mut stack = Vec::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        stack
+    }
+}
+
+/// Recursively builds the nodes of the subtree containing the given triangles in depth-first order
+fn build_subtree<R: Real>(
+    mesh: &TriMesh3d<R>,
+    centroids: &[Vector3<R>],
+    triangle_indices: &mut [usize],
+    start: usize,
+    parallel: bool,
+) -> Vec<BvhNode<R>> {
+    let n = triangle_indices.len();
+    if n <= MAX_LEAF_SIZE {
+        let kind = BvhNodeKind::Leaf {
+            start,
+            end: start + n,
+        };
+        return vec![build_node(mesh, triangle_indices, kind)];
+    }
+
+    // Split at the median centroid along the axis with the largest extent of the centroids
+    let mut centroid_aabb = Aabb3d::from_point(centroids[triangle_indices[0]]);
+    for &tri in triangle_indices.iter() {
+        centroid_aabb.join_with_point(&centroids[tri]);
+    }
+    let axis = centroid_aabb.extents().imax();
+    let mid = n / 2;
+    triangle_indices.select_nth_unstable_by(mid, |&a, &b| {
+        centroids[a][axis]
+            .partial_cmp(&centroids[b][axis])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let inner = build_node(
+        mesh,
+        triangle_indices,
+        BvhNodeKind::Inner { right_offset: 0 },
+    );
+    let (left_indices, right_indices) = triangle_indices.split_at_mut(mid);
+    let (left, right) = if parallel && n >= PARALLEL_BUILD_THRESHOLD {
+        rayon::join(
+            || build_subtree(mesh, centroids, left_indices, start, parallel),
+            || build_subtree(mesh, centroids, right_indices, start + mid, parallel),
+        )
+    } else {
+        (
+            build_subtree(mesh, centroids, left_indices, start, parallel),
+            build_subtree(mesh, centroids, right_indices, start + mid, parallel),
+        )
+    };
+
+    let mut nodes = Vec::with_capacity(1 + left.len() + right.len());
+    nodes.push(BvhNode {
+        kind: BvhNodeKind::Inner {
+            right_offset: 1 + left.len(),
+        },
+        ..inner
+    });
+    nodes.extend(left);
+    nodes.extend(right);
+    nodes
+}
+
+/// Computes the bounding box and the winding number data of a node containing the given triangles
+fn build_node<R: Real>(
+    mesh: &TriMesh3d<R>,
+    triangle_indices: &[usize],
+    kind: BvhNodeKind,
+) -> BvhNode<R> {
+    let half = R::from_f64(0.5).unwrap();
+    let three = R::from_f64(3.0).unwrap();
+
+    let mut aabb = Aabb3d::from_point(mesh.vertices[mesh.triangles[triangle_indices[0]][0]]);
+    let mut area_normal = Vector3::zeros();
+    let mut weighted_centroid = Vector3::zeros();
+    let mut area = R::zero();
+    for &tri in triangle_indices {
+        let [a, b, c] = mesh.triangles[tri].map(|v| mesh.vertices[v]);
+        aabb.join_with_point(&a);
+        aabb.join_with_point(&b);
+        aabb.join_with_point(&c);
+
+        let normal = (b - a).cross(&(c - a)) * half;
+        let tri_area = normal.norm();
+        area_normal += normal;
+        weighted_centroid += (a + b + c) * (tri_area / three);
+        area += tri_area;
+    }
+
+    let center = if area > R::zero() {
+        weighted_centroid / area
+    } else {
+        aabb.centroid()
+    };
+    let radius = triangle_indices
+        .iter()
+        .flat_map(|&tri| mesh.triangles[tri])
+        .map(|v| (mesh.vertices[v] - center).norm())
+        .fold(R::zero(), |max, d| max.max(d));
+
+    BvhNode {
+        aabb,
+        area_normal,
+        center,
+        radius,
+        kind,
+    }
+}
+
+/// Returns whether the ray intersects the bounding box for ray parameters in the range `[0, max_t]` (slab test)
+fn ray_intersects_aabb<R: Real>(
+    aabb: &Aabb3d<R>,
+    origin: &Vector3<R>,
+    inv_direction: &Vector3<R>,
+    max_t: R,
+) -> bool {
+    let mut t_min = R::zero();
+    let mut t_max = max_t;
+    for i in 0..3 {
+        let t1 = (aabb.min()[i] - origin[i]) * inv_direction[i];
+        let t2 = (aabb.max()[i] - origin[i]) * inv_direction[i];
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+    }
+    t_min <= t_max
+}
+
+/// Computes the ray parameter and barycentric coordinates of the intersection of a ray with the triangle `abc`
+///
+/// Based on Möller and Trumbore (1997), "Fast, Minimum Storage Ray/Triangle Intersection".
+fn ray_triangle_intersection<R: Real>(
+    origin: &Vector3<R>,
+    direction: &Vector3<R>,
+    a: &Vector3<R>,
+    b: &Vector3<R>,
+    c: &Vector3<R>,
+) -> Option<(R, Vector3<R>)> {
+    let e1 = b - a;
+    let e2 = c - a;
+    let p = direction.cross(&e2);
+    let det = e1.dot(&p);
+    if det == R::zero() {
+        return None;
+    }
+
+    let inv_det = R::one() / det;
+    let s = origin - a;
+    let u = s.dot(&p) * inv_det;
+    if u < R::zero() || u > R::one() {
+        return None;
+    }
+
+    let q = s.cross(&e1);
+    let v = direction.dot(&q) * inv_det;
+    if v < R::zero() || u + v > R::one() {
+        return None;
+    }
+
+    let t = e2.dot(&q) * inv_det;
+    Some((t, Vector3::new(R::one() - u - v, u, v)))
+}
+
+/// Returns the squared distance between a point and a bounding box (zero if the point is inside of the box)
+fn aabb_distance_sq<R: Real>(aabb: &Aabb3d<R>, point: &Vector3<R>) -> R {
+    (0..3)
+        .map(|i| {
+            let d = (aabb.min()[i] - point[i])
+                .max(point[i] - aabb.max()[i])
+                .max(R::zero());
+            d * d
+        })
+        .fold(R::zero(), |sum, d| sum + d)
+}
+
+/// Computes the signed solid angle of the triangle with the given vertices relative to the query point
+///
+/// Based on Van Oosterom and Strackee (1983), "The Solid Angle of a Plane Triangle".
+fn tri_solid_angle<R: Real>(a: &Vector3<R>, b: &Vector3<R>, c: &Vector3<R>) -> R {
+    let (la, lb, lc) = (a.norm(), b.norm(), c.norm());
+    let numerator = a.dot(&b.cross(c));
+    let denominator = la * lb * lc + a.dot(b) * lc + a.dot(c) * lb + b.dot(c) * la;
+    numerator.atan2(denominator) * R::from_f64(2.0).unwrap()
+}
@@ -0,0 +1,270 @@
+//! Functions for computing discrete curvature quantities of triangle meshes
+//!
+//! The curvature is evaluated per vertex on the one-rings of a [`HalfEdgeTriMesh`] using the discrete
+//! operators of Meyer et al. (2003), "Discrete Differential-Geometry Operators for Triangulated 2-Manifolds":
+//! the mean curvature is obtained from the cotangent Laplacian and the Gaussian curvature from the angle
+//! deficit, both normalized by the mixed Voronoi area of the vertex. The principal directions are
+//! obtained from a least squares fit of the second fundamental form to the normal curvatures along
+//! the edges of the one-ring as proposed in the same paper.
+
+use crate::halfedge_mesh::HalfEdgeTriMesh;
+use crate::mesh::{MeshAttribute, TriMesh3d};
+use crate::{profile, Real};
+use nalgebra::{Matrix3, Vector3};
+use rayon::prelude::*;
+
+/// Discrete curvature quantities at every vertex of a triangle mesh, see [`par_curvature`]
+///
+/// All curvatures are positive in convex regions of meshes with outward facing triangles (i.e. a
+/// sphere of radius `r` has a mean curvature of `1/r`). Boundary vertices and vertices without
+/// incident triangles have zero curvature and zero principal directions.
+#[derive(Clone, Debug, Default)]
+pub struct MeshCurvature<R: Real> {
+    /// Mean curvature of every vertex
+    pub mean_curvature: Vec<R>,
+    /// Gaussian curvature of every vertex
+    pub gaussian_curvature: Vec<R>,
+    /// Maximum principal curvature of every vertex
+    pub max_principal_curvature: Vec<R>,
+    /// Minimum principal curvature of every vertex
+    pub min_principal_curvature: Vec<R>,
+    /// Unit tangent vector in the direction of the maximum principal curvature of every vertex
+    pub max_principal_direction: Vec<Vector3<R>>,
+    /// Unit tangent vector in the direction of the minimum principal curvature of every vertex
+    pub min_principal_direction: Vec<Vector3<R>>,
+    /// Mixed Voronoi area of every vertex used to normalize the curvatures, e.g. to integrate curvature quantities over the surface
+    pub vertex_area: Vec<R>,
+}
+
+impl<R: Real> MeshCurvature<R> {
+    /// Converts the curvature quantities (except for the vertex areas) into named point attributes
+    ///
+    /// The attributes are named `mean_curvature`, `gaussian_curvature`, `max_principal_curvature`,
+    /// `min_principal_curvature`, `max_principal_direction` and `min_principal_direction`.
+    pub fn into_attributes(self) -> Vec<MeshAttribute<R>> {
+        vec![
+            MeshAttribute::new_real_scalar("mean_curvature", self.mean_curvature),
+            MeshAttribute::new_real_scalar("gaussian_curvature", self.gaussian_curvature),
+            MeshAttribute::new_real_scalar("max_principal_curvature", self.max_principal_curvature),
+            MeshAttribute::new_real_scalar("min_principal_curvature", self.min_principal_curvature),
+            MeshAttribute::new_real_vector3(
+                "max_principal_direction",
+                self.max_principal_direction,
+            ),
+            MeshAttribute::new_real_vector3(
+                "min_principal_direction",
+                self.min_principal_direction,
+            ),
+        ]
+    }
+
+    fn from_vertex_curvatures(vertex_curvatures: Vec<VertexCurvature<R>>) -> Self {
+        let n = vertex_curvatures.len();
+        let mut curvature = Self {
+            mean_curvature: Vec::with_capacity(n),
+            gaussian_curvature: Vec::with_capacity(n),
+            max_principal_curvature: Vec::with_capacity(n),
+            min_principal_curvature: Vec::with_capacity(n),
+            max_principal_direction: Vec::with_capacity(n),
+            min_principal_direction: Vec::with_capacity(n),
+            vertex_area: Vec::with_capacity(n),
+        };
+
+        for c in vertex_curvatures {
+            curvature.mean_curvature.push(c.mean);
+            curvature.gaussian_curvature.push(c.gaussian);
+            curvature.max_principal_curvature.push(c.max_principal);
+            curvature.min_principal_curvature.push(c.min_principal);
+            curvature.max_principal_direction.push(c.max_direction);
+            curvature.min_principal_direction.push(c.min_direction);
+            curvature.vertex_area.push(c.area);
+        }
+
+        curvature
+    }
+}
+
+/// Curvature quantities of a single vertex
+#[derive(Copy, Clone, Debug)]
+struct VertexCurvature<R: Real> {
+    mean: R,
+    gaussian: R,
+    max_principal: R,
+    min_principal: R,
+    max_direction: Vector3<R>,
+    min_direction: Vector3<R>,
+    area: R,
+}
+
+impl<R: Real> Default for VertexCurvature<R> {
+    fn default() -> Self {
+        Self {
+            mean: R::zero(),
+            gaussian: R::zero(),
+            max_principal: R::zero(),
+            min_principal: R::zero(),
+            max_direction: Vector3::zeros(),
+            min_direction: Vector3::zeros(),
+            area: R::zero(),
+        }
+    }
+}
+
+/// Computes the mean and Gaussian curvature as well as the principal curvatures and directions of all vertices of the mesh (parallelized version)
+///
+/// The mesh has to be manifold with consistently oriented triangles, otherwise its conversion into a
+/// [`HalfEdgeTriMesh`] is not meaningful (see [`TriMesh3d::repair_non_manifold`]).
+pub fn par_curvature<R: Real>(mesh: &TriMesh3d<R>) -> MeshCurvature<R> {
+    profile!("par_curvature");
+    let he_mesh = HalfEdgeTriMesh::from(mesh.clone());
+    let vertex_curvatures = (0..he_mesh.vertices.len())
+        .into_par_iter()
+        .map(|v| vertex_curvature(&he_mesh, v))
+        .collect();
+    MeshCurvature::from_vertex_curvatures(vertex_curvatures)
+}
+
+/// Computes the mean and Gaussian curvature as well as the principal curvatures and directions of all vertices of the mesh
+///
+/// The mesh has to be manifold with consistently oriented triangles, otherwise its conversion into a
+/// [`HalfEdgeTriMesh`] is not meaningful (see [`TriMesh3d::repair_non_manifold`]).
+pub fn curvature<R: Real>(mesh: &TriMesh3d<R>) -> MeshCurvature<R> {
+    profile!("curvature");
+    let he_mesh = HalfEdgeTriMesh::from(mesh.clone());
+    let vertex_curvatures = (0..he_mesh.vertices.len())
+        .map(|v| vertex_curvature(&he_mesh, v))
+        .collect();
+    MeshCurvature::from_vertex_curvatures(vertex_curvatures)
+}
+
+/// Cotangent of the angle between the two given vectors
+fn cot<R: Real>(a: &Vector3<R>, b: &Vector3<R>) -> R {
+    let sin = a.cross(b).norm();
+    if sin > R::zero() {
+        a.dot(b) / sin
+    } else {
+        R::zero()
+    }
+}
+
+/// Computes the curvature quantities of a single vertex from its one-ring
+fn vertex_curvature<R: Real>(mesh: &HalfEdgeTriMesh<R>, v: usize) -> VertexCurvature<R> {
+    let x_i = mesh.vertices[v];
+    let half_pi = R::frac_pi_2();
+
+    let mut area = R::zero();
+    let mut angle_sum = R::zero();
+    let mut area_normal = Vector3::zeros();
+    let mut laplacian = Vector3::zeros();
+    // Neighbor vertex and sum of the areas of the triangles adjacent to the edge to the neighbor
+    let mut edge_weights = Vec::with_capacity(mesh.vertex_one_ring_len(v));
+
+    for he in mesh.outgoing_half_edges(v) {
+        let x_j = mesh.vertices[he.to];
+        let mut cot_sum = R::zero();
+        let mut edge_area = R::zero();
+
+        // Triangle (i, j, k) to the left of the half-edge, every incident triangle is visited exactly once
+        match mesh.try_next(he) {
+            Some(next) => {
+                let x_k = mesh.vertices[next.to];
+                let (e_ij, e_ik) = (x_j - x_i, x_k - x_i);
+                let cross = e_ij.cross(&e_ik);
+                let tri_area = cross.norm() * R::from_f64(0.5).unwrap();
+
+                let angle_i = e_ij.angle(&e_ik);
+                let angle_j = (x_i - x_j).angle(&(x_k - x_j));
+                let angle_k = (x_i - x_k).angle(&(x_j - x_k));
+
+                // Mixed Voronoi area, falling back to fractions of the triangle area for obtuse triangles
+                area += if angle_i > half_pi {
+                    tri_area * R::from_f64(0.5).unwrap()
+                } else if angle_j > half_pi || angle_k > half_pi {
+                    tri_area * R::from_f64(0.25).unwrap()
+                } else {
+                    (e_ij.norm_squared() * cot(&(x_i - x_k), &(x_j - x_k))
+                        + e_ik.norm_squared() * cot(&(x_i - x_j), &(x_k - x_j)))
+                        * R::from_f64(0.125).unwrap()
+                };
+                angle_sum += angle_i;
+                area_normal += cross;
+
+                cot_sum += cot(&(x_i - x_k), &(x_j - x_k));
+                edge_area += tri_area;
+            }
+            None => return VertexCurvature::default(),
+        }
+
+        // Triangle (j, i, l) to the right of the half-edge
+        match mesh.try_next(mesh.opposite(he)) {
+            Some(next) => {
+                let x_l = mesh.vertices[next.to];
+                cot_sum += cot(&(x_i - x_l), &(x_j - x_l));
+                edge_area += (x_i - x_l).cross(&(x_j - x_l)).norm() * R::from_f64(0.5).unwrap();
+            }
+            None => return VertexCurvature::default(),
+        }
+
+        laplacian += (x_i - x_j) * cot_sum;
+        edge_weights.push((x_j, edge_area));
+    }
+
+    let normal = match area_normal.try_normalize(R::zero()) {
+        Some(normal) if area > R::zero() => normal,
+        _ => return VertexCurvature::default(),
+    };
+
+    // The cotangent Laplacian is `4 * A * H * n`
+    let mean = laplacian.dot(&normal) / (area * R::from_f64(4.0).unwrap());
+    let gaussian = (R::two_pi() - angle_sum) / area;
+    let discriminant = (mean * mean - gaussian).max(R::zero()).sqrt();
+
+    // Weighted least squares fit of the second fundamental form `[[a, b], [b, c]]` in a tangent
+    // frame to the normal curvatures in the directions of the one-ring edges
+    let two = R::from_f64(2.0).unwrap();
+    let e1 = tangent_vector(&normal);
+    let e2 = normal.cross(&e1);
+    let mut lhs = Matrix3::zeros();
+    let mut rhs = Vector3::zeros();
+    for (x_j, weight) in edge_weights {
+        let d = x_j - x_i;
+        let tangent = d - normal * normal.dot(&d);
+        if let Some(tangent) = tangent.try_normalize(R::zero()) {
+            let normal_curvature = normal.dot(&(-d)) * two / d.norm_squared();
+            let (u, v) = (tangent.dot(&e1), tangent.dot(&e2));
+            let row = Vector3::new(u * u, two * u * v, v * v);
+            lhs += row * row.transpose() * weight;
+            rhs += row * (normal_curvature * weight);
+        }
+    }
+
+    // The eigenvector of the second fundamental form with the larger eigenvalue is the direction of maximum curvature
+    let (max_direction, min_direction) = match lhs.lu().solve(&rhs) {
+        Some(form) => {
+            let (a, b, c) = (form[0], form[1], form[2]);
+            let theta = (b * two).atan2(a - c) / two;
+            let max_direction = e1 * theta.cos() + e2 * theta.sin();
+            (max_direction, normal.cross(&max_direction))
+        }
+        None => (Vector3::zeros(), Vector3::zeros()),
+    };
+
+    VertexCurvature {
+        mean,
+        gaussian,
+        max_principal: mean + discriminant,
+        min_principal: mean - discriminant,
+        max_direction,
+        min_direction,
+        area,
+    }
+}
+
+/// Returns an arbitrary unit vector orthogonal to the given unit normal
+fn tangent_vector<R: Real>(normal: &Vector3<R>) -> Vector3<R> {
+    // Use the coordinate axis that is the least aligned with the normal
+    let axis = normal.iamin();
+    let mut other = Vector3::zeros();
+    other[axis] = R::one();
+    normal.cross(&other).normalize()
+}
@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Context};
 use arrayvec::ArrayVec;
 use itertools::Itertools;
-use log::{info, trace};
+use log::{info, trace, warn};
 use nalgebra::Vector3;
 use num_integer::Integer;
 use num_traits::{FromPrimitive, NumCast};
@@ -11,23 +11,34 @@ use std::cell::RefCell;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use thread_local::ThreadLocal;
 
-use crate::density_map::sequential_compute_particle_densities_filtered;
-use crate::kernel::{CubicSplineKernel, SymmetricKernel3d};
+use crate::anisotropic_kernel::{
+    sequential_compute_anisotropic_kernels_filtered, AnisotropicKernel, AnisotropicKernelParameters,
+};
+use crate::boundary::BoundaryField;
+use crate::density_map::{
+    sequential_compute_particle_densities_filtered,
+    sequential_compute_particle_densities_polydisperse_filtered, DensityBlock, SparseDensityGrid,
+};
+use crate::kernel::{DynamicKernel, KernelType, SymmetricKernel3d};
 use crate::marching_cubes::marching_cubes_lut::marching_cubes_triangulation_iter;
 use crate::mesh::{HexMesh3d, TriMesh3d};
 use crate::neighborhood_search::{
     neighborhood_search_spatial_hashing_flat_filtered,
     neighborhood_search_spatial_hashing_parallel, FlatNeighborhoodList,
 };
-use crate::uniform_grid::{EdgeIndex, GridConstructionError, UniformCartesianCubeGrid3d};
+use crate::progress::{ReconstructionStage, SubdomainProgress};
+use crate::reconstruction::PerParticleData;
+use crate::surface_nets::{cell_vertex_offset, cells_around_edge, orient_quad, triangulate_quad};
+use crate::topology::Axis;
+use crate::uniform_grid::{
+    CellIndex, EdgeIndex, GridConstructionError, UniformCartesianCubeGrid3d,
+};
 use crate::{
-    new_map, new_parallel_map, profile, Aabb3d, MapType, Parameters, SpatialDecomposition,
-    SurfaceReconstruction,
+    new_map, new_parallel_map, profile, Aabb3d, MapType, OctreeDecompositionParameters, Parameters,
+    ReconstructionContext, SpatialDecomposition, SurfaceExtractionMethod, UniformGrid,
 };
 use crate::{Index, Real};
 
-// TODO: Implement single-threaded processing
-
 type GlobalIndex = u64;
 
 /// Converts any literal or expression to the Index type I (panics if value does not fit)
@@ -50,6 +61,9 @@ macro_rules! to_real {
     };
 }
 
+pub(crate) mod octree;
+pub(crate) mod streaming;
+
 pub(crate) struct ParametersSubdomainGrid<I: Index, R: Real> {
     /// SPH particle radius (in simulation units)
     #[allow(unused)]
@@ -58,8 +72,16 @@ pub(crate) struct ParametersSubdomainGrid<I: Index, R: Real> {
     particle_rest_mass: R,
     /// SPH kernel compact support radius (in simulation units)
     compact_support_radius: R,
+    /// SPH kernel function used for the density evaluation
+    kernel_type: KernelType,
+    /// Parameters for anisotropic kernels (if enabled)
+    anisotropic_kernel: Option<AnisotropicKernelParameters<R>>,
+    /// Largest distance from a particle to the boundary of its kernel support relative to its compact support radius
+    kernel_support_scale: R,
     /// Density value for the iso-surface
     surface_threshold: R,
+    /// Method used to extract the surface patches of the subdomains
+    extraction_method: SurfaceExtractionMethod,
     /// MC cube size (in simulation units)
     cube_size: R,
     /// Size of a subdomain in multiplies of MC cubes
@@ -68,15 +90,36 @@ pub(crate) struct ParametersSubdomainGrid<I: Index, R: Real> {
     ghost_particle_margin: R,
     /// Implicit global MC background grid (required to compute consistent float coordinates at domain boundaries)
     global_marching_cubes_grid: UniformCartesianCubeGrid3d<GlobalIndex, R>,
-    /// Implicit subdomain grid
+    /// Global MC background grid extended by one cell at its lower boundary (used instead of the global MC grid for the level-set grids of surface nets)
+    global_halo_grid: UniformCartesianCubeGrid3d<GlobalIndex, R>,
+    /// Implicit subdomain grid (consisting of the smallest subdomains if an octree is used)
     subdomain_grid: UniformCartesianCubeGrid3d<I, R>,
+    /// Parameters for the adaptive octree decomposition (if enabled)
+    octree_parameters: Option<OctreeDecompositionParameters>,
     /// Chunk size for chunked parallel processing
     chunk_size: usize,
+    /// Whether to process the particles and subdomains in parallel
+    enable_multi_threading: bool,
     /// Whether to return the global particle neighborhood list instead of only using per-domain lists internally
     global_neighborhood_list: bool,
 }
 
 impl<I: Index, R: Real> ParametersSubdomainGrid<I, R> {
+    /// Returns the margin for ghost particles required for a particle with the given compact support radius
+    fn ghost_particle_margin_for(&self, compact_support_radius: R) -> R {
+        ghost_particle_margin(
+            compact_support_radius * self.kernel_support_scale,
+            self.cube_size,
+            self.extraction_method,
+        )
+        .min(self.ghost_particle_margin)
+    }
+
+    /// Returns the size of a subdomain of the subdomain grid in multiples of MC cubes
+    pub(crate) fn subdomain_cubes(&self) -> I {
+        self.subdomain_cubes
+    }
+
     pub(crate) fn global_marching_cubes_grid(
         &self,
     ) -> Result<UniformCartesianCubeGrid3d<I, R>, GridConstructionError<I, R>> {
@@ -93,35 +136,157 @@ impl<I: Index, R: Real> ParametersSubdomainGrid<I, R> {
     }
 }
 
+/// Returns the number of cells that the level-set grid of a subdomain extends beyond its lower boundary
+fn levelset_halo_cubes(extraction_method: SurfaceExtractionMethod) -> usize {
+    match extraction_method {
+        SurfaceExtractionMethod::MarchingCubes => 0,
+        // The quads of edges on the lower boundary of a subdomain connect cells on both sides of the boundary
+        SurfaceExtractionMethod::SurfaceNets => 1,
+    }
+}
+
+/// Returns the margin for ghost particles required for kernels reaching up to the given distance from the particles
+fn ghost_particle_margin<R: Real>(
+    kernel_extent: R,
+    cube_size: R,
+    extraction_method: SurfaceExtractionMethod,
+) -> R {
+    // The level-set grids of the subdomains might extend beyond their lower boundary
+    let halo_cubes = to_real!(levelset_halo_cubes(extraction_method));
+    ((kernel_extent / cube_size).ceil() + halo_cubes) * cube_size * to_real!(1.01)
+}
+
 /// Result of the subdomain decomposition procedure
 pub(crate) struct Subdomains<I: Index> {
     // Flat subdomain coordinate indices (same order as the particle list)
     flat_subdomain_indices: Vec<I>,
+    // Number of cells of the subdomain grid covered by each subdomain along each axis (only larger than one for octree leaves)
+    subdomain_extents: Vec<I>,
     // Particles of each subdomain (including ghost particles)
     per_subdomain_particles: Vec<Vec<usize>>,
 }
 
-pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
+/// Returns the number of MC cubes per subdomain, the maximum kernel support scale and the thickness of the ghost particle margin
+///
+/// The ghost margin covers the support of the kernels of all particles that can contribute to the
+/// level-set of a subdomain. If it is thicker than half of the requested number of cubes per subdomain,
+/// the subdomains are enlarged accordingly, as the margin must not extend beyond the neighboring subdomains.
+pub(crate) fn subdomain_cubes_with_ghost_margin<R: Real>(
+    parameters: &Parameters<R>,
+    subdomain_cubes: u32,
+) -> Result<(u32, R, R), anyhow::Error> {
+    let cube_size = parameters.cube_size;
+
+    // Anisotropic kernels can reach further than the isotropic compact support radius
+    let kernel_support_scale = parameters
+        .anisotropic_kernel
+        .as_ref()
+        .map(|anisotropic_kernel| anisotropic_kernel.max_support_scale())
+        .unwrap_or_else(R::one);
+    let ghost_particle_margin = ghost_particle_margin(
+        parameters.compact_support_radius * kernel_support_scale,
+        cube_size,
+        parameters.extraction_method,
+    );
+
+    let ghost_margin_cubes = (ghost_particle_margin / cube_size)
+        .ceil()
+        .to_u32()
+        .context("ghost margin cube count has to fit in u32")?;
+    let min_subdomain_cubes = ghost_margin_cubes
+        .checked_mul(2)
+        .context("number of subdomain cubes for the ghost margin has to fit in u32")?;
+
+    Ok((
+        subdomain_cubes.max(min_subdomain_cubes),
+        kernel_support_scale,
+        ghost_particle_margin,
+    ))
+}
+
+/// Returns the number of MC cubes per dimension of the (smallest) subdomains and the octree parameters of the spatial decomposition
+fn subdomain_decomposition<R: Real>(
+    parameters: &Parameters<R>,
+) -> Result<(u32, Option<OctreeDecompositionParameters>), anyhow::Error> {
+    // With an octree, the subdomain grid consists of the subdomains at the finest octree level
+    match &parameters.spatial_decomposition {
+        Some(SpatialDecomposition::UniformGrid(grid_parameters)) => {
+            Ok((grid_parameters.subdomain_num_cubes_per_dim, None))
+        }
+        Some(SpatialDecomposition::Octree(octree_parameters)) => Ok((
+            octree_parameters.subdomain_num_cubes_per_dim,
+            Some(octree_parameters.clone()),
+        )),
+        None => Err(anyhow!(
+            "spatial decomposition parameters for subdomain grid are missing"
+        )),
+    }
+}
+
+/// Returns the number of subdomains and MC cells along each axis of the subdomain grid covering the given number of MC cells
+fn global_subdomain_grid_size(
+    cells_per_dim: &[GlobalIndex; 3],
+    subdomain_cubes: GlobalIndex,
+    octree_parameters: Option<&OctreeDecompositionParameters>,
+) -> Result<([GlobalIndex; 3], [GlobalIndex; 3]), anyhow::Error> {
+    // Compute the number of subdomains along each coordinate axis
+    let mut num_subdomains = [
+        int_ceil_div(cells_per_dim[0], subdomain_cubes),
+        int_ceil_div(cells_per_dim[1], subdomain_cubes),
+        int_ceil_div(cells_per_dim[2], subdomain_cubes),
+    ];
+
+    // The largest octree leaves have to be either completely inside or outside of the subdomain grid
+    if let Some(octree_parameters) = octree_parameters {
+        let max_leaf_subdomains = GlobalIndex::checked_pow(2, octree_parameters.max_depth)
+            .context("compute number of subdomains per largest octree leaf")?;
+        for n in num_subdomains.iter_mut() {
+            *n = int_ceil_div(*n, max_leaf_subdomains)
+                .checked_mul(max_leaf_subdomains)
+                .context("compute number of subdomains for octree")?;
+        }
+    }
+
+    let num_global_mc_cells = (|| -> Option<_> {
+        Some([
+            num_subdomains[0].checked_mul(subdomain_cubes)?,
+            num_subdomains[1].checked_mul(subdomain_cubes)?,
+            num_subdomains[2].checked_mul(subdomain_cubes)?,
+        ])
+    })()
+    .context("compute global number of marching cubes cells per dimension")?;
+
+    Ok((num_subdomains, num_global_mc_cells))
+}
+
+/// Returns the number of MC cells along each axis of the global background grid of the subdomain-grid reconstruction
+///
+/// The given background grid of the particles is extended to consist of whole subdomains (and whole
+/// octree leaves of the largest size), i.e. the reconstruction uses a larger grid.
+pub(crate) fn subdomain_grid_cells_per_dim<I: Index, R: Real>(
+    parameters: &Parameters<R>,
+    grid: &UniformGrid<I, R>,
+) -> Result<[GlobalIndex; 3], anyhow::Error> {
+    let (subdomain_cubes, octree_parameters) = subdomain_decomposition(parameters)?;
+    let (subdomain_cubes, _, _) = subdomain_cubes_with_ghost_margin(parameters, subdomain_cubes)?;
+    let cells_per_dim = grid
+        .cells_per_dim()
+        .map(|c| <GlobalIndex as NumCast>::from(c).unwrap());
+    let (_, num_global_mc_cells) = global_subdomain_grid_size(
+        &cells_per_dim,
+        <GlobalIndex as From<u32>>::from(subdomain_cubes),
+        octree_parameters.as_ref(),
+    )?;
+    Ok(num_global_mc_cells)
+}
+
+pub(crate) fn initialize_parameters<I: Index, R: Real>(
     parameters: &Parameters<R>,
-    _particles: &[Vector3<R>],
-    output_surface: &'a SurfaceReconstruction<I, R>,
+    grid: &UniformGrid<I, R>,
 ) -> Result<ParametersSubdomainGrid<I, R>, anyhow::Error> {
     let chunk_size = 500;
 
-    let Some(SpatialDecomposition::UniformGrid(grid_parameters)) =
-        &parameters.spatial_decomposition
-    else {
-        return Err(anyhow!(
-            "spatial decomposition parameters for uniform grid are missing"
-        ));
-    };
-
-    // A subdomain will be a cube consisting of this number of MC cubes along each coordinate axis
-    let subdomain_cubes_in = grid_parameters.subdomain_num_cubes_per_dim;
-    let subdomain_cubes = I::from_u32(subdomain_cubes_in)
-        .expect("number of subdomain cubes has to fit in index type");
-    let subdomain_cubes_global = GlobalIndex::from_u32(subdomain_cubes_in)
-        .expect("number of subdomain cubes has to fit in global index type");
+    let (subdomain_cubes_in, octree_parameters) = subdomain_decomposition(parameters)?;
 
     // Physical particle properties
     let particle_radius = parameters.particle_radius;
@@ -133,46 +298,52 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
     let particle_rest_volume = to_real!(4) * R::frac_pi_3() * particle_radius.powi(3);
     let particle_rest_mass = particle_rest_volume * particle_rest_density;
 
-    let ghost_particle_margin =
-        (compact_support_radius / cube_size).ceil() * cube_size * to_real!(1.01);
+    let (subdomain_cubes_in, kernel_support_scale, ghost_particle_margin) = {
+        let (subdomain_cubes, kernel_support_scale, ghost_particle_margin) =
+            subdomain_cubes_with_ghost_margin(parameters, subdomain_cubes_in)?;
+        if subdomain_cubes > subdomain_cubes_in {
+            warn!(
+                "The ghost margin is {:.2} cubes thick, while the subdomains only have an extent of {} cubes. The subdomains are enlarged to {} cubes per dimension.",
+                ghost_particle_margin / cube_size,
+                subdomain_cubes_in,
+                subdomain_cubes
+            );
+        }
+        (subdomain_cubes, kernel_support_scale, ghost_particle_margin)
+    };
+
+    // A subdomain will be a cube consisting of this number of MC cubes along each coordinate axis
+    let subdomain_cubes = I::from_u32(subdomain_cubes_in)
+        .context("number of subdomain cubes has to fit in index type")?;
+    let subdomain_cubes_global = GlobalIndex::from_u32(subdomain_cubes_in)
+        .context("number of subdomain cubes has to fit in global index type")?;
 
     // Compute information of ghost margin volume for debugging
     {
-        let ghost_margin_cubes = I::from((ghost_particle_margin / cube_size).ceil())
-            .expect("ghost margin cube count has to fit in index type");
+        let ghost_margin_cubes = (ghost_particle_margin / cube_size).ceil();
 
-        let vol_subdomain = subdomain_cubes
-            .checked_cubed()
-            .expect("number of cubes per subdomain has to be representable in index type");
-        let vol_margin = (ghost_margin_cubes * to_index!(2) + subdomain_cubes)
-            .checked_cubed()
-            .expect(
-                "number of cubes per subdomain with margin has to be representable in index type",
-            )
+        let vol_subdomain = subdomain_cubes.to_real_unchecked::<R>().powi(3);
+        let vol_margin = (ghost_margin_cubes * to_real!(2) + subdomain_cubes.to_real_unchecked())
+            .powi(3)
             - vol_subdomain;
 
         info!(
             "The ghost margin volume is {:.2}% of the subdomain volume",
-            (to_real!(vol_margin) / to_real!(vol_subdomain)) * to_real!(100.0)
+            (vol_margin / vol_subdomain) * to_real!(100.0)
         );
         info!(
             "The ghost margin is {:.2} MC cells or {:.2} subdomains thick",
             ghost_particle_margin / cube_size,
             ghost_particle_margin / (cube_size * subdomain_cubes.to_real_unchecked())
         );
-
-        if ghost_margin_cubes > subdomain_cubes / to_index!(2) {
-            panic!("The ghost margin is {ghost_margin_cubes} cubes thick (rounded up), while the subdomain only has an extent of {subdomain_cubes} cubes. The subdomain has to have at least twice the number of cubes ({})!", ghost_margin_cubes.times(2));
-        }
     }
 
     // AABB of the particles
-    let aabb = output_surface.grid.aabb();
+    let aabb = grid.aabb();
 
     let global_mc_grid = UniformCartesianCubeGrid3d::<GlobalIndex, R>::new(
         aabb.min(),
-        &output_surface
-            .grid
+        &grid
             .cells_per_dim()
             .map(|c| <GlobalIndex as NumCast>::from(c).unwrap()),
         cube_size,
@@ -180,23 +351,11 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
     .context("construct initial global marching cubes cell grid")?;
     trace!("Initial global MC Grid: {:?}", global_mc_grid);
 
-    // MC cubes along each coordinate axis of the entire global MC background grid
-    let cells_per_dim = global_mc_grid.cells_per_dim();
-    // Compute the number of subdomains along each coordinate axis
-    let num_subdomains = [
-        int_ceil_div(cells_per_dim[0], subdomain_cubes_global),
-        int_ceil_div(cells_per_dim[1], subdomain_cubes_global),
-        int_ceil_div(cells_per_dim[2], subdomain_cubes_global),
-    ];
-
-    let num_global_mc_cells = (|| -> Option<_> {
-        Some([
-            num_subdomains[0].checked_mul(subdomain_cubes_global)?,
-            num_subdomains[1].checked_mul(subdomain_cubes_global)?,
-            num_subdomains[2].checked_mul(subdomain_cubes_global)?,
-        ])
-    })()
-    .context("compute global number of marching cubes cells per dimension")?;
+    let (num_subdomains, num_global_mc_cells) = global_subdomain_grid_size(
+        global_mc_grid.cells_per_dim(),
+        subdomain_cubes_global,
+        octree_parameters.as_ref(),
+    )?;
 
     let global_mc_grid = UniformCartesianCubeGrid3d::<GlobalIndex, R>::new(
         &global_mc_grid.aabb().min(),
@@ -206,6 +365,13 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
     .context("construct final global marching cubes cell grid")?;
     trace!("Global MC Grid: {:?}", global_mc_grid);
 
+    let global_halo_grid = UniformCartesianCubeGrid3d::<GlobalIndex, R>::new(
+        &(global_mc_grid.aabb().min() - Vector3::repeat(cube_size)),
+        &num_global_mc_cells.map(|n| n + 1),
+        cube_size,
+    )
+    .context("construct global marching cubes cell grid with halo")?;
+
     // Convert number of subdomains back to local index type
     let num_subdomains = (|| -> Option<_> {
         Some([
@@ -250,13 +416,20 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
         particle_radius,
         particle_rest_mass,
         compact_support_radius,
+        kernel_type: parameters.kernel_type,
+        anisotropic_kernel: parameters.anisotropic_kernel.clone(),
+        kernel_support_scale,
         surface_threshold,
+        extraction_method: parameters.extraction_method,
         cube_size,
         subdomain_cubes,
         ghost_particle_margin,
         global_marching_cubes_grid: global_mc_grid,
+        global_halo_grid,
         subdomain_grid,
+        octree_parameters,
         chunk_size,
+        enable_multi_threading: parameters.enable_multi_threading,
         global_neighborhood_list: parameters.global_neighborhood_list,
     })
 }
@@ -373,35 +546,57 @@ pub(crate) fn decomposition<
 >(
     parameters: &ParametersSubdomainGrid<I, R>,
     particles: &[Vector3<R>],
+    particle_data: Option<&PerParticleData<R>>,
 ) -> Result<Subdomains<I>, anyhow::Error> {
     profile!("decomposition");
     info!("Starting classification of particles into subdomains.");
 
+    // With per-particle compact support radii, every particle only needs the ghost margin of its own support
+    let ghost_particle_margin = |particle_idx: usize| -> R {
+        match particle_data {
+            Some(particle_data) => parameters
+                .ghost_particle_margin_for(particle_data.compact_support_radii[particle_idx]),
+            None => parameters.ghost_particle_margin,
+        }
+    };
+
     // Count the number of particles and ghost particles per subdomain (with thread local counters)
     let per_subdomain_counter_tls = ThreadLocal::<RefCell<MapType<I, usize>>>::new();
     {
         profile!("classifying particles");
 
-        particles
-            .par_chunks(parameters.chunk_size)
-            .for_each(|particle_chunk| {
-                let mut per_subdomain_counter = per_subdomain_counter_tls
-                    .get_or(|| RefCell::new(new_map()))
-                    .borrow_mut();
-                let mut classifier = C::new();
-
-                for particle in particle_chunk.iter() {
-                    classifier.classify_particle(
-                        particle,
-                        &parameters.subdomain_grid,
-                        parameters.ghost_particle_margin,
-                    );
-                    for i in 0..classifier.len() {
-                        let flat_subdomain_idx = classifier.get(i);
-                        *per_subdomain_counter.entry(flat_subdomain_idx).or_insert(0) += 1;
-                    }
+        let count_chunk = |(chunk_idx, particle_chunk): (usize, &[Vector3<R>])| {
+            let chunk_offset = chunk_idx * parameters.chunk_size;
+            let mut per_subdomain_counter = per_subdomain_counter_tls
+                .get_or(|| RefCell::new(new_map()))
+                .borrow_mut();
+            let mut classifier = C::new();
+
+            for (particle_idx, particle) in particle_chunk.iter().enumerate() {
+                let particle_idx = chunk_offset + particle_idx;
+                classifier.classify_particle(
+                    particle,
+                    &parameters.subdomain_grid,
+                    ghost_particle_margin(particle_idx),
+                );
+                for i in 0..classifier.len() {
+                    let flat_subdomain_idx = classifier.get(i);
+                    *per_subdomain_counter.entry(flat_subdomain_idx).or_insert(0) += 1;
                 }
-            });
+            }
+        };
+
+        if parameters.enable_multi_threading {
+            particles
+                .par_chunks(parameters.chunk_size)
+                .enumerate()
+                .for_each(count_chunk);
+        } else {
+            particles
+                .chunks(parameters.chunk_size)
+                .enumerate()
+                .for_each(count_chunk);
+        }
     }
 
     // Merge all thread local subdomain particle counters
@@ -414,15 +609,23 @@ pub(crate) fn decomposition<
             .map(RefCell::into_inner)
             .collect::<Vec<_>>();
 
-        per_subdomain_counter_tls
-            .into_par_iter()
-            .for_each(|per_cell_counter| {
-                for (flat_cell_index, count) in per_cell_counter {
-                    *global_per_subdomain_counter
-                        .entry(flat_cell_index)
-                        .or_insert(0) += count;
-                }
-            });
+        let merge_counter = |per_cell_counter: MapType<I, usize>| {
+            for (flat_cell_index, count) in per_cell_counter {
+                *global_per_subdomain_counter
+                    .entry(flat_cell_index)
+                    .or_insert(0) += count;
+            }
+        };
+
+        if parameters.enable_multi_threading {
+            per_subdomain_counter_tls
+                .into_par_iter()
+                .for_each(merge_counter);
+        } else {
+            per_subdomain_counter_tls
+                .into_iter()
+                .for_each(merge_counter);
+        }
     }
 
     // Mapping from flat subdomain coordinate index to offset into contiguous subdomain storage
@@ -452,39 +655,47 @@ pub(crate) fn decomposition<
     {
         profile!("copying particles to subdomains");
 
-        particles
-            .par_chunks(parameters.chunk_size)
-            .enumerate()
-            .for_each(|(chunk_idx, particle_chunk)| {
-                let chunk_offset = chunk_idx * parameters.chunk_size;
-                let mut classifier = C::new();
-                for (particle_idx, particle) in particle_chunk.iter().enumerate() {
-                    let particle_idx = chunk_offset + particle_idx;
-                    classifier.classify_particle(
-                        particle,
-                        &parameters.subdomain_grid,
-                        parameters.ghost_particle_margin,
-                    );
-                    for i in 0..classifier.len() {
-                        let flat_subdomain_idx = classifier.get(i);
-
-                        let compressed_subdomain_idx =
-                            subdomain_compressed_indices[&flat_subdomain_idx];
-
-                        // Lock the subdomain for writing
-                        let mut subdomain_particles =
-                            per_subdomain_particles[compressed_subdomain_idx].lock();
-                        // Reserve full size of subdomain if it's still empty
-                        if subdomain_particles.is_empty() {
-                            let particle_count =
-                                per_subdomain_particle_count[compressed_subdomain_idx];
-                            subdomain_particles.reserve(particle_count);
-                        }
-                        // Add the particle to the subdomain
-                        subdomain_particles.push(particle_idx);
+        let copy_chunk = |(chunk_idx, particle_chunk): (usize, &[Vector3<R>])| {
+            let chunk_offset = chunk_idx * parameters.chunk_size;
+            let mut classifier = C::new();
+            for (particle_idx, particle) in particle_chunk.iter().enumerate() {
+                let particle_idx = chunk_offset + particle_idx;
+                classifier.classify_particle(
+                    particle,
+                    &parameters.subdomain_grid,
+                    ghost_particle_margin(particle_idx),
+                );
+                for i in 0..classifier.len() {
+                    let flat_subdomain_idx = classifier.get(i);
+
+                    let compressed_subdomain_idx =
+                        subdomain_compressed_indices[&flat_subdomain_idx];
+
+                    // Lock the subdomain for writing
+                    let mut subdomain_particles =
+                        per_subdomain_particles[compressed_subdomain_idx].lock();
+                    // Reserve full size of subdomain if it's still empty
+                    if subdomain_particles.is_empty() {
+                        let particle_count = per_subdomain_particle_count[compressed_subdomain_idx];
+                        subdomain_particles.reserve(particle_count);
                     }
+                    // Add the particle to the subdomain
+                    subdomain_particles.push(particle_idx);
                 }
-            });
+            }
+        };
+
+        if parameters.enable_multi_threading {
+            particles
+                .par_chunks(parameters.chunk_size)
+                .enumerate()
+                .for_each(copy_chunk);
+        } else {
+            particles
+                .chunks(parameters.chunk_size)
+                .enumerate()
+                .for_each(copy_chunk);
+        }
     }
 
     // Remove mutexes
@@ -497,17 +708,24 @@ pub(crate) fn decomposition<
     // will be in the same order
     {
         profile!("sort subdomain particles");
-        per_subdomain_particles
-            .par_iter_mut()
-            .for_each(|particles| {
-                //use rand::prelude::SliceRandom;
-                //let mut rng = rand::thread_rng();
-                //particles.shuffle(&mut rng)
-                particles.sort_unstable();
-            });
+        if parameters.enable_multi_threading {
+            per_subdomain_particles
+                .par_iter_mut()
+                .for_each(|particles| {
+                    //use rand::prelude::SliceRandom;
+                    //let mut rng = rand::thread_rng();
+                    //particles.shuffle(&mut rng)
+                    particles.sort_unstable();
+                });
+        } else {
+            per_subdomain_particles
+                .iter_mut()
+                .for_each(|particles| particles.sort_unstable());
+        }
     }
 
     Ok(Subdomains {
+        subdomain_extents: vec![I::one(); flat_subdomain_indices.len()],
         flat_subdomain_indices,
         per_subdomain_particles,
     })
@@ -516,13 +734,24 @@ pub(crate) fn decomposition<
 pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
     parameters: &ParametersSubdomainGrid<I, R>,
     global_particles: &[Vector3<R>],
+    global_particle_data: Option<&PerParticleData<R>>,
     subdomains: &Subdomains<I>,
-) -> (Vec<R>, Vec<Vec<usize>>) {
+    context: &ReconstructionContext,
+) -> (Vec<R>, Vec<Vec<usize>>, Option<Vec<AnisotropicKernel<R>>>) {
     profile!(parent, "compute_global_density_vector");
     info!("Starting computation of global density vector.");
 
     let global_particle_densities = Mutex::new(vec![R::zero(); global_particles.len()]);
     let global_neighbors = Mutex::new(vec![Vec::new(); global_particles.len()]);
+    let global_anisotropic_kernels = parameters.anisotropic_kernel.as_ref().map(|_| {
+        Mutex::new(vec![
+            AnisotropicKernel::isotropic(
+                Vector3::zeros(),
+                parameters.compact_support_radius
+            );
+            global_particles.len()
+        ])
+    });
 
     #[derive(Default)]
     struct SubdomainWorkspace<R: Real> {
@@ -532,134 +761,225 @@ pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
         neighborhood_lists: FlatNeighborhoodList,
         // Per particle density values of this subdomain
         particle_densities: Vec<R>,
+        // Per particle masses of this subdomain (only used with per-particle properties)
+        particle_masses: Vec<R>,
+        // Per particle compact support radii of this subdomain (only used with per-particle properties)
+        particle_compact_support_radii: Vec<R>,
+        // Per particle anisotropic kernels of this subdomain (only used with anisotropic kernels)
+        anisotropic_kernels: Vec<AnisotropicKernel<R>>,
         // Per particle flag whether the particle is in the interior of this subdomain (non-ghost particle)
         is_inside: Vec<bool>,
     }
 
     let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<R>>>::new();
+    let progress = SubdomainProgress::new(
+        context,
+        ReconstructionStage::ParticleDensities,
+        subdomains.flat_subdomain_indices.len(),
+    );
 
-    subdomains
-        .flat_subdomain_indices
-        .par_iter()
-        .copied()
-        .zip(subdomains.per_subdomain_particles.par_iter())
-        .for_each(|(flat_subdomain_idx, subdomain_particle_indices)| {
-            profile!("subdomain density computation", parent = parent);
+    let compute_subdomain = |(flat_subdomain_idx, subdomain_particle_indices): (I, &Vec<usize>)| {
+        // Skip the remaining subdomains, the caller has to check for cancellation
+        if context.is_cancelled() {
+            return;
+        }
 
-            // Obtain thread local workspace and clear it
-            let mut workspace = workspace_tls.get_or_default().borrow_mut();
+        profile!("subdomain density computation", parent = parent);
 
-            let SubdomainWorkspace {
-                subdomain_particles,
-                neighborhood_lists,
-                particle_densities,
-                is_inside,
-            } = &mut *workspace;
+        // Obtain thread local workspace and clear it
+        let mut workspace = workspace_tls.get_or_default().borrow_mut();
 
-            let flat_subdomain_idx: I = flat_subdomain_idx;
-            let subdomain_particle_indices: &[usize] = subdomain_particle_indices.as_slice();
+        let SubdomainWorkspace {
+            subdomain_particles,
+            neighborhood_lists,
+            particle_densities,
+            particle_masses,
+            particle_compact_support_radii,
+            anisotropic_kernels,
+            is_inside,
+        } = &mut *workspace;
 
-            // Collect all particle positions of this subdomain
-            {
-                profile!("collect subdomain data");
+        let flat_subdomain_idx: I = flat_subdomain_idx;
+        let subdomain_particle_indices: &[usize] = subdomain_particle_indices.as_slice();
+
+        // Collect all particle positions of this subdomain
+        {
+            profile!("collect subdomain data");
+            gather_subdomain_data(
+                global_particles,
+                subdomain_particle_indices,
+                subdomain_particles,
+            );
+            if let Some(global_particle_data) = global_particle_data {
                 gather_subdomain_data(
-                    global_particles,
+                    &global_particle_data.masses,
                     subdomain_particle_indices,
-                    subdomain_particles,
+                    particle_masses,
+                );
+                gather_subdomain_data(
+                    &global_particle_data.compact_support_radii,
+                    subdomain_particle_indices,
+                    particle_compact_support_radii,
                 );
             }
+        }
 
-            // Get the cell index and AABB of the subdomain
-            let subdomain_idx = parameters
-                .subdomain_grid
-                .try_unflatten_cell_index(flat_subdomain_idx)
-                .expect("Subdomain cell does not exist");
-            let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
+        // Get the cell index and AABB of the subdomain
+        let subdomain_idx = parameters
+            .subdomain_grid
+            .try_unflatten_cell_index(flat_subdomain_idx)
+            .expect("Subdomain cell does not exist");
+        let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
 
-            let margin_aabb = {
-                let mut margin_aabb = subdomain_aabb.clone();
-                // TODO: Verify if we can omit this extra margin?
-                margin_aabb.grow_uniformly(parameters.ghost_particle_margin * to_real!(1.5));
-                margin_aabb
-            };
+        let margin_aabb = {
+            let mut margin_aabb = subdomain_aabb.clone();
+            // TODO: Verify if we can omit this extra margin?
+            margin_aabb.grow_uniformly(parameters.ghost_particle_margin * to_real!(1.5));
+            margin_aabb
+        };
 
-            {
-                profile!("initialize particle filter");
-                is_inside.clear();
-                reserve_total(is_inside, subdomain_particle_indices.len());
-                is_inside.extend(
-                    subdomain_particles
-                        .iter()
-                        .map(|p| subdomain_aabb.contains_point(p)),
-                );
-            }
+        {
+            profile!("initialize particle filter");
+            is_inside.clear();
+            reserve_total(is_inside, subdomain_particle_indices.len());
+            is_inside.extend(
+                subdomain_particles
+                    .iter()
+                    .map(|p| subdomain_aabb.contains_point(p)),
+            );
+        }
+
+        neighborhood_search_spatial_hashing_flat_filtered::<I, R>(
+            &margin_aabb,
+            &subdomain_particles,
+            parameters.compact_support_radius,
+            neighborhood_lists,
+            |i| is_inside[i],
+        );
 
-            neighborhood_search_spatial_hashing_flat_filtered::<I, R>(
-                &margin_aabb,
+        if global_particle_data.is_some() {
+            sequential_compute_particle_densities_polydisperse_filtered::<I, R, _>(
                 &subdomain_particles,
-                parameters.compact_support_radius,
                 neighborhood_lists,
+                particle_masses,
+                particle_compact_support_radii,
+                parameters.kernel_type,
+                particle_densities,
                 |i| is_inside[i],
             );
-
+        } else {
             sequential_compute_particle_densities_filtered::<I, R, _>(
                 &subdomain_particles,
                 neighborhood_lists,
                 parameters.compact_support_radius,
+                parameters.kernel_type,
                 parameters.particle_rest_mass,
                 particle_densities,
                 |i| is_inside[i],
             );
+        }
 
-            // Write particle densities into global storage
-            {
-                profile!("update global density values");
-                // Lock global vector while this subdomain writes into it
-                let mut global_particle_densities = global_particle_densities.lock();
-                is_inside
-                    .iter()
-                    .copied()
-                    .zip(
-                        subdomain_particle_indices
-                            .iter()
-                            .copied()
-                            .zip(particle_densities.iter().copied()),
-                    )
-                    // Update density values only for particles inside of the subdomain (ghost particles have wrong values)
-                    .filter(|(is_inside, _)| *is_inside)
-                    .for_each(|(_, (particle_idx, density))| {
-                        global_particle_densities[particle_idx] = density;
-                    });
-            }
+        if let Some(anisotropic_kernel_parameters) = &parameters.anisotropic_kernel {
+            sequential_compute_anisotropic_kernels_filtered(
+                &subdomain_particles,
+                neighborhood_lists,
+                parameters.compact_support_radius,
+                global_particle_data.map(|_| particle_compact_support_radii.as_slice()),
+                anisotropic_kernel_parameters,
+                anisotropic_kernels,
+                |i| is_inside[i],
+            );
+        }
 
-            // Write particle neighbor lists into global storage
-            if parameters.global_neighborhood_list {
-                profile!("update global neighbor list");
-                // Lock global vector while this subdomain writes into it
-                let mut global_neighbors = global_neighbors.lock();
-                is_inside
-                    .iter()
-                    .copied()
-                    .zip(
-                        subdomain_particle_indices
-                            .iter()
-                            .copied()
-                            .zip(neighborhood_lists.iter()),
-                    )
-                    // Update density values only for particles inside of the subdomain (ghost particles have wrong values)
-                    .filter(|(is_inside, _)| *is_inside)
-                    .for_each(|(_, (particle_idx, neighbors))| {
-                        global_neighbors[particle_idx] = neighbors
-                            .iter()
-                            .copied()
-                            .map(|local| subdomain_particle_indices[local])
-                            .collect();
-                    });
-            }
-        });
+        // Write particle densities into global storage
+        {
+            profile!("update global density values");
+            // Lock global vector while this subdomain writes into it
+            let mut global_particle_densities = global_particle_densities.lock();
+            is_inside
+                .iter()
+                .copied()
+                .zip(
+                    subdomain_particle_indices
+                        .iter()
+                        .copied()
+                        .zip(particle_densities.iter().copied()),
+                )
+                // Update density values only for particles inside of the subdomain (ghost particles have wrong values)
+                .filter(|(is_inside, _)| *is_inside)
+                .for_each(|(_, (particle_idx, density))| {
+                    global_particle_densities[particle_idx] = density;
+                });
+        }
+
+        // Write anisotropic kernels into global storage
+        if let Some(global_anisotropic_kernels) = &global_anisotropic_kernels {
+            profile!("update global anisotropic kernels");
+            // Lock global vector while this subdomain writes into it
+            let mut global_anisotropic_kernels = global_anisotropic_kernels.lock();
+            is_inside
+                .iter()
+                .copied()
+                .zip(
+                    subdomain_particle_indices
+                        .iter()
+                        .copied()
+                        .zip(anisotropic_kernels.iter()),
+                )
+                // Update kernels only for particles inside of the subdomain (ghost particles have wrong values)
+                .filter(|(is_inside, _)| *is_inside)
+                .for_each(|(_, (particle_idx, kernel))| {
+                    global_anisotropic_kernels[particle_idx] = *kernel;
+                });
+        }
+
+        // Write particle neighbor lists into global storage
+        if parameters.global_neighborhood_list {
+            profile!("update global neighbor list");
+            // Lock global vector while this subdomain writes into it
+            let mut global_neighbors = global_neighbors.lock();
+            is_inside
+                .iter()
+                .copied()
+                .zip(
+                    subdomain_particle_indices
+                        .iter()
+                        .copied()
+                        .zip(neighborhood_lists.iter()),
+                )
+                // Update density values only for particles inside of the subdomain (ghost particles have wrong values)
+                .filter(|(is_inside, _)| *is_inside)
+                .for_each(|(_, (particle_idx, neighbors))| {
+                    global_neighbors[particle_idx] = neighbors
+                        .iter()
+                        .copied()
+                        .map(|local| subdomain_particle_indices[local])
+                        .collect();
+                });
+        }
+
+        progress.increment();
+    };
+
+    if parameters.enable_multi_threading {
+        subdomains
+            .flat_subdomain_indices
+            .par_iter()
+            .copied()
+            .zip(subdomains.per_subdomain_particles.par_iter())
+            .for_each(compute_subdomain);
+    } else {
+        subdomains
+            .flat_subdomain_indices
+            .iter()
+            .copied()
+            .zip(subdomains.per_subdomain_particles.iter())
+            .for_each(compute_subdomain);
+    }
 
     let global_particle_densities = global_particle_densities.into_inner();
     let global_neighbors = global_neighbors.into_inner();
+    let global_anisotropic_kernels = global_anisotropic_kernels.map(Mutex::into_inner);
 
     /*
     {
@@ -672,9 +992,14 @@ pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
     }
     */
 
-    (global_particle_densities, global_neighbors)
+    (
+        global_particle_densities,
+        global_neighbors,
+        global_anisotropic_kernels,
+    )
 }
 
+#[derive(Default)]
 pub(crate) struct SurfacePatch<I: Index, R: Real> {
     pub vertices: Vec<Vector3<R>>,
     pub triangles: Vec<[usize; 3]>,
@@ -685,37 +1010,195 @@ pub(crate) struct SurfacePatch<I: Index, R: Real> {
     pub exterior_vertex_edge_indices: Vec<(I, EdgeIndex<I>)>,
 }
 
+/// Triangulates the given cells of a subdomain using marching cubes and returns the resulting surface patch
+///
+/// The level-set values are stored per point of the `mc_grid` of the subdomain, points are inside
+/// of the surface if `is_inside` returns true for their value. The vertices are linearly
+/// interpolated at the iso-value on the edges between inside and outside points. Vertices on the
+/// boundary of the subdomain are marked as exterior and their edges are mapped to global edge
+/// indices with `globalize_edge` for the stitching of the patches.
+pub(crate) fn triangulate_subdomain_cells<I: Index, R: Real>(
+    mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    levelset_grid: &[R],
+    cells: impl Iterator<Item = CellIndex<I>>,
+    iso_value: R,
+    is_inside: impl Fn(R) -> bool,
+    globalize_edge: impl Fn(&EdgeIndex<I>) -> (I, EdgeIndex<I>),
+) -> SurfacePatch<I, R> {
+    profile!("mc triangulation loop");
+
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+
+    let mut vertex_inside_count = 0;
+    let mut triangle_inside_count = 0;
+
+    let mut vertex_inside_flags = Vec::new();
+    let mut triangle_inside_flags = Vec::new();
+
+    let mut exterior_vertex_edge_indices = Vec::new();
+
+    let mut edge_to_vertex = new_map();
+
+    for cell in cells {
+        let mut vertices_inside = [true; 8];
+        for local_point_index in 0..8 {
+            let point = cell.global_point_index_of(local_point_index).unwrap();
+            let flat_point_idx = mc_grid.flatten_point_index(&point);
+            let flat_point_idx = flat_point_idx.to_usize().unwrap();
+            // Update inside/outside surface flag
+            vertices_inside[local_point_index] = is_inside(levelset_grid[flat_point_idx]);
+        }
+
+        for triangle in marching_cubes_triangulation_iter(&vertices_inside) {
+            let mut global_triangle = [0; 3];
+            for (v_idx, local_edge_index) in triangle.iter().copied().enumerate() {
+                let edge = cell
+                    .global_edge_index_of(local_edge_index as usize)
+                    .unwrap();
+                let vertex_index = *edge_to_vertex.entry(edge).or_insert_with(|| {
+                    // TODO: Nonlinear interpolation
+
+                    let origin_coords = mc_grid.point_coordinates(edge.origin());
+                    let target_coords = mc_grid.point_coordinates(&edge.target());
+
+                    let flat_origin_idx = mc_grid
+                        .flatten_point_index(edge.origin())
+                        .to_usize()
+                        .unwrap();
+                    let flat_target_idx = mc_grid
+                        .flatten_point_index(&edge.target())
+                        .to_usize()
+                        .unwrap();
+
+                    let origin_value = levelset_grid[flat_origin_idx];
+                    let target_value = levelset_grid[flat_target_idx];
+
+                    let alpha = (iso_value - origin_value) / (target_value - origin_value);
+                    let interpolated_coords =
+                        origin_coords * (R::one() - alpha) + target_coords * alpha;
+
+                    vertices.push(interpolated_coords);
+                    let vertex_index = vertices.len() - 1;
+
+                    let is_interior_vertex = !mc_grid.is_boundary_edge(&edge);
+                    vertex_inside_count += is_interior_vertex as usize;
+                    vertex_inside_flags.push(is_interior_vertex);
+
+                    if !is_interior_vertex {
+                        exterior_vertex_edge_indices.push(globalize_edge(&edge));
+                    }
+
+                    vertex_index
+                });
+
+                global_triangle[v_idx] = vertex_index;
+            }
+
+            let all_tri_vertices_inside = global_triangle
+                .iter()
+                .copied()
+                .all(|v_idx| vertex_inside_flags[v_idx]);
+
+            triangles.push(global_triangle);
+            triangle_inside_count += all_tri_vertices_inside as usize;
+            triangle_inside_flags.push(all_tri_vertices_inside);
+        }
+    }
+
+    SurfacePatch {
+        vertices,
+        triangles,
+        vertex_inside_count,
+        triangle_inside_count,
+        vertex_inside_flags,
+        triangle_inside_flags,
+        exterior_vertex_edge_indices,
+    }
+}
+
 // TODO: Reduce code duplication between dense and sparse
 pub(crate) fn reconstruction<I: Index, R: Real>(
     parameters: &ParametersSubdomainGrid<I, R>,
     global_particles: &[Vector3<R>],
     global_particle_densities: &[R],
+    global_particle_data: Option<&PerParticleData<R>>,
+    global_anisotropic_kernels: Option<&[AnisotropicKernel<R>]>,
     subdomains: &Subdomains<I>,
+    boundary: Option<&BoundaryField<R>>,
+    density_grid: Option<&mut SparseDensityGrid<I, R>>,
+    context: &ReconstructionContext,
 ) -> Vec<SurfacePatch<I, R>> {
     profile!(parent, "reconstruction");
 
-    let squared_support = parameters.compact_support_radius * parameters.compact_support_radius;
-    // Add 1% so that we don't exclude grid points that are just on the kernel boundary
-    let squared_support_with_margin = squared_support * to_real!(1.01);
-    // Compute radial distance in terms of grid points we have to evaluate for each particle
-    let cube_radius = I::from((parameters.compact_support_radius / parameters.cube_size).ceil())
-        .expect("kernel radius in cubes has to fit in index type");
-    // Kernel
-    let kernel = CubicSplineKernel::new(parameters.compact_support_radius);
-    //let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, parameters.compact_support_radius);
+    // The level-set grids of the subdomains are only kept if a density grid is requested
+    let collect_density_blocks = density_grid.is_some();
+
+    /// Kernel related quantities of a particle required for the level-set evaluation
+    struct ParticleKernel<I: Index, R: Real> {
+        /// Mass of the particle
+        mass: R,
+        /// Kernel with the compact support radius of the particle
+        kernel: DynamicKernel<R>,
+        /// Radial distance in terms of grid points we have to evaluate for the particle
+        cube_radius: I,
+        /// Squared compact support radius of the particle with an additional margin
+        squared_support_with_margin: R,
+    }
 
-    let mc_total_cells = parameters.subdomain_cubes.cubed();
-    let mc_total_points = (parameters.subdomain_cubes + I::one()).cubed();
+    let particle_kernel = |mass: R, compact_support_radius: R| -> ParticleKernel<I, R> {
+        let squared_support = compact_support_radius * compact_support_radius;
+        ParticleKernel {
+            mass,
+            kernel: DynamicKernel::new(parameters.kernel_type, compact_support_radius),
+            cube_radius: I::from((compact_support_radius / parameters.cube_size).ceil())
+                .expect("kernel radius in cubes has to fit in index type"),
+            // Add 1% so that we don't exclude grid points that are just on the kernel boundary
+            squared_support_with_margin: squared_support * to_real!(1.01),
+        }
+    };
 
-    assert!(
-        mc_total_points.to_usize().is_some(),
-        "number of mc cubes per subdomain must be fit into usize"
+    // Kernel used for all particles if no per-particle properties are given
+    let uniform_particle_kernel = particle_kernel(
+        parameters.particle_rest_mass,
+        parameters.compact_support_radius,
     );
 
-    let max_particles = subdomains
-        .per_subdomain_particles
+    // Kernel with unit compact support that is evaluated in the transformed space of anisotropic kernels
+    let unit_kernel = DynamicKernel::new(parameters.kernel_type, R::one());
+
+    // Subdomains can span multiple cells of the subdomain grid (octree leaves)
+    let max_subdomain_extent = subdomains
+        .subdomain_extents
         .iter()
-        .map(|p| p.len())
+        .copied()
+        .max()
+        .unwrap_or_else(I::one);
+    let max_subdomain_cubes = parameters
+        .subdomain_cubes
+        .checked_mul(&max_subdomain_extent)
+        .expect("number of mc cubes per subdomain has to fit in index type");
+
+    assert!(
+        (max_subdomain_cubes + I::one())
+            .checked_cubed()
+            .and_then(|n| n.to_usize())
+            .is_some(),
+        "number of mc cubes per subdomain must be fit into usize"
+    );
+
+    // MC grid of a single cell of the subdomain grid (used to globalize edges of larger subdomains)
+    let subdomain_grid_cell_mc_grid = UniformCartesianCubeGrid3d::new(
+        &parameters.subdomain_grid.aabb().min(),
+        &[parameters.subdomain_cubes; 3],
+        parameters.cube_size,
+    )
+    .unwrap();
+
+    let max_particles = subdomains
+        .per_subdomain_particles
+        .iter()
+        .map(|p| p.len())
         .max()
         .unwrap_or(0);
     info!("Largest subdomain has {} particles.", max_particles);
@@ -733,84 +1216,18 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
 
     info!("Starting reconstruction (level-set evaluation and local triangulation).");
 
-    // Returns a unique identifier for any edge index of a subdomain that can be later used for stitching
-    let globalize_local_edge = |mc_grid: &UniformCartesianCubeGrid3d<I, R>,
-                                subdomain_grid: &UniformCartesianCubeGrid3d<I, R>,
-                                subdomain_index: I,
-                                local_edge: &EdgeIndex<I>|
-     -> (I, EdgeIndex<I>) {
-        // We globalize the boundary edge index by translating the local edge index to the subdomain
-        // where it lies on the lower boundary of that domain.
-
-        let max_mc_point_index = mc_grid.points_per_dim().map(|i| i - I::one());
-        let max_subdomain_index = subdomain_grid
-            .cells_per_dim()
-            .map(|i| i.saturating_sub(&I::one()).max(I::zero()));
-
-        // Check along which axes this edge is on the max boundary
-        let is_max = local_edge.axis().orthogonal_axes().map(|orth_axis| {
-            if local_edge.origin().index()[orth_axis.dim()] == max_mc_point_index[orth_axis.dim()] {
-                // We are on the max side of this domain along the axis
-                true
-            } else {
-                // We are either
-                //  - On the min side of this domain along the axis
-                //  - Somewhere in the middle (in this case this axis is irrelevant)
-                false
-            }
-        });
-
-        if !is_max[0] && !is_max[1] {
-            // Edge is already in the correct subdomain
-            (subdomain_index, local_edge.clone())
-        } else {
-            // We have to translate to the neighboring subdomain (+1 in all directions where is_max == true)
-            let subdomain_cell = subdomain_grid
-                .try_unflatten_cell_index(subdomain_index)
-                .expect("invalid subdomain index");
-
-            let mut target_subdomain_ijk = subdomain_cell.index().clone();
-            let mut target_local_origin_ijk = local_edge.origin().index().clone();
-
-            // Obtain index of new subdomain and new origin point
-            for (&orth_axis, &is_max) in local_edge
-                .axis()
-                .orthogonal_axes()
-                .iter()
-                .zip(is_max.iter())
-            {
-                if is_max {
-                    // Clamp the step to the subdomain grid because we are not interested in subdomains outside the grid
-                    // (globalization is not needed on the outermost boundary of the entire problem domain)
-                    target_subdomain_ijk[orth_axis.dim()] = (target_subdomain_ijk[orth_axis.dim()]
-                        + I::one())
-                    .min(max_subdomain_index[orth_axis.dim()]);
-                    // Move origin point from max boundary to min boundary
-                    target_local_origin_ijk[orth_axis.dim()] = I::zero();
-                }
-            }
-
-            let target_subdomain = subdomain_grid
-                .get_cell(target_subdomain_ijk)
-                .expect("target subdomain has to exist");
-            let flat_target_subdomain = subdomain_grid.flatten_cell_index(&target_subdomain);
-
-            // We re-use the same marching cubes domain here because the domain is anyway rectangular,
-            // therefore this shift gives the same result
-            let new_local_edge = mc_grid
-                .get_edge(target_local_origin_ijk, local_edge.axis())
-                .expect("failed to translate edge");
-
-            (flat_target_subdomain, new_local_edge)
-        }
-    };
-
     #[derive(Default)]
     struct SubdomainWorkspace<I: Index, R: Real> {
         // Particle positions of this subdomain
         subdomain_particles: Vec<Vector3<R>>,
         // Per particle density values of this subdomain
         subdomain_particle_densities: Vec<R>,
+        // Per particle masses of this subdomain (only used with per-particle properties)
+        subdomain_particle_masses: Vec<R>,
+        // Per particle compact support radii of this subdomain (only used with per-particle properties)
+        subdomain_particle_compact_support_radii: Vec<R>,
+        // Per particle anisotropic kernels of this subdomain (only used with anisotropic kernels)
+        subdomain_anisotropic_kernels: Vec<AnisotropicKernel<R>>,
         // Cache for the level-set values
         levelset_grid: Vec<R>,
         // Cache for indices
@@ -819,13 +1236,18 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
 
     let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<I, R>>>::new();
 
-    let reconstruct_dense = |flat_subdomain_idx: I, subdomain_particle_indices: &Vec<usize>| {
+    let reconstruct_dense = |flat_subdomain_idx: I,
+                             subdomain_extent: I,
+                             subdomain_particle_indices: &Vec<usize>| {
         // Obtain thread local workspace and clear it
         let mut workspace = workspace_tls.get_or_default().borrow_mut();
 
         let SubdomainWorkspace {
             subdomain_particles,
             subdomain_particle_densities,
+            subdomain_particle_masses,
+            subdomain_particle_compact_support_radii,
+            subdomain_anisotropic_kernels,
             levelset_grid,
             index_cache: _index_cache,
         } = &mut *workspace;
@@ -846,6 +1268,25 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                 subdomain_particle_indices,
                 subdomain_particle_densities,
             );
+            if let Some(global_particle_data) = global_particle_data {
+                gather_subdomain_data(
+                    &global_particle_data.masses,
+                    subdomain_particle_indices,
+                    subdomain_particle_masses,
+                );
+                gather_subdomain_data(
+                    &global_particle_data.compact_support_radii,
+                    subdomain_particle_indices,
+                    subdomain_particle_compact_support_radii,
+                );
+            }
+            if let Some(global_anisotropic_kernels) = global_anisotropic_kernels {
+                gather_subdomain_data(
+                    global_anisotropic_kernels,
+                    subdomain_particle_indices,
+                    subdomain_anisotropic_kernels,
+                );
+            }
         }
 
         // Get the cell index and AABB of the subdomain
@@ -854,29 +1295,81 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             .try_unflatten_cell_index(flat_subdomain_idx)
             .expect("Subdomain cell does not exist");
         let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
+        let subdomain_cubes = parameters.subdomain_cubes * subdomain_extent;
 
         let mc_grid = UniformCartesianCubeGrid3d::new(
             subdomain_aabb.min(),
-            &[parameters.subdomain_cubes; 3],
+            &[subdomain_cubes; 3],
             parameters.cube_size,
         )
         .unwrap();
 
+        // Surface nets additionally require the level-set values of a one cell halo at the lower boundary of the subdomain
+        let (levelset_mc_grid, global_levelset_grid) = match parameters.extraction_method {
+            SurfaceExtractionMethod::MarchingCubes => {
+                (mc_grid.clone(), &parameters.global_marching_cubes_grid)
+            }
+            SurfaceExtractionMethod::SurfaceNets => (
+                UniformCartesianCubeGrid3d::new(
+                    &(subdomain_aabb.min() - Vector3::repeat(parameters.cube_size)),
+                    &[subdomain_cubes + I::one(); 3],
+                    parameters.cube_size,
+                )
+                .unwrap(),
+                &parameters.global_halo_grid,
+            ),
+        };
+        let levelset_total_points = levelset_mc_grid
+            .points_per_dim()
+            .iter()
+            .map(|n| n.to_usize().unwrap())
+            .product();
+
         levelset_grid.fill(R::zero());
-        levelset_grid.resize(mc_total_points.to_usize().unwrap(), R::zero());
+        levelset_grid.resize(levelset_total_points, R::zero());
 
         {
             profile!("density grid loop");
 
-            let extents = mc_grid.points_per_dim();
+            let extents = levelset_mc_grid.points_per_dim();
 
-            for (p_i, rho_i) in subdomain_particles
+            for (i, (p_i, rho_i)) in subdomain_particles
                 .iter()
                 .copied()
                 .zip(subdomain_particle_densities.iter().copied())
+                .enumerate()
             {
+                let per_particle_kernel;
+                let ParticleKernel {
+                    mass: m_i,
+                    kernel,
+                    cube_radius,
+                    squared_support_with_margin,
+                } = if global_particle_data.is_some() {
+                    per_particle_kernel = particle_kernel(
+                        subdomain_particle_masses[i],
+                        subdomain_particle_compact_support_radii[i],
+                    );
+                    &per_particle_kernel
+                } else {
+                    &uniform_particle_kernel
+                };
+                let squared_support_with_margin = *squared_support_with_margin;
+
+                // With anisotropic kernels, the kernel is centered at the (smoothed) kernel center
+                let anisotropic_kernel =
+                    global_anisotropic_kernels.map(|_| &subdomain_anisotropic_kernels[i]);
+                let (p_i, cube_radius) = match anisotropic_kernel {
+                    Some(anisotropic_kernel) => (
+                        anisotropic_kernel.center,
+                        I::from((anisotropic_kernel.support_radius / parameters.cube_size).ceil())
+                            .expect("kernel radius in cubes has to fit in index type"),
+                    ),
+                    None => (p_i, *cube_radius),
+                };
+
                 // Get grid cell containing particle
-                let particle_cell = mc_grid.enclosing_cell(&p_i);
+                let particle_cell = levelset_mc_grid.enclosing_cell(&p_i);
 
                 // Compute lower and upper bounds of the grid points possibly affected by the particle
                 // We want to loop over the vertices of the enclosing cells plus all points in `cube_radius` distance from the cell
@@ -896,18 +1389,23 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                     (particle_cell[2] + cube_radius + I::two()).min(extents[2]),
                 ];
 
+                // The support of a shifted anisotropic kernel of a ghost particle might not overlap with the subdomain
+                if (0..3).any(|d| lower[d] >= upper[d]) {
+                    continue;
+                }
+
                 // Loop over all grid points around the enclosing cell
                 for i in I::range(lower[0], upper[0]).iter() {
                     for j in I::range(lower[1], upper[1]).iter() {
                         for k in I::range(lower[2], upper[2]).iter() {
                             let point_ijk = [i, j, k];
-                            let local_point = mc_grid
+                            let local_point = levelset_mc_grid
                                 .get_point(point_ijk)
                                 .expect("point has to be part of the subdomain grid");
                             //let point_coordinates = mc_grid.point_coordinates(&point);
 
                             let subdomain_ijk = subdomain_idx.index();
-                            let mc_cells_per_subdomain = mc_grid.cells_per_dim();
+                            let mc_cells_per_subdomain = [parameters.subdomain_cubes; 3];
 
                             fn local_to_global_point_ijk<I: Index>(
                                 local_point_ijk: [I; 3],
@@ -935,26 +1433,36 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                                 subdomain_ijk.clone(),
                                 mc_cells_per_subdomain.clone(),
                             );
-                            let global_point = parameters
-                                .global_marching_cubes_grid
+                            let global_point = global_levelset_grid
                                 .get_point(global_point_ijk)
                                 .expect("point has to be part of the global mc grid");
-                            let point_coordinates = parameters
-                                .global_marching_cubes_grid
-                                .point_coordinates(&global_point);
+                            let point_coordinates =
+                                global_levelset_grid.point_coordinates(&global_point);
 
                             let dx = p_i - point_coordinates;
-                            let dx_norm_sq = dx.norm_squared();
 
-                            if dx_norm_sq < squared_support_with_margin {
-                                let v_i = parameters.particle_rest_mass / rho_i;
-                                let r = dx_norm_sq.sqrt();
-                                let w_ij = kernel.evaluate(r);
-                                //let w_ij = kernel.evaluate(dx_norm_sq);
+                            let w_ij = if let Some(anisotropic_kernel) = anisotropic_kernel {
+                                // Evaluate the unit kernel in the transformed space of the anisotropic kernel
+                                let q_squared = (anisotropic_kernel.transform * dx).norm_squared();
+                                (q_squared < R::one()).then(|| {
+                                    anisotropic_kernel.determinant
+                                        * unit_kernel.evaluate(q_squared.sqrt())
+                                })
+                            } else {
+                                let dx_norm_sq = dx.norm_squared();
+                                (dx_norm_sq < squared_support_with_margin).then(|| {
+                                    let r = dx_norm_sq.sqrt();
+                                    kernel.evaluate(r)
+                                    //kernel.evaluate(dx_norm_sq)
+                                })
+                            };
 
+                            if let Some(w_ij) = w_ij {
+                                let v_i = *m_i / rho_i;
                                 let interpolated_value = v_i * w_ij;
 
-                                let flat_point_idx = mc_grid.flatten_point_index(&local_point);
+                                let flat_point_idx =
+                                    levelset_mc_grid.flatten_point_index(&local_point);
                                 let flat_point_idx = flat_point_idx.to_usize().unwrap();
                                 levelset_grid[flat_point_idx] += interpolated_value;
                             }
@@ -964,397 +1472,726 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             }
         }
 
-        let mut vertices = Vec::new();
-        let mut triangles = Vec::new();
+        if let Some(boundary) = boundary {
+            profile!("solid boundary");
+            boundary.apply_to_level_set_grid(
+                &levelset_mc_grid,
+                parameters.surface_threshold,
+                levelset_grid,
+            );
+        }
+
+        let density_blocks = if collect_density_blocks {
+            density_blocks_from_levelset(
+                parameters,
+                subdomain_idx.index(),
+                subdomain_extent,
+                &levelset_mc_grid,
+                levelset_grid,
+            )
+        } else {
+            Vec::new()
+        };
+
+        if parameters.extraction_method == SurfaceExtractionMethod::SurfaceNets {
+            profile!("surface nets loop");
+            let surface_patch = surface_nets_patch(
+                parameters,
+                subdomain_idx.index(),
+                &mc_grid,
+                &subdomain_grid_cell_mc_grid,
+                &levelset_mc_grid,
+                levelset_grid,
+            );
+            return (surface_patch, density_blocks);
+        }
 
-        let mut vertex_inside_count = 0;
-        let mut triangle_inside_count = 0;
+        let surface_patch = triangulate_subdomain_cells(
+            &mc_grid,
+            levelset_grid,
+            I::range(I::zero(), subdomain_cubes.cubed())
+                .iter()
+                .map(|flat_cell_idx| mc_grid.try_unflatten_cell_index(flat_cell_idx).unwrap()),
+            parameters.surface_threshold,
+            |density_value| density_value > parameters.surface_threshold,
+            |edge| {
+                globalize_subdomain_edge(
+                    &subdomain_grid_cell_mc_grid,
+                    &parameters.subdomain_grid,
+                    subdomain_idx.index(),
+                    edge,
+                )
+            },
+        );
+        (surface_patch, density_blocks)
+    };
 
-        let mut vertex_inside_flags = Vec::new();
-        let mut triangle_inside_flags = Vec::new();
+    let reconstruct_sparse =
+        |flat_subdomain_idx: I, subdomain_extent: I, subdomain_particle_indices: &Vec<usize>| {
+            // Obtain thread local workspace and clear it
+            let mut workspace = workspace_tls.get_or_default().borrow_mut();
 
-        let mut exterior_vertex_edge_indices = Vec::new();
+            let SubdomainWorkspace {
+                subdomain_particles,
+                subdomain_particle_densities,
+                subdomain_particle_masses,
+                subdomain_particle_compact_support_radii,
+                subdomain_anisotropic_kernels,
+                levelset_grid,
+                index_cache,
+            } = &mut *workspace;
 
-        let mut edge_to_vertex = new_map();
+            let flat_subdomain_idx: I = flat_subdomain_idx;
+            let subdomain_particle_indices: &[usize] = subdomain_particle_indices.as_slice();
 
-        {
-            profile!("mc triangulation loop");
-
-            for flat_cell_idx in I::range(I::zero(), mc_total_cells).iter() {
-                let cell = mc_grid.try_unflatten_cell_index(flat_cell_idx).unwrap();
-
-                let mut vertices_inside = [true; 8];
-                for local_point_index in 0..8 {
-                    let point = cell.global_point_index_of(local_point_index).unwrap();
-                    let flat_point_idx = mc_grid.flatten_point_index(&point);
-                    let flat_point_idx = flat_point_idx.to_usize().unwrap();
-                    // Get value of density map
-                    let density_value = levelset_grid[flat_point_idx];
-                    // Update inside/outside surface flag
-                    vertices_inside[local_point_index] =
-                        density_value > parameters.surface_threshold;
+            // Collect all particle positions and densities of this subdomain
+            {
+                //profile!("collect subdomain data");
+                gather_subdomain_data(
+                    global_particles,
+                    subdomain_particle_indices,
+                    subdomain_particles,
+                );
+                gather_subdomain_data(
+                    global_particle_densities,
+                    subdomain_particle_indices,
+                    subdomain_particle_densities,
+                );
+                if let Some(global_particle_data) = global_particle_data {
+                    gather_subdomain_data(
+                        &global_particle_data.masses,
+                        subdomain_particle_indices,
+                        subdomain_particle_masses,
+                    );
+                    gather_subdomain_data(
+                        &global_particle_data.compact_support_radii,
+                        subdomain_particle_indices,
+                        subdomain_particle_compact_support_radii,
+                    );
+                }
+                if let Some(global_anisotropic_kernels) = global_anisotropic_kernels {
+                    gather_subdomain_data(
+                        global_anisotropic_kernels,
+                        subdomain_particle_indices,
+                        subdomain_anisotropic_kernels,
+                    );
                 }
+            }
 
-                for triangle in marching_cubes_triangulation_iter(&vertices_inside) {
-                    let mut global_triangle = [0; 3];
-                    for (v_idx, local_edge_index) in triangle.iter().copied().enumerate() {
-                        let edge = cell
-                            .global_edge_index_of(local_edge_index as usize)
-                            .unwrap();
-                        let vertex_index = *edge_to_vertex.entry(edge).or_insert_with(|| {
-                            // TODO: Nonlinear interpolation
-
-                            let origin_coords = mc_grid.point_coordinates(&edge.origin());
-                            let target_coords = mc_grid.point_coordinates(&edge.target());
-
-                            let flat_origin_idx = mc_grid
-                                .flatten_point_index(&edge.origin())
-                                .to_usize()
-                                .unwrap();
-                            let flat_target_idx = mc_grid
-                                .flatten_point_index(&edge.target())
-                                .to_usize()
-                                .unwrap();
-
-                            let origin_value = levelset_grid[flat_origin_idx];
-                            let target_value = levelset_grid[flat_target_idx];
-
-                            let alpha = (parameters.surface_threshold - origin_value)
-                                / (target_value - origin_value);
-                            let interpolated_coords =
-                                origin_coords * (R::one() - alpha) + target_coords * alpha;
-                            let vertex_coords = interpolated_coords;
-
-                            vertices.push(vertex_coords);
-                            let vertex_index = vertices.len() - 1;
-
-                            let is_interior_vertex = !mc_grid.is_boundary_edge(&edge);
-                            vertex_inside_count += is_interior_vertex as usize;
-                            vertex_inside_flags.push(is_interior_vertex);
-
-                            if !is_interior_vertex {
-                                exterior_vertex_edge_indices.push(globalize_local_edge(
-                                    &mc_grid,
-                                    &parameters.subdomain_grid,
-                                    flat_subdomain_idx,
-                                    &edge,
-                                ));
-                            }
+            // Get the cell index and AABB of the subdomain
+            let subdomain_idx = parameters
+                .subdomain_grid
+                .try_unflatten_cell_index(flat_subdomain_idx)
+                .expect("Subdomain cell does not exist");
+            let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
+            let subdomain_cubes = parameters.subdomain_cubes * subdomain_extent;
+
+            let mc_grid = UniformCartesianCubeGrid3d::new(
+                subdomain_aabb.min(),
+                &[subdomain_cubes; 3],
+                parameters.cube_size,
+            )
+            .unwrap();
+
+            levelset_grid.fill(R::zero());
+            let mc_total_points = (subdomain_cubes + I::one()).cubed();
+            levelset_grid.resize(mc_total_points.to_usize().unwrap(), R::zero());
+
+            index_cache.clear();
 
-                            vertex_index
-                        });
+            {
+                profile!("density grid loop");
+
+                let extents = mc_grid.points_per_dim();
 
-                        global_triangle[v_idx] = vertex_index;
+                for (i, (p_i, rho_i)) in subdomain_particles
+                    .iter()
+                    .copied()
+                    .zip(subdomain_particle_densities.iter().copied())
+                    .enumerate()
+                {
+                    let per_particle_kernel;
+                    let ParticleKernel {
+                        mass: m_i,
+                        kernel,
+                        cube_radius,
+                        squared_support_with_margin,
+                    } = if global_particle_data.is_some() {
+                        per_particle_kernel = particle_kernel(
+                            subdomain_particle_masses[i],
+                            subdomain_particle_compact_support_radii[i],
+                        );
+                        &per_particle_kernel
+                    } else {
+                        &uniform_particle_kernel
+                    };
+                    let squared_support_with_margin = *squared_support_with_margin;
+
+                    // With anisotropic kernels, the kernel is centered at the (smoothed) kernel center
+                    let anisotropic_kernel =
+                        global_anisotropic_kernels.map(|_| &subdomain_anisotropic_kernels[i]);
+                    let (p_i, cube_radius) = match anisotropic_kernel {
+                        Some(anisotropic_kernel) => (
+                            anisotropic_kernel.center,
+                            I::from(
+                                (anisotropic_kernel.support_radius / parameters.cube_size).ceil(),
+                            )
+                            .expect("kernel radius in cubes has to fit in index type"),
+                        ),
+                        None => (p_i, *cube_radius),
+                    };
+
+                    // Get grid cell containing particle
+                    let particle_cell = mc_grid.enclosing_cell(&p_i);
+
+                    // Compute lower and upper bounds of the grid points possibly affected by the particle
+                    // We want to loop over the vertices of the enclosing cells plus all points in `cube_radius` distance from the cell
+
+                    let lower = [
+                        (particle_cell[0] - cube_radius).max(I::zero()),
+                        (particle_cell[1] - cube_radius).max(I::zero()),
+                        (particle_cell[2] - cube_radius).max(I::zero()),
+                    ];
+
+                    let upper = [
+                        // We add 2 because
+                        //  - we want to loop over all grid points of the cell (+1 for upper points) + the radius
+                        //  - the upper range limit is exclusive (+1)
+                        (particle_cell[0] + cube_radius + I::two()).min(extents[0]),
+                        (particle_cell[1] + cube_radius + I::two()).min(extents[1]),
+                        (particle_cell[2] + cube_radius + I::two()).min(extents[2]),
+                    ];
+
+                    // The support of a shifted anisotropic kernel of a ghost particle might not overlap with the subdomain
+                    if (0..3).any(|d| lower[d] >= upper[d]) {
+                        continue;
                     }
 
-                    let all_tri_vertices_inside = global_triangle
-                        .iter()
-                        .copied()
-                        .all(|v_idx| vertex_inside_flags[v_idx]);
+                    // Loop over all grid points around the enclosing cell
+                    for i in I::range(lower[0], upper[0]).iter() {
+                        for j in I::range(lower[1], upper[1]).iter() {
+                            for k in I::range(lower[2], upper[2]).iter() {
+                                let point_ijk = [i, j, k];
+                                let local_point = mc_grid
+                                    .get_point(point_ijk)
+                                    .expect("point has to be part of the subdomain grid");
+                                //let point_coordinates = mc_grid.point_coordinates(&point);
+
+                                let subdomain_ijk = subdomain_idx.index();
+                                let mc_cells_per_subdomain = [parameters.subdomain_cubes; 3];
+
+                                fn local_to_global_point_ijk<I: Index>(
+                                    local_point_ijk: [I; 3],
+                                    subdomain_ijk: [I; 3],
+                                    cells_per_subdomain: [I; 3],
+                                ) -> [GlobalIndex; 3] {
+                                    let local_point_ijk = local_point_ijk
+                                        .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
+                                    let subdomain_ijk = subdomain_ijk
+                                        .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
+                                    let cells_per_subdomain = cells_per_subdomain
+                                        .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
+                                    let [i, j, k] = local_point_ijk;
+
+                                    [
+                                        subdomain_ijk[0] * cells_per_subdomain[0] + i,
+                                        subdomain_ijk[1] * cells_per_subdomain[1] + j,
+                                        subdomain_ijk[2] * cells_per_subdomain[2] + k,
+                                    ]
+                                }
 
-                    triangles.push(global_triangle);
-                    triangle_inside_count += all_tri_vertices_inside as usize;
-                    triangle_inside_flags.push(all_tri_vertices_inside);
+                                // Use global coordinate calculation for consistency with neighboring domains
+                                let global_point_ijk = local_to_global_point_ijk(
+                                    point_ijk,
+                                    subdomain_ijk.clone(),
+                                    mc_cells_per_subdomain,
+                                );
+                                let global_point = parameters
+                                    .global_marching_cubes_grid
+                                    .get_point(global_point_ijk)
+                                    .expect("point has to be part of the global mc grid");
+                                let point_coordinates = parameters
+                                    .global_marching_cubes_grid
+                                    .point_coordinates(&global_point);
+
+                                let dx = p_i - point_coordinates;
+
+                                let w_ij = if let Some(anisotropic_kernel) = anisotropic_kernel {
+                                    // Evaluate the unit kernel in the transformed space of the anisotropic kernel
+                                    let q_squared =
+                                        (anisotropic_kernel.transform * dx).norm_squared();
+                                    (q_squared < R::one()).then(|| {
+                                        anisotropic_kernel.determinant
+                                            * unit_kernel.evaluate(q_squared.sqrt())
+                                    })
+                                } else {
+                                    let dx_norm_sq = dx.norm_squared();
+                                    (dx_norm_sq < squared_support_with_margin).then(|| {
+                                        let r = dx_norm_sq.sqrt();
+                                        kernel.evaluate(r)
+                                        //kernel.evaluate(dx_norm_sq)
+                                    })
+                                };
+
+                                if let Some(w_ij) = w_ij {
+                                    let v_i = *m_i / rho_i;
+                                    let interpolated_value = v_i * w_ij;
+
+                                    let flat_point_idx = mc_grid.flatten_point_index(&local_point);
+                                    let flat_point_idx = flat_point_idx.to_usize().unwrap();
+                                    levelset_grid[flat_point_idx] += interpolated_value;
+
+                                    if levelset_grid[flat_point_idx] > parameters.surface_threshold
+                                    {
+                                        for c in mc_grid
+                                            .cells_adjacent_to_point(
+                                                &mc_grid.get_point_neighborhood(&local_point),
+                                            )
+                                            .iter()
+                                            .flatten()
+                                        {
+                                            let flat_cell_index = mc_grid.flatten_cell_index(c);
+                                            index_cache.push(flat_cell_index);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
             }
-        }
 
-        SurfacePatch {
-            vertices,
-            triangles,
-            vertex_inside_count,
-            triangle_inside_count,
-            vertex_inside_flags,
-            triangle_inside_flags,
-            exterior_vertex_edge_indices,
-        }
-    };
+            let density_blocks = if collect_density_blocks {
+                density_blocks_from_levelset(
+                    parameters,
+                    subdomain_idx.index(),
+                    subdomain_extent,
+                    &mc_grid,
+                    levelset_grid,
+                )
+            } else {
+                Vec::new()
+            };
 
-    let reconstruct_sparse = |flat_subdomain_idx: I, subdomain_particle_indices: &Vec<usize>| {
-        // Obtain thread local workspace and clear it
-        let mut workspace = workspace_tls.get_or_default().borrow_mut();
+            index_cache.sort_unstable();
+            let surface_patch =
+                triangulate_subdomain_cells(
+                    &mc_grid,
+                    levelset_grid,
+                    index_cache.iter().copied().dedup().map(|flat_cell_idx| {
+                        mc_grid.try_unflatten_cell_index(flat_cell_idx).unwrap()
+                    }),
+                    parameters.surface_threshold,
+                    |density_value| density_value > parameters.surface_threshold,
+                    |edge| {
+                        globalize_subdomain_edge(
+                            &subdomain_grid_cell_mc_grid,
+                            &parameters.subdomain_grid,
+                            subdomain_idx.index(),
+                            edge,
+                        )
+                    },
+                );
 
-        let SubdomainWorkspace {
-            subdomain_particles,
-            subdomain_particle_densities,
-            levelset_grid,
-            index_cache,
-        } = &mut *workspace;
+            (surface_patch, density_blocks)
+        };
 
-        let flat_subdomain_idx: I = flat_subdomain_idx;
-        let subdomain_particle_indices: &[usize] = subdomain_particle_indices.as_slice();
+    // Returns whether the solid boundary may influence the level-set function of the subdomain
+    let is_affected_by_boundary = |flat_subdomain_idx: I, subdomain_extent: I| {
+        boundary.is_some_and(|boundary| {
+            let subdomain_idx = parameters
+                .subdomain_grid
+                .try_unflatten_cell_index(flat_subdomain_idx)
+                .expect("Subdomain cell does not exist");
+            let subdomain_min = *parameters.subdomain_grid.cell_aabb(&subdomain_idx).min();
+            let subdomain_size =
+                parameters.cube_size * to_real!(parameters.subdomain_cubes * subdomain_extent);
+            boundary.may_affect(&Aabb3d::new(
+                subdomain_min,
+                subdomain_min + Vector3::repeat(subdomain_size),
+            ))
+        })
+    };
 
-        // Collect all particle positions and densities of this subdomain
+    let progress = SubdomainProgress::new(
+        context,
+        ReconstructionStage::SurfaceExtraction,
+        subdomains.flat_subdomain_indices.len(),
+    );
+
+    let reconstruct_subdomain = |(
+        (flat_subdomain_idx, subdomain_extent),
+        subdomain_particle_indices,
+    ): ((I, I), &Vec<usize>)| {
+        // Skip the remaining subdomains with empty patches, the caller has to check for cancellation
+        if context.is_cancelled() {
+            return (SurfacePatch::default(), Vec::new());
+        }
+
+        // Surface nets are only implemented for dense level-set grids, the sparse reconstruction
+        // does not support solid boundaries
+        let result = if subdomain_particle_indices.len() <= sparse_limit
+            && parameters.extraction_method == SurfaceExtractionMethod::MarchingCubes
+            && !is_affected_by_boundary(flat_subdomain_idx, subdomain_extent)
         {
-            //profile!("collect subdomain data");
-            gather_subdomain_data(
-                global_particles,
+            profile!("subdomain reconstruction (sparse)", parent = parent);
+            reconstruct_sparse(
+                flat_subdomain_idx,
+                subdomain_extent,
                 subdomain_particle_indices,
-                subdomain_particles,
-            );
-            gather_subdomain_data(
-                global_particle_densities,
+            )
+        } else {
+            profile!("subdomain reconstruction (dense)", parent = parent);
+            reconstruct_dense(
+                flat_subdomain_idx,
+                subdomain_extent,
                 subdomain_particle_indices,
-                subdomain_particle_densities,
-            );
-        }
-
-        // Get the cell index and AABB of the subdomain
-        let subdomain_idx = parameters
-            .subdomain_grid
-            .try_unflatten_cell_index(flat_subdomain_idx)
-            .expect("Subdomain cell does not exist");
-        let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
-
-        let mc_grid = UniformCartesianCubeGrid3d::new(
-            subdomain_aabb.min(),
-            &[parameters.subdomain_cubes; 3],
-            parameters.cube_size,
-        )
-        .unwrap();
-
-        levelset_grid.fill(R::zero());
-        levelset_grid.resize(mc_total_points.to_usize().unwrap(), R::zero());
-
-        index_cache.clear();
+            )
+        };
+        progress.increment();
+        result
+    };
 
+    let mut surface_patches = Vec::with_capacity(subdomains.flat_subdomain_indices.len());
+    let mut subdomain_density_blocks = Vec::with_capacity(subdomains.flat_subdomain_indices.len());
+    if parameters.enable_multi_threading {
+        subdomains
+            .flat_subdomain_indices
+            .par_iter()
+            .copied()
+            .zip(subdomains.subdomain_extents.par_iter().copied())
+            .zip(subdomains.per_subdomain_particles.par_iter())
+            .map(reconstruct_subdomain)
+            .unzip_into_vecs(&mut surface_patches, &mut subdomain_density_blocks);
+    } else {
+        for (surface_patch, density_blocks) in subdomains
+            .flat_subdomain_indices
+            .iter()
+            .copied()
+            .zip(subdomains.subdomain_extents.iter().copied())
+            .zip(subdomains.per_subdomain_particles.iter())
+            .map(reconstruct_subdomain)
         {
-            profile!("density grid loop");
+            surface_patches.push(surface_patch);
+            subdomain_density_blocks.push(density_blocks);
+        }
+    }
 
-            let extents = mc_grid.points_per_dim();
+    if let Some(density_grid) = density_grid {
+        profile!("collect density grid");
+        for density_block in subdomain_density_blocks.into_iter().flatten() {
+            density_grid.insert_block(density_block);
+        }
+    }
 
-            for (p_i, rho_i) in subdomain_particles
-                .iter()
-                .copied()
-                .zip(subdomain_particle_densities.iter().copied())
-            {
-                // Get grid cell containing particle
-                let particle_cell = mc_grid.enclosing_cell(&p_i);
+    surface_patches
+}
 
-                // Compute lower and upper bounds of the grid points possibly affected by the particle
-                // We want to loop over the vertices of the enclosing cells plus all points in `cube_radius` distance from the cell
+/// Copies the level-set values of every cell of the subdomain grid covered by a subdomain into a separate density block
+///
+/// The blocks are indexed by the cells of the subdomain grid, i.e. a subdomain that spans multiple
+/// cells of the subdomain grid (octree leaf) results in multiple blocks.
+fn density_blocks_from_levelset<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    subdomain_ijk: &[I; 3],
+    subdomain_extent: I,
+    levelset_mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    levelset_grid: &[R],
+) -> Vec<DensityBlock<I, R>> {
+    // The level-set grid might extend beyond the lower boundary of the subdomain
+    let halo_cubes: I = to_index!(levelset_halo_cubes(parameters.extraction_method));
+    let block_cubes = parameters.subdomain_cubes;
+    let block_points = (block_cubes + I::one()).to_usize().unwrap().pow(3);
+
+    let mut density_blocks = Vec::with_capacity(subdomain_extent.cubed().to_usize().unwrap());
+    for bi in I::range(I::zero(), subdomain_extent).iter() {
+        for bj in I::range(I::zero(), subdomain_extent).iter() {
+            for bk in I::range(I::zero(), subdomain_extent).iter() {
+                let offset = [bi, bj, bk].map(|b| halo_cubes + b * block_cubes);
+
+                let mut values = Vec::with_capacity(block_points);
+                for i in I::range(I::zero(), block_cubes + I::one()).iter() {
+                    for j in I::range(I::zero(), block_cubes + I::one()).iter() {
+                        for k in I::range(I::zero(), block_cubes + I::one()).iter() {
+                            let flat_point_idx = levelset_mc_grid.flatten_point_indices(
+                                offset[0] + i,
+                                offset[1] + j,
+                                offset[2] + k,
+                            );
+                            values.push(levelset_grid[flat_point_idx.to_usize().unwrap()]);
+                        }
+                    }
+                }
 
-                let lower = [
-                    (particle_cell[0] - cube_radius).max(I::zero()),
-                    (particle_cell[1] - cube_radius).max(I::zero()),
-                    (particle_cell[2] - cube_radius).max(I::zero()),
-                ];
+                density_blocks.push(DensityBlock {
+                    block_ijk: [
+                        subdomain_ijk[0] + bi,
+                        subdomain_ijk[1] + bj,
+                        subdomain_ijk[2] + bk,
+                    ],
+                    values,
+                });
+            }
+        }
+    }
 
-                let upper = [
-                    // We add 2 because
-                    //  - we want to loop over all grid points of the cell (+1 for upper points) + the radius
-                    //  - the upper range limit is exclusive (+1)
-                    (particle_cell[0] + cube_radius + I::two()).min(extents[0]),
-                    (particle_cell[1] + cube_radius + I::two()).min(extents[1]),
-                    (particle_cell[2] + cube_radius + I::two()).min(extents[2]),
-                ];
+    density_blocks
+}
 
-                // Loop over all grid points around the enclosing cell
-                for i in I::range(lower[0], upper[0]).iter() {
-                    for j in I::range(lower[1], upper[1]).iter() {
-                        for k in I::range(lower[2], upper[2]).iter() {
-                            let point_ijk = [i, j, k];
-                            let local_point = mc_grid
-                                .get_point(point_ijk)
-                                .expect("point has to be part of the subdomain grid");
-                            //let point_coordinates = mc_grid.point_coordinates(&point);
+/// Extracts the surface patch of a subdomain from its level-set grid using surface nets
+///
+/// The level-set grid has to contain an additional layer of cells at the lower boundary of the
+/// subdomain (halo). Every subdomain generates the quads of all crossed edges with an origin point
+/// inside of the subdomain excluding its upper boundary. Vertices of cells that are also used by a
+/// neighboring subdomain are exterior and identified by the x-edge at the minimum corner of the
+/// cell in the cell of the subdomain grid containing the cell. Every quad is stored as two
+/// consecutive triangles.
+fn surface_nets_patch<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    subdomain_ijk: &[I; 3],
+    mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    subdomain_grid_cell_mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    levelset_mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    levelset_grid: &[R],
+) -> SurfacePatch<I, R> {
+    // The subdomain might span multiple cells of the subdomain grid
+    let subdomain_cubes = mc_grid.cells_per_dim()[0];
+    let surface_threshold = parameters.surface_threshold;
+
+    let value_at = |halo_point_ijk: &[I; 3]| -> R {
+        let flat_point_idx = levelset_mc_grid.flatten_point_index_array(halo_point_ijk);
+        levelset_grid[flat_point_idx.to_usize().unwrap()]
+    };
+    let is_inside = |value: R| value > surface_threshold;
+
+    // Returns the unique identifier of a cell of the halo grid that is shared with other subdomains
+    let globalize_halo_cell = |halo_cell_ijk: &[I; 3]| -> (I, EdgeIndex<I>) {
+        let cells_per_grid_cell = parameters.subdomain_cubes;
+        let mut owner_subdomain_ijk = [I::zero(); 3];
+        let mut owner_cell_ijk = [I::zero(); 3];
+        for dim in 0..3 {
+            // Cells with halo index zero are part of the lower neighbor subdomain
+            let global_cell =
+                subdomain_ijk[dim] * cells_per_grid_cell + halo_cell_ijk[dim] - I::one();
+            owner_subdomain_ijk[dim] = global_cell / cells_per_grid_cell;
+            owner_cell_ijk[dim] = global_cell % cells_per_grid_cell;
+        }
 
-                            let subdomain_ijk = subdomain_idx.index();
-                            let mc_cells_per_subdomain = mc_grid.cells_per_dim();
+        let owner_subdomain = parameters
+            .subdomain_grid
+            .flatten_cell_index_array(&owner_subdomain_ijk);
+        let edge = subdomain_grid_cell_mc_grid
+            .get_edge(owner_cell_ijk, Axis::X)
+            .expect("cell has to be part of the subdomain");
+        (owner_subdomain, edge)
+    };
 
-                            fn local_to_global_point_ijk<I: Index>(
-                                local_point_ijk: [I; 3],
-                                subdomain_ijk: [I; 3],
-                                cells_per_subdomain: [I; 3],
-                            ) -> [GlobalIndex; 3] {
-                                let local_point_ijk = local_point_ijk
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let subdomain_ijk = subdomain_ijk
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let cells_per_subdomain = cells_per_subdomain
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let [i, j, k] = local_point_ijk;
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
 
-                                [
-                                    subdomain_ijk[0] * cells_per_subdomain[0] + i,
-                                    subdomain_ijk[1] * cells_per_subdomain[1] + j,
-                                    subdomain_ijk[2] * cells_per_subdomain[2] + k,
-                                ]
-                            }
+    let mut vertex_inside_count = 0;
+    let mut triangle_inside_count = 0;
 
-                            // Use global coordinate calculation for consistency with neighboring domains
-                            let global_point_ijk = local_to_global_point_ijk(
-                                point_ijk,
-                                subdomain_ijk.clone(),
-                                mc_cells_per_subdomain.clone(),
-                            );
-                            let global_point = parameters
-                                .global_marching_cubes_grid
-                                .get_point(global_point_ijk)
-                                .expect("point has to be part of the global mc grid");
-                            let point_coordinates = parameters
-                                .global_marching_cubes_grid
-                                .point_coordinates(&global_point);
+    let mut vertex_inside_flags = Vec::new();
+    let mut triangle_inside_flags = Vec::new();
 
-                            let dx = p_i - point_coordinates;
-                            let dx_norm_sq = dx.norm_squared();
+    let mut exterior_vertex_edge_indices = Vec::new();
 
-                            if dx_norm_sq < squared_support_with_margin {
-                                let v_i = parameters.particle_rest_mass / rho_i;
-                                let r = dx_norm_sq.sqrt();
-                                let w_ij = kernel.evaluate(r);
-                                //let w_ij = kernel.evaluate(dx_norm_sq);
+    let mut cell_to_vertex = new_map();
 
-                                let interpolated_value = v_i * w_ij;
+    // The local points excluding the upper boundary correspond to the cells of the subdomain grid
+    for flat_point_idx in I::range(I::zero(), subdomain_cubes.cubed()).iter() {
+        let local_point = mc_grid.try_unflatten_cell_index(flat_point_idx).unwrap();
+        let origin_ijk = local_point.index().map(|i| i + I::one());
+        let origin_inside = is_inside(value_at(&origin_ijk));
 
-                                let flat_point_idx = mc_grid.flatten_point_index(&local_point);
-                                let flat_point_idx = flat_point_idx.to_usize().unwrap();
-                                levelset_grid[flat_point_idx] += interpolated_value;
+        for &axis in Axis::all_possible() {
+            let mut target_ijk = origin_ijk;
+            target_ijk[axis.dim()] += I::one();
+            if origin_inside == is_inside(value_at(&target_ijk)) {
+                continue;
+            }
 
-                                if levelset_grid[flat_point_idx] > parameters.surface_threshold {
-                                    for c in mc_grid
-                                        .cells_adjacent_to_point(
-                                            &mc_grid.get_point_neighborhood(&local_point),
-                                        )
-                                        .iter()
-                                        .flatten()
-                                    {
-                                        let flat_cell_index = mc_grid.flatten_cell_index(c);
-                                        index_cache.push(flat_cell_index);
-                                    }
-                                }
+            let cells = cells_around_edge(&origin_ijk, axis)
+                .expect("cells around owned edges have to be part of the halo grid");
+            // Skip edges on the lower boundary of the global grid
+            let is_outside_global_grid = |cell_ijk: &[I; 3]| {
+                (0..3).any(|dim| cell_ijk[dim] == I::zero() && subdomain_ijk[dim] == I::zero())
+            };
+            if cells.iter().any(is_outside_global_grid) {
+                continue;
+            }
+
+            let quad = cells.map(|cell_ijk| {
+                *cell_to_vertex.entry(cell_ijk).or_insert_with(|| {
+                    let mut corner_values = [R::zero(); 8];
+                    for (corner, corner_value) in corner_values.iter_mut().enumerate() {
+                        let mut corner_ijk = cell_ijk;
+                        for (dim, index) in corner_ijk.iter_mut().enumerate() {
+                            if corner & (1 << dim) != 0 {
+                                *index += I::one();
                             }
                         }
+                        *corner_value = value_at(&corner_ijk);
                     }
-                }
-            }
-        }
-
-        let mut vertices = Vec::new();
-        let mut triangles = Vec::new();
 
-        let mut vertex_inside_count = 0;
-        let mut triangle_inside_count = 0;
-
-        let mut vertex_inside_flags = Vec::new();
-        let mut triangle_inside_flags = Vec::new();
+                    let offset = cell_vertex_offset(&corner_values, surface_threshold).expect(
+                        "a cell around a crossed edge has to be crossed by the iso-surface",
+                    );
+                    vertices.push(
+                        levelset_mc_grid.point_coordinates_array(&cell_ijk)
+                            + offset * parameters.cube_size,
+                    );
+                    let vertex_index = vertices.len() - 1;
 
-        let mut exterior_vertex_edge_indices = Vec::new();
+                    // Cells at the lower and upper boundary of the subdomain are shared with neighboring subdomains
+                    let is_interior_vertex = cell_ijk
+                        .iter()
+                        .all(|&i| i > I::zero() && i < subdomain_cubes);
+                    vertex_inside_count += is_interior_vertex as usize;
+                    vertex_inside_flags.push(is_interior_vertex);
 
-        let mut edge_to_vertex = new_map();
+                    if !is_interior_vertex {
+                        exterior_vertex_edge_indices.push(globalize_halo_cell(&cell_ijk));
+                    }
 
-        {
-            profile!("mc triangulation loop");
+                    vertex_index
+                })
+            });
+            let quad = orient_quad(quad, origin_inside);
 
-            index_cache.sort_unstable();
-            for flat_cell_idx in index_cache.iter().copied().dedup() {
-                let cell = mc_grid.try_unflatten_cell_index(flat_cell_idx).unwrap();
-
-                let mut vertices_inside = [true; 8];
-                for local_point_index in 0..8 {
-                    let point = cell.global_point_index_of(local_point_index).unwrap();
-                    let flat_point_idx = mc_grid.flatten_point_index(&point);
-                    let flat_point_idx = flat_point_idx.to_usize().unwrap();
-                    // Get value of density map
-                    let density_value = levelset_grid[flat_point_idx];
-                    // Update inside/outside surface flag
-                    vertices_inside[local_point_index] =
-                        density_value > parameters.surface_threshold;
-                }
+            // Both triangles of a quad get the same flag to keep them consecutive after stitching
+            let all_quad_vertices_inside = quad.iter().all(|&v_idx| vertex_inside_flags[v_idx]);
+            for triangle in triangulate_quad(&vertices, &quad) {
+                triangles.push(triangle);
+                triangle_inside_flags.push(all_quad_vertices_inside);
+            }
+            triangle_inside_count += 2 * all_quad_vertices_inside as usize;
+        }
+    }
 
-                for triangle in marching_cubes_triangulation_iter(&vertices_inside) {
-                    let mut global_triangle = [0; 3];
-                    for (v_idx, local_edge_index) in triangle.iter().copied().enumerate() {
-                        let edge = cell
-                            .global_edge_index_of(local_edge_index as usize)
-                            .unwrap();
-                        let vertex_index = *edge_to_vertex.entry(edge).or_insert_with(|| {
-                            // TODO: Nonlinear interpolation
-
-                            let origin_coords = mc_grid.point_coordinates(&edge.origin());
-                            let target_coords = mc_grid.point_coordinates(&edge.target());
-
-                            let flat_origin_idx = mc_grid
-                                .flatten_point_index(&edge.origin())
-                                .to_usize()
-                                .unwrap();
-                            let flat_target_idx = mc_grid
-                                .flatten_point_index(&edge.target())
-                                .to_usize()
-                                .unwrap();
-
-                            let origin_value = levelset_grid[flat_origin_idx];
-                            let target_value = levelset_grid[flat_target_idx];
-
-                            let alpha = (parameters.surface_threshold - origin_value)
-                                / (target_value - origin_value);
-                            let interpolated_coords =
-                                origin_coords * (R::one() - alpha) + target_coords * alpha;
-                            let vertex_coords = interpolated_coords;
-
-                            vertices.push(vertex_coords);
-                            let vertex_index = vertices.len() - 1;
-
-                            let is_interior_vertex = !mc_grid.is_boundary_edge(&edge);
-                            vertex_inside_count += is_interior_vertex as usize;
-                            vertex_inside_flags.push(is_interior_vertex);
-
-                            if !is_interior_vertex {
-                                exterior_vertex_edge_indices.push(globalize_local_edge(
-                                    &mc_grid,
-                                    &parameters.subdomain_grid,
-                                    flat_subdomain_idx,
-                                    &edge,
-                                ));
-                            }
+    SurfacePatch {
+        vertices,
+        triangles,
+        vertex_inside_count,
+        triangle_inside_count,
+        vertex_inside_flags,
+        triangle_inside_flags,
+        exterior_vertex_edge_indices,
+    }
+}
 
-                            vertex_index
-                        });
+/// Returns a unique identifier for any edge index of a subdomain spanning one or more cells of the subdomain grid that can be later used for stitching
+///
+/// The edge is first translated to the cell of the subdomain grid containing it, such that the
+/// identifier is the same as the one computed by [`globalize_local_edge`] for a subdomain of the
+/// uniform grid containing the edge. The given MC grid has to be the grid of a single subdomain grid cell.
+fn globalize_subdomain_edge<I: Index, R: Real>(
+    subdomain_grid_cell_mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    subdomain_grid: &UniformCartesianCubeGrid3d<I, R>,
+    subdomain_ijk: &[I; 3],
+    local_edge: &EdgeIndex<I>,
+) -> (I, EdgeIndex<I>) {
+    let cells_per_grid_cell = subdomain_grid_cell_mc_grid.cells_per_dim()[0];
+    let local_origin_ijk = local_edge.origin().index();
+
+    let mut grid_cell_ijk = *subdomain_ijk;
+    let mut grid_cell_origin_ijk = [I::zero(); 3];
+    for dim in 0..3 {
+        let (offset, origin) = local_origin_ijk[dim].div_rem(&cells_per_grid_cell);
+        if origin == I::zero() && offset > I::zero() && dim != local_edge.axis().dim() {
+            // Points on the boundary between two grid cells are assigned to the upper boundary of
+            // the lower cell, which is consistent with edges on the upper boundary of the subdomain
+            grid_cell_ijk[dim] += offset - I::one();
+            grid_cell_origin_ijk[dim] = cells_per_grid_cell;
+        } else {
+            grid_cell_ijk[dim] += offset;
+            grid_cell_origin_ijk[dim] = origin;
+        }
+    }
 
-                        global_triangle[v_idx] = vertex_index;
-                    }
+    let grid_cell_edge = subdomain_grid_cell_mc_grid
+        .get_edge(grid_cell_origin_ijk, local_edge.axis())
+        .expect("edge has to be part of the subdomain grid cell");
+    globalize_local_edge(
+        subdomain_grid_cell_mc_grid,
+        subdomain_grid,
+        subdomain_grid.flatten_cell_index_array(&grid_cell_ijk),
+        &grid_cell_edge,
+    )
+}
 
-                    let all_tri_vertices_inside = global_triangle
-                        .iter()
-                        .copied()
-                        .all(|v_idx| vertex_inside_flags[v_idx]);
+/// Returns a unique identifier for any edge index of a subdomain that can be later used for stitching
+pub(crate) fn globalize_local_edge<I: Index, R: Real>(
+    mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    subdomain_grid: &UniformCartesianCubeGrid3d<I, R>,
+    subdomain_index: I,
+    local_edge: &EdgeIndex<I>,
+) -> (I, EdgeIndex<I>) {
+    // We globalize the boundary edge index by translating the local edge index to the subdomain
+    // where it lies on the lower boundary of that domain.
+
+    let max_mc_point_index = mc_grid.points_per_dim().map(|i| i - I::one());
+    let max_subdomain_index = subdomain_grid
+        .cells_per_dim()
+        .map(|i| i.saturating_sub(&I::one()).max(I::zero()));
+
+    // Check along which axes this edge is on the max boundary
+    let is_max = local_edge.axis().orthogonal_axes().map(|orth_axis| {
+        if local_edge.origin().index()[orth_axis.dim()] == max_mc_point_index[orth_axis.dim()] {
+            // We are on the max side of this domain along the axis
+            true
+        } else {
+            // We are either
+            //  - On the min side of this domain along the axis
+            //  - Somewhere in the middle (in this case this axis is irrelevant)
+            false
+        }
+    });
 
-                    triangles.push(global_triangle);
-                    triangle_inside_count += all_tri_vertices_inside as usize;
-                    triangle_inside_flags.push(all_tri_vertices_inside);
-                }
+    if !is_max[0] && !is_max[1] {
+        // Edge is already in the correct subdomain
+        (subdomain_index, local_edge.clone())
+    } else {
+        // We have to translate to the neighboring subdomain (+1 in all directions where is_max == true)
+        let subdomain_cell = subdomain_grid
+            .try_unflatten_cell_index(subdomain_index)
+            .expect("invalid subdomain index");
+
+        let mut target_subdomain_ijk = subdomain_cell.index().clone();
+        let mut target_local_origin_ijk = local_edge.origin().index().clone();
+
+        // Obtain index of new subdomain and new origin point
+        for (&orth_axis, &is_max) in local_edge
+            .axis()
+            .orthogonal_axes()
+            .iter()
+            .zip(is_max.iter())
+        {
+            if is_max {
+                // Clamp the step to the subdomain grid because we are not interested in subdomains outside the grid
+                // (globalization is not needed on the outermost boundary of the entire problem domain)
+                target_subdomain_ijk[orth_axis.dim()] = (target_subdomain_ijk[orth_axis.dim()]
+                    + I::one())
+                .min(max_subdomain_index[orth_axis.dim()]);
+                // Move origin point from max boundary to min boundary
+                target_local_origin_ijk[orth_axis.dim()] = I::zero();
             }
         }
 
-        SurfacePatch {
-            vertices,
-            triangles,
-            vertex_inside_count,
-            triangle_inside_count,
-            vertex_inside_flags,
-            triangle_inside_flags,
-            exterior_vertex_edge_indices,
-        }
-    };
+        let target_subdomain = subdomain_grid
+            .get_cell(target_subdomain_ijk)
+            .expect("target subdomain has to exist");
+        let flat_target_subdomain = subdomain_grid.flatten_cell_index(&target_subdomain);
 
-    let mut surface_patches = Vec::with_capacity(subdomains.flat_subdomain_indices.len());
-    subdomains
-        .flat_subdomain_indices
-        .par_iter()
-        .copied()
-        .zip(subdomains.per_subdomain_particles.par_iter())
-        .map(|(flat_subdomain_idx, subdomain_particle_indices)| {
-            if subdomain_particle_indices.len() <= sparse_limit {
-                profile!("subdomain reconstruction (sparse)", parent = parent);
-                reconstruct_sparse(flat_subdomain_idx, subdomain_particle_indices)
-            } else {
-                profile!("subdomain reconstruction (dense)", parent = parent);
-                reconstruct_dense(flat_subdomain_idx, subdomain_particle_indices)
-            }
-        })
-        .collect_into_vec(&mut surface_patches);
+        // We re-use the same marching cubes domain here because the domain is anyway rectangular,
+        // therefore this shift gives the same result
+        let new_local_edge = mc_grid
+            .get_edge(target_local_origin_ijk, local_edge.axis())
+            .expect("failed to translate edge");
 
-    surface_patches
+        (flat_target_subdomain, new_local_edge)
+    }
 }
 
 pub(crate) fn stitching<I: Index, R: Real>(
@@ -0,0 +1,200 @@
+//! Adaptive octree decomposition on top of the uniform subdomain grid
+//!
+//! Every node of the octree is a cube of `2^k` subdomains of the subdomain grid along each axis
+//! that is aligned to multiples of its extent. Nodes are subdivided if they cover more particles than
+//! allowed or if they are larger than the largest allowed subdomain. Only octants containing particles
+//! are kept as child nodes. The leaves of the octree are then processed like the subdomains of the
+//! uniform grid decomposition. As the leaves are aligned with the subdomain grid, vertices on their
+//! boundaries are identified in the same way as for the uniform grid to stitch the surface patches.
+
+use super::{ParametersSubdomainGrid, Subdomains};
+use crate::generic_tree::{
+    MutVisitableTree, ParMutVisitableTree, TreeNode, TreeNodeMut, VisitableTree,
+};
+use crate::{profile, Index, Real};
+use log::info;
+use rayon::prelude::*;
+
+/// Node of the subdomain octree
+struct OctreeNode<I: Index> {
+    /// Index of the subdomain (of the subdomain grid) at the minimum corner of the node
+    min_subdomain_ijk: [I; 3],
+    /// Number of subdomains of the subdomain grid covered by the node along each axis (power of two)
+    extent: I,
+    /// Positions of the non-empty subdomains covered by the node in the input subdomain list (moved to the children on subdivision)
+    subdomains: Vec<usize>,
+    /// Sum of the particle counts (including ghost particles) of all subdomains covered by the node
+    particle_count: usize,
+    /// Child nodes of the non-empty octants of the node
+    children: Vec<Box<OctreeNode<I>>>,
+}
+
+impl<I: Index> TreeNode for OctreeNode<I> {
+    fn children(&self) -> &[Box<Self>] {
+        self.children.as_slice()
+    }
+}
+
+impl<I: Index> TreeNodeMut for OctreeNode<I> {
+    fn children_mut(&mut self) -> &mut [Box<Self>] {
+        self.children.as_mut_slice()
+    }
+}
+
+impl<I: Index> OctreeNode<I> {
+    /// Splits the node into its (up to eight) non-empty octants
+    fn subdivide(&mut self, subdomain_ijks: &[[I; 3]], particle_counts: &[usize]) {
+        let half_extent = self.extent / I::two();
+
+        let mut octant_subdomains: [Vec<usize>; 8] = Default::default();
+        for subdomain in std::mem::take(&mut self.subdomains) {
+            let subdomain_ijk = &subdomain_ijks[subdomain];
+            let octant = (0..3)
+                .filter(|&dim| subdomain_ijk[dim] >= self.min_subdomain_ijk[dim] + half_extent)
+                .fold(0, |octant, dim| octant | (1 << dim));
+            octant_subdomains[octant].push(subdomain);
+        }
+
+        self.children = octant_subdomains
+            .into_iter()
+            .enumerate()
+            .filter(|(_, subdomains)| !subdomains.is_empty())
+            .map(|(octant, subdomains)| {
+                let mut min_subdomain_ijk = self.min_subdomain_ijk;
+                for (dim, index) in min_subdomain_ijk.iter_mut().enumerate() {
+                    if octant & (1 << dim) != 0 {
+                        *index += half_extent;
+                    }
+                }
+
+                let particle_count = subdomains.iter().map(|&s| particle_counts[s]).sum();
+                Box::new(OctreeNode {
+                    min_subdomain_ijk,
+                    extent: half_extent,
+                    subdomains,
+                    particle_count,
+                    children: Vec::new(),
+                })
+            })
+            .collect();
+    }
+}
+
+/// Merges the subdomains of the subdomain grid into the leaves of an octree that is adaptively refined according to the number of particles
+///
+/// The particle set of every leaf is the union of the particle sets of the subdomains it covers,
+/// which is exactly the set of particles inside of the leaf and inside of the ghost margin around the leaf.
+pub(crate) fn octree_decomposition<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    subdomains: Subdomains<I>,
+) -> Subdomains<I> {
+    profile!("octree decomposition");
+
+    let octree_parameters = parameters
+        .octree_parameters
+        .as_ref()
+        .expect("octree parameters are required for the octree decomposition");
+    let max_leaf_extent = I::from_u64(2u64.pow(octree_parameters.max_depth))
+        .expect("extent of the largest octree leaves has to fit in index type");
+
+    let subdomain_ijks = subdomains
+        .flat_subdomain_indices
+        .iter()
+        .map(|&flat_subdomain_idx| {
+            *parameters
+                .subdomain_grid
+                .try_unflatten_cell_index(flat_subdomain_idx)
+                .expect("Subdomain cell does not exist")
+                .index()
+        })
+        .collect::<Vec<_>>();
+    let particle_counts = subdomains
+        .per_subdomain_particles
+        .iter()
+        .map(Vec::len)
+        .collect::<Vec<_>>();
+
+    // The root node has to cover the entire subdomain grid
+    let num_subdomains = parameters.subdomain_grid.cells_per_dim();
+    let max_num_subdomains = num_subdomains[0]
+        .max(num_subdomains[1])
+        .max(num_subdomains[2]);
+    let mut root_extent = max_leaf_extent;
+    while root_extent < max_num_subdomains {
+        root_extent = root_extent.times(2);
+    }
+
+    let mut root = OctreeNode {
+        min_subdomain_ijk: [I::zero(); 3],
+        extent: root_extent,
+        subdomains: (0..subdomain_ijks.len()).collect(),
+        particle_count: particle_counts.iter().sum(),
+        children: Vec::new(),
+    };
+
+    {
+        profile!("octree refinement");
+        let max_particles = octree_parameters.max_particles_per_subdomain;
+        let refine = |node: &mut OctreeNode<I>| {
+            let too_large = node.extent > max_leaf_extent;
+            let too_many_particles = node.particle_count > max_particles && node.extent > I::one();
+            if too_large || too_many_particles {
+                node.subdivide(&subdomain_ijks, &particle_counts);
+            }
+        };
+
+        if parameters.enable_multi_threading {
+            root.par_visit_mut_bfs(refine);
+        } else {
+            root.visit_mut_bfs(refine);
+        }
+    }
+
+    let leaves = root
+        .dfs_iter()
+        .filter(|node| node.children.is_empty() && !node.subdomains.is_empty())
+        .collect::<Vec<_>>();
+
+    info!(
+        "Octree decomposition merged {} non-empty subdomains into {} octree leaves.",
+        subdomain_ijks.len(),
+        leaves.len()
+    );
+
+    let mut per_leaf_particles = Vec::with_capacity(leaves.len());
+    {
+        profile!("collect octree leaf particles");
+        let collect_leaf_particles = |leaf: &&OctreeNode<I>| {
+            let mut leaf_particles = Vec::with_capacity(leaf.particle_count);
+            for &subdomain in &leaf.subdomains {
+                leaf_particles.extend_from_slice(&subdomains.per_subdomain_particles[subdomain]);
+            }
+            // Ghost particles can be part of multiple subdomains of the leaf
+            leaf_particles.sort_unstable();
+            leaf_particles.dedup();
+            leaf_particles
+        };
+
+        if parameters.enable_multi_threading {
+            leaves
+                .par_iter()
+                .map(collect_leaf_particles)
+                .collect_into_vec(&mut per_leaf_particles);
+        } else {
+            per_leaf_particles.extend(leaves.iter().map(collect_leaf_particles));
+        }
+    }
+
+    Subdomains {
+        flat_subdomain_indices: leaves
+            .iter()
+            .map(|leaf| {
+                parameters
+                    .subdomain_grid
+                    .flatten_cell_index_array(&leaf.min_subdomain_ijk)
+            })
+            .collect(),
+        subdomain_extents: leaves.iter().map(|leaf| leaf.extent).collect(),
+        per_subdomain_particles: per_leaf_particles,
+    }
+}
@@ -0,0 +1,477 @@
+//! Out-of-core processing of the subdomain grid in batches of subdomains
+//!
+//! Instead of decomposing all particles at once, the particles are counted per subdomain in a
+//! first pass over the particle source. The non-empty subdomains are then grouped into batches
+//! (in the order of their flat indices) such that the particles required by a batch stay below a
+//! given limit. The particles of a batch are all particles of its subdomains and of the
+//! neighboring subdomains (including their ghost particles). This is sufficient to evaluate the
+//! densities of all particles that contribute to the level-set of the subdomains of the batch.
+//! The surface patches of a batch are stitched to the patches of the previous batches and written
+//! to the output sink right away.
+
+use super::subdomain_classification::{GhostMarginClassifier, ParticleToSubdomainClassifier};
+use super::{
+    compute_global_densities_and_neighbors, decomposition, reconstruction, ParametersSubdomainGrid,
+    Subdomains, SurfacePatch,
+};
+use crate::anisotropic_kernel::AnisotropicKernel;
+use crate::progress::ReconstructionStage;
+use crate::streaming::{MeshSink, ParticleSource};
+use crate::uniform_grid::EdgeIndex;
+use crate::{
+    new_map, profile, Aabb3d, Index, MapType, Real, ReconstructionContext, ReconstructionError,
+    SetType,
+};
+use anyhow::Context;
+use nalgebra::Vector3;
+use num_traits::NumCast;
+use rayon::prelude::*;
+
+/// Group of subdomains that are reconstructed together
+pub(crate) struct SubdomainBatch<I: Index> {
+    /// Flat indices of the subdomains to reconstruct in this batch (sorted)
+    pub subdomains: Vec<I>,
+    /// Flat indices of all non-empty subdomains whose particle densities are required by this batch (the subdomains of the batch and their neighbors)
+    pub required_subdomains: SetType<I>,
+    /// Number of particles of all required subdomains (ghost particles are counted for every subdomain they belong to)
+    pub particle_count: usize,
+}
+
+/// Returns an upper bound for the number of bytes required per particle (and per ghost particle) while processing a batch
+pub(crate) fn bytes_per_particle<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+) -> usize {
+    // Position, density, index in the particle list of a subdomain and (empty) global neighbor list
+    let mut bytes = std::mem::size_of::<Vector3<R>>()
+        + std::mem::size_of::<R>()
+        + std::mem::size_of::<usize>()
+        + std::mem::size_of::<Vec<usize>>();
+    if parameters.anisotropic_kernel.is_some() {
+        bytes += std::mem::size_of::<AnisotropicKernel<R>>();
+    }
+    bytes
+}
+
+/// Returns whether the particle is inside of the optional user provided AABB (particles outside of it are filtered out)
+fn is_inside_particle_aabb<R: Real>(
+    particle_aabb: Option<&Aabb3d<R>>,
+    particle: &Vector3<R>,
+) -> bool {
+    match particle_aabb {
+        Some(particle_aabb) => particle_aabb.contains_point(particle),
+        None => true,
+    }
+}
+
+/// Returns the flat indices of all subdomains the particle belongs to (including the subdomains where it is a ghost particle)
+fn particle_subdomains<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    particle: &Vector3<R>,
+) -> impl Iterator<Item = I> {
+    let mut classifier = <GhostMarginClassifier<I> as ParticleToSubdomainClassifier<I, R>>::new();
+    classifier.classify_particle(
+        particle,
+        &parameters.subdomain_grid,
+        parameters.ghost_particle_margin,
+    );
+    (0..ParticleToSubdomainClassifier::<I, R>::len(&classifier))
+        .map(move |i| ParticleToSubdomainClassifier::<I, R>::get(&classifier, i))
+}
+
+/// Counts the particles (including ghost particles) of every non-empty subdomain in a pass over all chunks of the particle source
+pub(crate) fn count_subdomain_particles<I: Index, R: Real, S: ParticleSource<R> + ?Sized>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    particle_source: &mut S,
+    particle_aabb: Option<&Aabb3d<R>>,
+) -> Result<MapType<I, usize>, anyhow::Error> {
+    profile!("count subdomain particles");
+
+    let count_particles = |mut counter: MapType<I, usize>, particles: &[Vector3<R>]| {
+        for particle in particles {
+            if !is_inside_particle_aabb(particle_aabb, particle) {
+                continue;
+            }
+            for flat_subdomain_idx in particle_subdomains(parameters, particle) {
+                *counter.entry(flat_subdomain_idx).or_insert(0) += 1;
+            }
+        }
+        counter
+    };
+
+    let merge_counters = |mut counter: MapType<I, usize>, other: MapType<I, usize>| {
+        for (flat_subdomain_idx, count) in other {
+            *counter.entry(flat_subdomain_idx).or_insert(0) += count;
+        }
+        counter
+    };
+
+    let mut per_subdomain_counter = new_map();
+    let mut chunk = Vec::new();
+    for chunk_index in 0..particle_source.num_chunks() {
+        particle_source
+            .read_chunk(chunk_index, &mut chunk)
+            .with_context(|| format!("failed to read particle chunk {}", chunk_index))?;
+
+        let chunk_counter = if parameters.enable_multi_threading {
+            chunk
+                .par_chunks(parameters.chunk_size)
+                .fold(new_map, count_particles)
+                .reduce(new_map, merge_counters)
+        } else {
+            count_particles(new_map(), &chunk)
+        };
+        per_subdomain_counter = merge_counters(per_subdomain_counter, chunk_counter);
+    }
+
+    Ok(per_subdomain_counter)
+}
+
+/// Appends the flat indices of all non-empty subdomains in the 3x3x3 neighborhood of the given subdomain (including itself)
+fn non_empty_neighborhood<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    per_subdomain_count: &MapType<I, usize>,
+    flat_subdomain_idx: I,
+    neighborhood: &mut Vec<I>,
+) {
+    let subdomain_ijk = *parameters
+        .subdomain_grid
+        .try_unflatten_cell_index(flat_subdomain_idx)
+        .expect("Subdomain cell does not exist")
+        .index();
+
+    for i in [-1, 0, 1] {
+        for j in [-1, 0, 1] {
+            for k in [-1, 0, 1] {
+                let neighbor_ijk = [
+                    subdomain_ijk[0] + to_index!(i),
+                    subdomain_ijk[1] + to_index!(j),
+                    subdomain_ijk[2] + to_index!(k),
+                ];
+                if let Some(cell) = parameters.subdomain_grid.get_cell(neighbor_ijk) {
+                    let flat_neighbor_idx = parameters.subdomain_grid.flatten_cell_index(&cell);
+                    if per_subdomain_count.contains_key(&flat_neighbor_idx) {
+                        neighborhood.push(flat_neighbor_idx);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Groups all non-empty subdomains into batches (in order of their flat indices) such that the particles required per batch do not exceed the given limit
+///
+/// A batch always contains at least one subdomain, even if its required particles exceed the limit.
+pub(crate) fn plan_batches<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    per_subdomain_count: &MapType<I, usize>,
+    max_batch_particles: usize,
+) -> Vec<SubdomainBatch<I>> {
+    profile!("plan subdomain batches");
+
+    let mut subdomains = per_subdomain_count.keys().copied().collect::<Vec<_>>();
+    subdomains.sort_unstable();
+
+    let new_batch = || SubdomainBatch {
+        subdomains: Vec::new(),
+        required_subdomains: SetType::default(),
+        particle_count: 0,
+    };
+
+    let mut batches = Vec::new();
+    let mut batch = new_batch();
+    let mut neighborhood = Vec::with_capacity(27);
+    for flat_subdomain_idx in subdomains {
+        neighborhood.clear();
+        non_empty_neighborhood(
+            parameters,
+            per_subdomain_count,
+            flat_subdomain_idx,
+            &mut neighborhood,
+        );
+
+        let additional_particles = |batch: &SubdomainBatch<I>| -> usize {
+            neighborhood
+                .iter()
+                .filter(|s| !batch.required_subdomains.contains(s))
+                .map(|s| per_subdomain_count[s])
+                .sum()
+        };
+
+        if !batch.subdomains.is_empty()
+            && batch.particle_count + additional_particles(&batch) > max_batch_particles
+        {
+            batches.push(std::mem::replace(&mut batch, new_batch()));
+        }
+
+        batch.particle_count += additional_particles(&batch);
+        batch
+            .required_subdomains
+            .extend(neighborhood.iter().copied());
+        batch.subdomains.push(flat_subdomain_idx);
+    }
+
+    if !batch.subdomains.is_empty() {
+        batches.push(batch);
+    }
+
+    batches
+}
+
+/// Collects all particles of the subdomains required by the batch (including ghost particles) from the particle source
+pub(crate) fn load_batch_particles<I: Index, R: Real, S: ParticleSource<R> + ?Sized>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    particle_source: &mut S,
+    particle_aabb: Option<&Aabb3d<R>>,
+    batch: &SubdomainBatch<I>,
+    particles: &mut Vec<Vector3<R>>,
+) -> Result<(), anyhow::Error> {
+    profile!("load batch particles");
+    particles.clear();
+
+    // Region of all particles that might be part of a required subdomain
+    let batch_aabb = {
+        let mut required_subdomains = batch.required_subdomains.iter();
+        let subdomain_aabb = |flat_subdomain_idx: &I| {
+            let subdomain_idx = parameters
+                .subdomain_grid
+                .try_unflatten_cell_index(*flat_subdomain_idx)
+                .expect("Subdomain cell does not exist");
+            parameters.subdomain_grid.cell_aabb(&subdomain_idx)
+        };
+
+        let mut batch_aabb = subdomain_aabb(
+            required_subdomains
+                .next()
+                .expect("a batch has to contain at least one subdomain"),
+        );
+        for flat_subdomain_idx in required_subdomains {
+            batch_aabb.join(&subdomain_aabb(flat_subdomain_idx));
+        }
+        batch_aabb.grow_uniformly(parameters.ghost_particle_margin);
+        batch_aabb
+    };
+
+    let is_required = |particle: &Vector3<R>| -> bool {
+        if !is_inside_particle_aabb(particle_aabb, particle) {
+            return false;
+        }
+
+        particle_subdomains(parameters, particle)
+            .any(|flat_subdomain_idx| batch.required_subdomains.contains(&flat_subdomain_idx))
+    };
+
+    let mut chunk = Vec::new();
+    for chunk_index in 0..particle_source.num_chunks() {
+        // Skip chunks that cannot contain any particles of the batch
+        if let Some(chunk_aabb) = particle_source.chunk_aabb(chunk_index) {
+            if !chunk_aabb.intersects_aabb(&batch_aabb) {
+                continue;
+            }
+        }
+
+        particle_source
+            .read_chunk(chunk_index, &mut chunk)
+            .with_context(|| format!("failed to read particle chunk {}", chunk_index))?;
+
+        if parameters.enable_multi_threading {
+            particles.par_extend(chunk.par_iter().copied().filter(is_required));
+        } else {
+            particles.extend(chunk.iter().copied().filter(is_required));
+        }
+    }
+
+    Ok(())
+}
+
+/// Keeps only the subdomains for which the predicate returns true
+fn retain_subdomains<I: Index>(
+    subdomains: Subdomains<I>,
+    mut predicate: impl FnMut(I) -> bool,
+) -> Subdomains<I> {
+    let mut retained = Subdomains {
+        flat_subdomain_indices: Vec::new(),
+        subdomain_extents: Vec::new(),
+        per_subdomain_particles: Vec::new(),
+    };
+
+    for ((flat_subdomain_idx, subdomain_extent), subdomain_particles) in subdomains
+        .flat_subdomain_indices
+        .into_iter()
+        .zip(subdomains.subdomain_extents)
+        .zip(subdomains.per_subdomain_particles)
+    {
+        if predicate(flat_subdomain_idx) {
+            retained.flat_subdomain_indices.push(flat_subdomain_idx);
+            retained.subdomain_extents.push(subdomain_extent);
+            retained.per_subdomain_particles.push(subdomain_particles);
+        }
+    }
+
+    retained
+}
+
+/// Computes the surface patches of all subdomains of the batch from the particles loaded for the batch
+///
+/// The observer of the `context` is notified about the stages and the processed subdomains of the batch.
+pub(crate) fn reconstruct_batch<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    particles: &[Vector3<R>],
+    batch: &SubdomainBatch<I>,
+    context: &ReconstructionContext,
+) -> Result<Vec<SurfacePatch<I, R>>, ReconstructionError<I, R>> {
+    profile!("reconstruct batch");
+
+    context.notify_stage(ReconstructionStage::Decomposition);
+    let subdomains = decomposition::<I, R, GhostMarginClassifier<I>>(parameters, particles, None)?;
+
+    // Subdomains at the boundary of the loaded region are incomplete
+    let subdomains = retain_subdomains(subdomains, |flat_subdomain_idx| {
+        batch.required_subdomains.contains(&flat_subdomain_idx)
+    });
+
+    context.check_cancelled()?;
+    context.notify_stage(ReconstructionStage::ParticleDensities);
+    let (particle_densities, _, anisotropic_kernels) =
+        compute_global_densities_and_neighbors(parameters, particles, None, &subdomains, context);
+    context.check_cancelled()?;
+
+    let subdomains = retain_subdomains(subdomains, |flat_subdomain_idx| {
+        batch.subdomains.binary_search(&flat_subdomain_idx).is_ok()
+    });
+    context.notify_stage(ReconstructionStage::SurfaceExtraction);
+    let surface_patches = reconstruction(
+        parameters,
+        particles,
+        &particle_densities,
+        None,
+        anisotropic_kernels.as_deref(),
+        &subdomains,
+        None,
+        None,
+        context,
+    );
+    // Patches of skipped subdomains are missing after a cancellation
+    context.check_cancelled()?;
+
+    Ok(surface_patches)
+}
+
+/// Stitches surface patches to a global mesh that is written incrementally to a [`MeshSink`]
+///
+/// In contrast to [`stitching`](super::stitching), vertices are written in the order in which they
+/// are first encountered. Only the global indices of exterior vertices that may still be shared
+/// with subdomains of subsequent batches are kept in memory.
+pub(crate) struct IncrementalStitcher<I: Index, R: Real> {
+    /// Global vertex indices of the exterior vertices written so far, identified by their globalized edge
+    exterior_vertex_mapping: MapType<(I, EdgeIndex<I>), usize>,
+    /// Total number of vertices written to the sink
+    num_vertices: usize,
+    /// Total number of triangles written to the sink
+    num_triangles: usize,
+    /// Buffer for the mapping of local to global vertex indices of a patch
+    local_to_global_vertex_mapping: Vec<usize>,
+    /// Buffer for the new vertices of a patch
+    vertex_buffer: Vec<Vector3<R>>,
+    /// Buffer for the globalized triangles of a patch
+    triangle_buffer: Vec<[usize; 3]>,
+}
+
+impl<I: Index, R: Real> IncrementalStitcher<I, R> {
+    pub(crate) fn new() -> Self {
+        Self {
+            exterior_vertex_mapping: new_map(),
+            num_vertices: 0,
+            num_triangles: 0,
+            local_to_global_vertex_mapping: Vec::new(),
+            vertex_buffer: Vec::new(),
+            triangle_buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the total number of vertices written to the sink
+    pub(crate) fn num_vertices(&self) -> usize {
+        self.num_vertices
+    }
+
+    /// Returns the total number of triangles written to the sink
+    pub(crate) fn num_triangles(&self) -> usize {
+        self.num_triangles
+    }
+
+    /// Writes all new vertices and all triangles of the patch to the sink
+    pub(crate) fn stitch_patch<M: MeshSink<R> + ?Sized>(
+        &mut self,
+        patch: &SurfacePatch<I, R>,
+        mesh_sink: &mut M,
+    ) -> Result<(), anyhow::Error> {
+        self.local_to_global_vertex_mapping.clear();
+        self.vertex_buffer.clear();
+        self.triangle_buffer.clear();
+
+        // For each exterior vertex there is a corresponding globalized edge index
+        let mut exterior_edge_indices = patch.exterior_vertex_edge_indices.iter();
+        for (vertex, is_interior) in patch
+            .vertices
+            .iter()
+            .zip(patch.vertex_inside_flags.iter().copied())
+        {
+            let next_global_index = self.num_vertices + self.vertex_buffer.len();
+            let global_index = if is_interior {
+                self.vertex_buffer.push(*vertex);
+                next_global_index
+            } else {
+                let edge_index = exterior_edge_indices
+                    .next()
+                    .expect("every exterior vertex has to have a globalized edge index");
+                *self
+                    .exterior_vertex_mapping
+                    .entry(*edge_index)
+                    .or_insert_with(|| {
+                        self.vertex_buffer.push(*vertex);
+                        next_global_index
+                    })
+            };
+            self.local_to_global_vertex_mapping.push(global_index);
+        }
+
+        self.triangle_buffer.extend(
+            patch
+                .triangles
+                .iter()
+                .map(|tri| tri.map(|v| self.local_to_global_vertex_mapping[v])),
+        );
+
+        mesh_sink.append_vertices(&self.vertex_buffer)?;
+        mesh_sink.append_triangles(&self.triangle_buffer)?;
+        self.num_vertices += self.vertex_buffer.len();
+        self.num_triangles += self.triangle_buffer.len();
+        Ok(())
+    }
+
+    /// Forgets all exterior vertices that cannot be shared with subdomains after the given subdomain anymore
+    ///
+    /// Exterior vertices are only shared between neighboring subdomains. Therefore, a vertex can
+    /// be forgotten once all subdomains in the neighborhood of the subdomain owning its edge are stitched.
+    pub(crate) fn forget_exterior_vertices_before(
+        &mut self,
+        parameters: &ParametersSubdomainGrid<I, R>,
+        last_stitched_subdomain: I,
+    ) {
+        let num_subdomains = parameters.subdomain_grid.cells_per_dim();
+        self.exterior_vertex_mapping
+            .retain(|(flat_subdomain_idx, _), _| {
+                let subdomain_ijk = *parameters
+                    .subdomain_grid
+                    .try_unflatten_cell_index(*flat_subdomain_idx)
+                    .expect("Subdomain cell does not exist")
+                    .index();
+                // The neighbor with the largest flat index
+                let last_neighbor_ijk = [0, 1, 2]
+                    .map(|dim| (subdomain_ijk[dim] + I::one()).min(num_subdomains[dim] - I::one()));
+                parameters
+                    .subdomain_grid
+                    .flatten_cell_index_array(&last_neighbor_ijk)
+                    > last_stitched_subdomain
+            });
+    }
+}
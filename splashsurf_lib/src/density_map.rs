@@ -19,7 +19,8 @@
 //! indices, even if the density map is only generated for a smaller subdomain.
 
 use crate::aabb::Aabb3d;
-use crate::kernel::DiscreteSquaredDistanceCubicKernel;
+use crate::anisotropic_kernel::AnisotropicKernel;
+use crate::kernel::{DiscreteSquaredDistanceCubicKernel, KernelType};
 use crate::mesh::{HexMesh3d, MeshAttribute, MeshWithData};
 use crate::neighborhood_search::NeighborhoodList;
 use crate::uniform_grid::UniformGrid;
@@ -59,6 +60,7 @@ pub fn compute_particle_densities<I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &[Vec<usize>],
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
     enable_multi_threading: bool,
 ) -> Vec<R> {
@@ -68,6 +70,7 @@ pub fn compute_particle_densities<I: Index, R: Real>(
             particle_positions,
             particle_neighbor_lists,
             compact_support_radius,
+            kernel_type,
             particle_rest_mass,
             &mut densities,
         )
@@ -76,6 +79,7 @@ pub fn compute_particle_densities<I: Index, R: Real>(
             particle_positions,
             particle_neighbor_lists,
             compact_support_radius,
+            kernel_type,
             particle_rest_mass,
             &mut densities,
         )
@@ -89,6 +93,7 @@ pub fn compute_particle_densities_inplace<I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &[Vec<usize>],
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
     enable_multi_threading: bool,
     densities: &mut Vec<R>,
@@ -98,6 +103,7 @@ pub fn compute_particle_densities_inplace<I: Index, R: Real>(
             particle_positions,
             particle_neighbor_lists,
             compact_support_radius,
+            kernel_type,
             particle_rest_mass,
             densities,
         )
@@ -106,6 +112,7 @@ pub fn compute_particle_densities_inplace<I: Index, R: Real>(
             particle_positions,
             particle_neighbor_lists,
             compact_support_radius,
+            kernel_type,
             particle_rest_mass,
             densities,
         )
@@ -124,6 +131,7 @@ pub fn sequential_compute_particle_densities<I: Index, R: Real, Nl: Neighborhood
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &Nl,
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
     particle_densities: &mut Vec<R>,
 ) {
@@ -133,6 +141,7 @@ pub fn sequential_compute_particle_densities<I: Index, R: Real, Nl: Neighborhood
         particle_positions,
         particle_neighbor_lists,
         compact_support_radius,
+        kernel_type,
         particle_rest_mass,
         particle_densities,
         |_| true,
@@ -148,6 +157,7 @@ pub fn sequential_compute_particle_densities_filtered<
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &Nl,
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
     particle_densities: &mut Vec<R>,
     filter: impl Fn(usize) -> bool,
@@ -157,7 +167,11 @@ pub fn sequential_compute_particle_densities_filtered<
     init_density_storage(particle_densities, particle_positions.len());
 
     // Pre-compute the kernel which can be queried using squared distances
-    let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, compact_support_radius);
+    let kernel = DiscreteSquaredDistanceCubicKernel::with_kernel_type::<f64>(
+        kernel_type,
+        1000,
+        compact_support_radius,
+    );
 
     for (i, particle_i_position) in particle_positions
         .iter()
@@ -184,6 +198,7 @@ pub fn parallel_compute_particle_densities<I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &[Vec<usize>],
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
     particle_densities: &mut Vec<R>,
 ) {
@@ -192,7 +207,11 @@ pub fn parallel_compute_particle_densities<I: Index, R: Real>(
     init_density_storage(particle_densities, particle_positions.len());
 
     // Pre-compute the kernel which can be queried using squared distances
-    let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, compact_support_radius);
+    let kernel = DiscreteSquaredDistanceCubicKernel::with_kernel_type::<f64>(
+        kernel_type,
+        1000,
+        compact_support_radius,
+    );
 
     particle_positions
         .par_iter()
@@ -214,6 +233,142 @@ pub fn parallel_compute_particle_densities<I: Index, R: Real>(
         );
 }
 
+/// Computes the individual densities of particles with per-particle masses and compact support radii inplace using a standard SPH sum
+///
+/// The density of particle `i` is given by `ρ_i = Σ_j m_j W(x_i - x_j, h_j)`, i.e. each neighbor
+/// contributes with its own mass and kernel. This is consistent with the evaluation of the
+/// density map for particles with varying size. The neighbor lists have to be computed with
+/// the largest compact support radius of all particles.
+#[inline(never)]
+pub fn compute_particle_densities_polydisperse_inplace<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_neighbor_lists: &[Vec<usize>],
+    particle_masses: &[R],
+    particle_compact_support_radii: &[R],
+    kernel_type: KernelType,
+    enable_multi_threading: bool,
+    densities: &mut Vec<R>,
+) {
+    if enable_multi_threading {
+        parallel_compute_particle_densities_polydisperse::<I, R>(
+            particle_positions,
+            particle_neighbor_lists,
+            particle_masses,
+            particle_compact_support_radii,
+            kernel_type,
+            densities,
+        )
+    } else {
+        sequential_compute_particle_densities_polydisperse_filtered::<I, R, _>(
+            particle_positions,
+            particle_neighbor_lists,
+            particle_masses,
+            particle_compact_support_radii,
+            kernel_type,
+            densities,
+            |_| true,
+        )
+    }
+}
+
+/// Computes the individual densities of particles with per-particle masses and compact support radii, sequential implementation that only updates the particles accepted by the filter
+#[inline(never)]
+pub fn sequential_compute_particle_densities_polydisperse_filtered<
+    I: Index,
+    R: Real,
+    Nl: NeighborhoodList + ?Sized,
+>(
+    particle_positions: &[Vector3<R>],
+    particle_neighbor_lists: &Nl,
+    particle_masses: &[R],
+    particle_compact_support_radii: &[R],
+    kernel_type: KernelType,
+    particle_densities: &mut Vec<R>,
+    filter: impl Fn(usize) -> bool,
+) {
+    profile!("sequential_compute_particle_densities_polydisperse_filtered");
+
+    init_density_storage(particle_densities, particle_positions.len());
+
+    // Pre-compute the kernel for the largest compact support radius, it is rescaled for all other radii
+    let kernel = DiscreteSquaredDistanceCubicKernel::with_kernel_type::<f64>(
+        kernel_type,
+        1000,
+        max_compact_support_radius(particle_compact_support_radii),
+    );
+
+    for (i, particle_i_position) in particle_positions
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| filter(*i))
+    {
+        let mut particle_i_density = particle_masses[i]
+            * kernel.evaluate_with_compact_support(R::zero(), particle_compact_support_radii[i]);
+        for &j in particle_neighbor_lists.neighbors(i).iter() {
+            let r_squared = (particle_positions[j] - particle_i_position).norm_squared();
+            particle_i_density += particle_masses[j]
+                * kernel
+                    .evaluate_with_compact_support(r_squared, particle_compact_support_radii[j]);
+        }
+        particle_densities[i] = particle_i_density;
+    }
+}
+
+/// Computes the individual densities of particles with per-particle masses and compact support radii, multi-threaded implementation
+#[inline(never)]
+pub fn parallel_compute_particle_densities_polydisperse<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_neighbor_lists: &[Vec<usize>],
+    particle_masses: &[R],
+    particle_compact_support_radii: &[R],
+    kernel_type: KernelType,
+    particle_densities: &mut Vec<R>,
+) {
+    profile!("parallel_compute_particle_densities_polydisperse");
+
+    init_density_storage(particle_densities, particle_positions.len());
+
+    // Pre-compute the kernel for the largest compact support radius, it is rescaled for all other radii
+    let kernel = DiscreteSquaredDistanceCubicKernel::with_kernel_type::<f64>(
+        kernel_type,
+        1000,
+        max_compact_support_radius(particle_compact_support_radii),
+    );
+
+    particle_positions
+        .par_iter()
+        .with_min_len(8)
+        .zip_eq(particle_neighbor_lists.par_iter())
+        .zip_eq(particle_densities.par_iter_mut())
+        .enumerate()
+        .for_each(
+            |(i, ((particle_i_position, particle_i_neighbors), particle_i_density))| {
+                let mut density = particle_masses[i]
+                    * kernel.evaluate_with_compact_support(
+                        R::zero(),
+                        particle_compact_support_radii[i],
+                    );
+                for &j in particle_i_neighbors.iter() {
+                    let r_squared = (particle_positions[j] - particle_i_position).norm_squared();
+                    density += particle_masses[j]
+                        * kernel.evaluate_with_compact_support(
+                            r_squared,
+                            particle_compact_support_radii[j],
+                        );
+                }
+                *particle_i_density = density;
+            },
+        );
+}
+
+/// Returns the largest of the given compact support radii
+fn max_compact_support_radius<R: Real>(compact_support_radii: &[R]) -> R {
+    compact_support_radii
+        .iter()
+        .copied()
+        .fold(R::zero(), |max, h| max.max(h))
+}
+
 /// A sparse density map
 ///
 /// The density map contains values for all points of the background grid where the density is not
@@ -277,6 +432,198 @@ impl<I: Index, R: Real> DensityMap<I, R> {
     }
 }
 
+/// A dense block of density values of a [`SparseDensityGrid`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DensityBlock<I: Index, R: Real> {
+    /// Index of the block in the grid of blocks, the first point of the block is the background grid point `block_ijk * block_cubes`
+    pub block_ijk: [I; 3],
+    /// Density values of all `(block_cubes + 1)^3` points of the block, in the same order as the flat point indices of the background grid
+    pub values: Vec<R>,
+}
+
+/// A sparse grid of dense blocks of density values
+///
+/// The background grid is divided into cubic blocks of `block_cubes` cells per dimension and only
+/// blocks where the density is not trivially zero are stored. Every block stores the values of
+/// all its points, i.e. points on the boundary between two blocks are stored in both blocks.
+/// Blocks at the upper boundary of the background grid may extend beyond the grid.
+///
+/// With the subdomain-grid reconstruction, every block corresponds to the dense level-set grid of
+/// a subdomain (see [`SurfaceReconstruction::density_grid`](crate::SurfaceReconstruction::density_grid)).
+#[derive(Clone, Debug)]
+pub struct SparseDensityGrid<I: Index, R: Real> {
+    /// Background grid of the density values
+    grid: UniformGrid<I, R>,
+    /// Number of cells of the background grid per block along each axis
+    block_cubes: I,
+    /// Maps the index of a block to its position in the block storage
+    block_map: MapType<[I; 3], usize>,
+    /// Storage of all blocks (in insertion order)
+    blocks: Vec<DensityBlock<I, R>>,
+}
+
+impl<I: Index, R: Real> SparseDensityGrid<I, R> {
+    /// Creates an empty sparse density grid with the given background grid and block size
+    pub fn new(grid: UniformGrid<I, R>, block_cubes: I) -> Self {
+        assert!(
+            block_cubes > I::zero(),
+            "number of cubes per block has to be positive"
+        );
+        Self {
+            grid,
+            block_cubes,
+            block_map: new_map(),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Converts a sparse density map into a sparse density grid with blocks of the given size
+    ///
+    /// Points of blocks that are not contained in the density map are set to zero.
+    pub fn from_density_map(
+        density_map: &DensityMap<I, R>,
+        grid: &UniformGrid<I, R>,
+        block_cubes: I,
+    ) -> Self {
+        profile!("SparseDensityGrid::from_density_map");
+
+        let mut density_grid = Self::new(grid.clone(), block_cubes);
+        let block_points = density_grid.block_points();
+        density_map.for_each(|flat_point_index, density| {
+            let point = grid.try_unflatten_point_index(flat_point_index).unwrap();
+            for block_ijk in density_grid.blocks_containing_point(point.index()) {
+                let local_index = density_grid.local_point_index(&block_ijk, point.index());
+                let num_blocks = density_grid.blocks.len();
+                let block_index = *density_grid
+                    .block_map
+                    .entry(block_ijk)
+                    .or_insert(num_blocks);
+                if block_index == num_blocks {
+                    density_grid.blocks.push(DensityBlock {
+                        block_ijk,
+                        values: vec![R::zero(); block_points],
+                    });
+                }
+                density_grid.blocks[block_index].values[local_index] = density;
+            }
+        });
+
+        density_grid
+    }
+
+    /// Returns the background grid of the density values
+    pub fn grid(&self) -> &UniformGrid<I, R> {
+        &self.grid
+    }
+
+    /// Returns the number of cells of the background grid per block along each axis
+    pub fn block_cubes(&self) -> I {
+        self.block_cubes
+    }
+
+    /// Returns the number of points (i.e. values) per block
+    pub fn block_points(&self) -> usize {
+        (self.block_cubes + I::one()).to_usize().unwrap().pow(3)
+    }
+
+    /// Returns the number of stored blocks
+    pub fn num_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns all stored blocks
+    pub fn blocks(&self) -> &[DensityBlock<I, R>] {
+        self.blocks.as_slice()
+    }
+
+    /// Returns the block with the given index if it is stored
+    pub fn get_block(&self, block_ijk: &[I; 3]) -> Option<&DensityBlock<I, R>> {
+        self.block_map
+            .get(block_ijk)
+            .map(|&block_index| &self.blocks[block_index])
+    }
+
+    /// Inserts a block into the grid, replacing an existing block with the same index
+    pub fn insert_block(&mut self, block: DensityBlock<I, R>) {
+        assert_eq!(
+            block.values.len(),
+            self.block_points(),
+            "number of values of the block does not match the block size"
+        );
+        match self.block_map.get(&block.block_ijk) {
+            Some(&block_index) => self.blocks[block_index] = block,
+            None => {
+                self.block_map.insert(block.block_ijk, self.blocks.len());
+                self.blocks.push(block);
+            }
+        }
+    }
+
+    /// Returns the density value at the given point of the background grid if it is part of a stored block
+    pub fn get(&self, point_ijk: &[I; 3]) -> Option<R> {
+        self.blocks_containing_point(point_ijk)
+            .find_map(|block_ijk| {
+                self.get_block(&block_ijk)
+                    .map(|block| block.values[self.local_point_index(&block_ijk, point_ijk)])
+            })
+    }
+
+    /// Returns the density values of all points of the background grid in the order of their flat point indices
+    ///
+    /// Points that are not part of any stored block are set to the given default value.
+    pub fn to_dense(&self, default_value: R) -> Vec<R> {
+        profile!("SparseDensityGrid::to_dense");
+
+        let points_per_dim = self.grid.points_per_dim().map(|n| n.to_usize().unwrap());
+        let mut values = vec![default_value; points_per_dim.iter().product()];
+
+        let block_cubes = self.block_cubes.to_usize().unwrap();
+        let n = block_cubes + 1;
+        for block in &self.blocks {
+            let offset = block.block_ijk.map(|b| b.to_usize().unwrap() * block_cubes);
+            for i in 0..n.min(points_per_dim[0].saturating_sub(offset[0])) {
+                for j in 0..n.min(points_per_dim[1].saturating_sub(offset[1])) {
+                    for k in 0..n.min(points_per_dim[2].saturating_sub(offset[2])) {
+                        let [gi, gj, gk] = [offset[0] + i, offset[1] + j, offset[2] + k];
+                        values[(gi * points_per_dim[1] + gj) * points_per_dim[2] + gk] =
+                            block.values[(i * n + j) * n + k];
+                    }
+                }
+            }
+        }
+
+        values
+    }
+
+    /// Returns the indices of all (possibly not stored) blocks containing the given point
+    fn blocks_containing_point(&self, point_ijk: &[I; 3]) -> impl Iterator<Item = [I; 3]> {
+        // Points on the boundary of a block are also part of the adjacent lower block
+        let candidates = point_ijk.map(|p| {
+            let (block, local) = p.div_rem(&self.block_cubes);
+            let lower = (local == I::zero() && block > I::zero()).then(|| block - I::one());
+            [Some(block), lower]
+        });
+
+        candidates[0].into_iter().flatten().flat_map(move |i| {
+            candidates[1]
+                .into_iter()
+                .flatten()
+                .flat_map(move |j| candidates[2].into_iter().flatten().map(move |k| [i, j, k]))
+        })
+    }
+
+    /// Returns the index of the given point in the values of the given block
+    fn local_point_index(&self, block_ijk: &[I; 3], point_ijk: &[I; 3]) -> usize {
+        let n = (self.block_cubes + I::one()).to_usize().unwrap();
+        let [i, j, k] = [0, 1, 2].map(|d| {
+            (point_ijk[d] - block_ijk[d] * self.block_cubes)
+                .to_usize()
+                .unwrap()
+        });
+        (i * n + j) * n + k
+    }
+}
+
 /// Computes a sparse density map for the fluid based on the specified background grid
 #[inline(never)]
 pub fn generate_sparse_density_map<I: Index, R: Real>(
@@ -286,6 +633,7 @@ pub fn generate_sparse_density_map<I: Index, R: Real>(
     active_particles: Option<&[usize]>,
     particle_rest_mass: R,
     compact_support_radius: R,
+    kernel_type: KernelType,
     cube_size: R,
     allow_threading: bool,
     density_map: &mut DensityMap<I, R>,
@@ -307,6 +655,7 @@ pub fn generate_sparse_density_map<I: Index, R: Real>(
             active_particles,
             particle_rest_mass,
             compact_support_radius,
+            kernel_type,
             cube_size,
         )?
     } else {
@@ -317,6 +666,7 @@ pub fn generate_sparse_density_map<I: Index, R: Real>(
             active_particles,
             particle_rest_mass,
             compact_support_radius,
+            kernel_type,
             cube_size,
         )?
     }
@@ -329,6 +679,159 @@ pub fn generate_sparse_density_map<I: Index, R: Real>(
     Ok(())
 }
 
+/// Computes a sparse density map for the fluid with per-particle masses and compact support radii based on the specified background grid
+///
+/// Every particle only contributes to the grid points inside of its own compact support radius,
+/// so the resulting narrow band of the density map adapts to the size of the particles.
+#[inline(never)]
+pub fn generate_sparse_density_map_polydisperse<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    particle_positions: &[Vector3<R>],
+    particle_densities: &[R],
+    particle_masses: &[R],
+    particle_compact_support_radii: &[R],
+    kernel_type: KernelType,
+    active_particles: Option<&[usize]>,
+    cube_size: R,
+    allow_threading: bool,
+    density_map: &mut DensityMap<I, R>,
+) -> Result<(), DensityMapError<R>> {
+    trace!(
+        "Starting construction of sparse density map with per-particle properties... (Input: {} particles)",
+        if let Some(active_particles) = active_particles {
+            active_particles.len()
+        } else {
+            particle_positions.len()
+        }
+    );
+
+    let density_map_generator = SparseDensityMapGenerator::try_new(
+        grid,
+        max_compact_support_radius(particle_compact_support_radii),
+        kernel_type,
+        cube_size,
+        R::zero(),
+    )?;
+
+    let process_particle = |map: &mut MapType<I, R>, i: usize| {
+        density_map_generator.compute_particle_density_contribution_polydisperse(
+            grid,
+            map,
+            &particle_positions[i],
+            particle_masses[i] / particle_densities[i],
+            particle_compact_support_radii[i],
+        );
+    };
+
+    if allow_threading {
+        profile!("parallel_generate_sparse_density_map_polydisperse");
+        *density_map = parallel_density_map_from_contributions(
+            particle_positions.len(),
+            active_particles,
+            process_particle,
+        );
+    } else {
+        profile!("sequential_generate_sparse_density_map_polydisperse");
+        let mut sparse_densities = new_map();
+        match active_particles {
+            None => (0..particle_positions.len())
+                .for_each(|i| process_particle(&mut sparse_densities, i)),
+            Some(indices) => indices
+                .iter()
+                .for_each(|&i| process_particle(&mut sparse_densities, i)),
+        }
+        *density_map = sparse_densities.into();
+    }
+
+    trace!(
+        "Sparse density map was constructed. (Output: density map with {} grid point data entries)",
+        density_map.len()
+    );
+
+    Ok(())
+}
+
+/// Computes a sparse density map for the fluid with anisotropic kernels based on the specified background grid
+///
+/// Every particle contributes to the density map with its own anisotropic kernel (see [`AnisotropicKernel`])
+/// around the kernel center instead of the particle position. If no per-particle masses are provided,
+/// all particles have the mass `particle_rest_mass`.
+#[inline(never)]
+pub fn generate_sparse_density_map_anisotropic<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    particle_densities: &[R],
+    particle_masses: Option<&[R]>,
+    particle_rest_mass: R,
+    anisotropic_kernels: &[AnisotropicKernel<R>],
+    kernel_type: KernelType,
+    active_particles: Option<&[usize]>,
+    cube_size: R,
+    allow_threading: bool,
+    density_map: &mut DensityMap<I, R>,
+) -> Result<(), DensityMapError<R>> {
+    assert_eq!(particle_densities.len(), anisotropic_kernels.len());
+    trace!(
+        "Starting construction of sparse density map with anisotropic kernels... (Input: {} particles)",
+        if let Some(active_particles) = active_particles {
+            active_particles.len()
+        } else {
+            anisotropic_kernels.len()
+        }
+    );
+
+    let max_support_radius = anisotropic_kernels
+        .iter()
+        .map(|kernel| kernel.support_radius)
+        .fold(R::zero(), |max, r| max.max(r));
+
+    let density_map_generator = SparseDensityMapGenerator::try_new(
+        grid,
+        max_support_radius,
+        kernel_type,
+        cube_size,
+        particle_rest_mass,
+    )?;
+
+    let process_particle = |map: &mut MapType<I, R>, i: usize| {
+        let particle_mass = particle_masses
+            .map(|masses| masses[i])
+            .unwrap_or(particle_rest_mass);
+        density_map_generator.compute_particle_density_contribution_anisotropic(
+            grid,
+            map,
+            &anisotropic_kernels[i],
+            particle_mass / particle_densities[i],
+        );
+    };
+
+    if allow_threading {
+        profile!("parallel_generate_sparse_density_map_anisotropic");
+        *density_map = parallel_density_map_from_contributions(
+            anisotropic_kernels.len(),
+            active_particles,
+            process_particle,
+        );
+    } else {
+        profile!("sequential_generate_sparse_density_map_anisotropic");
+        let mut sparse_densities = new_map();
+        match active_particles {
+            None => (0..anisotropic_kernels.len())
+                .for_each(|i| process_particle(&mut sparse_densities, i)),
+            Some(indices) => indices
+                .iter()
+                .for_each(|&i| process_particle(&mut sparse_densities, i)),
+        }
+        *density_map = sparse_densities.into();
+    }
+
+    trace!(
+        "Sparse density map was constructed. (Output: density map with {} grid point data entries)",
+        density_map.len()
+    );
+
+    Ok(())
+}
+
 /// Computes a sparse density map for the fluid based on the specified background grid, sequential implementation
 #[inline(never)]
 pub fn sequential_generate_sparse_density_map<I: Index, R: Real>(
@@ -338,6 +841,7 @@ pub fn sequential_generate_sparse_density_map<I: Index, R: Real>(
     active_particles: Option<&[usize]>,
     particle_rest_mass: R,
     compact_support_radius: R,
+    kernel_type: KernelType,
     cube_size: R,
 ) -> Result<DensityMap<I, R>, DensityMapError<R>> {
     profile!("sequential_generate_sparse_density_map");
@@ -347,6 +851,7 @@ pub fn sequential_generate_sparse_density_map<I: Index, R: Real>(
     let density_map_generator = SparseDensityMapGenerator::try_new(
         grid,
         compact_support_radius,
+        kernel_type,
         cube_size,
         particle_rest_mass,
     )?;
@@ -385,56 +890,64 @@ pub fn parallel_generate_sparse_density_map<I: Index, R: Real>(
     active_particles: Option<&[usize]>,
     particle_rest_mass: R,
     compact_support_radius: R,
+    kernel_type: KernelType,
     cube_size: R,
 ) -> Result<DensityMap<I, R>, DensityMapError<R>> {
     profile!("parallel_generate_sparse_density_map");
 
+    let density_map_generator = SparseDensityMapGenerator::try_new(
+        grid,
+        compact_support_radius,
+        kernel_type,
+        cube_size,
+        particle_rest_mass,
+    )?;
+
+    Ok(parallel_density_map_from_contributions(
+        particle_positions.len(),
+        active_particles,
+        |map, i| {
+            density_map_generator.compute_particle_density_contribution(
+                grid,
+                map,
+                &particle_positions[i],
+                particle_densities[i],
+            );
+        },
+    ))
+}
+
+/// Evaluates the density contributions of all (active) particles into thread local maps and merges them into a global density map
+fn parallel_density_map_from_contributions<I: Index, R: Real>(
+    num_particles: usize,
+    active_particles: Option<&[usize]>,
+    process_particle: impl Fn(&mut MapType<I, R>, usize) + Sync,
+) -> DensityMap<I, R> {
     // Each thread will write to its own local density map
     let sparse_densities: ThreadLocal<RefCell<MapType<I, R>>> = ThreadLocal::new();
 
     // Generate thread local density maps
     {
-        let density_map_generator = SparseDensityMapGenerator::try_new(
-            grid,
-            compact_support_radius,
-            cube_size,
-            particle_rest_mass,
-        )?;
-
         profile!("generate thread local maps");
 
         match active_particles {
             // Process particles, when no list of active particles was provided
             None => {
-                let chunk_size =
-                    ChunkSize::new(&ParallelPolicy::default(), particle_positions.len())
-                        .with_log("particles", "density map generation")
-                        .chunk_size;
-
-                particle_positions
-                    .par_chunks(chunk_size)
-                    .zip(particle_densities.par_chunks(chunk_size))
-                    .for_each(|(position_chunk, density_chunk)| {
+                let chunk_size = ChunkSize::new(&ParallelPolicy::default(), num_particles)
+                    .with_log("particles", "density map generation")
+                    .chunk_size;
+
+                (0..num_particles)
+                    .into_par_iter()
+                    .step_by(chunk_size)
+                    .for_each(|chunk_start| {
                         // Obtain mutable reference to thread local density map
                         let map = sparse_densities
                             .get_or(|| RefCell::new(MapType::with_hasher(HashState::default())));
                         let mut mut_map = map.borrow_mut();
 
-                        let process_particle_map = |particle_data: (&Vector3<R>, R)| {
-                            let (particle, particle_density) = particle_data;
-                            density_map_generator.compute_particle_density_contribution(
-                                grid,
-                                &mut mut_map,
-                                particle,
-                                particle_density,
-                            );
-                        };
-
-                        assert_eq!(position_chunk.len(), density_chunk.len());
-                        position_chunk
-                            .iter()
-                            .zip(density_chunk.iter().copied())
-                            .for_each(process_particle_map);
+                        let chunk_end = (chunk_start + chunk_size).min(num_particles);
+                        (chunk_start..chunk_end).for_each(|i| process_particle(&mut mut_map, i));
                     })
             }
             // Process particles, when only a subset is active
@@ -449,20 +962,9 @@ pub fn parallel_generate_sparse_density_map<I: Index, R: Real>(
                         .get_or(|| RefCell::new(MapType::with_hasher(HashState::default())));
                     let mut mut_map = map.borrow_mut();
 
-                    let process_particle_map = |particle_data: (&Vector3<R>, R)| {
-                        let (particle, particle_density) = particle_data;
-                        density_map_generator.compute_particle_density_contribution(
-                            grid,
-                            &mut mut_map,
-                            particle,
-                            particle_density,
-                        );
-                    };
-
                     index_chunk
                         .iter()
-                        .map(|&i| (&particle_positions[i], particle_densities[i]))
-                        .for_each(process_particle_map);
+                        .for_each(|&i| process_particle(&mut mut_map, i));
                 });
             }
         }
@@ -491,13 +993,14 @@ pub fn parallel_generate_sparse_density_map<I: Index, R: Real>(
             }
         });
 
-        Ok(global_density_map.into())
+        global_density_map.into()
     }
 }
 
 /// Internal helper type used to evaluate the density contribution for a particle
 struct SparseDensityMapGenerator<I: Index, R: Real> {
     particle_rest_mass: R,
+    cube_size: R,
     half_supported_cells: I,
     supported_points: I,
     kernel_evaluation_radius_sq: R,
@@ -544,6 +1047,7 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
     fn try_new(
         grid: &UniformGrid<I, R>,
         compact_support_radius: R,
+        kernel_type: KernelType,
         cube_size: R,
         particle_rest_mass: R,
     ) -> Result<Self, DensityMapError<R>> {
@@ -555,7 +1059,11 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
 
         // Pre-compute the kernel which can be queried using squared distances
         let kernel_evaluation_radius_sq = kernel_evaluation_radius * kernel_evaluation_radius;
-        let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, compact_support_radius);
+        let kernel = DiscreteSquaredDistanceCubicKernel::with_kernel_type::<f64>(
+            kernel_type,
+            1000,
+            compact_support_radius,
+        );
 
         // Shrink the allowed domain for particles by the kernel evaluation radius. This ensures that all cells/points
         // that are affected by a particle are actually part of the domain/grid, so it does not have to be checked in the loops below.
@@ -587,6 +1095,7 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
                 kernel,
                 allowed_domain,
                 particle_rest_mass,
+                cube_size,
             })
         }
     }
@@ -604,29 +1113,96 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
             return;
         }
 
-        // Compute grid points affected by the particle
-        let min_supported_point_ijk = {
-            let cell_ijk = grid.enclosing_cell(particle);
-            [
-                cell_ijk[0] - self.half_supported_cells,
-                cell_ijk[1] - self.half_supported_cells,
-                cell_ijk[2] - self.half_supported_cells,
-            ]
-        };
+        // Compute the volume of this particle
+        let particle_volume = self.particle_rest_mass / particle_density;
 
-        let max_supported_point_ijk = [
-            min_supported_point_ijk[0] + self.supported_points,
-            min_supported_point_ijk[1] + self.supported_points,
-            min_supported_point_ijk[2] + self.supported_points,
-        ];
+        self.particle_support_loop(
+            sparse_densities,
+            grid,
+            particle,
+            self.half_supported_cells,
+            self.supported_points,
+            self.kernel_evaluation_radius_sq,
+            |r_squared, _| particle_volume * self.kernel.evaluate(r_squared),
+        );
+    }
+
+    /// Computes all density contributions of a particle with its own volume and compact support radius to the background grid into the given map
+    fn compute_particle_density_contribution_polydisperse(
+        &self,
+        grid: &UniformGrid<I, R>,
+        sparse_densities: &mut MapType<I, R>,
+        particle: &Vector3<R>,
+        particle_volume: R,
+        compact_support_radius: R,
+    ) {
+        // Skip particles outside of allowed domain
+        if !self.allowed_domain.contains_point(particle) {
+            return;
+        }
+
+        // Only loop over the grid points that can be reached by the support of this particle
+        let GridKernelExtents {
+            half_supported_cells,
+            supported_points,
+            kernel_evaluation_radius,
+        } = compute_kernel_evaluation_radius(compact_support_radius, self.cube_size);
 
         self.particle_support_loop(
             sparse_densities,
             grid,
-            &min_supported_point_ijk,
-            &max_supported_point_ijk,
             particle,
-            particle_density,
+            half_supported_cells,
+            supported_points,
+            kernel_evaluation_radius * kernel_evaluation_radius,
+            |r_squared, _| {
+                particle_volume
+                    * self
+                        .kernel
+                        .evaluate_with_compact_support(r_squared, compact_support_radius)
+            },
+        );
+    }
+
+    /// Computes all density contributions of a particle with an anisotropic kernel to the background grid into the given map
+    fn compute_particle_density_contribution_anisotropic(
+        &self,
+        grid: &UniformGrid<I, R>,
+        sparse_densities: &mut MapType<I, R>,
+        anisotropic_kernel: &AnisotropicKernel<R>,
+        particle_volume: R,
+    ) {
+        // Skip particles with kernel centers outside of allowed domain
+        if !self
+            .allowed_domain
+            .contains_point(&anisotropic_kernel.center)
+        {
+            return;
+        }
+
+        // Only loop over the grid points that can be reached by the longest axis of the kernel
+        let GridKernelExtents {
+            half_supported_cells,
+            supported_points,
+            kernel_evaluation_radius,
+        } = compute_kernel_evaluation_radius(anisotropic_kernel.support_radius, self.cube_size);
+
+        self.particle_support_loop(
+            sparse_densities,
+            grid,
+            &anisotropic_kernel.center,
+            half_supported_cells,
+            supported_points,
+            kernel_evaluation_radius * kernel_evaluation_radius,
+            |_, dx| {
+                // Evaluate the isotropic kernel with unit compact support in the transformed space
+                let q_squared = (anisotropic_kernel.transform * dx).norm_squared();
+                particle_volume
+                    * anisotropic_kernel.determinant
+                    * self
+                        .kernel
+                        .evaluate_with_compact_support(q_squared, R::one())
+            },
         );
     }
 
@@ -636,13 +1212,27 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
         &self,
         sparse_densities: &mut MapType<I, R>,
         grid: &UniformGrid<I, R>,
-        min_supported_point_ijk: &[I; 3],
-        max_supported_point_ijk: &[I; 3],
         particle: &Vector3<R>,
-        particle_density: R,
+        half_supported_cells: I,
+        supported_points: I,
+        kernel_evaluation_radius_sq: R,
+        density_contribution: impl Fn(R, Vector3<R>) -> R,
     ) {
-        // Compute the volume of this particle
-        let particle_volume = self.particle_rest_mass / particle_density;
+        // Compute grid points affected by the particle
+        let min_supported_point_ijk = {
+            let cell_ijk = grid.enclosing_cell(particle);
+            [
+                cell_ijk[0] - half_supported_cells,
+                cell_ijk[1] - half_supported_cells,
+                cell_ijk[2] - half_supported_cells,
+            ]
+        };
+
+        let max_supported_point_ijk = [
+            min_supported_point_ijk[0] + supported_points,
+            min_supported_point_ijk[1] + supported_points,
+            min_supported_point_ijk[2] + supported_points,
+        ];
 
         // TODO: Check performance with just using multiplication
         let min_supported_point = grid.point_coordinates_array(&min_supported_point_ijk);
@@ -676,9 +1266,9 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
                     let dzdz = dz * dz;
 
                     let r_squared = dxdx + dydy + dzdz;
-                    if r_squared < self.kernel_evaluation_radius_sq {
+                    if r_squared < kernel_evaluation_radius_sq {
                         let density_contribution =
-                            particle_volume * self.kernel.evaluate(r_squared);
+                            density_contribution(r_squared, Vector3::new(dx, dy, dz));
 
                         let flat_point_index = grid.flatten_point_indices(i, j, k);
                         *sparse_densities
@@ -39,7 +39,7 @@ impl HalfEdge {
 /// A half-edge based triangle mesh data structure
 ///
 /// The main purpose of this data structure is to provide methods to perform consistent collapses of
-/// half-edges for decimation procedures.
+/// half-edges for decimation procedures as well as edge splits and flips for remeshing procedures.
 ///
 /// As [`splashsurf_lib`](crate) is focused on closed meshes, handling of holes is not specifically tested.
 /// In particular, it is not directly possible to walk along a mesh boundary using the half-edges of
@@ -47,6 +47,8 @@ impl HalfEdge {
 ///
 /// A [`HalfEdgeTriMesh`] can be easily constructed from a [`TriMesh3d`] using a [`From`](HalfEdgeTriMesh::from::<TriMesh3d>) implementation.
 ///
+/// Edge splits append new vertices, faces and half-edges to the mesh and edge flips only modify the
+/// affected faces and half-edges in place, both operations keep all existing indices valid.
 /// Note that affected vertex/face/half-edge indices become "invalid" after half-edge collapse is performed.
 /// The corresponding data still exist (i.e. they can be retrieved from the mesh) but following these
 /// indices amounts to following outdated connectivity.
@@ -85,6 +87,20 @@ pub enum IllegalHalfEdgeCollapse {
     FacelessEdge,
 }
 
+/// Error indicating why a specific edge flip is illegal
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum IllegalEdgeFlip {
+    /// Trying to flip a boundary edge
+    #[error("trying to flip a boundary edge")]
+    BoundaryEdge,
+    /// Trying to flip an edge between two vertices opposite to the edge that are already connected or identical
+    #[error("trying to flip an edge between two vertices opposite to the edge that are already connected or identical")]
+    ExistingEdge,
+    /// Trying to flip an edge with a vertex of valence three or less
+    #[error("trying to flip an edge with a vertex of valence three or less")]
+    LowValence,
+}
+
 impl<R: Real> HalfEdgeTriMesh<R> {
     /// Converts this mesh into a simple triangle mesh and a vertex-vertex connectivity map
     pub fn into_parts(mut self, keep_vertices: bool) -> (TriMesh3d<R>, Vec<Vec<usize>>) {
@@ -368,6 +384,199 @@ impl<R: Real> HalfEdgeTriMesh<R> {
         self.vertex_half_edge_map[v_neg].retain(|he_i| *he_i != he_onn.idx);
     }
 
+    /// Checks if the flip of the edge of the given half-edge is topologically legal
+    pub fn is_flip_ok(&self, half_edge: HalfEdge) -> Result<(), IllegalEdgeFlip> {
+        let he = half_edge;
+        let he_o = self.opposite(he);
+
+        if he.is_boundary() || he_o.is_boundary() {
+            return Err(IllegalEdgeFlip::BoundaryEdge);
+        }
+
+        // The vertices of the edge lose one neighbor each
+        if self.vertex_one_ring_len(he.to) <= 3 || self.vertex_one_ring_len(he_o.to) <= 3 {
+            return Err(IllegalEdgeFlip::LowValence);
+        }
+
+        // Vertices opposite to the edge to flip
+        let v_pos = self.next(he).to;
+        let v_neg = self.next(he_o).to;
+
+        if v_pos == v_neg || self.half_edge(v_pos, v_neg).is_some() {
+            return Err(IllegalEdgeFlip::ExistingEdge);
+        }
+
+        Ok(())
+    }
+
+    /// Flips the edge of the given half-edge if it is topologically legal, see [`is_flip_ok`](HalfEdgeTriMesh::is_flip_ok)
+    pub fn try_edge_flip(&mut self, half_edge: HalfEdge) -> Result<(), IllegalEdgeFlip> {
+        self.is_flip_ok(half_edge)?;
+
+        self.edge_flip(half_edge);
+        Ok(())
+    }
+
+    /// Flips the edge of the given half-edge such that it connects the two vertices opposite to the edge, assumes that the flip is legal
+    ///
+    /// The two faces adjacent to the edge keep their indices, the given half-edge afterwards points
+    /// from the vertex opposite to its opposite half-edge to the vertex opposite to itself.
+    pub fn edge_flip(&mut self, half_edge: HalfEdge) {
+        // Notation (before the flip):
+        //   he:    v0 -> v1 in face f0 = (v0, v1, v_pos)
+        //   he_o:  v1 -> v0 in face f1 = (v1, v0, v_neg)
+        let he = half_edge;
+        let he_o = self.opposite(he);
+
+        let he_n = self.next(he);
+        let he_nn = self.next(he_n);
+        let he_on = self.next(he_o);
+        let he_onn = self.next(he_on);
+
+        let v0 = he_o.to;
+        let v1 = he.to;
+        let v_pos = he_n.to;
+        let v_neg = he_on.to;
+
+        let f0 = he.face.expect("half-edge to flip must have a face");
+        let f1 = he_o.face.expect("half-edge to flip must have a face");
+
+        // New faces: f0 = (v_pos, v0, v_neg) and f1 = (v_neg, v1, v_pos)
+        self.triangles[f0] = [v_pos, v0, v_neg];
+        self.triangles[f1] = [v_neg, v1, v_pos];
+
+        {
+            let he = &mut self.half_edges[he.idx];
+            he.to = v_pos;
+            he.next = Some(he_nn.idx);
+        }
+        {
+            let he_o = &mut self.half_edges[he_o.idx];
+            he_o.to = v_neg;
+            he_o.next = Some(he_onn.idx);
+        }
+
+        // Loop of f0: he_nn (v_pos -> v0), he_on (v0 -> v_neg), he (v_neg -> v_pos)
+        self.half_edges[he_nn.idx].next = Some(he_on.idx);
+        self.half_edges[he_on.idx].next = Some(he.idx);
+        self.half_edges[he_on.idx].face = Some(f0);
+        // Loop of f1: he_onn (v_neg -> v1), he_n (v1 -> v_pos), he_o (v_pos -> v_neg)
+        self.half_edges[he_onn.idx].next = Some(he_n.idx);
+        self.half_edges[he_n.idx].next = Some(he_o.idx);
+        self.half_edges[he_n.idx].face = Some(f1);
+
+        // Move the flipped half-edges to their new origin vertices
+        self.vertex_half_edge_map[v0].retain(|he_i| *he_i != he.idx);
+        self.vertex_half_edge_map[v1].retain(|he_i| *he_i != he_o.idx);
+        self.vertex_half_edge_map[v_neg].push(he.idx);
+        self.vertex_half_edge_map[v_pos].push(he_o.idx);
+    }
+
+    /// Splits the edge of the given half-edge by inserting a new vertex at the given position, returns the index of the new vertex
+    ///
+    /// Every face adjacent to the edge is split into two faces by connecting the new vertex to the
+    /// vertex opposite to the edge. Boundary edges are supported, the new vertex is then a boundary vertex.
+    pub fn edge_split(&mut self, half_edge: HalfEdge, position: Vector3<R>) -> usize {
+        // Make sure that the half-edge to split has a face
+        let (he, he_o) = if half_edge.is_boundary() {
+            (self.opposite(half_edge), half_edge)
+        } else {
+            (half_edge, self.opposite(half_edge))
+        };
+        assert!(!he.is_boundary(), "cannot split an edge without faces");
+
+        let v0 = he_o.to;
+        let v1 = he.to;
+
+        let v_new = self.vertices.len();
+        self.vertices.push(position);
+        self.vertex_half_edge_map.push(Vec::with_capacity(6));
+
+        // Adds a pair of opposite half-edges between the two vertices, returns the index of the first half-edge
+        let add_edge = |mesh: &mut Self, from: usize, to: usize| -> usize {
+            let he_idx = mesh.half_edges.len();
+            mesh.half_edges.push(HalfEdge {
+                idx: he_idx,
+                to,
+                face: None,
+                next: None,
+                opposite: he_idx + 1,
+            });
+            mesh.half_edges.push(HalfEdge {
+                idx: he_idx + 1,
+                to: from,
+                face: None,
+                next: None,
+                opposite: he_idx,
+            });
+            mesh.vertex_half_edge_map[from].push(he_idx);
+            mesh.vertex_half_edge_map[to].push(he_idx + 1);
+            he_idx
+        };
+
+        // The existing half-edges end at the new vertex (v0 -> v_new and v1 -> v_new), two new
+        // half-edges start at the new vertex (v_new -> v1 and v_new -> v0) and become their opposites
+        let he_new_v1 = self.half_edges.len();
+        let he_new_v0 = he_new_v1 + 1;
+        self.half_edges.push(HalfEdge {
+            idx: he_new_v1,
+            to: v1,
+            face: None,
+            next: None,
+            opposite: he_o.idx,
+        });
+        self.half_edges.push(HalfEdge {
+            idx: he_new_v0,
+            to: v0,
+            face: None,
+            next: None,
+            opposite: he.idx,
+        });
+        self.vertex_half_edge_map[v_new].extend([he_new_v1, he_new_v0]);
+
+        self.half_edges[he.idx].to = v_new;
+        self.half_edges[he.idx].opposite = he_new_v0;
+        self.half_edges[he_o.idx].to = v_new;
+        self.half_edges[he_o.idx].opposite = he_new_v1;
+
+        // Splits the face of the given half-edge (v_a -> v_new), continued by the new half-edge (v_new -> v_b)
+        let split_face = |mesh: &mut Self, he: HalfEdge, he_rest: usize| {
+            let f_old = he.face.expect("half-edge must have a face");
+            let f_new = mesh.triangles.len();
+
+            // Remaining half-edges of the old face: he_n (v_b -> v_opp), he_nn (v_opp -> v_a)
+            let he_n = mesh.next(he);
+            let he_nn = mesh.next(he_n);
+            let v_a = he_nn.to;
+            let v_b = mesh.half_edges[he_rest].to;
+            let v_opp = he_n.to;
+
+            let he_mid = add_edge(mesh, v_new, v_opp);
+
+            // Old face: he (v_a -> v_new), he_mid (v_new -> v_opp), he_nn (v_opp -> v_a)
+            mesh.triangles[f_old] = [v_a, v_new, v_opp];
+            mesh.half_edges[he.idx].next = Some(he_mid);
+            mesh.half_edges[he_mid].face = Some(f_old);
+            mesh.half_edges[he_mid].next = Some(he_nn.idx);
+
+            // New face: he_rest (v_new -> v_b), he_n (v_b -> v_opp), opposite of he_mid (v_opp -> v_new)
+            mesh.triangles.push([v_new, v_b, v_opp]);
+            mesh.half_edges[he_rest].face = Some(f_new);
+            mesh.half_edges[he_rest].next = Some(he_n.idx);
+            mesh.half_edges[he_n.idx].face = Some(f_new);
+            mesh.half_edges[he_n.idx].next = Some(he_mid + 1);
+            mesh.half_edges[he_mid + 1].face = Some(f_new);
+            mesh.half_edges[he_mid + 1].next = Some(he_rest);
+        };
+
+        split_face(self, self.half_edges[he.idx], he_new_v1);
+        if !he_o.is_boundary() {
+            split_face(self, self.half_edges[he_o.idx], he_new_v0);
+        }
+
+        v_new
+    }
+
     /// Computes the largest angle in radians by which a face normals rotates of triangles affect by the given half edge collapse, assumes that the given half edge is valid
     pub fn half_edge_collapse_max_normal_change(&self, half_edge: HalfEdge) -> R {
         let he = half_edge;
@@ -584,3 +793,89 @@ fn test_half_edge_mesh() {
         assert_eq!(la, lb);
     }
 }
+
+#[test]
+fn test_half_edge_mesh_flip_and_split() {
+    // Octahedron
+    let tri_mesh = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        ],
+        triangles: vec![
+            [0, 1, 4],
+            [1, 2, 4],
+            [2, 3, 4],
+            [3, 0, 4],
+            [1, 0, 5],
+            [2, 1, 5],
+            [3, 2, 5],
+            [0, 3, 5],
+        ],
+    };
+    let volume = tri_mesh.enclosed_volume();
+
+    let check_mesh = |he_mesh: &HalfEdgeTriMesh<f64>| {
+        let (mesh, _) = he_mesh.clone().into_parts(true);
+        let info = mesh.compute_manifold_information();
+        assert!(info.is_closed());
+        assert!(info.is_manifold());
+        mesh
+    };
+
+    let mut he_mesh = HalfEdgeTriMesh::from(tri_mesh.clone());
+
+    // The vertices of the edge 0-1 have valence four, the vertices 4 and 5 are not connected
+    let he = he_mesh.half_edge(0, 1).unwrap();
+    assert_eq!(he_mesh.is_flip_ok(he), Ok(()));
+    he_mesh.edge_flip(he);
+    assert!(he_mesh.half_edge(0, 1).is_none());
+    assert!(he_mesh.half_edge(4, 5).is_some());
+    assert!(he_mesh.half_edge(5, 4).is_some());
+    assert_eq!(he_mesh.vertex_one_ring_len(0), 3);
+    assert_eq!(he_mesh.vertex_one_ring_len(4), 5);
+    check_mesh(&he_mesh);
+
+    // Vertex 0 has valence three after the flip
+    let he = he_mesh.half_edge(0, 4).unwrap();
+    assert_eq!(he_mesh.is_flip_ok(he), Err(IllegalEdgeFlip::LowValence));
+    // Flipping the edge 2-3 would connect the already connected vertices 4 and 5
+    let he = he_mesh.half_edge(2, 3).unwrap();
+    assert_eq!(he_mesh.is_flip_ok(he), Err(IllegalEdgeFlip::ExistingEdge));
+
+    // Splitting an edge at its midpoint does not change the shape of the mesh
+    let mut he_mesh = HalfEdgeTriMesh::from(tri_mesh.clone());
+    let he = he_mesh.half_edge(0, 4).unwrap();
+    let v_new = he_mesh.edge_split(he, Vector3::new(0.5, 0.0, 0.5));
+    assert_eq!(v_new, 6);
+    assert_eq!(he_mesh.vertex_one_ring_len(v_new), 4);
+    assert!(he_mesh.half_edge(0, 4).is_none());
+    for v in [0, 4, 1, 3] {
+        assert!(he_mesh.half_edge(v_new, v).is_some());
+        assert!(he_mesh.half_edge(v, v_new).is_some());
+    }
+
+    let mesh = check_mesh(&he_mesh);
+    assert_eq!(mesh.triangles.len(), 10);
+    assert!((mesh.enclosed_volume() - volume).abs() < 1e-12);
+
+    // Splitting a boundary edge only splits the face adjacent to the edge
+    let mut he_mesh = HalfEdgeTriMesh::from(TriMesh3d::<f64> {
+        vertices: tri_mesh.vertices[..5].to_vec(),
+        triangles: tri_mesh.triangles[..4].to_vec(),
+    });
+    let he = he_mesh.half_edge(1, 0).unwrap();
+    assert!(he.is_boundary());
+    let v_new = he_mesh.edge_split(he, Vector3::new(0.5, 0.5, 0.0));
+    assert!(he_mesh.is_boundary_vertex(v_new));
+    assert_eq!(he_mesh.vertex_one_ring_len(v_new), 3);
+
+    let (mesh, _) = he_mesh.into_parts(true);
+    assert_eq!(mesh.triangles.len(), 5);
+    assert!(mesh.compute_manifold_information().is_manifold());
+    assert_eq!(mesh.compute_manifold_information().boundary_edges.len(), 5);
+}
@@ -9,6 +9,7 @@ pub mod bgeo_format;
 pub mod json_format;
 pub mod obj_format;
 pub mod ply_format;
+pub mod sdg_format;
 pub mod vtk_format;
 pub mod xyz_format;
 
@@ -3,6 +3,7 @@
 use crate::mesh::{
     AttributeData, CellConnectivity, Mesh3d, MeshAttribute, MeshWithData, TriMesh3d,
 };
+use crate::streaming::MeshSink;
 use crate::{utils, Real};
 use anyhow::Context;
 use nalgebra::Vector3;
@@ -72,6 +73,62 @@ pub fn mesh_to_obj<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
     Ok(())
 }
 
+/// [`MeshSink`] that writes the mesh of a streaming reconstruction incrementally to an OBJ file
+///
+/// Vertices and faces are written in the order in which they are appended, i.e. the `v` and `f`
+/// lines of the resulting file are interleaved.
+pub struct ObjMeshSink<W: Write> {
+    writer: W,
+}
+
+impl ObjMeshSink<BufWriter<fs::File>> {
+    /// Creates the OBJ file at the given path (an existing file is truncated)
+    pub fn create<P: AsRef<Path>>(filename: P) -> Result<Self, anyhow::Error> {
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(filename)
+            .context("Failed to open file handle for writing OBJ file")?;
+        Ok(Self::new(BufWriter::with_capacity(100000, file)))
+    }
+}
+
+impl<W: Write> ObjMeshSink<W> {
+    /// Creates a sink that writes the OBJ data to the given writer
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Flushes all written data and returns the underlying writer
+    pub fn finish(mut self) -> Result<W, anyhow::Error> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<R: Real, W: Write> MeshSink<R> for ObjMeshSink<W> {
+    fn append_vertices(&mut self, vertices: &[Vector3<R>]) -> Result<(), anyhow::Error> {
+        for v in vertices {
+            write!(self.writer, "v {} {} {}\n", v.x, v.y, v.z)?;
+        }
+        Ok(())
+    }
+
+    fn append_triangles(&mut self, triangles: &[[usize; 3]]) -> Result<(), anyhow::Error> {
+        for tri in triangles {
+            write!(
+                self.writer,
+                "f {} {} {}\n",
+                tri[0] + 1,
+                tri[1] + 1,
+                tri[2] + 1
+            )?;
+        }
+        Ok(())
+    }
+}
+
 pub fn surface_mesh_from_obj<R: Real, P: AsRef<Path>>(
     obj_path: P,
 ) -> Result<MeshWithData<R, TriMesh3d<R>>, anyhow::Error> {
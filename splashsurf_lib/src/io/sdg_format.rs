@@ -0,0 +1,184 @@
+//! Helper functions for the SDG file format, a simple binary format for sparse density grids
+//!
+//! An SDG file stores the blocks of a [`SparseDensityGrid`] without any compression. All numbers
+//! are stored in little endian byte order. The file starts with the header:
+//!
+//! | Content                                                        | Type          |
+//! |----------------------------------------------------------------|---------------|
+//! | Magic bytes `SPLSHSDG`                                         | 8 × `u8`      |
+//! | Format version (currently 1)                                   | `u32`         |
+//! | Size of the density values in bytes (4 or 8)                   | `u32`         |
+//! | Coordinates of the minimum corner of the background grid       | 3 × `f64`     |
+//! | Cell size of the background grid                               | `f64`         |
+//! | Number of cells of the background grid along each axis         | 3 × `u64`     |
+//! | Number of cells per block along each axis `b`                  | `u64`         |
+//! | Number of blocks                                               | `u64`         |
+//!
+//! The header is followed by the blocks, each consisting of:
+//!
+//! | Content                                                        | Type          |
+//! |----------------------------------------------------------------|---------------|
+//! | Block index `(i, j, k)`, the first point of the block is the grid point `(i*b, j*b, k*b)` | 3 × `u64` |
+//! | Density values of all `(b+1)^3` points of the block with the last index varying fastest | `(b+1)^3` × `f32` or `f64` |
+
+use crate::density_map::{DensityBlock, SparseDensityGrid};
+use crate::uniform_grid::UniformGrid;
+use crate::{Index, Real};
+use anyhow::{anyhow, Context};
+use nalgebra::Vector3;
+use std::fs::{create_dir_all, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"SPLSHSDG";
+const VERSION: u32 = 1;
+
+/// Writes a sparse density grid to an SDG file at the given path
+///
+/// Density grids with `f32` values are stored with single precision, all other grids with double precision.
+pub fn density_grid_to_sdg<I: Index, R: Real, P: AsRef<Path>>(
+    density_grid: &SparseDensityGrid<I, R>,
+    filename: P,
+) -> Result<(), anyhow::Error> {
+    let filename = filename.as_ref();
+    if let Some(dir) = filename.parent() {
+        create_dir_all(dir).context("Failed to create parent directory of output file")?;
+    }
+    let file = File::create(filename).context("Failed to open file handle for writing SDG file")?;
+    let mut writer = BufWriter::with_capacity(100000, file);
+
+    let single_precision = std::mem::size_of::<R>() == 4;
+    let grid = density_grid.grid();
+    let to_u64 = |i: I| {
+        i.to_u64()
+            .ok_or_else(|| anyhow!("Index {} does not fit into u64", i))
+    };
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(if single_precision { 4u32 } else { 8u32 }).to_le_bytes())?;
+    for x in grid.aabb().min().iter() {
+        writer.write_all(&x.to_f64().unwrap().to_le_bytes())?;
+    }
+    writer.write_all(&grid.cell_size().to_f64().unwrap().to_le_bytes())?;
+    for n in grid.cells_per_dim() {
+        writer.write_all(&to_u64(*n)?.to_le_bytes())?;
+    }
+    writer.write_all(&to_u64(density_grid.block_cubes())?.to_le_bytes())?;
+    writer.write_all(&(density_grid.num_blocks() as u64).to_le_bytes())?;
+
+    for block in density_grid.blocks() {
+        for b in block.block_ijk {
+            writer.write_all(&to_u64(b)?.to_le_bytes())?;
+        }
+        for value in block.values.iter() {
+            if single_precision {
+                writer.write_all(&value.to_f32().unwrap().to_le_bytes())?;
+            } else {
+                writer.write_all(&value.to_f64().unwrap().to_le_bytes())?;
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads a sparse density grid from the SDG file at the given path
+pub fn density_grid_from_sdg<I: Index, R: Real, P: AsRef<Path>>(
+    filename: P,
+) -> Result<SparseDensityGrid<I, R>, anyhow::Error> {
+    let file = File::open(filename).context("Failed to open file handle for reading SDG file")?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(anyhow!("File is not an SDG file (invalid magic bytes)"));
+    }
+    let version = read_u32(&mut reader)?;
+    if version != VERSION {
+        return Err(anyhow!("Unsupported SDG format version {}", version));
+    }
+    let value_size = read_u32(&mut reader)?;
+    if value_size != 4 && value_size != 8 {
+        return Err(anyhow!(
+            "Unsupported size of density values: {} bytes",
+            value_size
+        ));
+    }
+
+    let to_real =
+        |x: f64| R::from_f64(x).ok_or_else(|| anyhow!("Value {} does not fit into real type", x));
+    let to_index =
+        |n: u64| I::from_u64(n).ok_or_else(|| anyhow!("Index {} does not fit into index type", n));
+
+    let min = Vector3::new(
+        to_real(read_f64(&mut reader)?)?,
+        to_real(read_f64(&mut reader)?)?,
+        to_real(read_f64(&mut reader)?)?,
+    );
+    let cell_size = to_real(read_f64(&mut reader)?)?;
+    let cells_per_dim = [
+        to_index(read_u64(&mut reader)?)?,
+        to_index(read_u64(&mut reader)?)?,
+        to_index(read_u64(&mut reader)?)?,
+    ];
+    let grid = UniformGrid::new(&min, &cells_per_dim, cell_size)
+        .context("Failed to construct background grid of SDG file")?;
+    let block_cubes = to_index(read_u64(&mut reader)?)?;
+    if block_cubes <= I::zero() {
+        return Err(anyhow!(
+            "Invalid number of cells per block: {}",
+            block_cubes
+        ));
+    }
+    let num_blocks = read_u64(&mut reader)?;
+
+    let mut density_grid = SparseDensityGrid::new(grid, block_cubes);
+    let block_points = density_grid.block_points();
+    for _ in 0..num_blocks {
+        let block_ijk = [
+            to_index(read_u64(&mut reader)?)?,
+            to_index(read_u64(&mut reader)?)?,
+            to_index(read_u64(&mut reader)?)?,
+        ];
+        let values = (0..block_points)
+            .map(|_| {
+                let value = if value_size == 4 {
+                    read_f32(&mut reader)? as f64
+                } else {
+                    read_f64(&mut reader)?
+                };
+                to_real(value)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        density_grid.insert_block(DensityBlock { block_ijk, values });
+    }
+
+    Ok(density_grid)
+}
+
+fn read_u32<Rd: Read>(reader: &mut Rd) -> Result<u32, anyhow::Error> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<Rd: Read>(reader: &mut Rd) -> Result<u64, anyhow::Error> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32<Rd: Read>(reader: &mut Rd) -> Result<f32, anyhow::Error> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+fn read_f64<Rd: Read>(reader: &mut Rd) -> Result<f64, anyhow::Error> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
@@ -1,15 +1,19 @@
 //! Helper functions for the VTK file format
 
+use crate::density_map::SparseDensityGrid;
 use crate::mesh::{AttributeData, IntoVtkDataSet, MeshAttribute, MeshWithData, TriMesh3d};
+use crate::signed_distance::SignedDistanceGrid;
+use crate::uniform_grid::UniformGrid;
 use crate::utils::IteratorExt;
-use crate::{Real, RealConvert};
+use crate::{Index, Real, RealConvert};
 use anyhow::{anyhow, Context};
 use nalgebra::Vector3;
 use std::borrow::Cow;
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
 use vtkio::model::{
-    Attribute, Attributes, CellType, Cells, PolyDataPiece, UnstructuredGridPiece, VertexNumbers,
+    Attribute, Attributes, CellType, Cells, Extent, ImageDataPiece, Piece, PolyDataPiece,
+    UnstructuredGridPiece, VertexNumbers,
 };
 use vtkio::model::{ByteOrder, DataSet, Version, Vtk};
 use vtkio::IOBuffer;
@@ -204,6 +208,97 @@ pub fn write_vtk<P: AsRef<Path>>(
         .context("Error while writing VTK output to file")
 }
 
+/// Tries to write the density field of a sparse density grid to a VTK image data (`.vti`) file at the given path
+///
+/// The image covers the entire background grid of the density grid, points that are not part of any
+/// block of the density grid are set to zero. The values are stored in a point attribute named `density`.
+pub fn density_grid_to_vti<I: Index, R: Real, P: AsRef<Path>>(
+    density_grid: &SparseDensityGrid<I, R>,
+    filename: P,
+) -> Result<(), anyhow::Error> {
+    let values = density_grid.to_dense(R::zero());
+    grid_values_to_vti(density_grid.grid(), &values, "density", filename)
+}
+
+/// Tries to write a signed distance field to a VTK image data (`.vti`) file at the given path
+///
+/// The image covers the entire grid of the signed distance field. The values are stored in a point
+/// attribute named `signed_distance`.
+pub fn signed_distance_grid_to_vti<I: Index, R: Real, P: AsRef<Path>>(
+    signed_distance_grid: &SignedDistanceGrid<I, R>,
+    filename: P,
+) -> Result<(), anyhow::Error> {
+    grid_values_to_vti(
+        signed_distance_grid.grid(),
+        signed_distance_grid.values(),
+        "signed_distance",
+        filename,
+    )
+}
+
+/// Writes the values of all points of the grid (in the order of their flat point indices) as a named point attribute of a VTK image data file
+fn grid_values_to_vti<I: Index, R: Real, P: AsRef<Path>>(
+    grid: &UniformGrid<I, R>,
+    values: &[R],
+    name: &str,
+    filename: P,
+) -> Result<(), anyhow::Error> {
+    let [ni, nj, nk] = grid.points_per_dim().map(|n| n.to_usize().unwrap());
+
+    // The points of VTK image data are ordered with the first index varying fastest
+    let mut vtk_values = Vec::with_capacity(values.len());
+    for k in 0..nk {
+        for j in 0..nj {
+            for i in 0..ni {
+                vtk_values.push(values[(i * nj + j) * nk + k]);
+            }
+        }
+    }
+
+    let max_point_index = |n: usize| {
+        i32::try_from(n - 1).context("Number of grid points is too large for VTK image data")
+    };
+    let extent = Extent::Ranges([
+        0..=max_point_index(ni)?,
+        0..=max_point_index(nj)?,
+        0..=max_point_index(nk)?,
+    ]);
+    let origin = grid.aabb().min();
+    let spacing = grid.cell_size().to_f32().unwrap();
+
+    let vtk_file = Vtk {
+        version: Version::new((1, 0)),
+        title: name.to_string(),
+        file_path: None,
+        byte_order: ByteOrder::LittleEndian,
+        data: DataSet::ImageData {
+            extent: extent.clone(),
+            origin: [
+                origin.x.to_f32().unwrap(),
+                origin.y.to_f32().unwrap(),
+                origin.z.to_f32().unwrap(),
+            ],
+            spacing: [spacing; 3],
+            meta: None,
+            pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                extent,
+                data: Attributes {
+                    point: vec![Attribute::scalars(name, 1).with_data(vtk_values)],
+                    cell: Vec::new(),
+                },
+            }))],
+        },
+    };
+
+    let filename = filename.as_ref();
+    if let Some(dir) = filename.parent() {
+        create_dir_all(dir).context("Failed to create parent directory of output file")?;
+    }
+    vtk_file
+        .export(filename)
+        .context("Error while writing VTK image data output to file")
+}
+
 /// Tries to read the given VTK file
 pub fn read_vtk<P: AsRef<Path>>(filename: P) -> Result<Vtk, anyhow::Error> {
     let filename = filename.as_ref();
@@ -331,6 +426,13 @@ fn try_convert_io_buffer_to_attribute<R: Real>(
                 })
             })
             .map(|v| AttributeData::ScalarReal(v)),
+            IOBuffer::U8(vec) => try_map_scalars_to_u64(&vec).map(AttributeData::ScalarU64),
+            IOBuffer::U16(vec) => try_map_scalars_to_u64(&vec).map(AttributeData::ScalarU64),
+            IOBuffer::U64(vec) => Ok(AttributeData::ScalarU64(vec.clone())),
+            IOBuffer::I8(vec) => try_map_scalars_to_u64(&vec).map(AttributeData::ScalarU64),
+            IOBuffer::I16(vec) => try_map_scalars_to_u64(&vec).map(AttributeData::ScalarU64),
+            IOBuffer::I32(vec) => try_map_scalars_to_u64(&vec).map(AttributeData::ScalarU64),
+            IOBuffer::I64(vec) => try_map_scalars_to_u64(&vec).map(AttributeData::ScalarU64),
             _ => Err(anyhow!("Unsupported IOBuffer scalar data type")),
         },
         3 => match &io_buffer {
@@ -360,6 +462,21 @@ fn try_map_scalars_to_real<R: Real, T: Copy, F: Fn(T) -> Result<R, anyhow::Error
         .try_collect_with_capacity(io_buffer.len())
 }
 
+/// Tries to convert integer scalars to `u64`, fails for negative values
+fn try_map_scalars_to_u64<T: Copy + TryInto<u64>>(
+    io_buffer: &[T],
+) -> Result<Vec<u64>, anyhow::Error> {
+    io_buffer
+        .iter()
+        .copied()
+        .map(|val| {
+            val.try_into().map_err(|_| {
+                anyhow!("Cannot convert a negative integer attribute value to an unsigned integer")
+            })
+        })
+        .try_collect_with_capacity(io_buffer.len())
+}
+
 /// Tries to convert a vector of consecutive coordinate triplets into a vector of `Vector3`, also converts between floating point types
 fn particles_from_coords<RealOut: Real, RealIn: Real>(
     coords: &Vec<RealIn>,
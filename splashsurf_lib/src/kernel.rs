@@ -4,8 +4,6 @@ use crate::{Real, RealConvert};
 use nalgebra::Vector3;
 use numeric_literals::replace_float_literals;
 
-// TODO: Add reference for the kernel function, document formula
-
 /// Trait for symmetric kernel functions in three dimensions
 pub trait SymmetricKernel3d<R: Real> {
     /// Evaluates the kernel at the radial distance `r` relative to the origin
@@ -16,7 +14,11 @@ pub trait SymmetricKernel3d<R: Real> {
     fn evaluate_gradient_norm(&self, r: R) -> R;
 }
 
-/// The commonly used cubic spline kernel
+/// The commonly used cubic spline kernel, `W(q) = 8/(π h³) [(1-q)³ - 4(1/2-q)³]` with `q = r/h`
+///
+/// The second term is only included for `q < 1/2`. See Monaghan, "Smoothed particle hydrodynamics",
+/// Annual Review of Astronomy and Astrophysics, 1992. Note that `h` is the compact support radius,
+/// i.e. twice the smoothing length used in the reference.
 pub struct CubicSplineKernel<R: Real> {
     /// Compact support radius of the kernel
     compact_support_radius: R,
@@ -144,6 +146,504 @@ fn test_cubic_kernel_r_integral() {
     }
 }
 
+/// Selection of the SPH kernel function used for the evaluation of densities and the level-set function
+///
+/// All kernels are normalized and vanish outside of the compact support radius `h`, i.e. for
+/// the same particle configuration they can be used interchangeably with the same compact support radius.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum KernelType {
+    /// The cubic spline kernel (Monaghan 1992), see [`CubicSplineKernel`]
+    ///
+    /// The compact support radius is twice the smoothing length of the spline. This is the kernel
+    /// that was used by all previous versions and the default.
+    #[default]
+    CubicSpline,
+    /// The Wendland C2 kernel (Wendland 1995, Dehnen & Aly 2012), see [`WendlandC2Kernel`]
+    ///
+    /// Smoother than the cubic spline and free of the pairing instability, the compact support
+    /// radius is the radius where the polynomial `(1-q)⁴ (1+4q)` vanishes.
+    WendlandC2,
+    /// The Wendland C4 kernel (Wendland 1995, Dehnen & Aly 2012), see [`WendlandC4Kernel`]
+    ///
+    /// The compact support radius is the radius where the polynomial `(1-q)⁶ (1+6q+35/3q²)` vanishes.
+    WendlandC4,
+    /// The Wendland C6 kernel (Wendland 1995, Dehnen & Aly 2012), see [`WendlandC6Kernel`]
+    ///
+    /// The compact support radius is the radius where the polynomial `(1-q)⁸ (1+8q+25q²+32q³)` vanishes.
+    WendlandC6,
+    /// The Poly6 kernel (Müller et al. 2003), see [`Poly6Kernel`]
+    ///
+    /// The compact support radius corresponds to the smoothing length `h` of the reference.
+    Poly6,
+    /// The quintic spline kernel (Morris 2000), see [`QuinticSplineKernel`]
+    ///
+    /// The compact support radius is three times the smoothing length of the spline.
+    QuinticSpline,
+}
+
+impl KernelType {
+    /// Returns a list of all supported kernel types
+    pub fn all() -> &'static [KernelType] {
+        &[
+            KernelType::CubicSpline,
+            KernelType::WendlandC2,
+            KernelType::WendlandC4,
+            KernelType::WendlandC6,
+            KernelType::Poly6,
+            KernelType::QuinticSpline,
+        ]
+    }
+}
+
+/// Kernel function that is selected at runtime using a [`KernelType`]
+pub enum DynamicKernel<R: Real> {
+    /// The cubic spline kernel, see [`KernelType::CubicSpline`]
+    CubicSpline(CubicSplineKernel<R>),
+    /// The Wendland C2 kernel, see [`KernelType::WendlandC2`]
+    WendlandC2(WendlandC2Kernel<R>),
+    /// The Wendland C4 kernel, see [`KernelType::WendlandC4`]
+    WendlandC4(WendlandC4Kernel<R>),
+    /// The Wendland C6 kernel, see [`KernelType::WendlandC6`]
+    WendlandC6(WendlandC6Kernel<R>),
+    /// The Poly6 kernel, see [`KernelType::Poly6`]
+    Poly6(Poly6Kernel<R>),
+    /// The quintic spline kernel, see [`KernelType::QuinticSpline`]
+    QuinticSpline(QuinticSplineKernel<R>),
+}
+
+impl<R: Real> DynamicKernel<R> {
+    /// Initializes a kernel of the given type with the given compact support radius
+    pub fn new(kernel_type: KernelType, compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        match kernel_type {
+            KernelType::CubicSpline => DynamicKernel::CubicSpline(CubicSplineKernel::new(h)),
+            KernelType::WendlandC2 => DynamicKernel::WendlandC2(WendlandC2Kernel::new(h)),
+            KernelType::WendlandC4 => DynamicKernel::WendlandC4(WendlandC4Kernel::new(h)),
+            KernelType::WendlandC6 => DynamicKernel::WendlandC6(WendlandC6Kernel::new(h)),
+            KernelType::Poly6 => DynamicKernel::Poly6(Poly6Kernel::new(h)),
+            KernelType::QuinticSpline => DynamicKernel::QuinticSpline(QuinticSplineKernel::new(h)),
+        }
+    }
+
+    /// Returns the type of this kernel
+    pub fn kernel_type(&self) -> KernelType {
+        match self {
+            DynamicKernel::CubicSpline(_) => KernelType::CubicSpline,
+            DynamicKernel::WendlandC2(_) => KernelType::WendlandC2,
+            DynamicKernel::WendlandC4(_) => KernelType::WendlandC4,
+            DynamicKernel::WendlandC6(_) => KernelType::WendlandC6,
+            DynamicKernel::Poly6(_) => KernelType::Poly6,
+            DynamicKernel::QuinticSpline(_) => KernelType::QuinticSpline,
+        }
+    }
+}
+
+/// Forwards a method call to the kernel contained in a [`DynamicKernel`]
+macro_rules! dispatch_kernel {
+    ($self:expr, $kernel:ident => $call:expr) => {
+        match $self {
+            DynamicKernel::CubicSpline($kernel) => $call,
+            DynamicKernel::WendlandC2($kernel) => $call,
+            DynamicKernel::WendlandC4($kernel) => $call,
+            DynamicKernel::WendlandC6($kernel) => $call,
+            DynamicKernel::Poly6($kernel) => $call,
+            DynamicKernel::QuinticSpline($kernel) => $call,
+        }
+    };
+}
+
+impl<R: Real> SymmetricKernel3d<R> for DynamicKernel<R> {
+    #[inline(always)]
+    fn evaluate(&self, r: R) -> R {
+        dispatch_kernel!(self, kernel => kernel.evaluate(r))
+    }
+
+    #[inline(always)]
+    fn evaluate_gradient(&self, x: Vector3<R>) -> Vector3<R> {
+        dispatch_kernel!(self, kernel => kernel.evaluate_gradient(x))
+    }
+
+    #[inline(always)]
+    fn evaluate_gradient_norm(&self, r: R) -> R {
+        dispatch_kernel!(self, kernel => kernel.evaluate_gradient_norm(r))
+    }
+}
+
+/// Implements [`SymmetricKernel3d`] for a kernel given by a normalized function `f(q)` of the parameter `q = r/h`
+///
+/// The kernel type has to provide the fields `compact_support_radius` and `normalization` as well
+/// as the associated functions `function(q)` and `function_dq(q)` that are zero for `q >= 1`.
+macro_rules! impl_normalized_kernel {
+    ($kernel:ident) => {
+        impl<R: Real> SymmetricKernel3d<R> for $kernel<R> {
+            fn evaluate(&self, r: R) -> R {
+                let q = r / self.compact_support_radius;
+                self.normalization * Self::function(q)
+            }
+
+            fn evaluate_gradient(&self, x: Vector3<R>) -> Vector3<R> {
+                // See the cubic spline kernel for the derivation of the gradient
+                let r = x.norm();
+                if r == R::zero() {
+                    return Vector3::zeros();
+                }
+                let drdx = x.unscale(r);
+                drdx.scale(self.evaluate_gradient_norm(r))
+            }
+
+            fn evaluate_gradient_norm(&self, r: R) -> R {
+                let q = r / self.compact_support_radius;
+                let dfdq = Self::function_dq(q);
+                let dqdr = self.compact_support_radius.recip();
+                self.normalization * dfdq * dqdr
+            }
+        }
+    };
+}
+
+/// The Wendland C2 kernel, `W(q) = 21/(2π h³) (1-q)⁴ (1+4q)` with `q = r/h`
+///
+/// See Wendland, "Piecewise polynomial, positive definite and compactly supported radial functions
+/// of minimal degree", Advances in Computational Mathematics, 1995 and Dehnen & Aly, "Improving
+/// convergence in smoothed particle hydrodynamics simulations without pairing instability", 2012.
+pub struct WendlandC2Kernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> WendlandC2Kernel<R> {
+    /// Initializes a Wendland C2 kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        let sigma = 21.0 / (2.0 * R::pi() * h * h * h);
+
+        Self {
+            compact_support_radius,
+            normalization: sigma,
+        }
+    }
+
+    /// The unnormalized Wendland C2 function
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function(q: R) -> R {
+        if q < 1.0 {
+            let x = 1.0 - q;
+            let x2 = x * x;
+            x2 * x2 * (1.0 + 4.0 * q)
+        } else {
+            0.0
+        }
+    }
+
+    /// The derivative of the unnormalized Wendland C2 function w.r.t to the parameter `q`
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function_dq(q: R) -> R {
+        if q < 1.0 {
+            let x = 1.0 - q;
+            -20.0 * q * x * x * x
+        } else {
+            0.0
+        }
+    }
+}
+
+impl_normalized_kernel!(WendlandC2Kernel);
+
+/// The Wendland C4 kernel, `W(q) = 495/(32π h³) (1-q)⁶ (1+6q+35/3q²)` with `q = r/h`
+pub struct WendlandC4Kernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> WendlandC4Kernel<R> {
+    /// Initializes a Wendland C4 kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        let sigma = 495.0 / (32.0 * R::pi() * h * h * h);
+
+        Self {
+            compact_support_radius,
+            normalization: sigma,
+        }
+    }
+
+    /// The unnormalized Wendland C4 function
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function(q: R) -> R {
+        if q < 1.0 {
+            let x = 1.0 - q;
+            let x2 = x * x;
+            x2 * x2 * x2 * (1.0 + 6.0 * q + (35.0 / 3.0) * q * q)
+        } else {
+            0.0
+        }
+    }
+
+    /// The derivative of the unnormalized Wendland C4 function w.r.t to the parameter `q`
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function_dq(q: R) -> R {
+        if q < 1.0 {
+            let x = 1.0 - q;
+            let x2 = x * x;
+            -(56.0 / 3.0) * q * x2 * x2 * x * (1.0 + 5.0 * q)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl_normalized_kernel!(WendlandC4Kernel);
+
+/// The Wendland C6 kernel, `W(q) = 1365/(64π h³) (1-q)⁸ (1+8q+25q²+32q³)` with `q = r/h`
+pub struct WendlandC6Kernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> WendlandC6Kernel<R> {
+    /// Initializes a Wendland C6 kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        let sigma = 1365.0 / (64.0 * R::pi() * h * h * h);
+
+        Self {
+            compact_support_radius,
+            normalization: sigma,
+        }
+    }
+
+    /// The unnormalized Wendland C6 function
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function(q: R) -> R {
+        if q < 1.0 {
+            let x = 1.0 - q;
+            let x2 = x * x;
+            let x4 = x2 * x2;
+            x4 * x4 * (1.0 + 8.0 * q + 25.0 * q * q + 32.0 * q * q * q)
+        } else {
+            0.0
+        }
+    }
+
+    /// The derivative of the unnormalized Wendland C6 function w.r.t to the parameter `q`
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function_dq(q: R) -> R {
+        if q < 1.0 {
+            let x = 1.0 - q;
+            let x2 = x * x;
+            let x4 = x2 * x2;
+            -22.0 * q * x4 * x2 * x * (1.0 + 7.0 * q + 16.0 * q * q)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl_normalized_kernel!(WendlandC6Kernel);
+
+/// The Poly6 kernel, `W(q) = 315/(64π h³) (1-q²)³` with `q = r/h`
+///
+/// See Müller et al., "Particle-based fluid simulation for interactive applications", SCA 2003.
+pub struct Poly6Kernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> Poly6Kernel<R> {
+    /// Initializes a Poly6 kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        let sigma = 315.0 / (64.0 * R::pi() * h * h * h);
+
+        Self {
+            compact_support_radius,
+            normalization: sigma,
+        }
+    }
+
+    /// The unnormalized Poly6 function
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function(q: R) -> R {
+        if q < 1.0 {
+            let x = 1.0 - q * q;
+            x * x * x
+        } else {
+            0.0
+        }
+    }
+
+    /// The derivative of the unnormalized Poly6 function w.r.t to the parameter `q`
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function_dq(q: R) -> R {
+        if q < 1.0 {
+            let x = 1.0 - q * q;
+            -6.0 * q * x * x
+        } else {
+            0.0
+        }
+    }
+}
+
+impl_normalized_kernel!(Poly6Kernel);
+
+/// The quintic spline kernel with three segments, `W(q) = 2187/(40π h³) [(1-q)⁵ - 6(2/3-q)⁵ + 15(1/3-q)⁵]` with `q = r/h`
+///
+/// Each of the terms is only included where its base is positive. See Morris, "Simulating surface
+/// tension with smoothed particle hydrodynamics", International Journal for Numerical Methods in Fluids, 2000.
+pub struct QuinticSplineKernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> QuinticSplineKernel<R> {
+    /// Initializes a quintic spline kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        let sigma = 2187.0 / (40.0 * R::pi() * h * h * h);
+
+        Self {
+            compact_support_radius,
+            normalization: sigma,
+        }
+    }
+
+    /// The unnormalized quintic spline function
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function(q: R) -> R {
+        let pow5 = |x: R| {
+            let x2 = x * x;
+            x2 * x2 * x
+        };
+
+        if q < 1.0 / 3.0 {
+            pow5(1.0 - q) - 6.0 * pow5(2.0 / 3.0 - q) + 15.0 * pow5(1.0 / 3.0 - q)
+        } else if q < 2.0 / 3.0 {
+            pow5(1.0 - q) - 6.0 * pow5(2.0 / 3.0 - q)
+        } else if q < 1.0 {
+            pow5(1.0 - q)
+        } else {
+            0.0
+        }
+    }
+
+    /// The derivative of the unnormalized quintic spline function w.r.t to the parameter `q`
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn function_dq(q: R) -> R {
+        let pow4 = |x: R| {
+            let x2 = x * x;
+            x2 * x2
+        };
+
+        if q < 1.0 / 3.0 {
+            -5.0 * (pow4(1.0 - q) - 6.0 * pow4(2.0 / 3.0 - q) + 15.0 * pow4(1.0 / 3.0 - q))
+        } else if q < 2.0 / 3.0 {
+            -5.0 * (pow4(1.0 - q) - 6.0 * pow4(2.0 / 3.0 - q))
+        } else if q < 1.0 {
+            -5.0 * pow4(1.0 - q)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl_normalized_kernel!(QuinticSplineKernel);
+
+/// Numerically integrates the kernel over its compact support using the midpoint rule
+#[cfg(test)]
+fn integrate_kernel<K: SymmetricKernel3d<f64>>(kernel: &K, h: f64, n: i32) -> f64 {
+    let dr = h / (n as f64);
+    let dvol = dr * dr * dr;
+
+    let mut integral = 0.0;
+    for i in -n..n {
+        for j in -n..n {
+            for k in -n..n {
+                let r_in = Vector3::new(i as f64, j as f64, k as f64) * dr;
+                let r_out = Vector3::new((i + 1) as f64, (j + 1) as f64, (k + 1) as f64) * dr;
+                let r = ((r_in + r_out) * 0.5).norm();
+
+                integral += dvol * kernel.evaluate(r);
+            }
+        }
+    }
+    integral
+}
+
+#[test]
+fn test_kernels_compact_support() {
+    let hs = [0.025, 0.1, 2.0];
+    for &kernel_type in KernelType::all() {
+        for &h in hs.iter() {
+            let kernel = DynamicKernel::new(kernel_type, h);
+            assert!(kernel.evaluate(0.0) > 0.0);
+            assert!(kernel.evaluate(0.99 * h) > 0.0, "{:?}", kernel_type);
+            assert_eq!(kernel.evaluate(h), 0.0);
+            assert_eq!(kernel.evaluate(2.0 * h), 0.0);
+            assert_eq!(kernel.evaluate(10.0 * h), 0.0);
+            assert_eq!(kernel.evaluate_gradient_norm(h), 0.0);
+        }
+    }
+}
+
+#[test]
+fn test_kernels_integral() {
+    let hs = [0.025, 0.1, 2.0];
+    for &kernel_type in KernelType::all() {
+        for &h in hs.iter() {
+            let kernel = DynamicKernel::new(kernel_type, h);
+            let integral = integrate_kernel(&kernel, h, 20);
+            assert!(
+                (integral - 1.0).abs() <= 1e-3,
+                "integral of {:?} kernel with h={} is {}",
+                kernel_type,
+                h,
+                integral
+            );
+        }
+    }
+}
+
+#[test]
+fn test_kernels_gradient() {
+    let h = 0.1;
+    let eps = 1e-7;
+    for &kernel_type in KernelType::all() {
+        let kernel = DynamicKernel::new(kernel_type, h);
+        for i in 1..100 {
+            let r = (i as f64) * h / 100.0;
+            let finite_difference =
+                (kernel.evaluate(r + eps) - kernel.evaluate(r - eps)) / (2.0 * eps);
+            let gradient_norm = kernel.evaluate_gradient_norm(r);
+            assert!(
+                (finite_difference - gradient_norm).abs() <= 1e-4 * kernel.evaluate(0.0) / h,
+                "{:?} kernel at r={}: finite difference {}, gradient norm {}",
+                kernel_type,
+                r,
+                finite_difference,
+                gradient_norm
+            );
+
+            let x = Vector3::new(r, 0.0, 0.0);
+            assert!((kernel.evaluate_gradient(x).x - gradient_norm).abs() <= 1e-12 / h);
+        }
+    }
+}
+
 /// Accelerator for efficient evaluation of a precomputed cubic kernel
 ///
 /// This structure is used to pre-compute a discrete representation of the cubic kernel function.
@@ -163,11 +663,20 @@ pub struct DiscreteSquaredDistanceCubicKernel<R: Real> {
     values: Vec<R>,
     /// The radial resolution of the discretization on a quadratic scale
     dr: R,
+    /// Compact support radius used for the pre-computation
+    compact_support_radius: R,
 }
 
 impl<R: Real> DiscreteSquaredDistanceCubicKernel<R> {
     /// Precomputes the discrete cubic kernel with compact support radius `h`, the squared radius `h * h` is divided into `n` segments for the quantization
     pub fn new<PR: Real>(n: usize, h: R) -> Self {
+        Self::with_kernel_type::<PR>(KernelType::CubicSpline, n, h)
+    }
+
+    /// Precomputes a discrete kernel of the given type with compact support radius `h`, the squared radius `h * h` is divided into `n` segments for the quantization
+    ///
+    /// Despite the name of this type, any of the kernels in [`KernelType`] can be pre-computed.
+    pub fn with_kernel_type<PR: Real>(kernel_type: KernelType, n: usize, h: R) -> Self {
         let mut values = Vec::with_capacity(n);
 
         let compact_support: PR = h
@@ -175,7 +684,7 @@ impl<R: Real> DiscreteSquaredDistanceCubicKernel<R> {
             .expect("Compact support radius `h` has to fit into kernel pre-computation type `PR`");
         let compact_support_squared = compact_support * compact_support;
 
-        let kernel = CubicSplineKernel::new(compact_support);
+        let kernel = DynamicKernel::new(kernel_type, compact_support);
 
         // Radial width of one discrete kernel value
         let dr = compact_support_squared
@@ -196,7 +705,11 @@ impl<R: Real> DiscreteSquaredDistanceCubicKernel<R> {
         }
 
         let dr = dr.try_convert().unwrap();
-        Self { values, dr }
+        Self {
+            values,
+            dr,
+            compact_support_radius: h,
+        }
     }
 
     /// Evaluates the precomputed kernel function at the specified squared radius, i.e. returns an approximate cubic kernel value at the radius `sqrt(r_squared)`
@@ -206,6 +719,22 @@ impl<R: Real> DiscreteSquaredDistanceCubicKernel<R> {
         let bin = normalized.to_usize().unwrap().min(self.values.len() - 1);
         self.values[bin]
     }
+
+    /// Evaluates the precomputed kernel function for a different compact support radius `h` at the specified squared radius
+    ///
+    /// This uses the self-similarity `k_h(r) = (h₀/h)³ k_h₀(r h₀/h)` of the kernel, where `h₀` is the
+    /// compact support radius that was used for the pre-computation. Returns zero for all squared
+    /// radii outside of the compact support `h`.
+    #[inline(always)]
+    pub fn evaluate_with_compact_support(&self, r_squared: R, h: R) -> R {
+        if r_squared >= h * h {
+            return R::zero();
+        }
+
+        let scale = self.compact_support_radius / h;
+        let scale_squared = scale * scale;
+        self.evaluate(r_squared * scale_squared) * scale_squared * scale
+    }
 }
 
 #[test]
@@ -241,3 +770,37 @@ fn test_discrete_kernel() {
         }
     }
 }
+
+#[test]
+fn test_discrete_kernel_with_compact_support() {
+    let n = 10000;
+    let h_ref = 0.1;
+
+    for &kernel_type in KernelType::all() {
+        let discrete_kernel =
+            DiscreteSquaredDistanceCubicKernel::with_kernel_type::<f64>(kernel_type, n, h_ref);
+
+        for &h in [0.025, 0.05, 0.1].iter() {
+            let kernel = DynamicKernel::new(kernel_type, h);
+
+            let dr = h / 100.0;
+            for i in 0..110 {
+                let r = (i as f64) * dr;
+
+                let discrete = discrete_kernel.evaluate_with_compact_support(r * r, h);
+                let continuous = kernel.evaluate(r);
+
+                let diff = (discrete - continuous).abs();
+                assert!(
+                    diff <= 1e-2 * kernel.evaluate(0.0),
+                    "{:?} kernel at r={}, h={}, discrete: {}, continuous: {}",
+                    kernel_type,
+                    r,
+                    h,
+                    discrete,
+                    continuous
+                );
+            }
+        }
+    }
+}
@@ -4,6 +4,9 @@
 //! Library for surface reconstruction of SPH particle data using marching cubes.
 //!
 //! Entry points are the [`reconstruct_surface`] or [`reconstruct_surface_inplace`] functions.
+//! Particle sets with varying particle sizes are reconstructed by [`reconstruct_surface_with_properties`].
+//! Progress reporting, cancellation and running the reconstruction in a custom thread pool are supported
+//! by [`reconstruct_surface_inplace_with_context`].
 //!
 //! ## Feature flags
 //! The following features are all non-default features to reduce the amount of additional dependencies.
@@ -35,13 +38,18 @@ use thiserror::Error as ThisError;
 pub use vtkio;
 
 pub use crate::aabb::{Aabb2d, Aabb3d, AxisAlignedBoundingBox};
-pub use crate::density_map::DensityMap;
+pub use crate::anisotropic_kernel::AnisotropicKernelParameters;
+pub use crate::boundary::{BoundaryParticles, SignedDistanceFunction, SolidBoundary};
+pub use crate::density_map::{DensityMap, SparseDensityGrid};
+pub use crate::kernel::KernelType;
+pub use crate::progress::{CancellationToken, ReconstructionObserver, ReconstructionStage};
 pub use crate::traits::{Index, Real, RealConvert, ThreadSafe};
 pub use crate::uniform_grid::UniformGrid;
 
 use crate::density_map::DensityMapError;
 use crate::marching_cubes::MarchingCubesError;
-use crate::mesh::TriMesh3d;
+use crate::mesh::{MixedTriQuadMesh3d, TriMesh3d, TriangleOrQuadCell};
+use crate::reconstruction::PerParticleData;
 use crate::uniform_grid::GridConstructionError;
 use crate::workspace::ReconstructionWorkspace;
 
@@ -52,6 +60,10 @@ pub mod profiling;
 pub mod profiling_macro;
 
 mod aabb;
+pub mod anisotropic_kernel;
+pub mod boundary;
+pub mod bvh;
+pub mod curvature;
 pub(crate) mod dense_subdomains;
 pub mod density_map;
 pub mod generic_tree;
@@ -62,10 +74,15 @@ pub mod io;
 pub mod kernel;
 pub mod marching_cubes;
 pub mod mesh;
+pub mod multiphase;
 pub mod neighborhood_search;
 pub mod postprocessing;
+pub mod progress;
 pub(crate) mod reconstruction;
+pub mod signed_distance;
 pub mod sph_interpolation;
+pub mod streaming;
+pub mod surface_nets;
 pub mod topology;
 mod traits;
 pub mod uniform_grid;
@@ -82,7 +99,6 @@ pub(crate) mod workspace;
 // TODO: Test kernels with property based testing?
 // TODO: More and better error messages with distinct types
 // TODO: Make flat indices strongly typed
-// TODO: Function that detects smallest usable index type
 
 pub(crate) type HashState = fxhash::FxBuildHasher;
 pub(crate) type MapType<K, V> = std::collections::HashMap<K, V, HashState>;
@@ -123,6 +139,14 @@ pub enum SpatialDecomposition {
     /// Only subdomains containing at least one particle will be processed.
     /// The small contiguous grid per subdomain make this approach very cache efficient.
     UniformGrid(GridDecompositionParameters),
+    /// Use an octree of subdomains that is adaptively refined according to the number of particles per subdomain
+    ///
+    /// The leaves of the octree are cubes of different sizes aligned with a uniform grid of the
+    /// smallest possible subdomains. Regions with few particles are processed in large subdomains,
+    /// while densely populated regions are split into smaller subdomains, resulting in a more even
+    /// distribution of the work per subdomain. The surface patches of all subdomains are stitched
+    /// together in the same way as with the uniform grid decomposition.
+    Octree(OctreeDecompositionParameters),
 }
 
 /// Default parameters for the spatial decomposition use the uniform grid based decomposition approach
@@ -136,6 +160,8 @@ impl Default for SpatialDecomposition {
 #[derive(Clone, Debug)]
 pub struct GridDecompositionParameters {
     /// Each uniform subdomain will be a cube consisting of this number of MC cube cells along each coordinate axis
+    ///
+    /// The subdomains are enlarged automatically if the ghost margin required for the kernel support is thicker than half of a subdomain.
     pub subdomain_num_cubes_per_dim: u32,
 }
 
@@ -147,6 +173,42 @@ impl Default for GridDecompositionParameters {
     }
 }
 
+/// Parameters for the octree-based spatial decomposition
+#[derive(Clone, Debug)]
+pub struct OctreeDecompositionParameters {
+    /// The smallest subdomains (leaves at the finest octree level) will be cubes consisting of this number of MC cube cells along each coordinate axis
+    ///
+    /// The subdomains are enlarged automatically if the ghost margin required for the kernel support is thicker than half of a subdomain.
+    pub subdomain_num_cubes_per_dim: u32,
+    /// Octree nodes with more particles (including ghost particles of the smallest subdomains) than this limit are subdivided further
+    pub max_particles_per_subdomain: usize,
+    /// Maximum number of levels above the finest octree level, the largest subdomains have `2^max_depth` times the extent of the smallest subdomains
+    pub max_depth: u32,
+}
+
+impl Default for OctreeDecompositionParameters {
+    fn default() -> Self {
+        Self {
+            subdomain_num_cubes_per_dim: 32,
+            max_particles_per_subdomain: 50_000,
+            max_depth: 3,
+        }
+    }
+}
+
+/// Method used to extract the iso-surface mesh from the density field
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceExtractionMethod {
+    /// Marching cubes, places the mesh vertices on the edges of the background grid
+    #[default]
+    MarchingCubes,
+    /// Surface nets, places one mesh vertex per cell of the background grid that is crossed by the
+    /// iso-surface and connects them with one quad per crossed grid edge (see [`surface_nets`])
+    ///
+    /// The resulting meshes have significantly fewer and better shaped elements than marching cubes meshes.
+    SurfaceNets,
+}
+
 /// Parameters for the surface reconstruction
 #[derive(Clone, Debug)]
 pub struct Parameters<R: Real> {
@@ -156,6 +218,10 @@ pub struct Parameters<R: Real> {
     pub rest_density: R,
     /// Compact support radius of the kernel, i.e. distance from the particle where kernel reaches zero (in distance units, not relative to particle radius)
     pub compact_support_radius: R,
+    /// SPH kernel function used for the evaluation of the particle densities and the density field
+    pub kernel_type: KernelType,
+    /// Method used to extract the surface mesh from the density field
+    pub extraction_method: SurfaceExtractionMethod,
     /// Edge length of the marching cubes implicit background grid (in distance units, not relative to particle radius)
     pub cube_size: R,
     /// Density threshold value to distinguish between the inside (above threshold) and outside (below threshold) of the fluid
@@ -172,29 +238,241 @@ pub struct Parameters<R: Real> {
     /// Parameters for the spatial decomposition of the surface reconstruction
     /// If not provided, no spatial decomposition is performed and a global approach is used instead.
     pub spatial_decomposition: Option<SpatialDecomposition>,
+    /// Parameters for anisotropic kernels used to evaluate the density field of the fluid
+    ///
+    /// If provided, the kernel of every particle is stretched and oriented according to the
+    /// distribution of its neighbors which results in smoother surfaces of flat regions and thin
+    /// sheets (see [`anisotropic_kernel`]). If not provided, isotropic kernels are used instead.
+    pub anisotropic_kernel: Option<AnisotropicKernelParameters<R>>,
     /// Whether to return the global particle neighborhood list from the reconstruction.
     /// Depending on the settings of the reconstruction, neighborhood lists are only computed locally
     /// in subdomains. Enabling this flag joins this data over all particles which can add a small overhead.
     pub global_neighborhood_list: bool,
+    /// Whether to return the density field used for the surface extraction as a sparse grid (see [`SurfaceReconstruction::density_grid`]).
+    /// With the subdomain-grid approach, the dense level-set grids of all subdomains are kept until
+    /// the end of the reconstruction which can significantly increase the memory consumption.
+    pub density_grid: bool,
+    /// Solid boundary (e.g. walls or obstacles) that the fluid surface should meet flush (see [`boundary`])
+    ///
+    /// If provided, the densities of fluid particles close to the boundary are corrected and the
+    /// volume of the solid is added to the density field where the fluid is present. Not supported
+    /// by the streaming reconstruction.
+    pub solid_boundary: Option<SolidBoundary<R>>,
 }
 
 impl<R: Real> Parameters<R> {
+    /// Returns parameters with the given particle radius, compact support radius and cube size and defaults for all other fields
+    ///
+    /// The defaults correspond to the defaults of the CLI: a rest density of `1000`, an iso-surface
+    /// threshold of `0.6`, the cubic spline kernel, marching cubes, multi-threading and the uniform
+    /// subdomain-grid decomposition. All optional features (e.g. anisotropic kernels or solid
+    /// boundaries) are disabled. Other fields can be set using the struct update syntax, e.g.
+    /// `Parameters { iso_surface_threshold: 0.5, ..Parameters::new(0.025, 0.1, 0.0125) }`.
+    pub fn new(particle_radius: R, compact_support_radius: R, cube_size: R) -> Self {
+        Self {
+            particle_radius,
+            rest_density: R::from_f64(1000.0).unwrap(),
+            compact_support_radius,
+            kernel_type: KernelType::default(),
+            extraction_method: SurfaceExtractionMethod::default(),
+            cube_size,
+            iso_surface_threshold: R::from_f64(0.6).unwrap(),
+            particle_aabb: None,
+            enable_multi_threading: true,
+            spatial_decomposition: Some(SpatialDecomposition::default()),
+            anisotropic_kernel: None,
+            global_neighborhood_list: false,
+            density_grid: false,
+            solid_boundary: None,
+        }
+    }
+
+    /// Returns the largest distance from a particle that can be influenced by its kernel
+    ///
+    /// Anisotropic kernels can reach further than the isotropic compact support radius
+    /// (one additional cell is added as the kernel centers are shifted independently of the grid).
+    pub(crate) fn kernel_extent(&self) -> R {
+        if let Some(anisotropic_kernel) = &self.anisotropic_kernel {
+            self.compact_support_radius * anisotropic_kernel.max_support_scale() + self.cube_size
+        } else {
+            self.compact_support_radius
+        }
+    }
+
     /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
     pub fn try_convert<T: Real>(&self) -> Option<Parameters<T>> {
         Some(Parameters {
             particle_radius: self.particle_radius.try_convert()?,
             rest_density: self.rest_density.try_convert()?,
             compact_support_radius: self.compact_support_radius.try_convert()?,
+            kernel_type: self.kernel_type,
+            extraction_method: self.extraction_method,
             cube_size: self.cube_size.try_convert()?,
             iso_surface_threshold: self.iso_surface_threshold.try_convert()?,
             particle_aabb: map_option!(&self.particle_aabb, aabb => aabb.try_convert()?),
             enable_multi_threading: self.enable_multi_threading,
             spatial_decomposition: self.spatial_decomposition.clone(),
+            anisotropic_kernel: map_option!(&self.anisotropic_kernel, anisotropic_kernel => anisotropic_kernel.try_convert()?),
             global_neighborhood_list: self.global_neighborhood_list,
+            density_grid: self.density_grid,
+            solid_boundary: map_option!(&self.solid_boundary, boundary => boundary.try_convert()?),
         })
     }
 }
 
+/// Optional per-particle properties for the reconstruction of particle sets with varying particle sizes
+///
+/// Every provided slice has to contain exactly one value per particle passed to the reconstruction.
+/// Properties that are not provided are derived from the global [`Parameters`]:
+///  - the mass of a particle is computed from its radius and the rest density, i.e. `4/3 π r³ ρ₀`,
+///  - the compact support radius of a particle is its radius scaled by the ratio of the global
+///    compact support radius and the global particle radius.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ParticleProperties<'a, R: Real> {
+    /// Radius per particle (used to calculate the particle masses and compact support radii if they are not provided)
+    pub radii: Option<&'a [R]>,
+    /// Mass per particle
+    pub masses: Option<&'a [R]>,
+    /// Compact support radius of the kernel per particle (in distance units, not relative to the particle radius)
+    pub compact_support_radii: Option<&'a [R]>,
+}
+
+impl<'a, R: Real> ParticleProperties<'a, R> {
+    /// Returns whether no per-particle property is provided
+    pub fn is_empty(&self) -> bool {
+        self.radii.is_none() && self.masses.is_none() && self.compact_support_radii.is_none()
+    }
+
+    /// Returns the largest compact support radius of all particles, missing values are derived from the global parameters
+    fn max_compact_support_radius(&self, parameters: &Parameters<R>) -> R {
+        let max = |values: &[R]| values.iter().copied().fold(R::zero(), |max, v| max.max(v));
+        if let Some(compact_support_radii) = self.compact_support_radii {
+            max(compact_support_radii)
+        } else if let Some(radii) = self.radii {
+            max(radii) * (parameters.compact_support_radius / parameters.particle_radius)
+        } else {
+            parameters.compact_support_radius
+        }
+    }
+
+    /// Checks that every provided property has exactly one value per particle
+    fn check_len<I: Index>(&self, num_particles: usize) -> Result<(), ReconstructionError<I, R>> {
+        for (property, values) in [
+            ("radii", self.radii),
+            ("masses", self.masses),
+            ("compact_support_radii", self.compact_support_radii),
+        ] {
+            if let Some(values) = values {
+                if values.len() != num_particles {
+                    return Err(ReconstructionError::ParticlePropertiesLengthMismatch {
+                        property,
+                        len: values.len(),
+                        num_particles,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Optional hooks for progress reporting, cancellation and the thread pool of a surface reconstruction
+///
+/// See [`reconstruct_surface_inplace_with_context`] and the [`progress`] module.
+#[derive(Clone, Default)]
+pub struct ReconstructionContext<'a> {
+    /// Observer that is notified about the progress of the reconstruction
+    observer: Option<&'a dyn ReconstructionObserver>,
+    /// Token that is checked regularly to abort the reconstruction
+    cancellation_token: Option<CancellationToken>,
+    /// Thread pool used for all parallel computations instead of the global rayon thread pool
+    thread_pool: Option<&'a rayon::ThreadPool>,
+}
+
+impl<'a> ReconstructionContext<'a> {
+    /// Returns a context without observer, cancellation token and thread pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the observer that is notified about the progress of the reconstruction
+    pub fn with_observer(mut self, observer: &'a dyn ReconstructionObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Sets the token that can be used to cancel the reconstruction
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    /// Sets the thread pool that is used for all parallel computations of the reconstruction
+    ///
+    /// Without a thread pool, the parallel computations run in the thread pool of the calling
+    /// thread, i.e. usually in the global rayon thread pool.
+    pub fn with_thread_pool(mut self, thread_pool: &'a rayon::ThreadPool) -> Self {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
+
+    /// Executes the given operation in the thread pool of this context
+    ///
+    /// All parallel computations of the operation run in the thread pool of the context, if one was
+    /// set. This can be used to run other functions of this crate, e.g. the [`postprocessing`]
+    /// functions or the [`SphInterpolator`](sph_interpolation::SphInterpolator), in the same thread
+    /// pool as the reconstruction. Without a thread pool, the operation is executed directly.
+    pub fn install<OP, T>(&self, op: OP) -> T
+    where
+        OP: FnOnce() -> T + Send,
+        T: Send,
+    {
+        if let Some(thread_pool) = self.thread_pool {
+            thread_pool.install(op)
+        } else {
+            op()
+        }
+    }
+
+    /// Notifies the observer that the reconstruction entered the given stage
+    pub(crate) fn notify_stage(&self, stage: ReconstructionStage) {
+        if let Some(observer) = self.observer {
+            observer.stage_started(stage);
+        }
+    }
+
+    /// Returns whether the cancellation of the reconstruction was requested
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancellation_token
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+    }
+
+    /// Returns [`ReconstructionError::Cancelled`] if the cancellation of the reconstruction was requested
+    pub(crate) fn check_cancelled<I: Index, R: Real>(
+        &self,
+    ) -> Result<(), ReconstructionError<I, R>> {
+        if self.is_cancelled() {
+            Err(ReconstructionError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl std::fmt::Debug for ReconstructionContext<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReconstructionContext")
+            .field("observer", &self.observer.is_some())
+            .field("cancellation_token", &self.cancellation_token)
+            .field(
+                "thread_pool",
+                &self.thread_pool.map(|pool| pool.current_num_threads()),
+            )
+            .finish()
+    }
+}
+
 /// Result data returned when the surface reconstruction was successful
 #[derive(Clone, Debug)]
 pub struct SurfaceReconstruction<I: Index, R: Real> {
@@ -208,6 +486,10 @@ pub struct SurfaceReconstruction<I: Index, R: Real> {
     particle_neighbors: Option<Vec<Vec<usize>>>,
     /// Surface mesh that is the result of the surface reconstruction
     mesh: TriMesh3d<R>,
+    /// Quads of the surface mesh (only available when using surface nets), each quad corresponds to two consecutive triangles of the mesh
+    quads: Option<Vec<[usize; 4]>>,
+    /// Density field that was used for the surface extraction (only available if requested by the parameters)
+    density_grid: Option<SparseDensityGrid<I, R>>,
     /// Workspace with allocated memory for subsequent surface reconstructions
     workspace: ReconstructionWorkspace<R>,
 }
@@ -221,6 +503,8 @@ impl<I: Index, R: Real> Default for SurfaceReconstruction<I, R> {
             particle_neighbors: None,
             particle_inside_aabb: None,
             mesh: TriMesh3d::default(),
+            quads: None,
+            density_grid: None,
             workspace: ReconstructionWorkspace::default(),
         }
     }
@@ -232,6 +516,30 @@ impl<I: Index, R: Real> SurfaceReconstruction<I, R> {
         &self.mesh
     }
 
+    /// Returns the quad mesh of the reconstructed surface if it was extracted using [`SurfaceExtractionMethod::SurfaceNets`]
+    ///
+    /// The quad mesh shares its vertices with the triangulated [`mesh`](Self::mesh).
+    pub fn quad_mesh(&self) -> Option<MixedTriQuadMesh3d<R>> {
+        self.quads.as_ref().map(|quads| MixedTriQuadMesh3d {
+            vertices: self.mesh.vertices.clone(),
+            cells: quads
+                .iter()
+                .copied()
+                .map(TriangleOrQuadCell::Quad)
+                .collect(),
+        })
+    }
+
+    /// Returns the density field that was used for the surface extraction if it was requested with [`Parameters::density_grid`]
+    ///
+    /// With the subdomain-grid approach, every block of the grid corresponds to a cell of the subdomain
+    /// grid. Otherwise, the sparse density map of the global reconstruction is divided into blocks.
+    /// The values of the density field are normalized such that the iso-surface is located at
+    /// [`Parameters::iso_surface_threshold`].
+    pub fn density_grid(&self) -> Option<&SparseDensityGrid<I, R>> {
+        self.density_grid.as_ref()
+    }
+
     /// Returns a reference to the global particle density vector if it was computed during the reconstruction (always `None` when using independent subdomains with domain decomposition)
     pub fn particle_densities(&self) -> Option<&Vec<R>> {
         self.particle_densities.as_ref()
@@ -280,6 +588,19 @@ pub enum ReconstructionError<I: Index, R: Real> {
         #[from]
         MarchingCubesError,
     ),
+    /// Error that occurs if a per-particle property does not provide exactly one value per particle
+    #[error("the per-particle property `{property}` has {len} values but there are {num_particles} particles")]
+    ParticlePropertiesLengthMismatch {
+        /// Name of the inconsistent property
+        property: &'static str,
+        /// Number of values provided for the property
+        len: usize,
+        /// Number of particles passed to the reconstruction
+        num_particles: usize,
+    },
+    /// Error that occurs if the reconstruction was cancelled using a [`CancellationToken`]
+    #[error("the reconstruction was cancelled")]
+    Cancelled,
     /// Any error that is not represented by some other explicit variant
     #[error(transparent)]
     Unknown(#[from] anyhow::Error),
@@ -290,6 +611,7 @@ pub enum ReconstructionError<I: Index, R: Real> {
 /// Initialization of the global thread pool happens exactly once.
 /// Therefore, if you call `initialize_thread_pool` a second time, it will return an error.
 /// An `Ok` result indicates that this is the first initialization of the thread pool.
+/// To run a reconstruction in a separate thread pool instead, see [`ReconstructionContext::with_thread_pool`].
 pub fn initialize_thread_pool(num_threads: usize) -> Result<(), anyhow::Error> {
     rayon::ThreadPoolBuilder::new()
         .num_threads(num_threads)
@@ -314,8 +636,103 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
     parameters: &Parameters<R>,
     output_surface: &'a mut SurfaceReconstruction<I, R>,
 ) -> Result<(), ReconstructionError<I, R>> {
+    reconstruct_surface_inplace_with_context(
+        particle_positions,
+        None,
+        parameters,
+        &ReconstructionContext::default(),
+        output_surface,
+    )
+}
+
+/// Performs a marching cubes surface construction of particles with varying sizes given by per-particle properties
+///
+/// Per-particle radii, masses and compact support radii can be provided for particle sets with
+/// varying particle sizes (see [`ParticleProperties`]). The global compact support radius of the
+/// `parameters` is replaced by the largest per-particle compact support radius for the neighborhood
+/// search, the margin of the background grid and the ghost particle margin of the subdomains.
+#[inline(never)]
+pub fn reconstruct_surface_with_properties<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_properties: &ParticleProperties<R>,
+    parameters: &Parameters<R>,
+) -> Result<SurfaceReconstruction<I, R>, ReconstructionError<I, R>> {
+    let mut surface = SurfaceReconstruction::default();
+    reconstruct_surface_with_properties_inplace(
+        particle_positions,
+        particle_properties,
+        parameters,
+        &mut surface,
+    )?;
+    Ok(surface)
+}
+
+/// Performs a marching cubes surface construction of particles with varying sizes given by per-particle properties, inplace
+///
+/// See [`reconstruct_surface_with_properties`] for details.
+pub fn reconstruct_surface_with_properties_inplace<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_properties: &ParticleProperties<R>,
+    parameters: &Parameters<R>,
+    output_surface: &mut SurfaceReconstruction<I, R>,
+) -> Result<(), ReconstructionError<I, R>> {
+    reconstruct_surface_inplace_with_context(
+        particle_positions,
+        Some(particle_properties),
+        parameters,
+        &ReconstructionContext::default(),
+        output_surface,
+    )
+}
+
+/// Performs a marching cubes surface construction inplace, reporting its progress to the observer of the given context
+///
+/// Behaves like [`reconstruct_surface_inplace`] (or [`reconstruct_surface_with_properties_inplace`]
+/// if per-particle properties are provided) but additionally notifies the observer of the
+/// `context` about the stages of the reconstruction and the number of processed subdomains. If the
+/// cancellation token of the `context` is cancelled, the reconstruction is aborted as soon as
+/// possible and [`ReconstructionError::Cancelled`] is returned. In this case, the contents of the
+/// `output_surface` are unspecified.
+///
+/// If a thread pool was set for the `context`, all parallel computations of the reconstruction run
+/// in this thread pool instead of the global rayon thread pool.
+pub fn reconstruct_surface_inplace_with_context<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_properties: Option<&ParticleProperties<R>>,
+    parameters: &Parameters<R>,
+    context: &ReconstructionContext,
+    output_surface: &mut SurfaceReconstruction<I, R>,
+) -> Result<(), ReconstructionError<I, R>> {
+    context.install(|| {
+        reconstruct_surface_inplace_impl(
+            particle_positions,
+            particle_properties,
+            parameters,
+            context,
+            output_surface,
+        )
+    })
+}
+
+/// Implementation of [`reconstruct_surface_inplace_with_context`], runs in the thread pool of the context
+fn reconstruct_surface_inplace_impl<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_properties: Option<&ParticleProperties<R>>,
+    parameters: &Parameters<R>,
+    context: &ReconstructionContext,
+    output_surface: &mut SurfaceReconstruction<I, R>,
+) -> Result<(), ReconstructionError<I, R>> {
+    context.check_cancelled()?;
+
     // Clear the existing mesh
     output_surface.mesh.clear();
+    output_surface.quads = None;
+    output_surface.density_grid = None;
+
+    let particle_properties = particle_properties.filter(|p| !p.is_empty());
+    if let Some(particle_properties) = particle_properties {
+        particle_properties.check_len(particle_positions.len())?;
+    }
 
     // Filter out particles
     let filtered_particle_positions = if let Some(particle_aabb) = &parameters.particle_aabb {
@@ -327,11 +744,20 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
             .take()
             .unwrap_or_default();
         utils::reserve_total(&mut particle_inside, particle_positions.len());
-        particle_positions
-            .par_iter()
-            .map(|p| particle_aabb.contains_point(p))
-            .collect_into_vec(&mut particle_inside);
-        let particle_inside_count = particle_inside.par_iter().copied().filter(|i| *i).count();
+        if parameters.enable_multi_threading {
+            particle_positions
+                .par_iter()
+                .map(|p| particle_aabb.contains_point(p))
+                .collect_into_vec(&mut particle_inside);
+        } else {
+            particle_inside.clear();
+            particle_inside.extend(
+                particle_positions
+                    .iter()
+                    .map(|p| particle_aabb.contains_point(p)),
+            );
+        }
+        let particle_inside_count = particle_inside.iter().copied().filter(|i| *i).count();
 
         // Take temporary storage for filtered particles from workspace
         let mut filtered_particles =
@@ -356,11 +782,38 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
     };
     let particle_positions = filtered_particle_positions.as_ref();
 
+    // Resolve per-particle masses and compact support radii (of the filtered particles)
+    let particle_data = particle_properties.map(|particle_properties| {
+        profile!("resolve per-particle properties");
+        PerParticleData::from_properties(
+            particle_properties,
+            parameters,
+            parameters
+                .particle_aabb
+                .as_ref()
+                .and(output_surface.particle_inside_aabb.as_deref()),
+        )
+    });
+
+    // The largest per-particle compact support radius bounds the influence of all particles
+    let parameters = if let Some(particle_data) = &particle_data {
+        let mut parameters = parameters.clone();
+        parameters.compact_support_radius = particle_data.max_compact_support_radius();
+        info!(
+            "Using per-particle properties, the largest compact support radius is {}",
+            parameters.compact_support_radius
+        );
+        Cow::Owned(parameters)
+    } else {
+        Cow::Borrowed(parameters)
+    };
+    let parameters = parameters.as_ref();
+
     // Initialize grid for the reconstruction
     output_surface.grid = grid_for_reconstruction(
         particle_positions,
         parameters.particle_radius,
-        parameters.compact_support_radius,
+        parameters.kernel_extent(),
         parameters.cube_size,
         parameters.particle_aabb.as_ref(),
         parameters.enable_multi_threading,
@@ -368,20 +821,24 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
 
     output_surface.grid.log_grid_info();
 
-    match &parameters.spatial_decomposition {
-        Some(SpatialDecomposition::UniformGrid(_)) => {
+    let result = match &parameters.spatial_decomposition {
+        Some(SpatialDecomposition::UniformGrid(_) | SpatialDecomposition::Octree(_)) => {
             reconstruction::reconstruct_surface_subdomain_grid::<I, R>(
                 particle_positions,
+                particle_data.as_ref(),
                 parameters,
+                context,
                 output_surface,
-            )?
+            )
         }
         None => reconstruction::reconstruct_surface_global(
             particle_positions,
+            particle_data.as_ref(),
             parameters,
+            context,
             output_surface,
-        )?,
-    }
+        ),
+    };
 
     // Put back temporary storage for filtered particles for next reconstruction
     if let Cow::Owned(mut filtered_particles) = filtered_particle_positions {
@@ -389,7 +846,7 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
         *output_surface.workspace.filtered_particles_mut() = filtered_particles;
     }
 
-    Ok(())
+    result
 }
 
 /// Constructs the background grid for marching cubes based on the parameters supplied to the surface reconstruction
@@ -433,3 +890,83 @@ pub fn grid_for_reconstruction<I: Index, R: Real>(
 
     Ok(UniformGrid::from_aabb(&particle_aabb, cube_size)?)
 }
+
+/// Range of flat grid point indices required by the background grid of a reconstruction, see [`required_index_range`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IndexRange {
+    /// Number of points of the background grid in each cartesian direction
+    pub points_per_dim: [i64; 3],
+    /// Total number of points of the background grid, i.e. the largest flat point index plus one
+    pub num_points: i64,
+}
+
+impl IndexRange {
+    /// Returns whether the flat indices of all points of the background grid can be represented by the given index type
+    pub fn fits<I: Index>(&self) -> bool {
+        I::from_i64(self.num_points).is_some()
+    }
+}
+
+/// Computes the range of indices required by the background grid for the reconstruction of the given particles
+///
+/// The background grid is constructed in the same way as by [`reconstruct_surface`] (see [`grid_for_reconstruction`])
+/// but using `i64` indices. With a spatial decomposition, the grid is extended to whole subdomains like in the
+/// reconstruction. If per-particle properties are provided, the margin of the grid accounts for the largest
+/// per-particle compact support radius. The result can be used to select the smallest [`Index`] type that can be
+/// used for the reconstruction, which reduces the memory usage of density maps and other index based data structures.
+pub fn required_index_range<R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_properties: Option<&ParticleProperties<R>>,
+    parameters: &Parameters<R>,
+) -> Result<IndexRange, ReconstructionError<i64, R>> {
+    // The largest per-particle compact support radius bounds the influence of all particles
+    let parameters = match particle_properties.filter(|p| !p.is_empty()) {
+        Some(particle_properties) => {
+            particle_properties.check_len(particle_positions.len())?;
+            let mut parameters = parameters.clone();
+            parameters.compact_support_radius =
+                particle_properties.max_compact_support_radius(&parameters);
+            Cow::Owned(parameters)
+        }
+        None => Cow::Borrowed(parameters),
+    };
+    let parameters = parameters.as_ref();
+
+    let grid = grid_for_reconstruction::<i64, R>(
+        particle_positions,
+        parameters.particle_radius,
+        parameters.kernel_extent(),
+        parameters.cube_size,
+        parameters.particle_aabb.as_ref(),
+        parameters.enable_multi_threading,
+    )?;
+
+    let points_per_dim = if parameters.spatial_decomposition.is_some() {
+        // The subdomain-grid reconstruction extends the background grid to whole subdomains
+        let cells_per_dim = dense_subdomains::subdomain_grid_cells_per_dim(parameters, &grid)?;
+        (|| -> Option<_> {
+            Some([
+                i64::try_from(cells_per_dim[0].checked_add(1)?).ok()?,
+                i64::try_from(cells_per_dim[1].checked_add(1)?).ok()?,
+                i64::try_from(cells_per_dim[2].checked_add(1)?).ok()?,
+            ])
+        })()
+        .ok_or(GridConstructionError::IndexTypeTooSmallPointsPerDim)?
+    } else {
+        *grid.points_per_dim()
+    };
+
+    let num_points = points_per_dim
+        .iter()
+        .try_fold(1i64, |n, &m| n.checked_mul(m))
+        .ok_or(GridConstructionError::IndexTypeTooSmallTotalPoints(
+            points_per_dim[0],
+            points_per_dim[1],
+            points_per_dim[2],
+        ))?;
+
+    Ok(IndexRange {
+        points_per_dim,
+        num_points,
+    })
+}
@@ -1,4 +1,4 @@
-//! Triangulation of [`DensityMap`]s using marching cubes
+//! Triangulation of [`DensityMap`]s and level sets using marching cubes
 
 use crate::marching_cubes::narrow_band_extraction::construct_mc_input;
 use crate::marching_cubes::triangulation::triangulate;
@@ -7,10 +7,12 @@ use crate::{new_map, profile, DensityMap, Index, MapType, Real, UniformGrid};
 use nalgebra::Vector3;
 use thiserror::Error as ThisError;
 
+mod level_set;
 pub mod marching_cubes_lut;
 mod narrow_band_extraction;
 mod triangulation;
 
+pub use level_set::{triangulate_level_set, MarchingCubesLevelSet};
 pub use triangulation::TriangulationError;
 
 /// Error enum for the marching cubes functions
@@ -0,0 +1,328 @@
+//! Marching cubes triangulation of arbitrary level set functions
+
+use crate::dense_subdomains::{
+    globalize_local_edge, stitching, triangulate_subdomain_cells, SurfacePatch,
+};
+use crate::mesh::TriMesh3d;
+use crate::uniform_grid::{GridConstructionError, UniformCartesianCubeGrid3d};
+use crate::{
+    profile, Aabb3d, GridDecompositionParameters, Index, Real, ReconstructionError, UniformGrid,
+};
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use std::cell::RefCell;
+use thread_local::ThreadLocal;
+
+/// Interface that has to be provided by a level set to be triangulated using marching cubes
+///
+/// The iso-surface that is triangulated is the zero level of the level set function. Negative
+/// values are considered to be inside, zero and positive values are considered to be outside (e.g.
+/// like a signed distance function). Triangles of the resulting mesh are oriented such that their
+/// normals point to the outside.
+pub trait MarchingCubesLevelSet<R: Real> {
+    /// Returns whether the level set may be supported within the given AABB.
+    ///
+    /// This function is used by the marching cubes algorithm to quickly discard regions of the domain
+    /// that will not contain any triangles in the final triangulation (i.e. regions that are not part
+    /// of the support of the level set).
+    ///
+    /// Therefore an implementation should conservatively return `true` and only return `false` if it
+    /// can be guaranteed that the region is not part of the support. Consequently an implementation
+    /// should:
+    ///  - return `true` if part of the region is guaranteed to be part of the support
+    ///  - return `true` if it *cannot* be ruled out that the region contains parts of the support of the level set
+    ///  - return `false` if it can be guaranteed that level set is not supported at all in the region, i.e. the level set is outside everywhere in the (closed) AABB
+    ///
+    fn is_region_supported(&self, aabb: &Aabb3d<R>) -> bool;
+    /// Returns the value of the level set function at the given coordinate
+    fn evaluate(&self, coordinate: &Vector3<R>) -> R;
+}
+
+/// Number of marching cubes cells per dimension of the smallest blocks that are culled using [`MarchingCubesLevelSet::is_region_supported`]
+const MIN_CULLING_BLOCK_CUBES: usize = 4;
+
+/// Performs a marching cubes triangulation of the zero level of a level set function on the given background grid
+///
+/// The background grid is decomposed into cubic subdomains with the given number of cells per
+/// dimension which are triangulated independently (in parallel if multi-threading is enabled) and
+/// stitched together afterwards, like in the subdomain-grid based particle surface reconstruction.
+/// Regions of the grid that are not supported by the level set (see
+/// [`MarchingCubesLevelSet::is_region_supported`]) are hierarchically culled so that the level set
+/// is only evaluated close to its support.
+///
+/// The level set is only evaluated at points of the background grid. Points on the boundary of the
+/// grid are always treated as outside, i.e. the resulting mesh is closed even if the level set is
+/// cut by the boundary of the grid.
+pub fn triangulate_level_set<I: Index, R: Real, L: MarchingCubesLevelSet<R> + Sync>(
+    grid: &UniformGrid<I, R>,
+    level_set: &L,
+    decomposition_parameters: &GridDecompositionParameters,
+    enable_multi_threading: bool,
+) -> Result<TriMesh3d<R>, ReconstructionError<I, R>> {
+    profile!(parent, "triangulate_level_set");
+
+    let subdomain_cubes = I::from_u32(decomposition_parameters.subdomain_num_cubes_per_dim)
+        .filter(|&n| n > I::zero())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "invalid number of cubes per subdomain: {}",
+                decomposition_parameters.subdomain_num_cubes_per_dim
+            )
+        })?;
+    // Subdomains larger than the entire grid only waste memory
+    let max_cells_per_dim = grid.cells_per_dim().iter().copied().fold(I::one(), I::max);
+    let subdomain_cubes = subdomain_cubes.min(max_cells_per_dim);
+    let cube_size = grid.cell_size();
+
+    // Subdomains that cover the entire background grid (the last subdomains may extend beyond the grid)
+    let num_subdomains = grid
+        .cells_per_dim()
+        .map(|n| (n + subdomain_cubes - I::one()) / subdomain_cubes);
+    let subdomain_grid = UniformCartesianCubeGrid3d::<I, R>::new(
+        grid.aabb().min(),
+        &num_subdomains,
+        cube_size * subdomain_cubes.to_real_unchecked(),
+    )?;
+    num_subdomains
+        .iter()
+        .try_fold(I::one(), |total, &n| {
+            total.checked_mul(&n)?.checked_mul(&subdomain_cubes)
+        })
+        .ok_or(GridConstructionError::IndexTypeTooSmallCellsPerDim)?;
+    // Number of points of the background grid, points outside of this range belong to subdomains extending beyond the grid
+    let points_per_dim = *grid.points_per_dim();
+
+    let subdomain_points = subdomain_cubes + I::one();
+    let mc_total_points = subdomain_points
+        .checked_mul(&subdomain_points)
+        .and_then(|n| n.checked_mul(&subdomain_points))
+        .and_then(|n| n.to_usize())
+        .ok_or(GridConstructionError::IndexTypeTooSmallTotalPoints(
+            subdomain_points,
+            subdomain_points,
+            subdomain_points,
+        ))?;
+
+    let subdomain_count = num_subdomains[0] * num_subdomains[1] * num_subdomains[2];
+    let flat_subdomain_indices = I::range(I::zero(), subdomain_count)
+        .iter()
+        .collect::<Vec<_>>();
+    info!(
+        "Triangulating level set with {} subdomains ({}x{}x{}).",
+        subdomain_count, num_subdomains[0], num_subdomains[1], num_subdomains[2]
+    );
+
+    #[derive(Default)]
+    struct SubdomainWorkspace<I: Index, R: Real> {
+        // Blocks of cells (lower and upper cell index, exclusive) that are not culled
+        blocks: Vec<([I; 3], [I; 3])>,
+        // Cache for the level-set values
+        levelset_grid: Vec<R>,
+        // Flags whether the level-set was already evaluated at a point
+        evaluated: Vec<bool>,
+    }
+
+    let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<I, R>>>::new();
+
+    let reconstruct_subdomain = |flat_subdomain_idx: I| -> Option<SurfacePatch<I, R>> {
+        let subdomain_idx = subdomain_grid
+            .try_unflatten_cell_index(flat_subdomain_idx)
+            .expect("Subdomain cell does not exist");
+        let subdomain_aabb = subdomain_grid.cell_aabb(&subdomain_idx);
+
+        // Cull the entire subdomain before doing anything else
+        if !level_set.is_region_supported(&subdomain_aabb) {
+            return None;
+        }
+
+        let mc_grid =
+            UniformCartesianCubeGrid3d::new(subdomain_aabb.min(), &[subdomain_cubes; 3], cube_size)
+                .unwrap();
+
+        let mut workspace = workspace_tls.get_or_default().borrow_mut();
+        let SubdomainWorkspace {
+            blocks,
+            levelset_grid,
+            evaluated,
+        } = &mut *workspace;
+
+        {
+            profile!("region culling");
+            blocks.clear();
+            collect_supported_blocks(
+                level_set,
+                &mc_grid,
+                [I::zero(); 3],
+                [subdomain_cubes; 3],
+                true,
+                blocks,
+            );
+        }
+
+        if blocks.is_empty() {
+            return None;
+        }
+
+        // Offset of the subdomain's points in the background grid
+        let point_offset = subdomain_idx.index().map(|i| i * subdomain_cubes);
+
+        // The value stored for points that are not evaluated, never used for interpolation as the adjacent cells are culled
+        let outside_value = R::one();
+        levelset_grid.clear();
+        levelset_grid.resize(mc_total_points, outside_value);
+        evaluated.clear();
+        evaluated.resize(mc_total_points, false);
+
+        {
+            profile!("level set evaluation");
+            for (lower, upper) in blocks.iter() {
+                for i in I::range(lower[0], upper[0] + I::one()).iter() {
+                    for j in I::range(lower[1], upper[1] + I::one()).iter() {
+                        for k in I::range(lower[2], upper[2] + I::one()).iter() {
+                            let local_point = mc_grid
+                                .get_point([i, j, k])
+                                .expect("point has to be part of the subdomain grid");
+                            let flat_point_idx = mc_grid
+                                .flatten_point_index(&local_point)
+                                .to_usize()
+                                .unwrap();
+                            if evaluated[flat_point_idx] {
+                                continue;
+                            }
+                            evaluated[flat_point_idx] = true;
+
+                            let global_point = [
+                                point_offset[0] + i,
+                                point_offset[1] + j,
+                                point_offset[2] + k,
+                            ];
+                            let is_outside_grid =
+                                (0..3).any(|d| global_point[d] >= points_per_dim[d]);
+                            let is_on_boundary = (0..3).any(|d| {
+                                global_point[d] == I::zero()
+                                    || global_point[d] == points_per_dim[d] - I::one()
+                            });
+
+                            // Points outside of the background grid are never evaluated
+                            if is_outside_grid {
+                                continue;
+                            }
+
+                            let value =
+                                level_set.evaluate(&mc_grid.point_coordinates(&local_point));
+                            levelset_grid[flat_point_idx] = if is_on_boundary {
+                                // Points on the boundary are outside to obtain a closed mesh, mirror
+                                // their value to put the vertices on adjacent edges in between
+                                value.abs()
+                            } else {
+                                value
+                            };
+                        }
+                    }
+                }
+            }
+        }
+
+        let cells = {
+            let mc_grid = &mc_grid;
+            blocks.iter().flat_map(move |(lower, upper)| {
+                I::range(lower[0], upper[0]).iter().flat_map(move |i| {
+                    I::range(lower[1], upper[1]).iter().flat_map(move |j| {
+                        I::range(lower[2], upper[2])
+                            .iter()
+                            .map(move |k| mc_grid.get_cell([i, j, k]).unwrap())
+                    })
+                })
+            })
+        };
+
+        // Points with negative values are inside, the vertices are interpolated at the zero level
+        Some(triangulate_subdomain_cells(
+            &mc_grid,
+            levelset_grid,
+            cells,
+            R::zero(),
+            |value| value < R::zero(),
+            |edge| globalize_local_edge(&mc_grid, &subdomain_grid, flat_subdomain_idx, edge),
+        ))
+    };
+
+    let surface_patches: Vec<_> = if enable_multi_threading {
+        flat_subdomain_indices
+            .into_par_iter()
+            .filter_map(|flat_subdomain_idx| {
+                profile!("subdomain triangulation", parent = parent);
+                reconstruct_subdomain(flat_subdomain_idx)
+            })
+            .collect()
+    } else {
+        flat_subdomain_indices
+            .into_iter()
+            .filter_map(|flat_subdomain_idx| {
+                profile!("subdomain triangulation", parent = parent);
+                reconstruct_subdomain(flat_subdomain_idx)
+            })
+            .collect()
+    };
+
+    info!(
+        "{} of {} subdomains are supported by the level set.",
+        surface_patches.len(),
+        subdomain_count
+    );
+
+    Ok(stitching(surface_patches))
+}
+
+/// Recursively subdivides the given block of cells and collects all blocks that are supported by the level set
+fn collect_supported_blocks<I: Index, R: Real, L: MarchingCubesLevelSet<R>>(
+    level_set: &L,
+    mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    lower: [I; 3],
+    upper: [I; 3],
+    is_supported: bool,
+    blocks: &mut Vec<([I; 3], [I; 3])>,
+) {
+    let is_supported = is_supported || {
+        let min = mc_grid.point_coordinates_array(&lower);
+        let max = mc_grid.point_coordinates_array(&upper);
+        level_set.is_region_supported(&Aabb3d::new(min, max))
+    };
+
+    if !is_supported {
+        return;
+    }
+
+    let min_block_size = I::from_usize(MIN_CULLING_BLOCK_CUBES).unwrap();
+    let extents = [0, 1, 2].map(|d| upper[d] - lower[d]);
+    if extents.iter().all(|&n| n <= min_block_size) {
+        blocks.push((lower, upper));
+        return;
+    }
+
+    // Split the block into up to eight children along the axes where it is still larger than the minimum size
+    let split = [0, 1, 2].map(|d| {
+        if extents[d] > min_block_size {
+            let mid = lower[d] + extents[d] / I::two();
+            vec![(lower[d], mid), (mid, upper[d])]
+        } else {
+            vec![(lower[d], upper[d])]
+        }
+    });
+
+    for &(x_lower, x_upper) in split[0].iter() {
+        for &(y_lower, y_upper) in split[1].iter() {
+            for &(z_lower, z_upper) in split[2].iter() {
+                collect_supported_blocks(
+                    level_set,
+                    mc_grid,
+                    [x_lower, y_lower, z_lower],
+                    [x_upper, y_upper, z_upper],
+                    false,
+                    blocks,
+                );
+            }
+        }
+    }
+}
@@ -43,6 +43,21 @@ pub fn tri_area<RIn: Real, RComp: Real>(
         .unscale(RComp::one() + RComp::one())
 }
 
+/// Computes the signed volume of the tetrahedron spanned by the origin and the given triangle
+///
+/// Summing up this volume over all triangles of a closed mesh with outward facing triangles
+/// results in the volume enclosed by the mesh.
+pub fn tri_signed_volume<RIn: Real, RComp: Real>(
+    a: &Vector3<RIn>,
+    b: &Vector3<RIn>,
+    c: &Vector3<RIn>,
+) -> RComp {
+    let a = a.convert::<RComp>();
+    let b = b.convert::<RComp>();
+    let c = c.convert::<RComp>();
+    a.dot(&b.cross(&c)) / RComp::from_i32(6).unwrap()
+}
+
 /// Computes the face normal of the given triangle
 pub fn tri_normal<RIn: Real, RComp: Real>(
     a: &Vector3<RIn>,
@@ -118,6 +133,71 @@ pub fn tri_aspect_ratio<RIn: Real, RComp: Real>(
     l_max / (sqrt_twelve * r_in)
 }
 
+/// Computes the barycentric coordinates of the point on the triangle `abc` that is closest to the point `p`
+///
+/// The closest point is given by `a * u + b * v + c * w` with the returned coordinates `[u, v, w]`.
+/// Based on "Real-Time Collision Detection" by Christer Ericson (2005), section 5.1.5.
+pub fn tri_closest_point_barycentric<R: Real>(
+    p: &Vector3<R>,
+    a: &Vector3<R>,
+    b: &Vector3<R>,
+    c: &Vector3<R>,
+) -> Vector3<R> {
+    let ab = b - a;
+    let ac = c - a;
+
+    // Vertex region of a
+    let ap = p - a;
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= R::zero() && d2 <= R::zero() {
+        return Vector3::new(R::one(), R::zero(), R::zero());
+    }
+
+    // Vertex region of b
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= R::zero() && d4 <= d3 {
+        return Vector3::new(R::zero(), R::one(), R::zero());
+    }
+
+    // Edge region of ab
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= R::zero() && d1 >= R::zero() && d3 <= R::zero() {
+        let v = d1 / (d1 - d3);
+        return Vector3::new(R::one() - v, v, R::zero());
+    }
+
+    // Vertex region of c
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= R::zero() && d5 <= d6 {
+        return Vector3::new(R::zero(), R::zero(), R::one());
+    }
+
+    // Edge region of ac
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= R::zero() && d2 >= R::zero() && d6 <= R::zero() {
+        let w = d2 / (d2 - d6);
+        return Vector3::new(R::one() - w, R::zero(), w);
+    }
+
+    // Edge region of bc
+    let va = d3 * d6 - d5 * d4;
+    if va <= R::zero() && (d4 - d3) >= R::zero() && (d5 - d6) >= R::zero() {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return Vector3::new(R::zero(), R::one() - w, w);
+    }
+
+    // Face region
+    let denom = R::one() / (va + vb + vc);
+    let v = vb * denom;
+    let w = vc * denom;
+    Vector3::new(R::one() - v - w, v, w)
+}
+
 /// Utility functions for triangles meshes
 pub trait TriMesh3dExt<R: Real> {
     /// Returns the slice of all triangle vertices of the mesh
@@ -146,6 +226,32 @@ pub trait TriMesh3dExt<R: Real> {
         let v = self.tri_vertices();
         tri_aspect_ratio(&v[ijk[0]], &v[ijk[1]], &v[ijk[2]])
     }
+
+    /// Computes the signed volume of the tetrahedron spanned by the origin and the triangle with the given vertices
+    fn tri_signed_volume_ijk<RComp: Real>(&self, ijk: &[usize; 3]) -> RComp {
+        let v = self.tri_vertices();
+        tri_signed_volume(&v[ijk[0]], &v[ijk[1]], &v[ijk[2]])
+    }
+
+    /// Computes the total area of the given triangles of the mesh
+    fn surface_area<RComp: Real>(&self, triangles: &[[usize; 3]]) -> RComp {
+        triangles
+            .iter()
+            .map(|tri| self.tri_area_ijk::<RComp>(tri))
+            .fold(RComp::zero(), |sum, area| sum + area)
+    }
+
+    /// Computes the signed volume enclosed by the given triangles of the mesh
+    ///
+    /// The volume is positive for closed meshes with outward facing triangles. For open meshes, the
+    /// result depends on the position of the mesh relative to the origin, see
+    /// [`MeshManifoldInformation::boundary_closing_volume`] to close the boundaries of open meshes.
+    fn signed_volume<RComp: Real>(&self, triangles: &[[usize; 3]]) -> RComp {
+        triangles
+            .iter()
+            .map(|tri| self.tri_signed_volume_ijk::<RComp>(tri))
+            .fold(RComp::zero(), |sum, volume| sum + volume)
+    }
 }
 
 impl<R: Real> TriMesh3dExt<R> for TriMesh3d<R> {
@@ -731,6 +837,256 @@ impl MeshManifoldInformation {
     pub fn is_manifold(&self) -> bool {
         self.non_manifold_edges.is_empty() && self.non_manifold_vertices.is_empty()
     }
+
+    /// Returns the signed volume of caps that close all boundary loops of the associated open mesh
+    ///
+    /// Every boundary loop is closed by a fan of triangles connecting its edges to the centroid of the
+    /// loop. Adding this volume to the [`signed_volume`](TriMesh3dExt::signed_volume) of the mesh
+    /// results in the volume enclosed by the mesh and the caps. The caps are exact for planar
+    /// boundary loops, e.g. of a closed mesh clipped by a single face of an AABB. Loops that wrap
+    /// around an edge of the AABB are approximated by the fan.
+    ///
+    /// The `vertices` have to be the vertices of the mesh this information was computed for.
+    pub fn boundary_closing_volume<R: Real>(&self, vertices: &[Vector3<R>]) -> R {
+        boundary_closing_volume(vertices, &self.boundary_edges, &Vector3::zeros())
+    }
+
+    /// Returns all boundary loops of the associated open mesh as sequences of vertex indices
+    ///
+    /// The vertices of a loop follow the direction of the boundary edges in their faces, i.e. faces
+    /// closing the hole of a loop have to traverse its vertices in reverse order to be consistently
+    /// oriented with the mesh.
+    /// For meshes with a manifold boundary every loop is closed (the last vertex is connected to
+    /// the first vertex), non-manifold boundaries may result in open chains.
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        boundary_loops(&self.boundary_edges)
+    }
+}
+
+/// Chains the given directed boundary edges into loops of vertex indices
+fn boundary_loops(boundary_edges: &[[usize; 2]]) -> Vec<Vec<usize>> {
+    // Map from start vertex to the boundary edges starting at this vertex
+    let mut outgoing_edges: MapType<usize, Vec<usize>> = new_map();
+    for (edge_idx, edge) in boundary_edges.iter().enumerate() {
+        outgoing_edges.entry(edge[0]).or_default().push(edge_idx);
+    }
+
+    let mut visited = vec![false; boundary_edges.len()];
+    let mut loops = Vec::new();
+    for start_edge in 0..boundary_edges.len() {
+        if visited[start_edge] {
+            continue;
+        }
+
+        // Follow the boundary edges until the loop is closed or no unvisited edge continues the loop
+        let mut loop_vertices = Vec::new();
+        let mut current_edge = Some(start_edge);
+        while let Some(edge_idx) = current_edge {
+            visited[edge_idx] = true;
+            loop_vertices.push(boundary_edges[edge_idx][0]);
+            current_edge = outgoing_edges
+                .get(&boundary_edges[edge_idx][1])
+                .and_then(|edges| edges.iter().copied().find(|e| !visited[*e]));
+        }
+        loops.push(loop_vertices);
+    }
+
+    loops
+}
+
+/// Computes the signed volume of triangle fans closing the loops formed by the given directed boundary edges
+///
+/// The volume is computed relative to the given origin, i.e. it has to be combined with the signed
+/// volume of the mesh relative to the same origin.
+fn boundary_closing_volume<R: Real>(
+    vertices: &[Vector3<R>],
+    boundary_edges: &[[usize; 2]],
+    origin: &Vector3<R>,
+) -> R {
+    let mut volume = R::zero();
+    for loop_vertices in boundary_loops(boundary_edges) {
+        let centroid = loop_vertices
+            .iter()
+            .map(|&v| vertices[v] - origin)
+            .fold(Vector3::zeros(), |sum, v| sum + v)
+            .unscale(R::from_usize(loop_vertices.len()).unwrap());
+        // The caps are oriented opposite to the boundary edges of the mesh
+        volume += (0..loop_vertices.len())
+            .map(|i| {
+                let v0 = vertices[loop_vertices[i]] - origin;
+                let v1 = vertices[loop_vertices[(i + 1) % loop_vertices.len()]] - origin;
+                tri_signed_volume::<R, R>(&centroid, &v1, &v0)
+            })
+            .fold(R::zero(), |sum, v| sum + v);
+    }
+
+    volume
+}
+
+/// Returns a map from every sorted edge to the incident triangles and the local index of the edge in these triangles
+fn edge_incident_faces(triangles: &[[usize; 3]]) -> MapType<[usize; 2], Vec<(usize, usize)>> {
+    let mut edge_faces: MapType<[usize; 2], Vec<(usize, usize)>> = new_map();
+    for (f, tri) in triangles.iter().enumerate() {
+        for i in 0..3 {
+            let (v0, v1) = (tri[i], tri[(i + 1) % 3]);
+            edge_faces
+                .entry([v0.min(v1), v0.max(v1)])
+                .or_default()
+                .push((f, i));
+        }
+    }
+    edge_faces
+}
+
+/// Pairs the triangles incident to a non-manifold edge that enclose the same wedge of the volume around the edge
+///
+/// The triangles are given as pairs of triangle index and local edge index. Triangles are sorted by
+/// their angle around the edge. A triangle is paired with its successor if the volume behind the
+/// first triangle (i.e. opposite to its normal) lies in the wedge between both triangles.
+fn pair_triangles_around_edge<R: Real>(
+    vertices: &[Vector3<R>],
+    triangles: &[[usize; 3]],
+    faces: &[(usize, usize)],
+) -> Vec<[(usize, usize); 2]> {
+    let (f0, i0) = faces[0];
+    let origin = vertices[triangles[f0][i0]];
+    let axis = match (vertices[triangles[f0][(i0 + 1) % 3]] - origin).try_normalize(R::zero()) {
+        Some(axis) => axis,
+        None => return Vec::new(),
+    };
+
+    // Component of the vector to the opposite vertex of a triangle that is orthogonal to the edge
+    let orthogonal_dir = |(f, i): (usize, usize)| {
+        let d = vertices[triangles[f][(i + 2) % 3]] - origin;
+        d - axis.scale(axis.dot(&d))
+    };
+    let u = match orthogonal_dir(faces[0]).try_normalize(R::zero()) {
+        Some(u) => u,
+        None => return Vec::new(),
+    };
+    let v = axis.cross(&u);
+
+    // Angle of each triangle around the edge and whether the enclosed volume lies in direction of increasing angles
+    let mut sorted_faces = faces
+        .iter()
+        .map(|&(f, i)| {
+            let d = orthogonal_dir((f, i));
+            let angle = d.dot(&v).atan2(d.dot(&u));
+            let [a, b, c] = triangles[f].map(|v| vertices[v]);
+            let normal = (b - a).cross(&(c - a));
+            let opens_forward = normal.dot(&axis.cross(&d)) < R::zero();
+            (angle, opens_forward, (f, i))
+        })
+        .collect::<Vec<_>>();
+    sorted_faces
+        .sort_by(|(a, _, _), (b, _, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let n = sorted_faces.len();
+    let mut paired = vec![false; n];
+    let mut pairs = Vec::new();
+    for current in 0..n {
+        let next = (current + 1) % n;
+        let (_, current_forward, (f, i)) = sorted_faces[current];
+        let (_, next_forward, (g, j)) = sorted_faces[next];
+        if !paired[current]
+            && !paired[next]
+            && current_forward
+            && !next_forward
+            && triangles[f][i] != triangles[g][j]
+        {
+            paired[current] = true;
+            paired[next] = true;
+            pairs.push([(f, i), (g, j)]);
+        }
+    }
+
+    pairs
+}
+
+/// Number of triangles, area and enclosed volume of a connected component of a triangle mesh
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeshComponent<R: Real> {
+    /// Number of triangles of the component
+    pub num_triangles: usize,
+    /// Total area of all triangles of the component
+    pub area: R,
+    /// Signed volume enclosed by the component
+    ///
+    /// The volume is positive for closed components with outward facing triangles. The boundary
+    /// loops of open components (e.g. components that were clipped) are closed with caps as
+    /// described in [`MeshManifoldInformation::boundary_closing_volume`].
+    pub volume: R,
+}
+
+/// Connected components of a triangle mesh, see [`TriMesh3d::connected_components`]
+#[derive(Clone, Debug)]
+pub struct MeshComponents<R: Real> {
+    /// Component index of every triangle of the mesh
+    triangle_components: Vec<usize>,
+    /// Properties of all components
+    components: Vec<MeshComponent<R>>,
+}
+
+impl<R: Real> MeshComponents<R> {
+    /// Returns the number of connected components
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns whether the mesh does not have any components (i.e. no triangles)
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Returns the properties of all components
+    pub fn components(&self) -> &[MeshComponent<R>] {
+        self.components.as_slice()
+    }
+
+    /// Returns the component index of every triangle of the mesh
+    pub fn triangle_components(&self) -> &[usize] {
+        self.triangle_components.as_slice()
+    }
+
+    /// Returns the indices of the triangles of every component
+    pub fn component_triangles(&self) -> Vec<Vec<usize>> {
+        let mut component_triangles: Vec<Vec<usize>> = self
+            .components
+            .iter()
+            .map(|c| Vec::with_capacity(c.num_triangles))
+            .collect();
+        for (tri_idx, component) in self.triangle_components.iter().copied().enumerate() {
+            component_triangles[component].push(tri_idx);
+        }
+        component_triangles
+    }
+
+    /// Returns the indices of all triangles that belong to a component fulfilling the given predicate
+    ///
+    /// The result can be used with [`Mesh3d::keep_cells`] to remove all other components from the mesh.
+    pub fn filter_triangles<F: Fn(&MeshComponent<R>) -> bool>(&self, predicate: F) -> Vec<usize> {
+        let keep_component: Vec<bool> = self.components.iter().map(predicate).collect();
+        self.triangle_components
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, component)| keep_component[*component])
+            .map(|(tri_idx, _)| tri_idx)
+            .collect()
+    }
+
+    /// Returns a cell attribute with the given name containing the component index of every triangle
+    pub fn to_cell_attribute<S: Into<String>>(&self, name: S) -> MeshAttribute<R> {
+        MeshAttribute::new(
+            name,
+            AttributeData::ScalarU64(
+                self.triangle_components
+                    .iter()
+                    .map(|component| *component as u64)
+                    .collect(),
+            ),
+        )
+    }
 }
 
 impl<R: Real> TriMesh3d<R> {
@@ -1077,6 +1433,91 @@ impl<R: Real> TriMesh3d<R> {
         non_manifold_verts
     }
 
+    /// Computes the total area of all triangles of the mesh
+    pub fn area(&self) -> R {
+        self.surface_area(&self.triangles)
+    }
+
+    /// Computes the volume enclosed by the mesh, boundaries of open meshes are closed with caps
+    ///
+    /// For closed meshes with outward facing triangles, this is the enclosed volume. If the mesh has
+    /// boundary edges (e.g. after clipping with an AABB), the boundary loops are closed as described
+    /// in [`MeshManifoldInformation::boundary_closing_volume`].
+    pub fn enclosed_volume(&self) -> R {
+        let volume = self.signed_volume::<R>(&self.triangles);
+        let boundary_edges: Vec<_> = self.compute_edge_information().boundary_edges().collect();
+        volume + boundary_closing_volume(&self.vertices, &boundary_edges, &Vector3::zeros())
+    }
+
+    /// Labels the connected components of the mesh and computes their number of triangles, area and enclosed volume
+    ///
+    /// Two triangles belong to the same component if they are connected by a sequence of triangles
+    /// sharing at least one vertex with their predecessor. The components are numbered in the order
+    /// of their first triangle in the mesh. The boundaries of open components are closed for the
+    /// volume computation, see [`TriMesh3d::enclosed_volume`].
+    pub fn connected_components(&self) -> MeshComponents<R> {
+        profile!("connected_components");
+
+        let connectivity = self.vertex_vertex_connectivity();
+        let mut vertex_components = vec![usize::MAX; self.vertices.len()];
+        let mut triangle_components = Vec::with_capacity(self.triangles.len());
+        let mut components = Vec::new();
+        // Per component reference point for the volume computation to reduce round-off errors
+        let mut origins = Vec::new();
+        let mut stack = Vec::new();
+
+        for tri in self.triangles.iter() {
+            if vertex_components[tri[0]] == usize::MAX {
+                // Flood fill the vertices of the new component
+                let component = components.len();
+                vertex_components[tri[0]] = component;
+                stack.push(tri[0]);
+                while let Some(i) = stack.pop() {
+                    for &j in connectivity[i].iter() {
+                        if vertex_components[j] == usize::MAX {
+                            vertex_components[j] = component;
+                            stack.push(j);
+                        }
+                    }
+                }
+
+                components.push(MeshComponent {
+                    num_triangles: 0,
+                    area: R::zero(),
+                    volume: R::zero(),
+                });
+                origins.push(self.vertices[tri[0]]);
+            }
+
+            let component = vertex_components[tri[0]];
+            triangle_components.push(component);
+
+            let origin = &origins[component];
+            let [a, b, c] = tri.map(|v| self.vertices[v] - origin);
+            let info = &mut components[component];
+            info.num_triangles += 1;
+            info.area += tri_area::<R, R>(&a, &b, &c);
+            info.volume += tri_signed_volume::<R, R>(&a, &b, &c);
+        }
+
+        // Close the boundary loops of open components (every loop belongs to a single component)
+        let mut component_boundary_edges = vec![Vec::new(); components.len()];
+        for edge in self.compute_edge_information().boundary_edges() {
+            component_boundary_edges[vertex_components[edge[0]]].push(edge);
+        }
+        for (component, boundary_edges) in component_boundary_edges.iter().enumerate() {
+            if !boundary_edges.is_empty() {
+                components[component].volume +=
+                    boundary_closing_volume(&self.vertices, boundary_edges, &origins[component]);
+            }
+        }
+
+        MeshComponents {
+            triangle_components,
+            components,
+        }
+    }
+
     /// Returns a struct with lists of all boundary edges, non-manifold edges and non-manifold vertices
     ///
     /// Note that the output order is not necessarily deterministic due to the internal use of hashmaps.
@@ -1093,6 +1534,155 @@ impl<R: Real> TriMesh3d<R> {
             non_manifold_vertices,
         }
     }
+
+    /// Repairs all non-manifold edges and vertices of the mesh by duplicating vertices, returns maps from the new to the original vertex and triangle indices
+    ///
+    /// Triangles with repeated vertex indices are removed. At edges with more than two incident
+    /// triangles, the triangles are sorted by their angle around the edge and pairs of neighboring
+    /// triangles enclosing the same wedge of the volume stay connected, all other triangles are
+    /// separated along this edge. Edges between two triangles with inconsistent orientation are
+    /// separated as well. Afterwards, every vertex is split into one vertex per fan of connected
+    /// triangles around it. Triangles at edges which cannot be resolved this way are detached from
+    /// the remaining mesh.
+    ///
+    /// The resulting mesh is manifold and every interior edge is traversed in opposite directions by
+    /// its two triangles, i.e. it can always be converted into a [`HalfEdgeTriMesh`](crate::halfedge_mesh::HalfEdgeTriMesh).
+    /// Original vertices keep their index and new vertices are appended. The returned vertex map
+    /// contains the index of the original vertex for every vertex of the repaired mesh and can be
+    /// used to transfer point attributes. The returned triangle map contains the index of the
+    /// original triangle for every triangle of the repaired mesh and can be used to transfer cell
+    /// attributes (it only differs from the identity if degenerate triangles were removed).
+    pub fn repair_non_manifold(&mut self) -> (Vec<usize>, Vec<usize>) {
+        profile!("repair_non_manifold");
+
+        let is_degenerate =
+            |tri: &[usize; 3]| tri[0] == tri[1] || tri[1] == tri[2] || tri[2] == tri[0];
+        let triangle_map = (0..self.triangles.len())
+            .filter(|&f| !is_degenerate(&self.triangles[f]))
+            .collect::<Vec<_>>();
+        self.triangles.retain(|tri| !is_degenerate(tri));
+
+        let mut vertex_map = (0..self.vertices.len()).collect::<Vec<_>>();
+        let mut detached = vec![false; self.triangles.len()];
+
+        loop {
+            let neighbors = self.manifold_triangle_neighbors(&detached);
+            self.split_vertex_fans(&neighbors, &mut vertex_map);
+
+            // Detach all triangles except for one connected pair at edges that are still not manifold
+            let mut num_detached = 0;
+            for (_, faces) in edge_incident_faces(&self.triangles) {
+                let faces = faces
+                    .into_iter()
+                    .filter(|(f, _)| !detached[*f])
+                    .collect::<Vec<_>>();
+                let is_manifold = faces.len() < 2
+                    || (faces.len() == 2 && neighbors[faces[0].0][faces[0].1] == Some(faces[1].0));
+                if is_manifold {
+                    continue;
+                }
+
+                let kept_pair = faces
+                    .iter()
+                    .find_map(|&(f, i)| neighbors[f][i].map(|g| [f, g]))
+                    .filter(|[_, g]| faces.iter().any(|(h, _)| h == g))
+                    .unwrap_or([faces[0].0, faces[0].0]);
+                for &(f, _) in &faces {
+                    if !kept_pair.contains(&f) {
+                        detached[f] = true;
+                        num_detached += 1;
+                    }
+                }
+            }
+
+            if num_detached == 0 {
+                break;
+            }
+        }
+
+        (vertex_map, triangle_map)
+    }
+
+    /// Returns for every local edge of every triangle the neighboring triangle that stays connected across this edge
+    fn manifold_triangle_neighbors(&self, detached: &[bool]) -> Vec<[Option<usize>; 3]> {
+        let mut neighbors = vec![[None; 3]; self.triangles.len()];
+        for (_, faces) in edge_incident_faces(&self.triangles) {
+            let faces = faces
+                .into_iter()
+                .filter(|(f, _)| !detached[*f])
+                .collect::<Vec<_>>();
+
+            let pairs = match faces.len() {
+                0 | 1 => Vec::new(),
+                // Only triangles with consistent orientation stay connected
+                2 if self.triangles[faces[0].0][faces[0].1]
+                    != self.triangles[faces[1].0][faces[1].1] =>
+                {
+                    vec![[faces[0], faces[1]]]
+                }
+                2 => Vec::new(),
+                _ => pair_triangles_around_edge(&self.vertices, &self.triangles, &faces),
+            };
+
+            for [(f, i), (g, j)] in pairs {
+                neighbors[f][i] = Some(g);
+                neighbors[g][j] = Some(f);
+            }
+        }
+
+        neighbors
+    }
+
+    /// Duplicates every vertex with more than one fan of connected triangles such that every fan gets its own vertex
+    fn split_vertex_fans(&mut self, neighbors: &[[Option<usize>; 3]], vertex_map: &mut Vec<usize>) {
+        // Union-find over all triangle corners, corners of the same vertex are merged across connected edges
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        let mut parent = (0..3 * self.triangles.len()).collect::<Vec<_>>();
+        for (f, tri_neighbors) in neighbors.iter().enumerate() {
+            for (i, neighbor) in tri_neighbors.iter().enumerate() {
+                if let Some(g) = *neighbor {
+                    for local_vertex in [i, (i + 1) % 3] {
+                        let v = self.triangles[f][local_vertex];
+                        let j = self.triangles[g]
+                            .iter()
+                            .position(|w| *w == v)
+                            .expect("connected triangles have to share the edge vertices");
+                        let root_f = find(&mut parent, 3 * f + local_vertex);
+                        let root_g = find(&mut parent, 3 * g + j);
+                        parent[root_f] = root_g;
+                    }
+                }
+            }
+        }
+
+        // The first fan of every vertex keeps the original vertex, all other fans get a copy
+        let mut vertex_used = vec![false; self.vertices.len()];
+        let mut fan_vertex = vec![None; parent.len()];
+        for f in 0..self.triangles.len() {
+            for i in 0..3 {
+                let root = find(&mut parent, 3 * f + i);
+                let v = *fan_vertex[root].get_or_insert_with(|| {
+                    let v = self.triangles[f][i];
+                    if !vertex_used[v] {
+                        vertex_used[v] = true;
+                        v
+                    } else {
+                        self.vertices.push(self.vertices[v]);
+                        vertex_map.push(vertex_map[v]);
+                        self.vertices.len() - 1
+                    }
+                });
+                self.triangles[f][i] = v;
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1149,6 +1739,92 @@ mod tri_mesh_tests {
         }
     }
 
+    /// Returns a closed tetrahedron with outward facing triangles, scaled and translated
+    fn tetrahedron(scale: f64, offset: Vector3<f64>) -> TriMesh3d<f64> {
+        TriMesh3d::<f64> {
+            vertices: vec![
+                Vector3::new(0.0, 0.0, 0.0) * scale + offset,
+                Vector3::new(1.0, 0.0, 0.0) * scale + offset,
+                Vector3::new(0.0, 1.0, 0.0) * scale + offset,
+                Vector3::new(0.0, 0.0, 1.0) * scale + offset,
+            ],
+            triangles: vec![[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]],
+        }
+    }
+
+    #[test]
+    fn test_tri_mesh_connected_components() {
+        let mut mesh = tetrahedron(1.0, Vector3::zeros());
+        mesh.append(&mut tetrahedron(2.0, Vector3::new(5.0, 0.0, 0.0)));
+        mesh.append(&mut mesh_one_tri());
+
+        let components = mesh.connected_components();
+        assert_eq!(components.len(), 3);
+        assert_eq!(
+            components.triangle_components(),
+            &[0, 0, 0, 0, 1, 1, 1, 1, 2]
+        );
+
+        let [small, large, tri] = [0, 1, 2].map(|i| components.components()[i]);
+        assert_eq!(small.num_triangles, 4);
+        assert_eq!(tri.num_triangles, 1);
+        assert!((small.volume - 1.0 / 6.0).abs() < 1e-12);
+        assert!((large.volume - 8.0 / 6.0).abs() < 1e-12);
+        assert!((small.area - (1.5 + 0.75f64.sqrt())).abs() < 1e-12);
+        assert!((large.area - 4.0 * small.area).abs() < 1e-12);
+
+        let kept = components.filter_triangles(|c| c.volume.abs() > 0.5);
+        assert_eq!(kept, [4, 5, 6, 7]);
+        let filtered = mesh.keep_cells(&kept, false);
+        assert_eq!(filtered.vertices.len(), 4);
+        assert_eq!(filtered.connected_components().len(), 1);
+
+        let component_triangles = components.component_triangles();
+        assert_eq!(component_triangles[2], [8]);
+    }
+
+    #[test]
+    fn test_tri_mesh_connected_components_clipped() {
+        // Remove the planar bottom face of the large tetrahedron far away from the origin, e.g. by clipping with an AABB
+        let mut mesh = tetrahedron(1.0, Vector3::zeros());
+        mesh.append(
+            &mut tetrahedron(2.0, Vector3::new(50.0, -20.0, 10.0)).keep_cells(&[1, 2, 3], false),
+        );
+        assert!(!mesh.compute_manifold_information().is_closed());
+
+        let components = mesh.connected_components();
+        assert_eq!(components.len(), 2);
+        let [small, clipped] = [0, 1].map(|i| components.components()[i]);
+        assert_eq!(clipped.num_triangles, 3);
+        assert!((small.volume - 1.0 / 6.0).abs() < 1e-12);
+        assert!((clipped.volume - 8.0 / 6.0).abs() < 1e-12);
+
+        let kept = components.filter_triangles(|c| c.volume.abs() > 0.5);
+        assert_eq!(kept, [4, 5, 6]);
+    }
+
+    #[test]
+    fn test_tri_mesh_volume_and_area() {
+        let offset = Vector3::new(3.0, -2.0, 1.0);
+        let mesh = tetrahedron(2.0, offset);
+        assert!((mesh.area() - 4.0 * (1.5 + 0.75f64.sqrt())).abs() < 1e-12);
+        assert!((mesh.signed_volume::<f64>(&mesh.triangles) - 8.0 / 6.0).abs() < 1e-12);
+        assert!((mesh.enclosed_volume() - 8.0 / 6.0).abs() < 1e-12);
+
+        // Removing the planar bottom face, e.g. by clipping with an AABB, does not change the enclosed volume
+        let open_mesh = mesh.keep_cells(&[1, 2, 3], false);
+        let info = open_mesh.compute_manifold_information();
+        assert!(!info.is_closed());
+        assert!((open_mesh.area() - mesh.area() + 2.0).abs() < 1e-12);
+        let closing_volume = info.boundary_closing_volume(&open_mesh.vertices);
+        assert!(
+            (open_mesh.signed_volume::<f64>(&open_mesh.triangles) + closing_volume - 8.0 / 6.0)
+                .abs()
+                < 1e-12
+        );
+        assert!((open_mesh.enclosed_volume() - 8.0 / 6.0).abs() < 1e-12);
+    }
+
     #[test]
     fn test_tri_mesh_edge_info() {
         let mesh = mesh_non_manifold_edge();
@@ -1211,6 +1887,166 @@ mod tri_mesh_tests {
             assert_eq!(info.non_manifold_vertices.len(), 1);
         }
     }
+
+    /// Checks that a repaired mesh is manifold, consistently oriented and matches the original vertices
+    fn assert_repaired(
+        mesh: &TriMesh3d<f64>,
+        original: &TriMesh3d<f64>,
+        (vertex_map, triangle_map): &(Vec<usize>, Vec<usize>),
+    ) {
+        assert!(mesh.compute_manifold_information().is_manifold());
+        assert_eq!(vertex_map.len(), mesh.vertices.len());
+        for (v, &v_orig) in vertex_map.iter().enumerate() {
+            assert_eq!(mesh.vertices[v], original.vertices[v_orig]);
+        }
+        assert_eq!(triangle_map.len(), mesh.triangles.len());
+        for (tri, &f_orig) in mesh.triangles.iter().zip(triangle_map) {
+            assert_eq!(tri.map(|v| vertex_map[v]), original.triangles[f_orig]);
+        }
+
+        let mut directed_edges = BTreeSet::new();
+        for tri in &mesh.triangles {
+            for i in 0..3 {
+                assert!(directed_edges.insert([tri[i], tri[(i + 1) % 3]]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_tri_mesh_repair_non_manifold() {
+        {
+            let original = mesh_non_manifold_edge();
+            let mut mesh = original.clone();
+            let maps = mesh.repair_non_manifold();
+            assert_repaired(&mesh, &original, &maps);
+            let (vertex_map, _) = maps;
+            assert_eq!(mesh.triangles.len(), 3);
+            assert!(mesh.vertices.len() > original.vertices.len());
+            assert_eq!(&vertex_map[..5], &[0, 1, 2, 3, 4]);
+        }
+
+        {
+            let original = mesh_non_manifold_edge_double();
+            let mut mesh = original.clone();
+            let maps = mesh.repair_non_manifold();
+            assert_repaired(&mesh, &original, &maps);
+            assert_eq!(mesh.triangles.len(), 4);
+        }
+
+        {
+            let original = mesh_non_manifold_vertex();
+            let mut mesh = original.clone();
+            let maps = mesh.repair_non_manifold();
+            assert_repaired(&mesh, &original, &maps);
+            let (vertex_map, _) = maps;
+            assert_eq!(vertex_map, [0, 1, 2, 3, 4, 2]);
+        }
+
+        {
+            // Already manifold meshes are not modified
+            let original = tetrahedron(1.0, Vector3::zeros());
+            let mut mesh = original.clone();
+            let (vertex_map, triangle_map) = mesh.repair_non_manifold();
+            assert_eq!(mesh.triangles, original.triangles);
+            assert_eq!(vertex_map, [0, 1, 2, 3]);
+            assert_eq!(triangle_map, [0, 1, 2, 3]);
+        }
+
+        {
+            // Degenerate triangles are removed
+            let mut original = tetrahedron(1.0, Vector3::zeros());
+            original.triangles.insert(1, [0, 0, 1]);
+            original.triangles.push([2, 3, 2]);
+            let mut mesh = original.clone();
+            let maps = mesh.repair_non_manifold();
+            assert_repaired(&mesh, &original, &maps);
+            assert_eq!(maps.1, [0, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn test_tri_mesh_repair_touching_tetrahedra() {
+        // Two tetrahedra that only share the edge between vertex 0 and 1
+        let mut original = TriMesh3d::<f64> {
+            vertices: vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                Vector3::new(1.0, 0.0, 0.5),
+                Vector3::new(1.0, 1.0, 0.5),
+                Vector3::new(-1.0, 0.0, 0.5),
+                Vector3::new(-1.0, -1.0, 0.5),
+            ],
+            triangles: Vec::new(),
+        };
+        for [a, b, c, d] in [[0, 1, 2, 3], [0, 1, 4, 5]] {
+            for [v0, v1, v2, opposite] in [[a, b, c, d], [a, b, d, c], [a, c, d, b], [b, c, d, a]] {
+                let [p0, p1, p2, p_opposite] = [v0, v1, v2, opposite].map(|v| original.vertices[v]);
+                // Orient all triangles away from the opposite vertex
+                if (p1 - p0).cross(&(p2 - p0)).dot(&(p_opposite - p0)) < 0.0 {
+                    original.triangles.push([v0, v1, v2]);
+                } else {
+                    original.triangles.push([v0, v2, v1]);
+                }
+            }
+        }
+
+        let info = original.compute_manifold_information();
+        assert_eq!(info.non_manifold_edges.len(), 1);
+        let volume = original.enclosed_volume();
+
+        let mut mesh = original.clone();
+        let maps = mesh.repair_non_manifold();
+        assert_repaired(&mesh, &original, &maps);
+        let (vertex_map, _) = maps;
+        assert_eq!(vertex_map, [0, 1, 2, 3, 4, 5, 0, 1]);
+
+        // Both tetrahedra are closed and separated
+        assert!(mesh.compute_manifold_information().is_closed());
+        assert_eq!(mesh.connected_components().len(), 2);
+        assert!((mesh.enclosed_volume() - volume).abs() < 1e-12);
+
+        let he_mesh = crate::halfedge_mesh::HalfEdgeTriMesh::from(mesh);
+        assert_eq!(he_mesh.vertices.len(), 8);
+    }
+
+    #[test]
+    fn test_mesh_with_data_remap_attributes() {
+        let mut original = mesh_non_manifold_vertex();
+        original.triangles.push([3, 3, 4]);
+        let num_vertices = original.vertices.len();
+        let num_triangles = original.triangles.len();
+
+        let mut mesh = MeshWithData::new(original)
+            .with_point_data(MeshAttribute::new_real_scalar(
+                "vertex",
+                (0..num_vertices).map(|v| v as f64).collect::<Vec<_>>(),
+            ))
+            .with_cell_data(MeshAttribute::new(
+                "triangle",
+                (0..num_triangles as u64).collect::<Vec<_>>(),
+            ));
+        let (vertex_map, triangle_map) = mesh.mesh.repair_non_manifold();
+        mesh.remap_attributes(&vertex_map, &triangle_map);
+
+        // The attributes of the duplicated vertices and the remaining triangles are copied
+        match &mesh.point_attributes[0].data {
+            AttributeData::ScalarReal(values) => {
+                assert!(values.iter().zip(&vertex_map).all(|(&x, &v)| x == v as f64));
+                assert_eq!(values.len(), mesh.mesh.vertices.len());
+            }
+            _ => panic!("point attribute changed its type"),
+        }
+        match &mesh.cell_attributes[0].data {
+            AttributeData::ScalarU64(values) => {
+                assert!(values
+                    .iter()
+                    .zip(&triangle_map)
+                    .all(|(&x, &f)| x == f as u64));
+                assert_eq!(values.len(), num_triangles - 1);
+            }
+            _ => panic!("cell attribute changed its type"),
+        }
+    }
 }
 
 /// Wrapper type for meshes with attached point or cell data
@@ -1344,6 +2180,22 @@ impl<R: Real, MeshT: Mesh3d<R>> MeshWithData<R, MeshT> {
         self.cell_attributes.push(cell_attribute);
         self
     }
+
+    /// Transfers the attached data to the mesh after it was modified in place, e.g. by [`TriMesh3d::repair_non_manifold`]
+    ///
+    /// The maps contain the index of the original vertex for every vertex and the index of the
+    /// original cell for every cell of the modified mesh, panics if their lengths do not match the
+    /// modified mesh.
+    pub fn remap_attributes(&mut self, vertex_map: &[usize], cell_map: &[usize]) {
+        assert_eq!(vertex_map.len(), self.mesh.vertices().len());
+        assert_eq!(cell_map.len(), self.mesh.cells().len());
+        for attribute in self.point_attributes.iter_mut() {
+            *attribute = attribute.keep_indices(vertex_map);
+        }
+        for attribute in self.cell_attributes.iter_mut() {
+            *attribute = attribute.keep_indices(cell_map);
+        }
+    }
 }
 
 impl<R: Real> MeshAttribute<R> {
@@ -1388,7 +2240,7 @@ impl<R: Real> MeshAttribute<R> {
     }
 
     /// Returns a new attribute keeping only the entries with the given index
-    fn keep_indices(&self, indices: &[usize]) -> Self {
+    pub(crate) fn keep_indices(&self, indices: &[usize]) -> Self {
         let data = match &self.data {
             AttributeData::ScalarU64(d) => {
                 AttributeData::ScalarU64(indices.iter().copied().map(|i| d[i].clone()).collect())
@@ -0,0 +1,417 @@
+//! Surface reconstruction of multiphase particle data with one closed surface per phase
+//!
+//! Every particle is assigned to a phase by an integer phase id (e.g. oil and water particles of a
+//! mixture). The reconstruction computes the particle densities with all particles of the mixture
+//! and then evaluates a separate density field `φ_p` for every phase `p` using only the particles of that phase.
+//! Before the extraction of the surface of a phase, its density field is replaced by
+//! ```text
+//! f_p(x) = min(Σ_q φ_q(x), τ + φ_p(x) - max_{q != p} φ_q(x))
+//! ```
+//! where `τ` is the iso-surface threshold. At a free surface of the phase, this corresponds to the
+//! density field of the whole mixture, while in the interior of the mixture the iso-surface of a phase
+//! is located where its density field is equal to the largest density field of the other phases.
+//! As the fields of two touching phases are evaluated on the same background grid and are
+//! antisymmetric with respect to `τ` at their interface, the surfaces of both phases share exactly the
+//! same vertices in the interface region (with opposite orientation) and no gaps or overlaps appear
+//! between the phases.
+//!
+//! With a [solid boundary](crate::boundary), the volume fraction of the solid is added only once to the
+//! density field `Σ_q φ_q(x)` of the whole mixture and not to the density fields of the individual phases.
+//!
+//! The reconstruction is always performed globally without domain decomposition, i.e. the
+//! [`Parameters::spatial_decomposition`] is ignored.
+
+use crate::boundary::BoundaryField;
+use crate::density_map::DensityMap;
+use crate::mesh::{AttributeData, MeshAttribute, MeshWithData, TriMesh3d};
+use crate::progress::ReconstructionStage;
+use crate::reconstruction::compute_particle_densities_and_neighbors;
+use crate::uniform_grid::UniformGrid;
+use crate::{
+    anisotropic_kernel, density_map, grid_for_reconstruction, marching_cubes, new_map, profile,
+    surface_nets, Index, MapType, Parameters, Real, ReconstructionContext, ReconstructionError,
+    SurfaceExtractionMethod,
+};
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Name of the cell attribute containing the phase id of every triangle of a merged multiphase mesh
+pub const PHASE_ATTRIBUTE_NAME: &str = "phase";
+
+/// Reconstructed surface of a single phase
+#[derive(Clone, Debug)]
+pub struct PhaseSurface<R: Real> {
+    /// Phase id of the particles enclosed by the surface
+    pub phase_id: u64,
+    /// Closed triangle mesh of the phase
+    pub mesh: TriMesh3d<R>,
+}
+
+/// Result data returned when the multiphase surface reconstruction was successful
+#[derive(Clone, Debug)]
+pub struct MultiphaseReconstruction<I: Index, R: Real> {
+    /// Background grid that was used as a basis for generating the density maps of all phases
+    grid: UniformGrid<I, R>,
+    /// Surfaces of all phases, sorted by ascending phase id
+    phases: Vec<PhaseSurface<R>>,
+}
+
+impl<I: Index, R: Real> MultiphaseReconstruction<I, R> {
+    /// Returns a reference to the global grid used for the reconstruction of all phases
+    pub fn grid(&self) -> &UniformGrid<I, R> {
+        &self.grid
+    }
+
+    /// Returns the surfaces of all phases, sorted by ascending phase id
+    pub fn phases(&self) -> &[PhaseSurface<R>] {
+        self.phases.as_slice()
+    }
+
+    /// Returns the surface of the phase with the given id, if any particle belongs to this phase
+    pub fn phase(&self, phase_id: u64) -> Option<&PhaseSurface<R>> {
+        self.phases
+            .iter()
+            .find(|surface| surface.phase_id == phase_id)
+    }
+
+    /// Consumes the reconstruction and returns the surfaces of all phases
+    pub fn into_phases(self) -> Vec<PhaseSurface<R>> {
+        self.phases
+    }
+
+    /// Merges the surfaces of all phases into a single mesh with a cell attribute containing the phase id of every triangle
+    ///
+    /// The cell attribute is called [`PHASE_ATTRIBUTE_NAME`]. Vertices of the interfaces between
+    /// phases are not merged, i.e. each phase keeps its own copy of the interface vertices.
+    pub fn to_merged_mesh(&self) -> MeshWithData<R, TriMesh3d<R>> {
+        let mut mesh = TriMesh3d::default();
+        let mut phase_ids = Vec::new();
+        for surface in &self.phases {
+            mesh.append(&mut surface.mesh.clone());
+            phase_ids.resize(mesh.triangles.len(), surface.phase_id);
+        }
+
+        MeshWithData::new(mesh).with_cell_data(MeshAttribute::new(
+            PHASE_ATTRIBUTE_NAME,
+            AttributeData::ScalarU64(phase_ids),
+        ))
+    }
+}
+
+/// Reconstructs one closed surface per phase of the given particles
+///
+/// The slice `phase_ids` has to contain the phase id of every particle. The returned surfaces are
+/// sorted by ascending phase id and contain a surface for every phase id that occurs in `phase_ids`
+/// (the mesh of a phase may be empty if it does not contain enough particles for a surface).
+/// See the [module level documentation](self) for details.
+pub fn reconstruct_surface_multiphase<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    phase_ids: &[u64],
+    parameters: &Parameters<R>,
+) -> Result<MultiphaseReconstruction<I, R>, ReconstructionError<I, R>> {
+    reconstruct_surface_multiphase_with_context(
+        particle_positions,
+        phase_ids,
+        parameters,
+        &ReconstructionContext::default(),
+    )
+}
+
+/// Reconstructs one closed surface per phase, reporting its progress to the observer of the given context
+///
+/// Behaves like [`reconstruct_surface_multiphase`] but additionally notifies the observer of the
+/// `context` about the stages of the reconstruction. The cancellation token of the `context` is checked
+/// between the stages and between the phases. If it is cancelled, the reconstruction is aborted and
+/// [`ReconstructionError::Cancelled`] is returned.
+///
+/// If a thread pool was set for the `context`, all parallel computations of the reconstruction run
+/// in this thread pool instead of the global rayon thread pool.
+pub fn reconstruct_surface_multiphase_with_context<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    phase_ids: &[u64],
+    parameters: &Parameters<R>,
+    context: &ReconstructionContext,
+) -> Result<MultiphaseReconstruction<I, R>, ReconstructionError<I, R>> {
+    context.install(|| {
+        reconstruct_surface_multiphase_impl(particle_positions, phase_ids, parameters, context)
+    })
+}
+
+/// Implementation of [`reconstruct_surface_multiphase_with_context`], runs in the thread pool of the context
+fn reconstruct_surface_multiphase_impl<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    phase_ids: &[u64],
+    parameters: &Parameters<R>,
+    context: &ReconstructionContext,
+) -> Result<MultiphaseReconstruction<I, R>, ReconstructionError<I, R>> {
+    profile!("reconstruct_surface_multiphase");
+    context.check_cancelled()?;
+
+    if phase_ids.len() != particle_positions.len() {
+        return Err(ReconstructionError::ParticlePropertiesLengthMismatch {
+            property: "phase_ids",
+            len: phase_ids.len(),
+            num_particles: particle_positions.len(),
+        });
+    }
+
+    // Filter out particles
+    let (particle_positions, phase_ids) = if let Some(particle_aabb) = &parameters.particle_aabb {
+        profile!("filtering particles");
+        particle_positions
+            .iter()
+            .zip(phase_ids.iter())
+            .filter(|(p, _)| particle_aabb.contains_point(p))
+            .map(|(p, phase_id)| (*p, *phase_id))
+            .unzip()
+    } else {
+        (particle_positions.to_vec(), phase_ids.to_vec())
+    };
+
+    let grid = grid_for_reconstruction(
+        &particle_positions,
+        parameters.particle_radius,
+        parameters.kernel_extent(),
+        parameters.cube_size,
+        parameters.particle_aabb.as_ref(),
+        parameters.enable_multi_threading,
+    )?;
+    grid.log_grid_info();
+
+    // Indices of the particles of every phase, sorted by phase id
+    let phase_particles = {
+        let mut phase_particles: Vec<(u64, Vec<usize>)> = Vec::new();
+        let mut sorted_phase_ids = phase_ids.clone();
+        sorted_phase_ids.sort_unstable();
+        sorted_phase_ids.dedup();
+        phase_particles.extend(sorted_phase_ids.into_iter().map(|id| (id, Vec::new())));
+        for (i, phase_id) in phase_ids.iter().enumerate() {
+            let phase_idx = phase_particles
+                .binary_search_by_key(phase_id, |(id, _)| *id)
+                .expect("phase id has to be present");
+            phase_particles[phase_idx].1.push(i);
+        }
+        phase_particles
+    };
+    info!(
+        "Reconstructing surfaces of {} phases with phase ids {:?}",
+        phase_particles.len(),
+        phase_particles
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>()
+    );
+
+    let boundary = parameters
+        .solid_boundary
+        .as_ref()
+        .map(|boundary| BoundaryField::new(boundary, parameters));
+
+    context.notify_stage(ReconstructionStage::ParticleDensities);
+    // The densities are computed with all particles of the mixture, so the particles at the interface
+    // between two phases are not considered to be at a free surface
+    let mut particle_neighbor_lists = Vec::new();
+    let mut particle_densities = Vec::new();
+    compute_particle_densities_and_neighbors(
+        &grid,
+        &particle_positions,
+        None,
+        parameters,
+        &mut particle_neighbor_lists,
+        &mut particle_densities,
+    );
+    if let Some(boundary) = &boundary {
+        boundary.add_to_particle_densities(
+            &particle_positions,
+            parameters.rest_density,
+            parameters.enable_multi_threading,
+            &mut particle_densities,
+        );
+    }
+
+    // The anisotropy of a particle is only determined by the neighbors of the same phase, so that
+    // the kernels of particles at an interface are flattened along the interface
+    let anisotropic_kernels = parameters
+        .anisotropic_kernel
+        .as_ref()
+        .map(|kernel_parameters| {
+            profile!("compute anisotropic kernels");
+            let same_phase_neighbor_lists: Vec<Vec<usize>> = particle_neighbor_lists
+                .iter()
+                .enumerate()
+                .map(|(i, neighbors)| {
+                    neighbors
+                        .iter()
+                        .copied()
+                        .filter(|j| phase_ids[*j] == phase_ids[i])
+                        .collect()
+                })
+                .collect();
+
+            let mut anisotropic_kernels = Vec::new();
+            anisotropic_kernel::compute_anisotropic_kernels(
+                &particle_positions,
+                &same_phase_neighbor_lists,
+                parameters.compact_support_radius,
+                None,
+                kernel_parameters,
+                parameters.enable_multi_threading,
+                &mut anisotropic_kernels,
+            );
+            anisotropic_kernels
+        });
+
+    let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
+        * parameters.particle_radius.powi(3);
+    let particle_rest_mass = particle_rest_volume * parameters.rest_density;
+
+    context.check_cancelled()?;
+    context.notify_stage(ReconstructionStage::SurfaceExtraction);
+
+    // Density map of every phase evaluated only with the particles of the phase
+    let mut density_maps = Vec::with_capacity(phase_particles.len());
+    for (phase_id, particles) in &phase_particles {
+        profile!("generate phase density map");
+        context.check_cancelled()?;
+        info!(
+            "Computing density map of phase {} with {} particles",
+            phase_id,
+            particles.len()
+        );
+
+        let mut density_map = DensityMap::default();
+        if let Some(anisotropic_kernels) = &anisotropic_kernels {
+            density_map::generate_sparse_density_map_anisotropic(
+                &grid,
+                &particle_densities,
+                None,
+                particle_rest_mass,
+                anisotropic_kernels,
+                parameters.kernel_type,
+                Some(particles.as_slice()),
+                parameters.cube_size,
+                parameters.enable_multi_threading,
+                &mut density_map,
+            )?;
+        } else {
+            density_map::generate_sparse_density_map(
+                &grid,
+                &particle_positions,
+                &particle_densities,
+                Some(particles.as_slice()),
+                particle_rest_mass,
+                parameters.compact_support_radius,
+                parameters.kernel_type,
+                parameters.cube_size,
+                parameters.enable_multi_threading,
+                &mut density_map,
+            )?;
+        }
+
+        density_maps.push(density_map);
+    }
+
+    let mut phases = Vec::with_capacity(phase_particles.len());
+    for (phase_idx, (phase_id, _)) in phase_particles.iter().enumerate() {
+        profile!("extract phase surface");
+        context.check_cancelled()?;
+
+        let density_map = phase_surface_density_map(
+            &grid,
+            &density_maps,
+            phase_idx,
+            boundary.as_ref(),
+            parameters.iso_surface_threshold,
+            parameters.enable_multi_threading,
+        );
+
+        let mut mesh = TriMesh3d::default();
+        match parameters.extraction_method {
+            SurfaceExtractionMethod::MarchingCubes => {
+                marching_cubes::triangulate_density_map_append(
+                    &grid,
+                    &density_map,
+                    parameters.iso_surface_threshold,
+                    &mut mesh,
+                )?;
+            }
+            SurfaceExtractionMethod::SurfaceNets => {
+                let mut quads = Vec::new();
+                surface_nets::extract_density_map_append(
+                    &grid,
+                    &density_map,
+                    parameters.iso_surface_threshold,
+                    &mut mesh.vertices,
+                    &mut quads,
+                );
+                mesh.triangles = surface_nets::triangulate_quads(&mesh.vertices, &quads);
+            }
+        }
+
+        info!(
+            "Surface of phase {} has {} vertices and {} triangles",
+            phase_id,
+            mesh.vertices.len(),
+            mesh.triangles.len()
+        );
+        phases.push(PhaseSurface {
+            phase_id: *phase_id,
+            mesh,
+        });
+    }
+
+    Ok(MultiphaseReconstruction { grid, phases })
+}
+
+/// Returns the density map of the given phase used for the extraction of its surface (see the module level documentation)
+fn phase_surface_density_map<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    density_maps: &[DensityMap<I, R>],
+    phase_idx: usize,
+    boundary: Option<&BoundaryField<R>>,
+    iso_surface_threshold: R,
+    enable_multi_threading: bool,
+) -> DensityMap<I, R> {
+    let phase_value = |(flat_point_idx, value): (I, R)| {
+        let (other_sum, other_max) = density_maps
+            .iter()
+            .enumerate()
+            .filter(|(other_idx, _)| *other_idx != phase_idx)
+            .filter_map(|(_, other_map)| other_map.get(flat_point_idx))
+            .fold((R::zero(), R::zero()), |(sum, max), other| {
+                (sum + other, max.max(other))
+            });
+
+        // The solid is added only once to the density field of the whole mixture
+        let mixture_value = value + other_sum;
+        let mixture_value = if let Some(boundary) = boundary {
+            let point = grid
+                .try_unflatten_point_index(flat_point_idx)
+                .expect("density map entry has to belong to a grid point");
+            boundary.apply_to_value(
+                mixture_value,
+                &grid.point_coordinates(&point),
+                iso_surface_threshold,
+            )
+        } else {
+            mixture_value
+        };
+
+        (
+            flat_point_idx,
+            mixture_value.min(iso_surface_threshold + value - other_max),
+        )
+    };
+
+    let values = density_maps[phase_idx].to_vec();
+    let mut map: MapType<I, R> = new_map();
+    map.reserve(values.len());
+    if enable_multi_threading {
+        let values: Vec<_> = values.into_par_iter().map(phase_value).collect();
+        map.extend(values);
+    } else {
+        map.extend(values.into_iter().map(phase_value));
+    }
+    DensityMap::from(map)
+}
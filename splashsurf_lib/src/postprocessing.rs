@@ -1,13 +1,21 @@
-//! Functions for post-processing of surface meshes (decimation, smoothing, etc.)
-
-use crate::halfedge_mesh::{HalfEdgeTriMesh, IllegalHalfEdgeCollapse};
-use crate::mesh::{Mesh3d, MixedTriQuadMesh3d, TriMesh3d, TriMesh3dExt, TriangleOrQuadCell};
+//! Functions for post-processing of surface meshes (decimation, smoothing, remeshing, hole filling, subdivision, etc.)
+//!
+//! The parallel functions (prefixed with `par_`) run in the rayon thread pool of the calling thread,
+//! use [`ReconstructionContext::install`](crate::ReconstructionContext::install) to run them in a custom thread pool.
+
+use crate::halfedge_mesh::{HalfEdge, HalfEdgeTriMesh, IllegalHalfEdgeCollapse};
+use crate::mesh::{
+    angle_between, tri_area, tri_closest_point_barycentric, AttributeData, Mesh3d, MeshAttribute,
+    MeshWithData, MixedTriQuadMesh3d, TriMesh3d, TriMesh3dExt, TriangleCell, TriangleOrQuadCell,
+};
 use crate::topology::{Axis, DirectedAxis, Direction};
 use crate::uniform_grid::UniformCartesianCubeGrid3d;
-use crate::{profile, Index, MapType, Real, SetType};
+use crate::{new_map, profile, Aabb3d, Index, MapType, Real, RealConvert, SetType};
 use log::{info, warn};
-use nalgebra::Vector3;
+use nalgebra::{Matrix3, Vector2, Vector3};
 use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 
 /// Laplacian Smoothing with feature weights
 ///
@@ -34,23 +42,302 @@ pub fn par_laplacian_smoothing_inplace<R: Real>(
             .par_iter_mut()
             .enumerate()
             .for_each(|(i, vertex_i)| {
-                let beta_eff = beta * weights[i];
+                laplacian_smoothing_vertex(
+                    i,
+                    vertex_i,
+                    &vertex_buffer,
+                    vertex_connectivity,
+                    beta * weights[i],
+                )
+            });
+    }
+}
 
-                // Compute mean position of neighboring vertices
-                let mut vertex_sum = Vector3::zeros();
-                for j in vertex_connectivity[i].iter() {
-                    vertex_sum += vertex_buffer[j.clone()];
-                }
-                if vertex_connectivity[i].len() > 0 {
-                    let n = R::from_usize(vertex_connectivity[i].len()).unwrap();
-                    vertex_sum /= n;
-                }
+/// Laplacian Smoothing with feature weights (single-threaded version of [`par_laplacian_smoothing_inplace`])
+pub fn laplacian_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    beta: R,
+    weights: &[R],
+) {
+    profile!("laplacian_smoothing");
+
+    let mut vertex_buffer = mesh.vertices.clone();
+
+    for _ in 0..iterations {
+        profile!("laplacian_smoothing iter");
+
+        std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+
+        mesh.vertices
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, vertex_i)| {
+                laplacian_smoothing_vertex(
+                    i,
+                    vertex_i,
+                    &vertex_buffer,
+                    vertex_connectivity,
+                    beta * weights[i],
+                )
+            });
+    }
+}
+
+/// Moves the vertex `i` towards the mean position of its neighbors in the previous iteration
+#[inline(always)]
+fn laplacian_smoothing_vertex<R: Real>(
+    i: usize,
+    vertex_i: &mut Vector3<R>,
+    vertex_buffer: &[Vector3<R>],
+    vertex_connectivity: &[Vec<usize>],
+    beta_eff: R,
+) {
+    // Compute mean position of neighboring vertices
+    let mut vertex_sum = Vector3::zeros();
+    for j in vertex_connectivity[i].iter() {
+        vertex_sum += vertex_buffer[j.clone()];
+    }
+    if vertex_connectivity[i].len() > 0 {
+        let n = R::from_usize(vertex_connectivity[i].len()).unwrap();
+        vertex_sum /= n;
+    }
+
+    *vertex_i = vertex_i.scale(R::one() - beta_eff) + vertex_sum.scale(beta_eff);
+}
+
+/// Taubin λ/μ smoothing with feature weights
+///
+/// Every iteration consists of a Laplacian smoothing step with the positive factor `lambda` followed
+/// by a step with the negative factor `mu` (with `|mu| > lambda`, e.g. `lambda = 0.5` and `mu = -0.53`)
+/// that inflates the mesh again. In contrast to [`par_laplacian_smoothing_inplace`], this avoids
+/// the shrinkage of the mesh over many iterations. The feature weights scale both factors per vertex.
+pub fn par_taubin_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    lambda: R,
+    mu: R,
+    weights: &[R],
+) {
+    profile!("taubin_smoothing");
+
+    let mut vertex_buffer = mesh.vertices.clone();
+
+    for _ in 0..iterations {
+        profile!("taubin_smoothing iter");
+
+        for factor in [lambda, mu] {
+            std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+
+            mesh.vertices
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(i, vertex_i)| {
+                    *vertex_i = vertex_buffer[i];
+                    laplacian_smoothing_vertex(
+                        i,
+                        vertex_i,
+                        &vertex_buffer,
+                        vertex_connectivity,
+                        factor * weights[i],
+                    )
+                });
+        }
+    }
+}
+
+/// Taubin λ/μ smoothing with feature weights (single-threaded version of [`par_taubin_smoothing_inplace`])
+pub fn taubin_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    lambda: R,
+    mu: R,
+    weights: &[R],
+) {
+    profile!("taubin_smoothing");
+
+    let mut vertex_buffer = mesh.vertices.clone();
+
+    for _ in 0..iterations {
+        profile!("taubin_smoothing iter");
+
+        for factor in [lambda, mu] {
+            std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+
+            mesh.vertices
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, vertex_i)| {
+                    *vertex_i = vertex_buffer[i];
+                    laplacian_smoothing_vertex(
+                        i,
+                        vertex_i,
+                        &vertex_buffer,
+                        vertex_connectivity,
+                        factor * weights[i],
+                    )
+                });
+        }
+    }
+}
+
+/// HC (Humphrey's Classes) Laplacian smoothing with feature weights
+///
+/// After each Laplacian smoothing step, the vertices are pushed back towards a blend of their
+/// original positions (factor `alpha`) and their positions before the step, averaged with the
+/// corrections of their neighbors (factor `beta`), see Vollmer et al. (1999), "Improved Laplacian
+/// Smoothing of Noisy Surface Meshes". Typical values are `alpha = 0.1` and `beta = 0.6`.
+/// In contrast to [`par_laplacian_smoothing_inplace`], this avoids the shrinkage of the mesh over
+/// many iterations. The feature weights blend between the previous and the smoothed position per vertex.
+pub fn par_hc_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    alpha: R,
+    beta: R,
+    weights: &[R],
+) {
+    profile!("hc_smoothing");
+
+    let original_vertices = mesh.vertices.clone();
+    let mut vertex_buffer = mesh.vertices.clone();
+    let mut corrections = vec![Vector3::zeros(); mesh.vertices.len()];
+
+    for _ in 0..iterations {
+        profile!("hc_smoothing iter");
+
+        std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+
+        mesh.vertices
+            .par_iter_mut()
+            .zip(corrections.par_iter_mut())
+            .enumerate()
+            .for_each(|(i, (vertex_i, correction_i))| {
+                *correction_i = hc_smoothing_vertex_correction(
+                    i,
+                    vertex_i,
+                    &vertex_buffer,
+                    &original_vertices,
+                    vertex_connectivity,
+                    alpha,
+                )
+            });
+
+        mesh.vertices
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, vertex_i)| {
+                hc_smoothing_vertex_push_back(
+                    i,
+                    vertex_i,
+                    &vertex_buffer,
+                    &corrections,
+                    vertex_connectivity,
+                    beta,
+                    weights[i],
+                )
+            });
+    }
+}
+
+/// HC (Humphrey's Classes) Laplacian smoothing with feature weights (single-threaded version of [`par_hc_smoothing_inplace`])
+pub fn hc_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    alpha: R,
+    beta: R,
+    weights: &[R],
+) {
+    profile!("hc_smoothing");
+
+    let original_vertices = mesh.vertices.clone();
+    let mut vertex_buffer = mesh.vertices.clone();
+    let mut corrections = vec![Vector3::zeros(); mesh.vertices.len()];
+
+    for _ in 0..iterations {
+        profile!("hc_smoothing iter");
+
+        std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+
+        mesh.vertices
+            .iter_mut()
+            .zip(corrections.iter_mut())
+            .enumerate()
+            .for_each(|(i, (vertex_i, correction_i))| {
+                *correction_i = hc_smoothing_vertex_correction(
+                    i,
+                    vertex_i,
+                    &vertex_buffer,
+                    &original_vertices,
+                    vertex_connectivity,
+                    alpha,
+                )
+            });
 
-                *vertex_i = vertex_i.scale(R::one() - beta_eff) + vertex_sum.scale(beta_eff);
+        mesh.vertices
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, vertex_i)| {
+                hc_smoothing_vertex_push_back(
+                    i,
+                    vertex_i,
+                    &vertex_buffer,
+                    &corrections,
+                    vertex_connectivity,
+                    beta,
+                    weights[i],
+                )
             });
     }
 }
 
+/// Moves the vertex `i` to the mean position of its neighbors and returns the difference to the blend of its original and previous position
+#[inline(always)]
+fn hc_smoothing_vertex_correction<R: Real>(
+    i: usize,
+    vertex_i: &mut Vector3<R>,
+    vertex_buffer: &[Vector3<R>],
+    original_vertices: &[Vector3<R>],
+    vertex_connectivity: &[Vec<usize>],
+    alpha: R,
+) -> Vector3<R> {
+    *vertex_i = vertex_buffer[i];
+    laplacian_smoothing_vertex(i, vertex_i, vertex_buffer, vertex_connectivity, R::one());
+    *vertex_i - (original_vertices[i].scale(alpha) + vertex_buffer[i].scale(R::one() - alpha))
+}
+
+/// Pushes the smoothed vertex `i` back by its own correction and the mean correction of its neighbors
+#[inline(always)]
+fn hc_smoothing_vertex_push_back<R: Real>(
+    i: usize,
+    vertex_i: &mut Vector3<R>,
+    vertex_buffer: &[Vector3<R>],
+    corrections: &[Vector3<R>],
+    vertex_connectivity: &[Vec<usize>],
+    beta: R,
+    weight: R,
+) {
+    let neighbors = &vertex_connectivity[i];
+    if neighbors.is_empty() {
+        *vertex_i = vertex_buffer[i];
+        return;
+    }
+
+    let mut mean_correction = Vector3::zeros();
+    for &j in neighbors {
+        mean_correction += corrections[j];
+    }
+    mean_correction /= R::from_usize(neighbors.len()).unwrap();
+
+    let smoothed =
+        *vertex_i - (corrections[i].scale(beta) + mean_correction.scale(R::one() - beta));
+    *vertex_i = vertex_buffer[i] + (smoothed - vertex_buffer[i]).scale(weight);
+}
+
 /// Laplacian smoothing of a normal field
 pub fn par_laplacian_smoothing_normals_inplace<R: Real>(
     normals: &mut Vec<Vector3<R>>,
@@ -70,16 +357,48 @@ pub fn par_laplacian_smoothing_normals_inplace<R: Real>(
             .par_iter_mut()
             .enumerate()
             .for_each(|(i, normal_i)| {
-                *normal_i = Vector3::zeros();
-                for j in vertex_connectivity[i].iter().copied() {
-                    let normal_j = normal_buffer[j];
-                    *normal_i += normal_j;
-                }
-                normal_i.normalize_mut();
+                laplacian_smoothing_normal(i, normal_i, &normal_buffer, vertex_connectivity)
             });
     }
 }
 
+/// Laplacian smoothing of a normal field (single-threaded version of [`par_laplacian_smoothing_normals_inplace`])
+pub fn laplacian_smoothing_normals_inplace<R: Real>(
+    normals: &mut Vec<Vector3<R>>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+) {
+    profile!("laplacian_smoothing_normals_inplace");
+
+    let mut normal_buffer = normals.clone();
+
+    for _ in 0..iterations {
+        profile!("smoothing iteration");
+
+        std::mem::swap(&mut normal_buffer, normals);
+
+        normals.iter_mut().enumerate().for_each(|(i, normal_i)| {
+            laplacian_smoothing_normal(i, normal_i, &normal_buffer, vertex_connectivity)
+        });
+    }
+}
+
+/// Replaces the normal `i` by the normalized sum of the normals of its neighbors in the previous iteration
+#[inline(always)]
+fn laplacian_smoothing_normal<R: Real>(
+    i: usize,
+    normal_i: &mut Vector3<R>,
+    normal_buffer: &[Vector3<R>],
+    vertex_connectivity: &[Vec<usize>],
+) {
+    *normal_i = Vector3::zeros();
+    for j in vertex_connectivity[i].iter().copied() {
+        let normal_j = normal_buffer[j];
+        *normal_i += normal_j;
+    }
+    normal_i.normalize_mut();
+}
+
 /// Mesh simplification designed for marching cubes surfaces meshes inspired by the "Compact Contouring"/"Mesh displacement" approach by Doug Moore and Joe Warren
 ///
 /// See Moore and Warren: ["Mesh Displacement: An Improved Contouring Method for Trivariate Data"](https://citeseerx.ist.psu.edu/viewdoc/download?doi=10.1.1.49.5214&rep=rep1&type=pdf) (1991)
@@ -650,6 +969,1733 @@ pub fn merge_double_barnacle_configurations_he<R: Real>(mesh: &mut HalfEdgeTriMe
     process_collapse_queue_iterative(mesh, half_edge_collapses.iter().map(|(i, j)| (*i, *j)));
 }
 
+/// Parameters for the quadric error metric (QEM) based mesh decimation, see [`qem_decimation`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QemDecimationParameters<R: Real> {
+    /// The decimation stops as soon as the mesh has at most this number of triangles
+    pub target_triangle_count: Option<usize>,
+    /// Largest quadric error of a collapse, i.e. the sum of the squared distances of the remaining vertex to the planes of the triangles merged into it
+    pub max_error: Option<R>,
+    /// Largest angle (in radians) by which the normal of a triangle may rotate during a collapse, see [`HalfEdgeTriMesh::half_edge_collapse_max_normal_change`]
+    pub max_normal_change: Option<R>,
+    /// Largest factor by which the aspect ratio of a triangle may increase during a collapse, see [`HalfEdgeTriMesh::half_edge_collapse_max_aspect_ratio_change`]
+    pub max_aspect_ratio_change: Option<R>,
+}
+
+impl<R: Real> Default for QemDecimationParameters<R> {
+    /// Returns parameters without target triangle count and error bound that reject collapses rotating triangle normals by more than 45°
+    fn default() -> Self {
+        Self {
+            target_triangle_count: None,
+            max_error: None,
+            max_normal_change: Some(R::frac_pi_4()),
+            max_aspect_ratio_change: None,
+        }
+    }
+}
+
+impl<R: Real> QemDecimationParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<QemDecimationParameters<T>> {
+        let convert = |value: Option<R>| -> Option<Option<T>> {
+            match value {
+                Some(value) => Some(Some(value.try_convert()?)),
+                None => Some(None),
+            }
+        };
+        Some(QemDecimationParameters {
+            target_triangle_count: self.target_triangle_count,
+            max_error: convert(self.max_error)?,
+            max_normal_change: convert(self.max_normal_change)?,
+            max_aspect_ratio_change: convert(self.max_aspect_ratio_change)?,
+        })
+    }
+}
+
+/// Quadric `x^T A x + 2 b^T x + c` measuring the sum of squared distances of a point to a set of planes
+#[derive(Copy, Clone, Debug)]
+struct Quadric<R: Real> {
+    a: Matrix3<R>,
+    b: Vector3<R>,
+    c: R,
+}
+
+impl<R: Real> Quadric<R> {
+    fn zero() -> Self {
+        Self {
+            a: Matrix3::zeros(),
+            b: Vector3::zeros(),
+            c: R::zero(),
+        }
+    }
+
+    /// Returns the quadric of the plane through the given point with the given unit normal
+    fn from_plane(normal: &Vector3<R>, point: &Vector3<R>) -> Self {
+        let d = -normal.dot(point);
+        Self {
+            a: normal * normal.transpose(),
+            b: normal.scale(d),
+            c: d * d,
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.a += other.a;
+        self.b += other.b;
+        self.c += other.c;
+    }
+
+    fn error(&self, x: &Vector3<R>) -> R {
+        (x.dot(&(self.a * x)) + (self.b.dot(x) + self.b.dot(x)) + self.c).max(R::zero())
+    }
+}
+
+/// Candidate for an edge collapse in the priority queue of the QEM decimation
+#[derive(Copy, Clone, Debug)]
+struct CollapseCandidate<R: Real> {
+    error: R,
+    from: usize,
+    to: usize,
+    /// Versions of both vertices when the candidate was computed, outdated candidates are skipped
+    versions: [usize; 2],
+}
+
+impl<R: Real> PartialEq for CollapseCandidate<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<R: Real> Eq for CollapseCandidate<R> {}
+
+impl<R: Real> PartialOrd for CollapseCandidate<R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R: Real> Ord for CollapseCandidate<R> {
+    /// Reversed order of the errors to obtain a min-heap from a [`BinaryHeap`]
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .error
+            .partial_cmp(&self.error)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| (other.from, other.to).cmp(&(self.from, self.to)))
+    }
+}
+
+/// Simplifies the mesh by collapsing the edges with the smallest quadric error until the target triangle count or the error bound is reached
+///
+/// Implements the quadric error metric (QEM) of Garland and Heckbert using half-edge collapses,
+/// i.e. the remaining vertex of a collapse keeps its position. Vertices on the boundary of open
+/// meshes are not removed. Collapses that are topologically illegal (see [`HalfEdgeTriMesh::is_collapse_ok`])
+/// or that violate the normal and aspect ratio guards of the `parameters` are skipped.
+/// If neither a target triangle count nor an error bound is specified, the mesh is not modified.
+///
+/// Returns the vertex-vertex connectivity of the decimated mesh.
+pub fn qem_decimation<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &QemDecimationParameters<R>,
+    keep_vertices: bool,
+) -> Vec<Vec<usize>> {
+    profile!("qem_decimation");
+
+    let mut mesh_he = HalfEdgeTriMesh::from(std::mem::take(mesh));
+    if parameters.target_triangle_count.is_some() || parameters.max_error.is_some() {
+        qem_decimation_he(&mut mesh_he, parameters);
+    }
+
+    {
+        profile!("convert mesh back");
+        let (new_mesh, vertex_map) = mesh_he.into_parts(keep_vertices);
+        *mesh = new_mesh;
+        vertex_map
+    }
+}
+
+/// Performs the QEM decimation of [`qem_decimation`] on a half-edge mesh, returns the number of performed collapses
+pub fn qem_decimation_he<R: Real>(
+    mesh: &mut HalfEdgeTriMesh<R>,
+    parameters: &QemDecimationParameters<R>,
+) -> usize {
+    let mut num_triangles = (0..mesh.triangles.len())
+        .filter(|&f| mesh.is_valid_triangle(f))
+        .count();
+    let target_triangle_count = parameters.target_triangle_count.unwrap_or(0);
+    if num_triangles <= target_triangle_count {
+        return 0;
+    }
+
+    let mut quadrics = {
+        profile!("compute vertex quadrics");
+
+        let mut quadrics = vec![Quadric::zero(); mesh.vertices.len()];
+        for (f, tri) in mesh.triangles.iter().enumerate() {
+            if !mesh.is_valid_triangle(f) {
+                continue;
+            }
+
+            let [a, b, c] = tri.map(|i| mesh.vertices[i]);
+            // Skip degenerate triangles without a well-defined plane
+            if let Some(normal) = (b - a).cross(&(c - a)).try_normalize(R::zero()) {
+                let quadric = Quadric::from_plane(&normal, &a);
+                for &i in tri {
+                    quadrics[i].add(&quadric);
+                }
+            }
+        }
+        quadrics
+    };
+
+    let mut versions = vec![0; mesh.vertices.len()];
+    let is_fixed = (0..mesh.vertices.len())
+        .map(|i| !mesh.is_valid_vertex(i) || mesh.is_boundary_vertex(i))
+        .collect::<Vec<_>>();
+
+    // Returns the better of the two half-edge collapses of the given edge (if a collapse is possible)
+    let candidate = |vertices: &[Vector3<R>],
+                     quadrics: &[Quadric<R>],
+                     versions: &[usize],
+                     v0: usize,
+                     v1: usize| {
+        let error = |from: usize, to: usize| {
+            (!is_fixed[from]).then(|| {
+                let x = &vertices[to];
+                quadrics[from].error(x) + quadrics[to].error(x)
+            })
+        };
+        let (error, from, to) = match (error(v0, v1), error(v1, v0)) {
+            (Some(e01), Some(e10)) if e10 < e01 => (e10, v1, v0),
+            (Some(e01), _) => (e01, v0, v1),
+            (None, Some(e10)) => (e10, v1, v0),
+            (None, None) => return None,
+        };
+        Some(CollapseCandidate {
+            error,
+            from,
+            to,
+            versions: [versions[from], versions[to]],
+        })
+    };
+
+    let mut queue = {
+        profile!("initialize collapse queue");
+        (0..mesh.vertices.len())
+            .filter(|&v0| mesh.is_valid_vertex(v0))
+            .flat_map(|v0| {
+                mesh.vertex_one_ring(v0)
+                    .filter(move |&v1| v0 < v1)
+                    .map(move |v1| (v0, v1))
+            })
+            .filter_map(|(v0, v1)| candidate(&mesh.vertices, &quadrics, &versions, v0, v1))
+            .collect::<BinaryHeap<_>>()
+    };
+
+    profile!("process collapse queue");
+    let is_collapse_allowed = |mesh: &HalfEdgeTriMesh<R>, he: HalfEdge| -> bool {
+        mesh.is_collapse_ok(he).is_ok()
+            && parameters
+                .max_normal_change
+                .map(|max| mesh.half_edge_collapse_max_normal_change(he) <= max)
+                .unwrap_or(true)
+            && parameters
+                .max_aspect_ratio_change
+                .map(|max| mesh.half_edge_collapse_max_aspect_ratio_change(he) <= max)
+                .unwrap_or(true)
+    };
+
+    let mut num_collapses = 0;
+    while let Some(c) = queue.pop() {
+        if num_triangles <= target_triangle_count {
+            break;
+        }
+
+        // Skip candidates of removed vertices or vertices whose quadrics changed
+        if !mesh.is_valid_vertex(c.from)
+            || !mesh.is_valid_vertex(c.to)
+            || c.versions != [versions[c.from], versions[c.to]]
+        {
+            continue;
+        }
+
+        // The queue is sorted by error, all remaining candidates exceed the error bound
+        if parameters
+            .max_error
+            .map(|max_error| c.error > max_error)
+            .unwrap_or(false)
+        {
+            break;
+        }
+
+        let he = match mesh.half_edge(c.from, c.to) {
+            Some(he) if is_collapse_allowed(mesh, he) => he,
+            _ => continue,
+        };
+
+        mesh.half_edge_collapse(he);
+        num_collapses += 1;
+        // Every collapse of an edge between two faces removes both faces
+        num_triangles = num_triangles.saturating_sub(2);
+
+        let from_quadric = quadrics[c.from];
+        quadrics[c.to].add(&from_quadric);
+        versions[c.to] += 1;
+
+        // Update the candidates of all edges incident to the remaining vertex
+        if mesh.is_valid_vertex(c.to) {
+            queue.extend(
+                mesh.vertex_one_ring(c.to)
+                    .filter_map(|v| candidate(&mesh.vertices, &quadrics, &versions, c.to, v)),
+            );
+        }
+    }
+
+    info!(
+        "QEM decimation performed {} edge collapses, {} triangles remaining",
+        num_collapses, num_triangles
+    );
+
+    num_collapses
+}
+
+/// Parameters for the isotropic remeshing of a mesh, see [`isotropic_remeshing`]
+#[derive(Clone, Debug)]
+pub struct IsotropicRemeshingParameters<R: Real> {
+    /// Target length of the edges of the remeshed mesh, the remeshing panics if it is not positive and finite
+    pub target_edge_length: R,
+    /// Number of iterations of edge splits, collapses, flips and tangential relaxation
+    pub iterations: usize,
+    /// Whether the vertices are projected back onto the original surface after every relaxation step
+    pub project_to_surface: bool,
+}
+
+impl<R: Real> IsotropicRemeshingParameters<R> {
+    /// Returns parameters for the given target edge length with 10 iterations and projection onto the original surface
+    pub fn new(target_edge_length: R) -> Self {
+        Self {
+            target_edge_length,
+            iterations: 10,
+            project_to_surface: true,
+        }
+    }
+
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<IsotropicRemeshingParameters<T>> {
+        Some(IsotropicRemeshingParameters {
+            target_edge_length: self.target_edge_length.try_convert()?,
+            iterations: self.iterations,
+            project_to_surface: self.project_to_surface,
+        })
+    }
+}
+
+/// Remeshes the mesh such that all edges are close to the target edge length and all triangles are close to equilateral
+///
+/// Implements the isotropic remeshing of Botsch and Kobbelt (2004), every iteration consists of:
+///  1. splitting all edges longer than 4/3 of the target edge length at their midpoint,
+///  2. collapsing all edges shorter than 4/5 of the target edge length if no edge longer than 4/3
+///     of the target edge length is created and no triangle is folded over,
+///  3. flipping edges if this reduces the deviation of the vertex valences from six (four on the boundary),
+///  4. moving all vertices towards the centroid of their one-ring in the tangent plane of the surface
+///     and optionally projecting them back onto the original surface.
+///
+/// Vertices on the boundary of open meshes are not moved or removed, boundary edges are only split.
+///
+/// Only the tangential relaxation is parallelized, the edge splits, collapses and flips are sequential.
+///
+/// Returns the vertex-vertex connectivity of the remeshed mesh.
+pub fn par_isotropic_remeshing<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &IsotropicRemeshingParameters<R>,
+    keep_vertices: bool,
+) -> Vec<Vec<usize>> {
+    profile!("par_isotropic_remeshing");
+    isotropic_remeshing_impl(mesh, parameters, keep_vertices, true)
+}
+
+/// Remeshes the mesh such that all edges are close to the target edge length (single-threaded version of [`par_isotropic_remeshing`])
+pub fn isotropic_remeshing<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &IsotropicRemeshingParameters<R>,
+    keep_vertices: bool,
+) -> Vec<Vec<usize>> {
+    profile!("isotropic_remeshing");
+    isotropic_remeshing_impl(mesh, parameters, keep_vertices, false)
+}
+
+fn isotropic_remeshing_impl<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &IsotropicRemeshingParameters<R>,
+    keep_vertices: bool,
+    enable_multi_threading: bool,
+) -> Vec<Vec<usize>> {
+    let mut mesh_he = HalfEdgeTriMesh::from(std::mem::take(mesh));
+    isotropic_remeshing_he_impl(&mut mesh_he, parameters, enable_multi_threading);
+
+    {
+        profile!("convert mesh back");
+        let (new_mesh, vertex_map) = mesh_he.into_parts(keep_vertices);
+        *mesh = new_mesh;
+        vertex_map
+    }
+}
+
+/// Performs the isotropic remeshing of [`par_isotropic_remeshing`] on a half-edge mesh (parallelized version)
+pub fn par_isotropic_remeshing_he<R: Real>(
+    mesh: &mut HalfEdgeTriMesh<R>,
+    parameters: &IsotropicRemeshingParameters<R>,
+) {
+    isotropic_remeshing_he_impl(mesh, parameters, true);
+}
+
+/// Performs the isotropic remeshing of [`isotropic_remeshing`] on a half-edge mesh (single-threaded version of [`par_isotropic_remeshing_he`])
+pub fn isotropic_remeshing_he<R: Real>(
+    mesh: &mut HalfEdgeTriMesh<R>,
+    parameters: &IsotropicRemeshingParameters<R>,
+) {
+    isotropic_remeshing_he_impl(mesh, parameters, false);
+}
+
+fn isotropic_remeshing_he_impl<R: Real>(
+    mesh: &mut HalfEdgeTriMesh<R>,
+    parameters: &IsotropicRemeshingParameters<R>,
+    enable_multi_threading: bool,
+) {
+    let target_edge_length = parameters.target_edge_length;
+    assert!(
+        target_edge_length.is_finite() && target_edge_length > R::zero(),
+        "the target edge length of the isotropic remeshing has to be positive and finite (got {:?})",
+        target_edge_length
+    );
+    let max_edge_length = target_edge_length * R::from_f64(4.0 / 3.0).unwrap();
+    let min_edge_length = target_edge_length * R::from_f64(4.0 / 5.0).unwrap();
+
+    let projector = parameters.project_to_surface.then(|| {
+        profile!("build surface projector");
+        let triangles = (0..mesh.triangles.len())
+            .filter(|&f| mesh.is_valid_triangle(f))
+            .map(|f| mesh.triangles[f])
+            .collect::<Vec<_>>();
+        SurfaceProjector::new(mesh.vertices.clone(), triangles, max_edge_length)
+    });
+
+    let edge_length = |mesh: &HalfEdgeTriMesh<R>, he: HalfEdge| -> R {
+        (mesh.vertices[he.to] - mesh.vertices[mesh.opposite(he).to]).norm()
+    };
+
+    // Visits every edge (i.e. one of its half-edges) that exists when the pass starts exactly once
+    let edges = |mesh: &HalfEdgeTriMesh<R>| -> Vec<usize> {
+        (0..mesh.half_edges.len())
+            .filter(|&i| mesh.is_valid_half_edge(i) && i < mesh.half_edges[i].opposite)
+            .collect()
+    };
+
+    let mut num_splits = 0;
+    let mut num_collapses = 0;
+    let mut num_flips = 0;
+
+    for _ in 0..parameters.iterations {
+        profile!("isotropic_remeshing iter");
+
+        // Split long edges until all edges are short enough
+        {
+            profile!("split long edges");
+            // Every split halves an edge, so the number of passes only grows logarithmically with the
+            // ratio of the longest edge to the target edge length, the limit is just a safeguard
+            const MAX_SPLIT_PASSES: usize = 64;
+            for pass in 0.. {
+                if pass == MAX_SPLIT_PASSES {
+                    warn!(
+                        "Edge splits of the isotropic remeshing did not converge after {} passes",
+                        MAX_SPLIT_PASSES
+                    );
+                    break;
+                }
+
+                let mut num_iter_splits = 0;
+                for he_idx in edges(mesh) {
+                    let he = mesh.half_edges[he_idx];
+                    if edge_length(mesh, he) > max_edge_length {
+                        let midpoint = (mesh.vertices[he.to] + mesh.vertices[mesh.opposite(he).to])
+                            .scale(R::from_f64(0.5).unwrap());
+                        mesh.edge_split(he, midpoint);
+                        num_iter_splits += 1;
+                    }
+                }
+
+                num_splits += num_iter_splits;
+                if num_iter_splits == 0 {
+                    break;
+                }
+            }
+        }
+
+        // Collapse short edges
+        {
+            profile!("collapse short edges");
+            let is_collapse_allowed = |mesh: &HalfEdgeTriMesh<R>, he: HalfEdge| -> bool {
+                let v_from = mesh.opposite(he).to;
+                let v_to = he.to;
+                !mesh.is_boundary_vertex(v_from)
+                    && mesh.is_collapse_ok(he).is_ok()
+                    // Collapses must not create long edges that would be split again
+                    && mesh
+                        .vertex_one_ring(v_from)
+                        .all(|v| (mesh.vertices[v] - mesh.vertices[v_to]).norm() <= max_edge_length)
+                    // Collapses must not fold over triangles
+                    && mesh.half_edge_collapse_max_normal_change(he) < R::frac_pi_2()
+            };
+
+            for he_idx in edges(mesh) {
+                if !mesh.is_valid_half_edge(he_idx) {
+                    continue;
+                }
+
+                let he = mesh.half_edges[he_idx];
+                if edge_length(mesh, he) >= min_edge_length {
+                    continue;
+                }
+
+                let he_o = mesh.opposite(he);
+                if is_collapse_allowed(mesh, he) {
+                    mesh.half_edge_collapse(he);
+                    num_collapses += 1;
+                } else if is_collapse_allowed(mesh, he_o) {
+                    mesh.half_edge_collapse(he_o);
+                    num_collapses += 1;
+                }
+            }
+        }
+
+        // Flip edges to equalize vertex valences
+        {
+            profile!("flip edges");
+            let target_valence = |mesh: &HalfEdgeTriMesh<R>, v: usize| -> i64 {
+                if mesh.is_boundary_vertex(v) {
+                    4
+                } else {
+                    6
+                }
+            };
+            let valence_deviation =
+                |mesh: &HalfEdgeTriMesh<R>, vertices: [usize; 4], offsets: [i64; 4]| {
+                    vertices
+                        .iter()
+                        .zip(offsets)
+                        .map(|(&v, offset)| {
+                            (mesh.vertex_one_ring_len(v) as i64 + offset - target_valence(mesh, v))
+                                .abs()
+                        })
+                        .sum::<i64>()
+                };
+
+            for he_idx in edges(mesh) {
+                let he = mesh.half_edges[he_idx];
+                if mesh.is_flip_ok(he).is_err() {
+                    continue;
+                }
+
+                let he_o = mesh.opposite(he);
+                let vertices = [he_o.to, he.to, mesh.next(he).to, mesh.next(he_o).to];
+                let deviation_before = valence_deviation(mesh, vertices, [0, 0, 0, 0]);
+                let deviation_after = valence_deviation(mesh, vertices, [-1, -1, 1, 1]);
+
+                if deviation_after < deviation_before && flip_preserves_orientation(mesh, vertices)
+                {
+                    mesh.edge_flip(he);
+                    num_flips += 1;
+                }
+            }
+        }
+
+        // Tangential relaxation
+        {
+            profile!("tangential relaxation");
+            let relax_vertex = |v: usize| {
+                let p = mesh.vertices[v];
+                if !mesh.is_valid_vertex(v)
+                    || mesh.vertex_one_ring_len(v) == 0
+                    || mesh.is_boundary_vertex(v)
+                {
+                    return p;
+                }
+
+                let centroid = mesh
+                    .vertex_one_ring(v)
+                    .map(|w| mesh.vertices[w])
+                    .sum::<Vector3<R>>()
+                    / R::from_usize(mesh.vertex_one_ring_len(v)).unwrap();
+                let normal = mesh
+                    .incident_faces(v)
+                    .map(|f| {
+                        let [a, b, c] = mesh.triangles[f].map(|i| mesh.vertices[i]);
+                        (b - a).cross(&(c - a))
+                    })
+                    .sum::<Vector3<R>>();
+
+                let displacement = centroid - p;
+                let relaxed = match normal.try_normalize(R::zero()) {
+                    Some(n) => p + displacement - n.scale(n.dot(&displacement)),
+                    None => p,
+                };
+
+                match &projector {
+                    Some(projector) => projector.project(&relaxed).unwrap_or(relaxed),
+                    None => relaxed,
+                }
+            };
+            let relaxed_vertices = if enable_multi_threading {
+                (0..mesh.vertices.len())
+                    .into_par_iter()
+                    .map(relax_vertex)
+                    .collect::<Vec<_>>()
+            } else {
+                (0..mesh.vertices.len())
+                    .map(relax_vertex)
+                    .collect::<Vec<_>>()
+            };
+            mesh.vertices = relaxed_vertices;
+        }
+    }
+
+    info!(
+        "Isotropic remeshing performed {} edge splits, {} edge collapses and {} edge flips",
+        num_splits, num_collapses, num_flips
+    );
+}
+
+/// Returns whether the flip of the edge `v0 -> v1` with the opposite vertices `v_pos` and `v_neg` keeps the orientation of both new faces
+fn flip_preserves_orientation<R: Real>(
+    mesh: &HalfEdgeTriMesh<R>,
+    [v0, v1, v_pos, v_neg]: [usize; 4],
+) -> bool {
+    let normal = |[i, j, k]: [usize; 3]| {
+        let [a, b, c] = [i, j, k].map(|i| mesh.vertices[i]);
+        (b - a).cross(&(c - a))
+    };
+
+    let old_normal = normal([v0, v1, v_pos]) + normal([v1, v0, v_neg]);
+    normal([v_pos, v0, v_neg]).dot(&old_normal) > R::zero()
+        && normal([v_neg, v1, v_pos]).dot(&old_normal) > R::zero()
+}
+
+/// Triangles of a reference surface sorted into a spatial hash grid for closest point queries
+struct SurfaceProjector<R: Real> {
+    vertices: Vec<Vector3<R>>,
+    triangles: Vec<[usize; 3]>,
+    cell_size: R,
+    cells: MapType<[i64; 3], Vec<usize>>,
+}
+
+impl<R: Real> SurfaceProjector<R> {
+    /// Constructs the spatial hash grid, the cell size is increased to the longest edge of the surface if necessary
+    fn new(vertices: Vec<Vector3<R>>, triangles: Vec<[usize; 3]>, min_cell_size: R) -> Self {
+        let cell_size = triangles
+            .iter()
+            .flat_map(|tri| (0..3).map(|i| (vertices[tri[(i + 1) % 3]] - vertices[tri[i]]).norm()))
+            .fold(min_cell_size, |a, b| a.max(b));
+
+        let mut cells: MapType<[i64; 3], Vec<usize>> = new_map();
+        for (f, tri) in triangles.iter().enumerate() {
+            let [a, b, c] = tri.map(|i| vertices[i]);
+            let min = Self::cell_of(cell_size, &a.inf(&b).inf(&c));
+            let max = Self::cell_of(cell_size, &a.sup(&b).sup(&c));
+            for i in min[0]..=max[0] {
+                for j in min[1]..=max[1] {
+                    for k in min[2]..=max[2] {
+                        cells.entry([i, j, k]).or_default().push(f);
+                    }
+                }
+            }
+        }
+
+        Self {
+            vertices,
+            triangles,
+            cell_size,
+            cells,
+        }
+    }
+
+    fn cell_of(cell_size: R, point: &Vector3<R>) -> [i64; 3] {
+        [0, 1, 2].map(|d| {
+            (point[d] / cell_size)
+                .floor()
+                .to_i64()
+                .expect("mesh vertex coordinate has to fit into the spatial hash grid")
+        })
+    }
+
+    /// Returns the closest point on the triangles in the cells around the given point (if there are any)
+    fn project(&self, point: &Vector3<R>) -> Option<Vector3<R>> {
+        let [i, j, k] = Self::cell_of(self.cell_size, point);
+
+        let mut closest: Option<(R, Vector3<R>)> = None;
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let Some(triangles) = self.cells.get(&[i + di, j + dj, k + dk]) else {
+                        continue;
+                    };
+
+                    for &f in triangles {
+                        let [a, b, c] = self.triangles[f].map(|i| self.vertices[i]);
+                        let barycentric = tri_closest_point_barycentric(point, &a, &b, &c);
+                        let candidate = a * barycentric.x + b * barycentric.y + c * barycentric.z;
+                        let distance_sq = (candidate - point).norm_squared();
+                        match closest {
+                            Some((d, _)) if d <= distance_sq => {}
+                            _ => closest = Some((distance_sq, candidate)),
+                        }
+                    }
+                }
+            }
+        }
+
+        closest.map(|(_, p)| p)
+    }
+}
+
+/// Triangulation methods for closing holes, see [`fill_holes`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HoleTriangulation {
+    /// Connects all edges of a hole to a new vertex at the centroid of its boundary vertices
+    Fan,
+    /// Triangulates the boundary polygon of a hole without new vertices such that the total area of the triangles is minimal
+    ///
+    /// The runtime is cubic in the number of boundary edges, holes with more than
+    /// [`HoleTriangulation::MAX_MINIMUM_AREA_BOUNDARY_EDGES`] boundary edges are triangulated by ear
+    /// clipping in the plane of best fit of their boundary instead.
+    MinimumArea,
+}
+
+impl HoleTriangulation {
+    /// Maximum number of boundary edges of holes triangulated by [`HoleTriangulation::MinimumArea`]
+    pub const MAX_MINIMUM_AREA_BOUNDARY_EDGES: usize = 500;
+}
+
+/// Parameters for closing the holes of a mesh, see [`fill_holes`]
+#[derive(Clone, Debug)]
+pub struct HoleFillingParameters {
+    /// Triangulation that is used to close the holes
+    pub triangulation: HoleTriangulation,
+    /// Holes with more boundary edges than this value are not filled, all holes are filled if `None`
+    pub max_boundary_edges: Option<usize>,
+    /// Number of smoothing iterations of the fairing, if specified the triangulations of the holes are refined and smoothed
+    pub fairing_iterations: Option<usize>,
+}
+
+impl HoleFillingParameters {
+    /// Default maximum number of boundary edges of filled holes, larger boundaries are usually intended openings of a mesh
+    pub const DEFAULT_MAX_BOUNDARY_EDGES: usize = 1000;
+}
+
+impl Default for HoleFillingParameters {
+    /// Returns parameters that close all holes with at most [`HoleFillingParameters::DEFAULT_MAX_BOUNDARY_EDGES`] boundary edges with minimum area triangulations without fairing
+    fn default() -> Self {
+        Self {
+            triangulation: HoleTriangulation::MinimumArea,
+            max_boundary_edges: Some(Self::DEFAULT_MAX_BOUNDARY_EDGES),
+            fairing_iterations: None,
+        }
+    }
+}
+
+/// Closes the holes of an open mesh by triangulating its boundary loops
+///
+/// The holes are given by the [`boundary_loops`](crate::mesh::MeshManifoldInformation::boundary_loops)
+/// of the mesh. Open chains of non-manifold boundaries and loops that visit a vertex more than once
+/// are not filled. The new triangles are appended to the triangles of the mesh and are oriented
+/// consistently with the adjacent triangles. New vertices are appended to the vertices of the mesh.
+/// With [`HoleTriangulation::Fan`] and without fairing, exactly one vertex is added per filled hole
+/// in the order of the returned loops.
+///
+/// If fairing is enabled, the triangulation of every hole is refined following Liepa (2003), "Filling
+/// Holes in Meshes": triangles are split at their centroid until their size matches the mean length
+/// of the boundary edges of the hole, alternated with edge flips to obtain a Delaunay triangulation.
+/// Afterwards, all new vertices are moved to the mean position of their neighbors in every
+/// iteration while the boundary of the hole stays fixed, resulting in a smooth patch.
+///
+/// Returns the boundary loops of all filled holes.
+pub fn fill_holes<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &HoleFillingParameters,
+) -> Vec<Vec<usize>> {
+    profile!("fill_holes");
+
+    let manifold_info = mesh.compute_manifold_information();
+    let boundary_edges = manifold_info
+        .boundary_edges
+        .iter()
+        .copied()
+        .collect::<SetType<_>>();
+
+    let mut filled_loops = Vec::new();
+    for loop_vertices in manifold_info.boundary_loops() {
+        let n = loop_vertices.len();
+        if n < 3
+            || parameters
+                .max_boundary_edges
+                .map(|max| n > max)
+                .unwrap_or(false)
+        {
+            continue;
+        }
+
+        // Skip open chains and loops that visit a vertex more than once
+        if !is_closed_simple_loop(&loop_vertices, &boundary_edges) {
+            continue;
+        }
+
+        // The faces closing the hole traverse the loop in reverse order
+        let polygon = loop_vertices.iter().rev().copied().collect::<Vec<_>>();
+        let mut patch = TriMesh3d {
+            vertices: polygon.iter().map(|&v| mesh.vertices[v]).collect(),
+            triangles: Vec::with_capacity(n),
+        };
+
+        match parameters.triangulation {
+            HoleTriangulation::Fan => {
+                let centroid = patch
+                    .vertices
+                    .iter()
+                    .fold(Vector3::zeros(), |sum, v| sum + v)
+                    .unscale(R::from_usize(n).unwrap());
+                patch.vertices.push(centroid);
+                patch.triangles.extend((0..n).map(|i| [i, (i + 1) % n, n]));
+            }
+            HoleTriangulation::MinimumArea
+                if n <= HoleTriangulation::MAX_MINIMUM_AREA_BOUNDARY_EDGES =>
+            {
+                patch.triangles = minimum_area_triangulation(&patch.vertices);
+            }
+            HoleTriangulation::MinimumArea => {
+                let polygon = [(0..n).collect::<Vec<_>>()];
+                let triangles = polygon_plane_basis(&patch.vertices).and_then(|basis| {
+                    let (triangles, _, num_skipped) =
+                        triangulate_planar_polygons(&patch.vertices, &polygon, basis);
+                    (num_skipped == 0).then_some(triangles)
+                });
+                match triangles {
+                    Some(triangles) => patch.triangles = triangles,
+                    None => {
+                        warn!(
+                            "Hole filling skipped a hole with {} boundary edges that could not be triangulated",
+                            n
+                        );
+                        continue;
+                    }
+                }
+            }
+        }
+
+        if let Some(fairing_iterations) = parameters.fairing_iterations {
+            refine_hole_patch(&mut patch, n);
+
+            let vertex_connectivity = patch.vertex_vertex_connectivity();
+            let weights = (0..patch.vertices.len())
+                .map(|i| if i < n { R::zero() } else { R::one() })
+                .collect::<Vec<_>>();
+            laplacian_smoothing_inplace(
+                &mut patch,
+                &vertex_connectivity,
+                fairing_iterations,
+                R::one(),
+                &weights,
+            );
+        }
+
+        // Insert the patch into the mesh
+        let first_new_vertex = mesh.vertices.len();
+        let global_index = |i: usize| {
+            if i < n {
+                polygon[i]
+            } else {
+                first_new_vertex + i - n
+            }
+        };
+        mesh.vertices.extend_from_slice(&patch.vertices[n..]);
+        mesh.triangles
+            .extend(patch.triangles.iter().map(|tri| tri.map(global_index)));
+
+        filled_loops.push(loop_vertices);
+    }
+
+    info!("Hole filling closed {} holes", filled_loops.len());
+
+    filled_loops
+}
+
+/// Returns the triangulation of the given polygon with minimal total triangle area
+fn minimum_area_triangulation<R: Real>(polygon: &[Vector3<R>]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+
+    // Minimal area of the triangulation of the sub-polygon i..=j and the vertex of the triangle on the edge (i, j)
+    let mut min_area = vec![R::zero(); n * n];
+    let mut split_vertex = vec![0; n * n];
+    for len in 2..n {
+        for i in 0..n - len {
+            let j = i + len;
+            let area = |m: usize| {
+                min_area[i * n + m]
+                    + min_area[m * n + j]
+                    + tri_area::<R, R>(&polygon[i], &polygon[m], &polygon[j])
+            };
+            let mut best = (area(i + 1), i + 1);
+            for m in i + 2..j {
+                let area = area(m);
+                if area < best.0 {
+                    best = (area, m);
+                }
+            }
+            min_area[i * n + j] = best.0;
+            split_vertex[i * n + j] = best.1;
+        }
+    }
+
+    let mut triangles = Vec::with_capacity(n - 2);
+    let mut stack = vec![(0, n - 1)];
+    while let Some((i, j)) = stack.pop() {
+        if j - i < 2 {
+            continue;
+        }
+        let m = split_vertex[i * n + j];
+        triangles.push([i, m, j]);
+        stack.push((i, m));
+        stack.push((m, j));
+    }
+
+    triangles
+}
+
+/// Closes the holes of a mesh clipped with an AABB by planar triangulations of its boundary loops on the faces of the AABB
+///
+/// Every closed boundary loop (see [`fill_holes`]) whose vertices are all closest to the same face of
+/// the AABB is projected onto this face. All loops of a face are triangulated together by ear clipping,
+/// loops enclosed by another loop of the face (e.g. the inner loop of an annular cut through a hollow
+/// object) become holes of the enclosing cap instead of being closed separately. Loops wrapping around
+/// an edge of the AABB are triangulated by ear clipping in their plane of best fit. No vertices are added
+/// to the mesh, the caps are planar if the vertices were clamped to the AABB (see
+/// [`Mesh3d::par_clamp_with_aabb`]). The runtime is quadratic in the number of boundary edges of a face.
+///
+/// The new triangles are appended to the triangles of the mesh. Returns for every new triangle the
+/// index of a triangle of the original mesh adjacent to its cap, e.g. to transfer cell attributes with
+/// [`MeshWithData::remap_attributes`].
+pub fn cap_aabb_holes<R: Real>(mesh: &mut TriMesh3d<R>, aabb: &Aabb3d<R>) -> Vec<usize> {
+    profile!("cap_aabb_holes");
+
+    let manifold_info = mesh.compute_manifold_information();
+    let boundary_edges = manifold_info
+        .boundary_edges
+        .iter()
+        .copied()
+        .collect::<SetType<_>>();
+
+    // The triangle incident to every boundary edge
+    let mut boundary_edge_triangles: MapType<[usize; 2], usize> = new_map();
+    for (f, tri) in mesh.triangles.iter().enumerate() {
+        for i in 0..3 {
+            let edge = [tri[i], tri[(i + 1) % 3]];
+            if boundary_edges.contains(&edge) {
+                boundary_edge_triangles.insert(edge, f);
+            }
+        }
+    }
+
+    // Distance of a vertex to the plane of every face, face `2 * d + 1` is at the maximum along axis `d`
+    let face_distances = |p: &Vector3<R>| -> [R; 6] {
+        std::array::from_fn(|f| {
+            let d = f / 2;
+            let plane = if f % 2 == 1 {
+                aabb.max()[d]
+            } else {
+                aabb.min()[d]
+            };
+            (p[d] - plane).abs()
+        })
+    };
+    let tolerance = aabb.max_extent() * R::from_f64(1e-6).unwrap();
+
+    // The faces closing the holes traverse the loops in reverse order
+    let mut face_polygons = vec![Vec::new(); 6];
+    let mut other_polygons = Vec::new();
+    for loop_vertices in manifold_info.boundary_loops() {
+        if loop_vertices.len() < 3 || !is_closed_simple_loop(&loop_vertices, &boundary_edges) {
+            continue;
+        }
+
+        let mut faces = [true; 6];
+        for v in loop_vertices.iter() {
+            let distances = face_distances(&mesh.vertices[*v]);
+            let min_distance = distances.iter().copied().fold(distances[0], R::min);
+            for (is_closest, distance) in faces.iter_mut().zip(distances) {
+                *is_closest &= distance <= min_distance + tolerance;
+            }
+        }
+
+        let polygon = loop_vertices.into_iter().rev().collect::<Vec<_>>();
+        match faces.iter().position(|&is_closest| is_closest) {
+            Some(f) => face_polygons[f].push(polygon),
+            None => other_polygons.push(polygon),
+        }
+    }
+
+    // Polygons that are triangulated together and the basis of their plane
+    let mut groups = Vec::new();
+    for (f, polygons) in face_polygons.into_iter().enumerate() {
+        if polygons.is_empty() {
+            continue;
+        }
+        // Basis of the face such that counter-clockwise polygons are oriented along its outward normal
+        let d = f / 2;
+        let (u, v) = if f % 2 == 1 {
+            ((d + 1) % 3, (d + 2) % 3)
+        } else {
+            ((d + 2) % 3, (d + 1) % 3)
+        };
+        groups.push((
+            polygons,
+            [Vector3::ith(u, R::one()), Vector3::ith(v, R::one())],
+        ));
+    }
+
+    let mut num_skipped_loops = 0;
+    for polygon in other_polygons {
+        let vertices = polygon
+            .iter()
+            .map(|&v| mesh.vertices[v])
+            .collect::<Vec<_>>();
+        match polygon_plane_basis(&vertices) {
+            Some(basis) => groups.push((vec![polygon], basis)),
+            None => num_skipped_loops += 1,
+        }
+    }
+
+    let num_triangles_before = mesh.triangles.len();
+    let mut source_triangles = Vec::new();
+    let mut num_capped_loops = 0;
+    for (polygons, basis) in groups {
+        let (triangles, outer_polygons, num_skipped) =
+            triangulate_planar_polygons(&mesh.vertices, &polygons, basis);
+        for (tri, p) in triangles.into_iter().zip(outer_polygons) {
+            let polygon = &polygons[p];
+            mesh.triangles.push(tri);
+            source_triangles.push(boundary_edge_triangles[&[polygon[1], polygon[0]]]);
+        }
+        num_capped_loops += polygons.len() - num_skipped;
+        num_skipped_loops += num_skipped;
+    }
+
+    info!(
+        "Capping closed {} boundary loops with {} triangles",
+        num_capped_loops,
+        mesh.triangles.len() - num_triangles_before
+    );
+    if num_skipped_loops > 0 {
+        warn!(
+            "Capping skipped {} boundary loops that could not be triangulated",
+            num_skipped_loops
+        );
+    }
+
+    source_triangles
+}
+
+/// Returns whether the given boundary loop is closed and visits every vertex only once
+fn is_closed_simple_loop(loop_vertices: &[usize], boundary_edges: &SetType<[usize; 2]>) -> bool {
+    let n = loop_vertices.len();
+    boundary_edges.contains(&[loop_vertices[n - 1], loop_vertices[0]])
+        && loop_vertices.iter().copied().collect::<SetType<_>>().len() == n
+}
+
+/// Returns an orthonormal basis of the plane of best fit of a closed polygon such that the polygon is counter-clockwise in this basis
+fn polygon_plane_basis<R: Real>(polygon: &[Vector3<R>]) -> Option<[Vector3<R>; 2]> {
+    // Normal of the polygon following Newell's method
+    let n = polygon.len();
+    let centroid = polygon
+        .iter()
+        .fold(Vector3::zeros(), |sum, v| sum + v)
+        .unscale(R::from_usize(n).unwrap());
+    let normal = (0..n)
+        .map(|i| (polygon[i] - centroid).cross(&(polygon[(i + 1) % n] - centroid)))
+        .fold(Vector3::zeros(), |sum, n| sum + n)
+        .try_normalize(R::default_epsilon())?;
+
+    let axis = normal.iamin();
+    let e1 = normal.cross(&Vector3::ith(axis, R::one())).normalize();
+    let e2 = normal.cross(&e1);
+    Some([e1, e2])
+}
+
+/// Triangulates closed polygons projected onto the plane of the given basis by ear clipping
+///
+/// Counter-clockwise polygons are outer boundaries of caps and clockwise polygons are holes of the
+/// smallest enclosing outer polygon. Returns the triangles (indices of the vertices), the index of
+/// the outer polygon of every triangle and the number of polygons that could not be triangulated.
+fn triangulate_planar_polygons<R: Real>(
+    vertices: &[Vector3<R>],
+    polygons: &[Vec<usize>],
+    basis: [Vector3<R>; 2],
+) -> (Vec<[usize; 3]>, Vec<usize>, usize) {
+    // Projected points of all polygons, polygons are given by indices of these points
+    let mut points = Vec::new();
+    let mut point_vertices = Vec::new();
+    let mut local_polygons = Vec::with_capacity(polygons.len());
+    for polygon in polygons {
+        local_polygons.push((points.len()..points.len() + polygon.len()).collect::<Vec<_>>());
+        for &v in polygon {
+            points.push(Vector2::new(
+                vertices[v].dot(&basis[0]),
+                vertices[v].dot(&basis[1]),
+            ));
+            point_vertices.push(v);
+        }
+    }
+
+    let areas = local_polygons
+        .iter()
+        .map(|polygon| polygon_signed_area(&points, polygon))
+        .collect::<Vec<_>>();
+
+    // Assign every hole to the smallest enclosing outer polygon
+    let mut num_skipped = 0;
+    let mut holes = vec![Vec::new(); polygons.len()];
+    for (h, hole) in local_polygons.iter().enumerate() {
+        if areas[h] > R::zero() {
+            continue;
+        }
+        let enclosing = (0..polygons.len())
+            .filter(|&p| {
+                areas[p] > R::zero()
+                    && point_in_polygon(&points, &local_polygons[p], &points[hole[0]])
+            })
+            .min_by(|&a, &b| areas[a].partial_cmp(&areas[b]).unwrap_or(Ordering::Equal));
+        match enclosing {
+            Some(p) if areas[h] < R::zero() => holes[p].push(h),
+            _ => num_skipped += 1,
+        }
+    }
+
+    let mut triangles = Vec::new();
+    let mut outer_polygons = Vec::new();
+    for (p, mut polygon) in local_polygons.iter().cloned().enumerate() {
+        if areas[p] <= R::zero() {
+            continue;
+        }
+
+        // Merge the holes into the outer polygon from right to left
+        let max_x = |h: usize| {
+            local_polygons[h]
+                .iter()
+                .map(|&i| points[i].x)
+                .fold(points[local_polygons[h][0]].x, R::max)
+        };
+        let mut polygon_holes = holes[p].clone();
+        polygon_holes.sort_by(|&a, &b| max_x(b).partial_cmp(&max_x(a)).unwrap_or(Ordering::Equal));
+        let mut num_failed = 0;
+        for &h in polygon_holes.iter() {
+            if !bridge_hole(&points, &mut polygon, &local_polygons[h]) {
+                num_failed += 1;
+            }
+        }
+
+        match ear_clipping(&points, &polygon) {
+            Some(polygon_triangles) => {
+                for tri in polygon_triangles {
+                    triangles.push(tri.map(|i| point_vertices[i]));
+                    outer_polygons.push(p);
+                }
+                num_skipped += num_failed;
+            }
+            None => num_skipped += 1 + polygon_holes.len(),
+        }
+    }
+
+    (triangles, outer_polygons, num_skipped)
+}
+
+/// Returns twice the signed area of a polygon, positive if it is counter-clockwise
+fn polygon_signed_area<R: Real>(points: &[Vector2<R>], polygon: &[usize]) -> R {
+    let n = polygon.len();
+    (0..n)
+        .map(|i| points[polygon[i]].perp(&points[polygon[(i + 1) % n]]))
+        .fold(R::zero(), |sum, a| sum + a)
+}
+
+/// Returns twice the signed area of the triangle `a`, `b`, `c`, positive if it is counter-clockwise
+fn orientation<R: Real>(a: &Vector2<R>, b: &Vector2<R>, c: &Vector2<R>) -> R {
+    (b - a).perp(&(c - a))
+}
+
+/// Returns whether a point is inside of a polygon using the even-odd rule
+fn point_in_polygon<R: Real>(points: &[Vector2<R>], polygon: &[usize], point: &Vector2<R>) -> bool {
+    let n = polygon.len();
+    let mut inside = false;
+    for i in 0..n {
+        let a = &points[polygon[i]];
+        let b = &points[polygon[(i + 1) % n]];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Merges a clockwise hole into a counter-clockwise polygon by a pair of edges to a visible vertex of the polygon
+///
+/// Follows Eberly (2008), "Triangulation by Ear Clipping": a ray from the rightmost vertex of the hole
+/// in positive x-direction determines the closest edge of the polygon, its endpoint or the reflex
+/// vertex with the smallest angle to the ray is visible from the hole vertex. Returns `false` if no
+/// edge of the polygon is hit by the ray.
+fn bridge_hole<R: Real>(points: &[Vector2<R>], polygon: &mut Vec<usize>, hole: &[usize]) -> bool {
+    let n = polygon.len();
+    let hole_start = (0..hole.len())
+        .max_by(|&a, &b| {
+            points[hole[a]]
+                .x
+                .partial_cmp(&points[hole[b]].x)
+                .unwrap_or(Ordering::Equal)
+        })
+        .unwrap();
+    let m = points[hole[hole_start]];
+
+    // Closest intersection of the ray with an edge of the polygon
+    let mut closest: Option<(R, usize)> = None;
+    for i in 0..n {
+        let a = &points[polygon[i]];
+        let b = &points[polygon[(i + 1) % n]];
+        if a.y.min(b.y) > m.y || a.y.max(b.y) < m.y {
+            continue;
+        }
+        let x = if a.y == b.y {
+            a.x.min(b.x)
+        } else {
+            a.x + (m.y - a.y) * (b.x - a.x) / (b.y - a.y)
+        };
+        if x >= m.x && closest.map(|(closest_x, _)| x < closest_x).unwrap_or(true) {
+            // The visible candidate is the intersected vertex or the endpoint further in ray direction
+            let j = if a.y == m.y && a.x == x {
+                i
+            } else if b.y == m.y && b.x == x {
+                (i + 1) % n
+            } else if a.x > b.x {
+                i
+            } else {
+                (i + 1) % n
+            };
+            closest = Some((x, j));
+        }
+    }
+    let Some((x, mut visible)) = closest else {
+        return false;
+    };
+
+    // Vertices inside of the triangle spanned by the ray and the candidate may occlude the candidate
+    let intersection = Vector2::new(x, m.y);
+    let candidate = points[polygon[visible]];
+    if candidate != intersection {
+        let (t1, t2) = if candidate.y < m.y {
+            (candidate, intersection)
+        } else {
+            (intersection, candidate)
+        };
+        let tangent = |p: &Vector2<R>| (p.y - m.y).abs() / (p.x - m.x);
+        let mut best = (tangent(&candidate), (candidate - m).norm_squared());
+        for i in 0..n {
+            let p = &points[polygon[i]];
+            if p.x > m.x
+                && polygon[i] != polygon[visible]
+                && point_in_triangle(&m, &t1, &t2, p)
+                && is_reflex(points, polygon, i)
+            {
+                let key = (tangent(p), (p - m).norm_squared());
+                if key < best {
+                    best = key;
+                    visible = i;
+                }
+            }
+        }
+    }
+
+    // Bridges of previous holes may duplicate the visible vertex, use the copy whose corner contains the hole vertex
+    if let Some(i) =
+        (0..n).find(|&i| polygon[i] == polygon[visible] && locally_inside(points, polygon, i, &m))
+    {
+        visible = i;
+    }
+
+    let bridge = hole[hole_start..]
+        .iter()
+        .chain(hole[..=hole_start].iter())
+        .copied()
+        .chain(std::iter::once(polygon[visible]))
+        .collect::<Vec<_>>();
+    polygon.splice(visible + 1..visible + 1, bridge);
+    true
+}
+
+/// Returns whether `p` is inside of or on the boundary of the counter-clockwise triangle `a`, `b`, `c`
+fn point_in_triangle<R: Real>(
+    a: &Vector2<R>,
+    b: &Vector2<R>,
+    c: &Vector2<R>,
+    p: &Vector2<R>,
+) -> bool {
+    orientation(a, b, p) >= R::zero()
+        && orientation(b, c, p) >= R::zero()
+        && orientation(c, a, p) >= R::zero()
+}
+
+/// Returns whether the corner of a counter-clockwise polygon at the given position is reflex or degenerate
+fn is_reflex<R: Real>(points: &[Vector2<R>], polygon: &[usize], i: usize) -> bool {
+    let n = polygon.len();
+    orientation(
+        &points[polygon[(i + n - 1) % n]],
+        &points[polygon[i]],
+        &points[polygon[(i + 1) % n]],
+    ) <= R::zero()
+}
+
+/// Returns whether a point is inside of the corner of a counter-clockwise polygon at the given position
+fn locally_inside<R: Real>(
+    points: &[Vector2<R>],
+    polygon: &[usize],
+    i: usize,
+    p: &Vector2<R>,
+) -> bool {
+    let n = polygon.len();
+    let prev = &points[polygon[(i + n - 1) % n]];
+    let corner = &points[polygon[i]];
+    let next = &points[polygon[(i + 1) % n]];
+    if is_reflex(points, polygon, i) {
+        orientation(prev, corner, p) >= R::zero() || orientation(corner, next, p) >= R::zero()
+    } else {
+        orientation(prev, corner, p) >= R::zero() && orientation(corner, next, p) >= R::zero()
+    }
+}
+
+/// Triangulates a counter-clockwise polygon by ear clipping, polygons with bridged holes may contain a point more than once
+///
+/// Returns `None` if the polygon is self-intersecting such that no ear can be found.
+fn ear_clipping<R: Real>(points: &[Vector2<R>], polygon: &[usize]) -> Option<Vec<[usize; 3]>> {
+    let n = polygon.len();
+    let mut prev = (0..n).map(|i| (i + n - 1) % n).collect::<Vec<_>>();
+    let mut next = (0..n).map(|i| (i + 1) % n).collect::<Vec<_>>();
+    let p = |i: usize| &points[polygon[i]];
+
+    let is_ear = |prev: &[usize], next: &[usize], b: usize, allow_degenerate: bool| {
+        let (a, c) = (prev[b], next[b]);
+        let area = orientation(p(a), p(b), p(c));
+        if area < R::zero() || (area == R::zero() && !allow_degenerate) {
+            return false;
+        }
+        // No other reflex vertex of the remaining polygon may be inside of the ear
+        let mut i = next[c];
+        while i != a {
+            let is_ear_vertex = [a, b, c]
+                .iter()
+                .any(|&j| polygon[j] == polygon[i] || p(j) == p(i));
+            if !is_ear_vertex
+                && orientation(p(prev[i]), p(i), p(next[i])) <= R::zero()
+                && point_in_triangle(p(a), p(b), p(c), p(i))
+            {
+                return false;
+            }
+            i = next[i];
+        }
+        true
+    };
+
+    let mut triangles = Vec::with_capacity(n.saturating_sub(2));
+    let mut remaining = n;
+    let mut current = 0;
+    let mut num_attempts = 0;
+    let mut allow_degenerate = false;
+    while remaining > 3 {
+        if is_ear(&prev, &next, current, allow_degenerate) {
+            let (a, c) = (prev[current], next[current]);
+            triangles.push([polygon[a], polygon[current], polygon[c]]);
+            next[a] = c;
+            prev[c] = a;
+            remaining -= 1;
+            current = c;
+            num_attempts = 0;
+            allow_degenerate = false;
+        } else {
+            current = next[current];
+            num_attempts += 1;
+            if num_attempts > remaining {
+                // Degenerate ears are only clipped if no proper ear is left, e.g. for collinear vertices
+                if allow_degenerate {
+                    return None;
+                }
+                allow_degenerate = true;
+                num_attempts = 0;
+            }
+        }
+    }
+    triangles.push([
+        polygon[prev[current]],
+        polygon[current],
+        polygon[next[current]],
+    ]);
+
+    Some(triangles)
+}
+
+/// Refines the triangulation of a hole by centroid splits and Delaunay edge flips until the triangle sizes match the mean length of the boundary edges
+///
+/// The first `num_boundary_vertices` vertices of the patch have to form its boundary loop.
+fn refine_hole_patch<R: Real>(patch: &mut TriMesh3d<R>, num_boundary_vertices: usize) {
+    let n = num_boundary_vertices;
+    let edge_length = (0..n)
+        .map(|i| (patch.vertices[(i + 1) % n] - patch.vertices[i]).norm())
+        .fold(R::zero(), |sum, l| sum + l)
+        .unscale(R::from_usize(n).unwrap());
+    // Density control factor of Liepa (2003)
+    let alpha = R::from_f64(std::f64::consts::SQRT_2).unwrap();
+    let three = R::from_usize(3).unwrap();
+
+    // Upper bound of the refinement rounds, every round reduces the triangle sizes
+    const MAX_REFINEMENT_ROUNDS: usize = 100;
+    for _ in 0..MAX_REFINEMENT_ROUNDS {
+        let mut num_splits = 0;
+        for f in 0..patch.triangles.len() {
+            let tri = patch.triangles[f];
+            let centroid = tri
+                .iter()
+                .fold(Vector3::zeros(), |sum, &i| sum + patch.vertices[i])
+                .unscale(three);
+            if tri
+                .iter()
+                .all(|&i| (patch.vertices[i] - centroid).norm() * alpha > edge_length)
+            {
+                let m = patch.vertices.len();
+                patch.vertices.push(centroid);
+                patch.triangles[f] = [tri[0], tri[1], m];
+                patch.triangles.push([tri[1], tri[2], m]);
+                patch.triangles.push([tri[2], tri[0], m]);
+                num_splits += 1;
+            }
+        }
+
+        if num_splits == 0 {
+            break;
+        }
+
+        // Relax the new edges by flipping them until the triangulation is Delaunay
+        let mut patch_he = HalfEdgeTriMesh::from(std::mem::take(patch));
+        for _ in 0..MAX_REFINEMENT_ROUNDS {
+            let mut num_flips = 0;
+            for he_idx in 0..patch_he.half_edges.len() {
+                let he = patch_he.half_edges[he_idx];
+                if he_idx > he.opposite || patch_he.is_flip_ok(he).is_err() {
+                    continue;
+                }
+
+                let he_o = patch_he.opposite(he);
+                let vertices = [he_o.to, he.to, patch_he.next(he).to, patch_he.next(he_o).to];
+                let [v0, v1, v_pos, v_neg] = vertices.map(|i| patch_he.vertices[i]);
+                let opposite_angles = angle_between::<R, R>(&v0, &v_pos, &v1)
+                    + angle_between::<R, R>(&v1, &v_neg, &v0);
+                if opposite_angles > R::pi() + R::default_epsilon()
+                    && flip_preserves_orientation(&patch_he, vertices)
+                {
+                    patch_he.edge_flip(he);
+                    num_flips += 1;
+                }
+            }
+
+            if num_flips == 0 {
+                break;
+            }
+        }
+        *patch = patch_he.into_parts(true).0;
+    }
+}
+
+/// Performs the given number of Loop subdivision steps on a triangle mesh
+///
+/// Every step splits each triangle into four triangles by inserting a new vertex on every edge.
+/// The positions of the new vertices and the updated positions of the existing vertices are computed
+/// with the masks of Loop (1987), "Smooth Subdivision Surfaces Based on Triangles". Edges that are
+/// not incident to exactly two triangles are treated as boundary (crease) edges and are subdivided
+/// with the masks of cubic B-spline curves. Vertices with more than two such edges are kept fixed.
+/// If `project_to_limit` is enabled, the vertices of the final mesh are moved to their positions
+/// on the limit surface.
+///
+/// The existing vertices keep their indices and the new vertices are appended to the mesh.
+/// The triangles `4 * i` to `4 * i + 3` of the subdivided mesh are the children of triangle `i`.
+pub fn loop_subdivision<R: Real>(
+    mesh: &TriMesh3d<R>,
+    iterations: usize,
+    project_to_limit: bool,
+) -> TriMesh3d<R> {
+    profile!("loop_subdivision");
+
+    let mut mesh = mesh.clone();
+    for _ in 0..iterations {
+        mesh = loop_subdivision_step(&mesh).0;
+    }
+    if project_to_limit {
+        mesh.vertices = LoopEdges::new(&mesh).limit_positions(&mesh.vertices);
+    }
+    mesh
+}
+
+/// Performs Loop subdivision of a mesh with attached point and cell attributes, see [`loop_subdivision`]
+///
+/// Point attributes are subdivided linearly, i.e. the existing vertices keep their values and every
+/// new vertex gets the average of the values of the vertices of its edge (`u64` attributes get the value
+/// of the first vertex of the edge). Note that vector attributes like normals are therefore not
+/// necessarily of unit length after the subdivision. Cell attributes are copied to the four children
+/// of every triangle.
+pub fn loop_subdivision_with_data<R: Real, MeshT: Mesh3d<R, Cell = TriangleCell>>(
+    mesh: &MeshWithData<R, MeshT>,
+    iterations: usize,
+    project_to_limit: bool,
+) -> MeshWithData<R, TriMesh3d<R>> {
+    profile!("loop_subdivision_with_data");
+
+    let mut subdivided = MeshWithData {
+        mesh: TriMesh3d {
+            vertices: mesh.vertices().to_vec(),
+            triangles: mesh.cells().iter().map(|cell| cell.0).collect(),
+        },
+        point_attributes: mesh.point_attributes.clone(),
+        cell_attributes: mesh.cell_attributes.clone(),
+    };
+
+    for _ in 0..iterations {
+        let (new_mesh, edges) = loop_subdivision_step(&subdivided.mesh);
+        let parent_triangles = (0..subdivided.mesh.triangles.len())
+            .flat_map(|tri| [tri; 4])
+            .collect::<Vec<_>>();
+
+        subdivided.point_attributes = subdivided
+            .point_attributes
+            .iter()
+            .map(|attribute| subdivide_point_attribute(attribute, &edges))
+            .collect();
+        subdivided.cell_attributes = subdivided
+            .cell_attributes
+            .iter()
+            .map(|attribute| attribute.keep_indices(&parent_triangles))
+            .collect();
+        subdivided.mesh = new_mesh;
+    }
+
+    if project_to_limit {
+        subdivided.mesh.vertices =
+            LoopEdges::new(&subdivided.mesh).limit_positions(&subdivided.mesh.vertices);
+    }
+    subdivided
+}
+
+/// Appends the linearly interpolated values of the vertices inserted on the given edges to a point attribute
+fn subdivide_point_attribute<R: Real>(
+    attribute: &MeshAttribute<R>,
+    edges: &[[usize; 2]],
+) -> MeshAttribute<R> {
+    let half = R::from_f64(0.5).unwrap();
+    let data = match &attribute.data {
+        AttributeData::ScalarU64(values) => AttributeData::ScalarU64(
+            values
+                .iter()
+                .copied()
+                .chain(edges.iter().map(|[v0, _]| values[*v0]))
+                .collect(),
+        ),
+        AttributeData::ScalarReal(values) => AttributeData::ScalarReal(
+            values
+                .iter()
+                .copied()
+                .chain(
+                    edges
+                        .iter()
+                        .map(|[v0, v1]| (values[*v0] + values[*v1]) * half),
+                )
+                .collect(),
+        ),
+        AttributeData::Vector3Real(values) => AttributeData::Vector3Real(
+            values
+                .iter()
+                .copied()
+                .chain(
+                    edges
+                        .iter()
+                        .map(|[v0, v1]| (values[*v0] + values[*v1]) * half),
+                )
+                .collect(),
+        ),
+    };
+
+    MeshAttribute::new(attribute.name.clone(), data)
+}
+
+/// Performs a single Loop subdivision step, returns the subdivided mesh and the edge of every new vertex
+fn loop_subdivision_step<R: Real>(mesh: &TriMesh3d<R>) -> (TriMesh3d<R>, Vec<[usize; 2]>) {
+    let edges = LoopEdges::new(mesh);
+    let num_vertices = mesh.vertices.len();
+
+    let three_eighths = R::from_f64(3.0 / 8.0).unwrap();
+    let one_eighth = R::from_f64(1.0 / 8.0).unwrap();
+    let half = R::from_f64(0.5).unwrap();
+
+    // Update the positions of the existing vertices and insert the new vertices on the edges
+    let mut vertices = (0..num_vertices)
+        .map(|v| edges.even_position(&mesh.vertices, v))
+        .collect::<Vec<_>>();
+    vertices.extend(edges.edges.iter().zip(edges.opposite_vertices.iter()).map(
+        |([v0, v1], opposite)| {
+            let (x0, x1) = (mesh.vertices[*v0], mesh.vertices[*v1]);
+            match opposite.as_slice() {
+                [o0, o1] => {
+                    (x0 + x1) * three_eighths
+                        + (mesh.vertices[*o0] + mesh.vertices[*o1]) * one_eighth
+                }
+                _ => (x0 + x1) * half,
+            }
+        },
+    ));
+
+    // Split every triangle into four triangles
+    let edge_vertex =
+        |v0: usize, v1: usize| num_vertices + edges.edge_map[&[v0.min(v1), v0.max(v1)]];
+    let triangles = mesh
+        .triangles
+        .iter()
+        .flat_map(|&[a, b, c]| {
+            let (ab, bc, ca) = (edge_vertex(a, b), edge_vertex(b, c), edge_vertex(c, a));
+            [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+        })
+        .collect();
+
+    (
+        TriMesh3d {
+            vertices,
+            triangles,
+        },
+        edges.edges,
+    )
+}
+
+/// Edge and neighborhood information required for the Loop subdivision masks
+struct LoopEdges {
+    /// All edges of the mesh with sorted vertex indices
+    edges: Vec<[usize; 2]>,
+    /// Map from an edge to its index
+    edge_map: MapType<[usize; 2], usize>,
+    /// The vertices opposite to every edge in its incident triangles
+    opposite_vertices: Vec<Vec<usize>>,
+    /// The neighbors of every vertex
+    neighbors: Vec<Vec<usize>>,
+    /// The neighbors of every vertex connected by boundary edges (i.e. edges not incident to exactly two triangles)
+    boundary_neighbors: Vec<Vec<usize>>,
+}
+
+impl LoopEdges {
+    fn new<R: Real>(mesh: &TriMesh3d<R>) -> Self {
+        let mut edges = Vec::new();
+        let mut edge_map = new_map();
+        let mut opposite_vertices: Vec<Vec<usize>> = Vec::new();
+        for tri in &mesh.triangles {
+            for i in 0..3 {
+                let (v0, v1) = (tri[i], tri[(i + 1) % 3]);
+                let edge = [v0.min(v1), v0.max(v1)];
+                let edge_idx = *edge_map.entry(edge).or_insert_with(|| {
+                    edges.push(edge);
+                    opposite_vertices.push(Vec::with_capacity(2));
+                    edges.len() - 1
+                });
+                opposite_vertices[edge_idx].push(tri[(i + 2) % 3]);
+            }
+        }
+
+        let mut neighbors = vec![Vec::new(); mesh.vertices.len()];
+        let mut boundary_neighbors = vec![Vec::new(); mesh.vertices.len()];
+        for ([v0, v1], opposite) in edges.iter().zip(opposite_vertices.iter()) {
+            neighbors[*v0].push(*v1);
+            neighbors[*v1].push(*v0);
+            if opposite.len() != 2 {
+                boundary_neighbors[*v0].push(*v1);
+                boundary_neighbors[*v1].push(*v0);
+            }
+        }
+
+        Self {
+            edges,
+            edge_map,
+            opposite_vertices,
+            neighbors,
+            boundary_neighbors,
+        }
+    }
+
+    /// Returns the Loop weight of the neighbors of an interior vertex with the given valence
+    fn beta<R: Real>(valence: usize) -> R {
+        let n = R::from_usize(valence).unwrap();
+        let cos = (R::two_pi() / n).cos();
+        let a = R::from_f64(3.0 / 8.0).unwrap() + R::from_f64(0.25).unwrap() * cos;
+        (R::from_f64(5.0 / 8.0).unwrap() - a * a) / n
+    }
+
+    /// Returns the updated position of an existing vertex after a subdivision step
+    fn even_position<R: Real>(&self, vertices: &[Vector3<R>], v: usize) -> Vector3<R> {
+        let x = vertices[v];
+        match (
+            self.neighbors[v].len(),
+            self.boundary_neighbors[v].as_slice(),
+        ) {
+            (0, _) => x,
+            (valence, []) => {
+                let beta = Self::beta::<R>(valence);
+                let sum = self.neighbors[v]
+                    .iter()
+                    .fold(Vector3::zeros(), |sum, n| sum + vertices[*n]);
+                x * (R::one() - R::from_usize(valence).unwrap() * beta) + sum * beta
+            }
+            (_, [b0, b1]) => {
+                x * R::from_f64(0.75).unwrap()
+                    + (vertices[*b0] + vertices[*b1]) * R::from_f64(0.125).unwrap()
+            }
+            _ => x,
+        }
+    }
+
+    /// Returns the positions of all vertices on the limit surface
+    fn limit_positions<R: Real>(&self, vertices: &[Vector3<R>]) -> Vec<Vector3<R>> {
+        (0..vertices.len())
+            .map(|v| {
+                let x = vertices[v];
+                match (
+                    self.neighbors[v].len(),
+                    self.boundary_neighbors[v].as_slice(),
+                ) {
+                    (0, _) => x,
+                    (valence, []) => {
+                        let n = R::from_usize(valence).unwrap();
+                        let chi = R::one()
+                            / (n + R::from_f64(3.0 / 8.0).unwrap() / Self::beta::<R>(valence));
+                        let sum = self.neighbors[v]
+                            .iter()
+                            .fold(Vector3::zeros(), |sum, n| sum + vertices[*n]);
+                        x * (R::one() - n * chi) + sum * chi
+                    }
+                    (_, [b0, b1]) => {
+                        x * R::from_f64(2.0 / 3.0).unwrap()
+                            + (vertices[*b0] + vertices[*b1]) * R::from_f64(1.0 / 6.0).unwrap()
+                    }
+                    _ => x,
+                }
+            })
+            .collect()
+    }
+}
+
 /// Merges triangles sharing an edge to quads if they fulfill the given criteria
 pub fn convert_tris_to_quads<R: Real>(
     mesh: &TriMesh3d<R>,
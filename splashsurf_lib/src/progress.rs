@@ -0,0 +1,103 @@
+//! Progress reporting and cancellation of running surface reconstructions
+//!
+//! A [`ReconstructionObserver`] and a [`CancellationToken`] can be attached to a reconstruction with a
+//! [`ReconstructionContext`](crate::ReconstructionContext) that is passed to
+//! [`reconstruct_surface_inplace_with_context`](crate::reconstruct_surface_inplace_with_context),
+//! [`reconstruct_surface_streaming_with_context`](crate::streaming::reconstruct_surface_streaming_with_context)
+//! or [`reconstruct_surface_multiphase_with_context`](crate::multiphase::reconstruct_surface_multiphase_with_context).
+//!
+//! With the subdomain-grid approach, the observer is notified after every processed subdomain and
+//! the cancellation token is checked before every subdomain, so a cancelled reconstruction returns
+//! promptly with [`ReconstructionError::Cancelled`](crate::ReconstructionError::Cancelled).
+//! The global approach without domain decomposition only reports its stages and checks for
+//! cancellation between them.
+
+use crate::ReconstructionContext;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Stages of the surface reconstruction that are reported to a [`ReconstructionObserver`]
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ReconstructionStage {
+    /// Spatial decomposition of the particles into subdomains (only with the subdomain-grid approach)
+    Decomposition,
+    /// Neighborhood search and computation of the per-particle densities (and anisotropic kernels)
+    ParticleDensities,
+    /// Evaluation of the density field on the background grid and extraction of the surface patches
+    SurfaceExtraction,
+    /// Stitching of the surface patches of all subdomains (only with the subdomain-grid approach)
+    Stitching,
+}
+
+/// Receives progress notifications from a running surface reconstruction
+///
+/// All methods have empty default implementations. As subdomains are processed in parallel, the
+/// methods may be called concurrently from multiple threads and should return quickly.
+pub trait ReconstructionObserver: Sync {
+    /// Called when the reconstruction enters the given stage
+    fn stage_started(&self, _stage: ReconstructionStage) {}
+
+    /// Called after a subdomain was processed in the given stage, `processed` out of `total` subdomains of the stage are done
+    ///
+    /// Due to the parallel processing, notifications with smaller values of `processed` may arrive
+    /// after notifications with larger values.
+    fn subdomains_processed(&self, _stage: ReconstructionStage, _processed: usize, _total: usize) {}
+}
+
+/// Token that can be used to cancel a running surface reconstruction from another thread
+///
+/// Clones of the token share their state, i.e. cancelling any clone cancels all reconstructions
+/// using one of the clones.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a new token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the cancellation of all reconstructions using this token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Counts the processed subdomains of a stage and notifies the observer of the context
+pub(crate) struct SubdomainProgress<'a, 'b> {
+    context: &'a ReconstructionContext<'b>,
+    stage: ReconstructionStage,
+    total: usize,
+    processed: AtomicUsize,
+}
+
+impl<'a, 'b> SubdomainProgress<'a, 'b> {
+    pub(crate) fn new(
+        context: &'a ReconstructionContext<'b>,
+        stage: ReconstructionStage,
+        total: usize,
+    ) -> Self {
+        Self {
+            context,
+            stage,
+            total,
+            processed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Marks one more subdomain as processed
+    pub(crate) fn increment(&self) {
+        if let Some(observer) = self.context.observer {
+            let processed = self.processed.fetch_add(1, Ordering::Relaxed) + 1;
+            observer.subdomains_processed(self.stage, processed, self.total);
+        }
+    }
+}
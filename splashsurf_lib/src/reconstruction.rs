@@ -1,28 +1,103 @@
+use crate::boundary::BoundaryField;
 use crate::dense_subdomains::{
-    compute_global_densities_and_neighbors, decomposition, initialize_parameters, reconstruction,
-    stitching, subdomain_classification::GhostMarginClassifier,
+    compute_global_densities_and_neighbors, decomposition, initialize_parameters,
+    octree::octree_decomposition, reconstruction, stitching,
+    subdomain_classification::GhostMarginClassifier,
 };
+use crate::density_map::SparseDensityGrid;
 use crate::mesh::TriMesh3d;
 use crate::uniform_grid::UniformGrid;
 use crate::workspace::LocalReconstructionWorkspace;
 use crate::{
-    density_map, marching_cubes, neighborhood_search, profile, Index, Parameters, Real,
-    ReconstructionError, SurfaceReconstruction,
+    anisotropic_kernel, density_map, marching_cubes, neighborhood_search, profile, surface_nets,
+    Index, Parameters, ParticleProperties, Real, ReconstructionContext, ReconstructionError,
+    ReconstructionStage, SpatialDecomposition, SurfaceExtractionMethod, SurfaceReconstruction,
 };
 use anyhow::Context;
 use log::{info, trace};
 use nalgebra::Vector3;
 
-/// Performs a surface reconstruction with a regular grid for domain decomposition
+/// Number of cubes per dimension of the blocks of density grids returned by the global reconstruction
+const GLOBAL_DENSITY_GRID_BLOCK_CUBES: u32 = 16;
+
+/// Per-particle masses and compact support radii resolved from user provided [`ParticleProperties`]
+pub(crate) struct PerParticleData<R: Real> {
+    /// Mass per particle
+    pub masses: Vec<R>,
+    /// Compact support radius of the kernel per particle
+    pub compact_support_radii: Vec<R>,
+}
+
+impl<R: Real> PerParticleData<R> {
+    /// Resolves the per-particle data, missing properties are derived from the global parameters
+    ///
+    /// If `particle_inside` is provided, only the data of particles flagged as inside is kept.
+    pub(crate) fn from_properties(
+        properties: &ParticleProperties<R>,
+        parameters: &Parameters<R>,
+        particle_inside: Option<&[bool]>,
+    ) -> Self {
+        let num_particles = properties
+            .radii
+            .or(properties.masses)
+            .or(properties.compact_support_radii)
+            .map(|values| values.len())
+            .unwrap_or(0);
+
+        let is_inside = |i: &usize| particle_inside.map(|inside| inside[*i]).unwrap_or(true);
+        let radius = |i: usize| {
+            properties
+                .radii
+                .map(|radii| radii[i])
+                .unwrap_or(parameters.particle_radius)
+        };
+
+        let rest_volume_factor = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap();
+        let masses = (0..num_particles)
+            .filter(is_inside)
+            .map(|i| match properties.masses {
+                Some(masses) => masses[i],
+                None => rest_volume_factor * radius(i).powi(3) * parameters.rest_density,
+            })
+            .collect();
+
+        let support_to_radius_ratio =
+            parameters.compact_support_radius / parameters.particle_radius;
+        let compact_support_radii = (0..num_particles)
+            .filter(is_inside)
+            .map(|i| match properties.compact_support_radii {
+                Some(compact_support_radii) => compact_support_radii[i],
+                None => radius(i) * support_to_radius_ratio,
+            })
+            .collect();
+
+        Self {
+            masses,
+            compact_support_radii,
+        }
+    }
+
+    /// Returns the largest compact support radius of all particles
+    pub(crate) fn max_compact_support_radius(&self) -> R {
+        self.compact_support_radii
+            .iter()
+            .copied()
+            .fold(R::zero(), |max, h| max.max(h))
+    }
+}
+
+/// Performs a surface reconstruction with a regular grid (optionally refined by an octree) for domain decomposition
 pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
+    particle_data: Option<&PerParticleData<R>>,
     parameters: &Parameters<R>,
+    context: &ReconstructionContext,
     output_surface: &'a mut SurfaceReconstruction<I, R>,
-) -> Result<(), anyhow::Error> {
+) -> Result<(), ReconstructionError<I, R>> {
     profile!("surface reconstruction subdomain-grid");
 
-    let internal_parameters =
-        initialize_parameters(parameters, &particle_positions, output_surface)?;
+    context.notify_stage(ReconstructionStage::Decomposition);
+    let internal_parameters = initialize_parameters(parameters, &output_surface.grid)?;
     output_surface.grid = internal_parameters
         .global_marching_cubes_grid()
         .context("failed to convert global marching cubes grid")?;
@@ -33,8 +108,11 @@ pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
     let particles = narrow_band_particles;
      */
 
-    let subdomains =
-        decomposition::<I, R, GhostMarginClassifier<I>>(&internal_parameters, &particle_positions)?;
+    let subdomains = decomposition::<I, R, GhostMarginClassifier<I>>(
+        &internal_parameters,
+        &particle_positions,
+        particle_data,
+    )?;
 
     /*
     {
@@ -47,19 +125,62 @@ pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
     }
      */
 
-    let (particle_densities, particle_neighbors) = compute_global_densities_and_neighbors(
-        &internal_parameters,
-        &particle_positions,
-        &subdomains,
-    );
+    context.check_cancelled()?;
+    context.notify_stage(ReconstructionStage::ParticleDensities);
+    let (mut particle_densities, particle_neighbors, anisotropic_kernels) =
+        compute_global_densities_and_neighbors(
+            &internal_parameters,
+            &particle_positions,
+            particle_data,
+            &subdomains,
+            context,
+        );
+    context.check_cancelled()?;
+
+    let boundary = parameters
+        .solid_boundary
+        .as_ref()
+        .map(|boundary| BoundaryField::new(boundary, parameters));
+    if let Some(boundary) = &boundary {
+        boundary.add_to_particle_densities(
+            particle_positions,
+            parameters.rest_density,
+            parameters.enable_multi_threading,
+            &mut particle_densities,
+        );
+    }
+    context.notify_stage(ReconstructionStage::SurfaceExtraction);
+
+    // Merge the subdomains of the grid into octree leaves adapted to the particle distribution
+    let subdomains =
+        if let Some(SpatialDecomposition::Octree(_)) = &parameters.spatial_decomposition {
+            octree_decomposition(&internal_parameters, subdomains)
+        } else {
+            subdomains
+        };
+
+    // The blocks of the density grid correspond to the cells of the subdomain grid
+    let mut density_grid = parameters.density_grid.then(|| {
+        SparseDensityGrid::new(
+            output_surface.grid.clone(),
+            internal_parameters.subdomain_cubes(),
+        )
+    });
 
     let surface_patches = reconstruction(
         &internal_parameters,
         &particle_positions,
         &particle_densities,
+        particle_data,
+        anisotropic_kernels.as_deref(),
         &subdomains,
+        boundary.as_ref(),
+        density_grid.as_mut(),
+        context,
     );
+    context.check_cancelled()?;
 
+    context.notify_stage(ReconstructionStage::Stitching);
     let global_mesh = stitching(surface_patches);
     info!(
         "Global mesh has {} vertices and {} triangles.",
@@ -67,7 +188,15 @@ pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
         global_mesh.triangles.len()
     );
 
+    // The stitching preserves the consecutive triangle pairs of the surface nets quads
+    if parameters.extraction_method == SurfaceExtractionMethod::SurfaceNets {
+        output_surface.quads = Some(surface_nets::quads_from_triangle_pairs(
+            &global_mesh.triangles,
+        ));
+    }
+
     output_surface.mesh = global_mesh;
+    output_surface.density_grid = density_grid;
     output_surface.particle_densities = Some(particle_densities);
     if parameters.global_neighborhood_list {
         output_surface.particle_neighbors = Some(particle_neighbors);
@@ -78,7 +207,9 @@ pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
 /// Performs a global surface reconstruction without domain decomposition
 pub(crate) fn reconstruct_surface_global<'a, I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
+    particle_data: Option<&PerParticleData<R>>,
     parameters: &Parameters<R>,
+    context: &ReconstructionContext,
     output_surface: &'a mut SurfaceReconstruction<I, R>,
 ) -> Result<(), ReconstructionError<I, R>> {
     profile!("reconstruct_surface_global");
@@ -103,15 +234,24 @@ pub(crate) fn reconstruct_surface_global<'a, I: Index, R: Real>(
 
     // Clear the current mesh, as reconstruction will be appended to output
     output_surface.mesh.clear();
+    let mut quads = Vec::new();
     // Perform global reconstruction without domain decomposition
     reconstruct_single_surface_append(
         &mut *workspace,
         &output_surface.grid,
         particle_positions,
+        particle_data,
         parameters,
         &mut output_surface.mesh,
+        &mut quads,
+        &mut output_surface.density_grid,
+        context,
     )?;
 
+    if parameters.extraction_method == SurfaceExtractionMethod::SurfaceNets {
+        output_surface.quads = Some(quads);
+    }
+
     output_surface.particle_densities = Some(std::mem::take(&mut workspace.particle_densities));
 
     Ok(())
@@ -121,6 +261,7 @@ pub(crate) fn reconstruct_surface_global<'a, I: Index, R: Real>(
 pub(crate) fn compute_particle_densities_and_neighbors<I: Index, R: Real>(
     grid: &UniformGrid<I, R>,
     particle_positions: &[Vector3<R>],
+    particle_data: Option<&PerParticleData<R>>,
     parameters: &Parameters<R>,
     particle_neighbor_lists: &mut Vec<Vec<usize>>,
     densities: &mut Vec<R>,
@@ -142,61 +283,182 @@ pub(crate) fn compute_particle_densities_and_neighbors<I: Index, R: Real>(
     );
 
     trace!("Computing particle densities...");
-    density_map::compute_particle_densities_inplace::<I, R>(
-        particle_positions,
-        particle_neighbor_lists.as_slice(),
-        parameters.compact_support_radius,
-        particle_rest_mass,
-        parameters.enable_multi_threading,
-        densities,
-    );
+    if let Some(particle_data) = particle_data {
+        density_map::compute_particle_densities_polydisperse_inplace::<I, R>(
+            particle_positions,
+            particle_neighbor_lists.as_slice(),
+            &particle_data.masses,
+            &particle_data.compact_support_radii,
+            parameters.kernel_type,
+            parameters.enable_multi_threading,
+            densities,
+        );
+    } else {
+        density_map::compute_particle_densities_inplace::<I, R>(
+            particle_positions,
+            particle_neighbor_lists.as_slice(),
+            parameters.compact_support_radius,
+            parameters.kernel_type,
+            particle_rest_mass,
+            parameters.enable_multi_threading,
+            densities,
+        );
+    }
 }
 
 /// Reconstruct a surface, appends triangulation to the given mesh
+///
+/// When using surface nets, the quads of the surface are additionally appended to `output_quads`
+/// and every quad is split into two consecutive triangles of the mesh. If requested by the
+/// parameters, the density map is stored as a sparse density grid in `output_density_grid`.
+/// The stages of the reconstruction are reported to the observer of the `context` and the
+/// reconstruction is cancelled between the stages if requested.
 pub(crate) fn reconstruct_single_surface_append<'a, I: Index, R: Real>(
     workspace: &mut LocalReconstructionWorkspace<R>,
     grid: &UniformGrid<I, R>,
     particle_positions: &[Vector3<R>],
+    particle_data: Option<&PerParticleData<R>>,
     parameters: &Parameters<R>,
     output_mesh: &'a mut TriMesh3d<R>,
+    output_quads: &mut Vec<[usize; 4]>,
+    output_density_grid: &mut Option<SparseDensityGrid<I, R>>,
+    context: &ReconstructionContext,
 ) -> Result<(), ReconstructionError<I, R>> {
     let particle_rest_density = parameters.rest_density;
     let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
         * parameters.particle_radius.powi(3);
     let particle_rest_mass = particle_rest_volume * particle_rest_density;
 
+    context.notify_stage(ReconstructionStage::ParticleDensities);
+    let boundary = parameters
+        .solid_boundary
+        .as_ref()
+        .map(|boundary| BoundaryField::new(boundary, parameters));
+
     let particle_densities = {
         compute_particle_densities_and_neighbors(
             grid,
             particle_positions,
+            particle_data,
             parameters,
             &mut workspace.particle_neighbor_lists,
             &mut workspace.particle_densities,
         );
+        if let Some(boundary) = &boundary {
+            boundary.add_to_particle_densities(
+                particle_positions,
+                parameters.rest_density,
+                parameters.enable_multi_threading,
+                &mut workspace.particle_densities,
+            );
+        }
         workspace.particle_densities.as_slice()
     };
 
+    context.check_cancelled()?;
+    context.notify_stage(ReconstructionStage::SurfaceExtraction);
+
     // Create a new density map, reusing memory with the workspace is bad for cache efficiency
     // Alternatively one could reuse memory with a custom caching allocator
     let mut density_map = Default::default();
-    density_map::generate_sparse_density_map(
-        grid,
-        particle_positions,
-        particle_densities,
-        None,
-        particle_rest_mass,
-        parameters.compact_support_radius,
-        parameters.cube_size,
-        parameters.enable_multi_threading,
-        &mut density_map,
-    )?;
+    if let Some(anisotropic_kernel_parameters) = &parameters.anisotropic_kernel {
+        trace!("Computing anisotropic kernels...");
+        anisotropic_kernel::compute_anisotropic_kernels(
+            particle_positions,
+            workspace.particle_neighbor_lists.as_slice(),
+            parameters.compact_support_radius,
+            particle_data.map(|particle_data| particle_data.compact_support_radii.as_slice()),
+            anisotropic_kernel_parameters,
+            parameters.enable_multi_threading,
+            &mut workspace.anisotropic_kernels,
+        );
 
-    marching_cubes::triangulate_density_map_append(
-        grid,
-        &density_map,
-        parameters.iso_surface_threshold,
-        output_mesh,
-    )?;
+        density_map::generate_sparse_density_map_anisotropic(
+            grid,
+            particle_densities,
+            particle_data.map(|particle_data| particle_data.masses.as_slice()),
+            particle_rest_mass,
+            &workspace.anisotropic_kernels,
+            parameters.kernel_type,
+            None,
+            parameters.cube_size,
+            parameters.enable_multi_threading,
+            &mut density_map,
+        )?;
+    } else if let Some(particle_data) = particle_data {
+        density_map::generate_sparse_density_map_polydisperse(
+            grid,
+            particle_positions,
+            particle_densities,
+            &particle_data.masses,
+            &particle_data.compact_support_radii,
+            parameters.kernel_type,
+            None,
+            parameters.cube_size,
+            parameters.enable_multi_threading,
+            &mut density_map,
+        )?;
+    } else {
+        density_map::generate_sparse_density_map(
+            grid,
+            particle_positions,
+            particle_densities,
+            None,
+            particle_rest_mass,
+            parameters.compact_support_radius,
+            parameters.kernel_type,
+            parameters.cube_size,
+            parameters.enable_multi_threading,
+            &mut density_map,
+        )?;
+    }
+
+    if let Some(boundary) = &boundary {
+        boundary.apply_to_density_map(
+            grid,
+            parameters.iso_surface_threshold,
+            parameters.enable_multi_threading,
+            &mut density_map,
+        );
+    }
+
+    context.check_cancelled()?;
+
+    match parameters.extraction_method {
+        SurfaceExtractionMethod::MarchingCubes => {
+            marching_cubes::triangulate_density_map_append(
+                grid,
+                &density_map,
+                parameters.iso_surface_threshold,
+                output_mesh,
+            )?;
+        }
+        SurfaceExtractionMethod::SurfaceNets => {
+            let quads_begin = output_quads.len();
+            surface_nets::extract_density_map_append(
+                grid,
+                &density_map,
+                parameters.iso_surface_threshold,
+                &mut output_mesh.vertices,
+                output_quads,
+            );
+            output_mesh
+                .triangles
+                .extend(surface_nets::triangulate_quads(
+                    &output_mesh.vertices,
+                    &output_quads[quads_begin..],
+                ));
+        }
+    }
+
+    if parameters.density_grid {
+        *output_density_grid = Some(SparseDensityGrid::from_density_map(
+            &density_map,
+            grid,
+            I::from_u32(GLOBAL_DENSITY_GRID_BLOCK_CUBES)
+                .expect("number of cubes per block has to fit in index type"),
+        ));
+    }
 
     Ok(())
 }
@@ -0,0 +1,240 @@
+//! Sampling of signed distance fields of closed triangle meshes on uniform grids
+//!
+//! The distances are computed exactly as point-triangle distances using a [`TriMeshBvh`], the sign
+//! is determined by the generalized winding number (see [`TriMeshBvh::winding_number`]) which is
+//! robust for meshes with small holes, self-intersections or inconsistencies.
+
+use crate::bvh::TriMeshBvh;
+use crate::mesh::TriMesh3d;
+use crate::topology::DirectedAxis;
+use crate::uniform_grid::UniformGrid;
+use crate::{profile, Index, Real};
+use nalgebra::Vector3;
+use num_traits::Bounded;
+use rayon::prelude::*;
+
+/// Signed distance values of a mesh sampled at all points of a [`UniformGrid`]
+///
+/// The distances are negative inside and positive outside of the mesh. If the field was sampled
+/// in a narrow band, the values of points farther away from the surface than the narrow band width
+/// are clamped to the width (with the correct sign).
+#[derive(Clone, Debug)]
+pub struct SignedDistanceGrid<I: Index, R: Real> {
+    /// Grid of the sampled points
+    grid: UniformGrid<I, R>,
+    /// Signed distance values of all grid points in the order of their flat point indices
+    values: Vec<R>,
+    /// Width of the narrow band around the surface in which the distance values are exact
+    narrow_band_width: Option<R>,
+}
+
+impl<I: Index, R: Real> SignedDistanceGrid<I, R> {
+    /// Returns the grid of the sampled points
+    pub fn grid(&self) -> &UniformGrid<I, R> {
+        &self.grid
+    }
+
+    /// Returns the signed distance values of all grid points in the order of their flat point indices
+    pub fn values(&self) -> &[R] {
+        self.values.as_slice()
+    }
+
+    /// Returns the width of the narrow band if the field was only sampled close to the surface
+    pub fn narrow_band_width(&self) -> Option<R> {
+        self.narrow_band_width
+    }
+
+    /// Returns the signed distance value at the given point of the grid or `None` if the point is not part of the grid
+    pub fn get(&self, point_ijk: &[I; 3]) -> Option<R> {
+        self.grid.get_point(*point_ijk).map(|point| {
+            let flat_index = self.grid.flatten_point_index(&point);
+            self.values[flat_index.to_usize().unwrap()]
+        })
+    }
+
+    /// Consumes the grid and returns the signed distance values of all grid points in the order of their flat point indices
+    pub fn into_values(self) -> Vec<R> {
+        self.values
+    }
+}
+
+/// Samples the signed distance of a closed mesh at all points of the grid (parallelized version)
+///
+/// If a narrow band width is given, exact distances are only computed for points closer to the
+/// surface than the width, all other points are set to plus or minus the width. If the width is
+/// larger than half the cell size of the grid, the inside/outside queries are only performed for
+/// points in the band and the signs of the remaining points are propagated by a flood fill over
+/// the grid (a single query per connected region outside of the band). In this case, sampling a
+/// narrow band is considerably faster than sampling the full grid if the width is small compared to
+/// the size of the grid.
+/// For an empty mesh, all values are set to the maximum value of `R` (or to the narrow band width).
+pub fn par_signed_distance_grid<I: Index, R: Real>(
+    mesh: &TriMesh3d<R>,
+    grid: &UniformGrid<I, R>,
+    narrow_band_width: Option<R>,
+) -> SignedDistanceGrid<I, R> {
+    profile!("par_signed_distance_grid");
+    let bvh = TriMeshBvh::par_new(mesh);
+    let values = match narrow_band_width {
+        Some(width) => {
+            let propagate_signs = propagate_signs(grid, width);
+            let values = (0..num_points(grid))
+                .into_par_iter()
+                .map(|flat_index| {
+                    sample_narrow_band(&bvh, grid, width, propagate_signs, flat_index)
+                })
+                .collect();
+            fill_outside_of_band(&bvh, grid, width, values)
+        }
+        None => (0..num_points(grid))
+            .into_par_iter()
+            .map(|flat_index| sample_point(&bvh, grid, flat_index))
+            .collect(),
+    };
+
+    SignedDistanceGrid {
+        grid: grid.clone(),
+        values,
+        narrow_band_width,
+    }
+}
+
+/// Samples the signed distance of a closed mesh at all points of the grid
+///
+/// See [`par_signed_distance_grid`] for details on the narrow band.
+/// For an empty mesh, all values are set to the maximum value of `R` (or to the narrow band width).
+pub fn signed_distance_grid<I: Index, R: Real>(
+    mesh: &TriMesh3d<R>,
+    grid: &UniformGrid<I, R>,
+    narrow_band_width: Option<R>,
+) -> SignedDistanceGrid<I, R> {
+    profile!("signed_distance_grid");
+    let bvh = TriMeshBvh::new(mesh);
+    let values = match narrow_band_width {
+        Some(width) => {
+            let propagate_signs = propagate_signs(grid, width);
+            let values = (0..num_points(grid))
+                .map(|flat_index| {
+                    sample_narrow_band(&bvh, grid, width, propagate_signs, flat_index)
+                })
+                .collect();
+            fill_outside_of_band(&bvh, grid, width, values)
+        }
+        None => (0..num_points(grid))
+            .map(|flat_index| sample_point(&bvh, grid, flat_index))
+            .collect(),
+    };
+
+    SignedDistanceGrid {
+        grid: grid.clone(),
+        values,
+        narrow_band_width,
+    }
+}
+
+/// Returns the total number of points of the grid
+fn num_points<I: Index, R: Real>(grid: &UniformGrid<I, R>) -> usize {
+    grid.points_per_dim()
+        .iter()
+        .map(|n| n.to_usize().unwrap())
+        .product()
+}
+
+/// Returns whether the signs of the points outside of the narrow band can be propagated between neighboring grid points
+///
+/// The segment between two neighboring points outside of the band cannot cross the surface if all
+/// points of the segment are farther away from the surface than the width minus half the cell size.
+fn propagate_signs<I: Index, R: Real>(grid: &UniformGrid<I, R>, narrow_band_width: R) -> bool {
+    narrow_band_width > grid.cell_size() / (R::one() + R::one())
+}
+
+/// Returns the given distance with a negative sign if the point is inside of the mesh
+fn signed<R: Real>(bvh: &TriMeshBvh<R>, x: &Vector3<R>, distance: R) -> R {
+    if bvh.is_inside(x) {
+        -distance
+    } else {
+        distance
+    }
+}
+
+/// Computes the signed distance value of the grid point with the given flat index
+fn sample_point<I: Index, R: Real>(
+    bvh: &TriMeshBvh<R>,
+    grid: &UniformGrid<I, R>,
+    flat_index: usize,
+) -> R {
+    let x = point_coordinates(grid, flat_index);
+    let distance = bvh
+        .closest_point(&x)
+        .map(|closest| closest.distance)
+        .unwrap_or(<R as Bounded>::max_value());
+    signed(bvh, &x, distance)
+}
+
+/// Computes the signed distance value of the grid point with the given flat index if it is inside of the narrow band
+///
+/// For points outside of the band, `None` is returned if the signs are propagated later on,
+/// otherwise the signed narrow band width is returned.
+fn sample_narrow_band<I: Index, R: Real>(
+    bvh: &TriMeshBvh<R>,
+    grid: &UniformGrid<I, R>,
+    narrow_band_width: R,
+    propagate_signs: bool,
+    flat_index: usize,
+) -> Option<R> {
+    let x = point_coordinates(grid, flat_index);
+    match bvh.closest_point_within(&x, narrow_band_width) {
+        Some(closest) => Some(signed(bvh, &x, closest.distance)),
+        None if propagate_signs => None,
+        None => Some(signed(bvh, &x, narrow_band_width)),
+    }
+}
+
+/// Assigns the signed narrow band width to all points without value by a flood fill over the connected regions of these points
+fn fill_outside_of_band<I: Index, R: Real>(
+    bvh: &TriMeshBvh<R>,
+    grid: &UniformGrid<I, R>,
+    narrow_band_width: R,
+    mut values: Vec<Option<R>>,
+) -> Vec<R> {
+    profile!("fill outside of narrow band");
+
+    let mut stack = Vec::new();
+    for seed in 0..values.len() {
+        if values[seed].is_some() {
+            continue;
+        }
+
+        // All points of a connected region outside of the band are on the same side of the surface
+        let value = signed(bvh, &point_coordinates(grid, seed), narrow_band_width);
+        values[seed] = Some(value);
+        stack.push(seed);
+        while let Some(flat_index) = stack.pop() {
+            let point = grid
+                .try_unflatten_point_index(I::from_usize(flat_index).unwrap())
+                .unwrap();
+            for direction in DirectedAxis::all_possible() {
+                if let Some(neighbor) = grid.get_point_neighbor(&point, *direction) {
+                    let neighbor = grid.flatten_point_index(&neighbor).to_usize().unwrap();
+                    if values[neighbor].is_none() {
+                        values[neighbor] = Some(value);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+    }
+
+    values
+        .into_iter()
+        .map(|value| value.expect("all points have to be filled"))
+        .collect()
+}
+
+/// Returns the coordinates of the grid point with the given flat index
+fn point_coordinates<I: Index, R: Real>(grid: &UniformGrid<I, R>, flat_index: usize) -> Vector3<R> {
+    let point = grid
+        .try_unflatten_point_index(I::from_usize(flat_index).unwrap())
+        .unwrap();
+    grid.point_coordinates(&point)
+}
@@ -1,9 +1,9 @@
 //! Functions for interpolating quantities (e.g. normals, scalar fields) by evaluating SPH sums
 
-use crate::kernel::SymmetricKernel3d;
+use crate::kernel::{DynamicKernel, KernelType, SymmetricKernel3d};
 use crate::profile;
 use crate::Real;
-use crate::{kernel, ThreadSafe};
+use crate::ThreadSafe;
 use nalgebra::{SVector, Unit, Vector3};
 use rayon::prelude::*;
 use rstar::primitives::GeomWithData;
@@ -11,8 +11,13 @@ use rstar::RTree;
 use std::ops::AddAssign;
 
 /// Acceleration structure for interpolating field quantities of the fluid to arbitrary points using SPH interpolation
+///
+/// The construction and the interpolation functions run in the rayon thread pool of the calling thread, use
+/// [`ReconstructionContext::install`](crate::ReconstructionContext::install) to run them in a custom thread pool.
 pub struct SphInterpolator<R: Real> {
     compact_support_radius: R,
+    kernel_type: KernelType,
+    enable_multi_threading: bool,
     tree: RTree<Particle<R>>,
 }
 
@@ -69,10 +74,34 @@ impl<R: Real> SphInterpolator<R> {
 
         Self {
             compact_support_radius,
+            kernel_type: KernelType::default(),
+            enable_multi_threading: true,
             tree,
         }
     }
 
+    /// Sets the SPH kernel function used for the interpolation (the cubic spline kernel is used by default)
+    pub fn with_kernel_type(mut self, kernel_type: KernelType) -> Self {
+        self.kernel_type = kernel_type;
+        self
+    }
+
+    /// Returns the SPH kernel function used for the interpolation
+    pub fn kernel_type(&self) -> KernelType {
+        self.kernel_type
+    }
+
+    /// Sets whether the interpolation is performed in parallel over the interpolation points (enabled by default)
+    pub fn with_multi_threading(mut self, enable_multi_threading: bool) -> Self {
+        self.enable_multi_threading = enable_multi_threading;
+        self
+    }
+
+    /// Returns whether the interpolation is performed in parallel over the interpolation points
+    pub fn multi_threading(&self) -> bool {
+        self.enable_multi_threading
+    }
+
     /// Interpolates surface normals (i.e. normalized SPH gradient of the indicator function) of the fluid to the given points using SPH interpolation, appends to the given vector
     pub fn interpolate_normals_inplace(
         &self,
@@ -82,39 +111,46 @@ impl<R: Real> SphInterpolator<R> {
         profile!("interpolate_normals_inplace");
 
         let squared_support = self.compact_support_radius * self.compact_support_radius;
-        let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
-
-        interpolation_points
-            .par_iter()
-            .map(|x_i| {
-                // Compute the gradient of the particle density field which points in the same direction as surface normals
-                let mut density_grad = Vector3::zeros();
-
-                // SPH: Iterate over all other particles within the squared support radius
-                let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
-                for p_j in self
-                    .tree
-                    .locate_within_distance(query_point, squared_support)
-                {
-                    // Volume of the neighbor particle
-                    let vol_j = p_j.data.volume;
-                    // Position of the neighbor particle
-                    let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
-
-                    // Relative position `dx` and distance `r` of the neighbor particle
-                    let dx = x_j - x_i;
-                    let r = dx.norm();
-
-                    // Compute the contribution of the neighbor to the gradient of the density field
-                    // TODO: Replace this by a discrete gradient norm evaluation
-                    let kernel_grad = dx.unscale(r) * kernel.evaluate_gradient_norm(r);
-                    density_grad += kernel_grad * vol_j;
-                }
-
-                // Normalize the gradient to get the surface normal
-                Unit::new_normalize(density_grad)
-            })
-            .collect_into_vec(normals);
+        let kernel = DynamicKernel::new(self.kernel_type, self.compact_support_radius);
+
+        let interpolate_normal = |x_i: &Vector3<R>| {
+            // Compute the gradient of the particle density field which points in the same direction as surface normals
+            let mut density_grad = Vector3::zeros();
+
+            // SPH: Iterate over all other particles within the squared support radius
+            let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
+            for p_j in self
+                .tree
+                .locate_within_distance(query_point, squared_support)
+            {
+                // Volume of the neighbor particle
+                let vol_j = p_j.data.volume;
+                // Position of the neighbor particle
+                let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
+
+                // Relative position `dx` and distance `r` of the neighbor particle
+                let dx = x_j - x_i;
+                let r = dx.norm();
+
+                // Compute the contribution of the neighbor to the gradient of the density field
+                // TODO: Replace this by a discrete gradient norm evaluation
+                let kernel_grad = dx.unscale(r) * kernel.evaluate_gradient_norm(r);
+                density_grad += kernel_grad * vol_j;
+            }
+
+            // Normalize the gradient to get the surface normal
+            Unit::new_normalize(density_grad)
+        };
+
+        if self.enable_multi_threading {
+            interpolation_points
+                .par_iter()
+                .map(interpolate_normal)
+                .collect_into_vec(normals);
+        } else {
+            normals.clear();
+            normals.extend(interpolation_points.iter().map(interpolate_normal));
+        }
     }
 
     /// Interpolates surface normals (i.e. normalized SPH gradient of the indicator function) of the fluid to the given points using SPH interpolation
@@ -208,7 +244,7 @@ impl<R: Real> SphInterpolator<R> {
         assert_eq!(particle_quantity.len(), self.tree.size());
 
         let squared_support = self.compact_support_radius * self.compact_support_radius;
-        let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
+        let kernel = DynamicKernel::new(self.kernel_type, self.compact_support_radius);
 
         let enable_correction = if first_order_correction {
             R::one()
@@ -216,40 +252,47 @@ impl<R: Real> SphInterpolator<R> {
             R::zero()
         };
 
-        interpolation_points
-            .par_iter()
-            .map(|x_i| {
-                let mut interpolated_value = T::zero();
-                let mut correction = R::zero();
-
-                // SPH: Iterate over all other particles within the squared support radius
-                let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
-                for p_j in self
-                    .tree
-                    .locate_within_distance(query_point, squared_support)
-                {
-                    // Volume of the neighbor particle
-                    let vol_j = p_j.data.volume;
-                    // Position of the neighbor particle
-                    let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
-
-                    // Relative position `dx` and distance `r` of the neighbor particle
-                    let dx = x_j - x_i;
-                    let r = dx.norm();
-
-                    // Unchecked access is fine as we asserted before that the slice has the correct length
-                    let A_j = unsafe { particle_quantity.get_unchecked(p_j.data.index).clone() };
-                    let W_ij = kernel.evaluate(r);
-
-                    interpolated_value += A_j.scale(vol_j * W_ij);
-                    correction += vol_j * W_ij;
-                }
-
-                let correction_factor =
-                    enable_correction * correction.recip() + (R::one() - enable_correction);
-                interpolated_value.scale(correction_factor)
-            })
-            .collect_into_vec(interpolated_values);
+        let interpolate_value = |x_i: &Vector3<R>| {
+            let mut interpolated_value = T::zero();
+            let mut correction = R::zero();
+
+            // SPH: Iterate over all other particles within the squared support radius
+            let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
+            for p_j in self
+                .tree
+                .locate_within_distance(query_point, squared_support)
+            {
+                // Volume of the neighbor particle
+                let vol_j = p_j.data.volume;
+                // Position of the neighbor particle
+                let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
+
+                // Relative position `dx` and distance `r` of the neighbor particle
+                let dx = x_j - x_i;
+                let r = dx.norm();
+
+                // Unchecked access is fine as we asserted before that the slice has the correct length
+                let A_j = unsafe { particle_quantity.get_unchecked(p_j.data.index).clone() };
+                let W_ij = kernel.evaluate(r);
+
+                interpolated_value += A_j.scale(vol_j * W_ij);
+                correction += vol_j * W_ij;
+            }
+
+            let correction_factor =
+                enable_correction * correction.recip() + (R::one() - enable_correction);
+            interpolated_value.scale(correction_factor)
+        };
+
+        if self.enable_multi_threading {
+            interpolation_points
+                .par_iter()
+                .map(interpolate_value)
+                .collect_into_vec(interpolated_values);
+        } else {
+            interpolated_values.clear();
+            interpolated_values.extend(interpolation_points.iter().map(interpolate_value));
+        }
     }
 }
 
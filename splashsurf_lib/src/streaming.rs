@@ -0,0 +1,395 @@
+//! Out-of-core surface reconstruction for particle sets that do not fit into memory
+//!
+//! The streaming reconstruction is based on the subdomain-grid approach (see
+//! [`SpatialDecomposition::UniformGrid`]). Instead of a slice of particles, it reads the particles
+//! chunk by chunk from a [`ParticleSource`] and processes the subdomains in batches whose
+//! particles fit into a given memory budget (see [`StreamingParameters`]). The stitched surface
+//! patches of every batch are written immediately to a [`MeshSink`], so the final mesh is never
+//! stored in memory as a whole (unless the sink does so, e.g. the implementation for [`TriMesh3d`]).
+//!
+//! The particle source is read multiple times: once to determine the bounding box of the particles
+//! (only if no [`Parameters::particle_aabb`] is provided), once to count the particles per subdomain
+//! and once per batch to collect the particles of the batch. For the latter, chunks whose bounding box
+//! does not intersect with the region of the batch are skipped. Therefore, sources that are
+//! sorted spatially (and provide bounding boxes for their chunks) are read significantly less often.
+
+use crate::dense_subdomains::initialize_parameters;
+use crate::dense_subdomains::streaming::{
+    bytes_per_particle, count_subdomain_particles, load_batch_particles, plan_batches,
+    reconstruct_batch, IncrementalStitcher,
+};
+use crate::mesh::TriMesh3d;
+use crate::progress::ReconstructionStage;
+use crate::uniform_grid::UniformGrid;
+use crate::{
+    grid_for_reconstruction, profile, Aabb3d, Index, Parameters, Real, ReconstructionContext,
+    ReconstructionError, SpatialDecomposition,
+};
+use anyhow::{anyhow, Context};
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Source of particle positions that can be read in chunks
+///
+/// All chunks together have to contain every particle exactly once. The reconstruction reads the
+/// chunks multiple times, every read of a chunk has to return the same particles.
+pub trait ParticleSource<R: Real> {
+    /// Returns the number of chunks of this source
+    fn num_chunks(&self) -> usize;
+
+    /// Returns a bounding box of all particles of the given chunk, if known without reading the chunk
+    ///
+    /// If a bounding box is returned, the chunk is only read for batches of subdomains that intersect it.
+    fn chunk_aabb(&self, _chunk_index: usize) -> Option<Aabb3d<R>> {
+        None
+    }
+
+    /// Reads the particle positions of the given chunk into the buffer, replacing its current content
+    fn read_chunk(
+        &mut self,
+        chunk_index: usize,
+        particles: &mut Vec<Vector3<R>>,
+    ) -> Result<(), anyhow::Error>;
+}
+
+/// Receiver of the mesh generated by the streaming reconstruction
+///
+/// Vertices are always appended before any triangle referring to them. The vertex indices of the
+/// triangles refer to all vertices appended so far, i.e. the first vertex of the first call of
+/// [`append_vertices`](MeshSink::append_vertices) has index zero.
+pub trait MeshSink<R: Real> {
+    /// Appends vertices to the mesh
+    fn append_vertices(&mut self, vertices: &[Vector3<R>]) -> Result<(), anyhow::Error>;
+    /// Appends triangles to the mesh
+    fn append_triangles(&mut self, triangles: &[[usize; 3]]) -> Result<(), anyhow::Error>;
+}
+
+impl<R: Real> MeshSink<R> for TriMesh3d<R> {
+    fn append_vertices(&mut self, vertices: &[Vector3<R>]) -> Result<(), anyhow::Error> {
+        self.vertices.extend_from_slice(vertices);
+        Ok(())
+    }
+
+    fn append_triangles(&mut self, triangles: &[[usize; 3]]) -> Result<(), anyhow::Error> {
+        self.triangles.extend_from_slice(triangles);
+        Ok(())
+    }
+}
+
+/// [`ParticleSource`] that splits a slice of particles already in memory into chunks of consecutive particles
+pub struct SliceParticleSource<'a, R: Real> {
+    particles: &'a [Vector3<R>],
+    chunk_size: usize,
+    chunk_aabbs: Vec<Aabb3d<R>>,
+}
+
+impl<'a, R: Real> SliceParticleSource<'a, R> {
+    /// Creates a source with chunks of the given number of particles (the bounding boxes of all chunks are computed immediately)
+    pub fn new(particles: &'a [Vector3<R>], chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size has to be positive");
+        let chunk_aabbs = particles
+            .par_chunks(chunk_size)
+            .map(Aabb3d::from_points)
+            .collect();
+        Self {
+            particles,
+            chunk_size,
+            chunk_aabbs,
+        }
+    }
+}
+
+impl<'a, R: Real> ParticleSource<R> for SliceParticleSource<'a, R> {
+    fn num_chunks(&self) -> usize {
+        self.chunk_aabbs.len()
+    }
+
+    fn chunk_aabb(&self, chunk_index: usize) -> Option<Aabb3d<R>> {
+        Some(self.chunk_aabbs[chunk_index].clone())
+    }
+
+    fn read_chunk(
+        &mut self,
+        chunk_index: usize,
+        particles: &mut Vec<Vector3<R>>,
+    ) -> Result<(), anyhow::Error> {
+        let start = chunk_index * self.chunk_size;
+        let end = (start + self.chunk_size).min(self.particles.len());
+        particles.clear();
+        particles.extend_from_slice(&self.particles[start..end]);
+        Ok(())
+    }
+}
+
+/// Parameters for the streaming reconstruction
+#[derive(Clone, Debug)]
+pub struct StreamingParameters {
+    /// Approximate number of bytes that may be used for the particle data of a batch of subdomains
+    ///
+    /// This includes the particle positions, densities and per-subdomain particle lists of all
+    /// subdomains of a batch and their neighbors but not the per-thread workspaces of the
+    /// subdomains and the surface patches of the batch. A batch always consists of at least one
+    /// subdomain, even if its particle data exceeds the budget.
+    pub memory_budget: usize,
+}
+
+impl Default for StreamingParameters {
+    fn default() -> Self {
+        Self {
+            memory_budget: 1 << 30,
+        }
+    }
+}
+
+/// Summary of a streaming surface reconstruction
+#[derive(Clone, Debug)]
+pub struct StreamingReconstruction<I: Index, R: Real> {
+    grid: UniformGrid<I, R>,
+    num_batches: usize,
+    num_vertices: usize,
+    num_triangles: usize,
+}
+
+impl<I: Index, R: Real> StreamingReconstruction<I, R> {
+    /// Returns a reference to the global marching cubes grid that was used for the reconstruction
+    pub fn grid(&self) -> &UniformGrid<I, R> {
+        &self.grid
+    }
+
+    /// Returns the number of batches of subdomains that were processed
+    pub fn num_batches(&self) -> usize {
+        self.num_batches
+    }
+
+    /// Returns the total number of vertices written to the mesh sink
+    pub fn num_vertices(&self) -> usize {
+        self.num_vertices
+    }
+
+    /// Returns the total number of triangles written to the mesh sink
+    pub fn num_triangles(&self) -> usize {
+        self.num_triangles
+    }
+}
+
+/// Performs a surface reconstruction of the particles of the source in batches of subdomains and writes the mesh incrementally to the sink
+///
+/// Requires the [`SpatialDecomposition::UniformGrid`] decomposition. The resulting surface is the same
+/// as the one of [`reconstruct_surface`](crate::reconstruct_surface), only the order of the vertices and
+/// triangles differs. With surface nets, every quad is written as two consecutive triangles.
+/// Per-particle properties, particle densities, neighborhood lists and density grids are not supported.
+/// Returns an error if a [solid boundary](crate::Parameters::solid_boundary) is specified.
+pub fn reconstruct_surface_streaming<
+    I: Index,
+    R: Real,
+    S: ParticleSource<R> + ?Sized,
+    M: MeshSink<R> + ?Sized,
+>(
+    particle_source: &mut S,
+    parameters: &Parameters<R>,
+    streaming_parameters: &StreamingParameters,
+    mesh_sink: &mut M,
+) -> Result<StreamingReconstruction<I, R>, ReconstructionError<I, R>> {
+    reconstruct_surface_streaming_impl(
+        particle_source,
+        parameters,
+        streaming_parameters,
+        &ReconstructionContext::default(),
+        mesh_sink,
+    )
+}
+
+/// Performs a streaming surface reconstruction, reporting its progress to the observer of the given context
+///
+/// Behaves like [`reconstruct_surface_streaming`] but additionally notifies the observer of the
+/// `context` about the stages and the number of processed subdomains of every batch. The cancellation
+/// token of the `context` is checked before every batch and while processing its subdomains. If it is
+/// cancelled, the reconstruction is aborted as soon as possible and [`ReconstructionError::Cancelled`]
+/// is returned. In this case, the mesh sink may already contain the surface of the previous batches.
+///
+/// If a thread pool was set for the `context`, all parallel computations of the reconstruction run
+/// in this thread pool instead of the global rayon thread pool. As the whole reconstruction is
+/// executed in the thread pool, the particle source and the mesh sink have to be [`Send`].
+pub fn reconstruct_surface_streaming_with_context<
+    I: Index,
+    R: Real,
+    S: ParticleSource<R> + Send + ?Sized,
+    M: MeshSink<R> + Send + ?Sized,
+>(
+    particle_source: &mut S,
+    parameters: &Parameters<R>,
+    streaming_parameters: &StreamingParameters,
+    context: &ReconstructionContext,
+    mesh_sink: &mut M,
+) -> Result<StreamingReconstruction<I, R>, ReconstructionError<I, R>> {
+    context.install(|| {
+        reconstruct_surface_streaming_impl(
+            particle_source,
+            parameters,
+            streaming_parameters,
+            context,
+            mesh_sink,
+        )
+    })
+}
+
+/// Implementation of [`reconstruct_surface_streaming_with_context`], runs in the thread pool of the context
+fn reconstruct_surface_streaming_impl<
+    I: Index,
+    R: Real,
+    S: ParticleSource<R> + ?Sized,
+    M: MeshSink<R> + ?Sized,
+>(
+    particle_source: &mut S,
+    parameters: &Parameters<R>,
+    streaming_parameters: &StreamingParameters,
+    context: &ReconstructionContext,
+    mesh_sink: &mut M,
+) -> Result<StreamingReconstruction<I, R>, ReconstructionError<I, R>> {
+    profile!("reconstruct_surface_streaming");
+    context.check_cancelled()?;
+
+    match &parameters.spatial_decomposition {
+        Some(SpatialDecomposition::UniformGrid(_)) => {}
+        _ => {
+            return Err(anyhow!(
+                "the streaming reconstruction requires the uniform subdomain-grid spatial decomposition"
+            )
+            .into())
+        }
+    }
+    if parameters.solid_boundary.is_some() {
+        return Err(
+            anyhow!("solid boundaries are not supported by the streaming reconstruction").into(),
+        );
+    }
+
+    // Neighborhood lists and level-set grids are only used internally per batch
+    let mut parameters = parameters.clone();
+    parameters.global_neighborhood_list = false;
+    parameters.density_grid = false;
+    let parameters = &parameters;
+
+    let particle_aabb = if let Some(particle_aabb) = &parameters.particle_aabb {
+        particle_aabb.clone()
+    } else {
+        profile!("compute minimum enclosing aabb");
+
+        let mut particle_aabb: Option<Aabb3d<R>> = None;
+        let mut chunk = Vec::new();
+        for chunk_index in 0..particle_source.num_chunks() {
+            particle_source
+                .read_chunk(chunk_index, &mut chunk)
+                .with_context(|| format!("failed to read particle chunk {}", chunk_index))?;
+            if chunk.is_empty() {
+                continue;
+            }
+
+            let chunk_aabb = if parameters.enable_multi_threading {
+                Aabb3d::par_from_points(&chunk)
+            } else {
+                Aabb3d::from_points(&chunk)
+            };
+            match &mut particle_aabb {
+                Some(particle_aabb) => particle_aabb.join(&chunk_aabb),
+                None => particle_aabb = Some(chunk_aabb),
+            }
+        }
+
+        let mut particle_aabb = particle_aabb
+            .ok_or_else(|| anyhow!("the particle source does not contain any particles"))?;
+        particle_aabb.grow_uniformly(parameters.particle_radius);
+        info!(
+            "Minimal enclosing bounding box of particles was computed as: {:?}",
+            particle_aabb
+        );
+        particle_aabb
+    };
+
+    let grid = grid_for_reconstruction::<I, R>(
+        &[],
+        parameters.particle_radius,
+        parameters.kernel_extent(),
+        parameters.cube_size,
+        Some(&particle_aabb),
+        parameters.enable_multi_threading,
+    )?;
+    grid.log_grid_info();
+
+    let internal_parameters = initialize_parameters(parameters, &grid)?;
+    let grid = internal_parameters
+        .global_marching_cubes_grid()
+        .context("failed to convert global marching cubes grid")?;
+
+    // Particles outside of the user provided AABB are filtered out
+    let particle_filter = parameters.particle_aabb.as_ref();
+
+    let per_subdomain_count =
+        count_subdomain_particles(&internal_parameters, particle_source, particle_filter)?;
+
+    let max_batch_particles =
+        (streaming_parameters.memory_budget / bytes_per_particle(&internal_parameters)).max(1);
+    let batches = plan_batches(
+        &internal_parameters,
+        &per_subdomain_count,
+        max_batch_particles,
+    );
+    info!(
+        "Streaming reconstruction of {} non-empty subdomains in {} batches (at most {} particles per batch).",
+        per_subdomain_count.len(),
+        batches.len(),
+        max_batch_particles
+    );
+
+    let mut stitcher = IncrementalStitcher::new();
+    let mut batch_particles = Vec::new();
+    for (batch_index, batch) in batches.iter().enumerate() {
+        profile!("process batch");
+        context.check_cancelled()?;
+        info!(
+            "Processing batch {}/{} with {} subdomains and {} required particles.",
+            batch_index + 1,
+            batches.len(),
+            batch.subdomains.len(),
+            batch.particle_count
+        );
+
+        load_batch_particles(
+            &internal_parameters,
+            particle_source,
+            particle_filter,
+            batch,
+            &mut batch_particles,
+        )?;
+        let surface_patches =
+            reconstruct_batch(&internal_parameters, &batch_particles, batch, context)?;
+
+        {
+            profile!("stitching");
+            context.notify_stage(ReconstructionStage::Stitching);
+            for patch in &surface_patches {
+                stitcher.stitch_patch(patch, mesh_sink)?;
+            }
+        }
+
+        let last_subdomain = *batch
+            .subdomains
+            .last()
+            .expect("a batch has to contain at least one subdomain");
+        stitcher.forget_exterior_vertices_before(&internal_parameters, last_subdomain);
+    }
+
+    info!(
+        "Streamed mesh has {} vertices and {} triangles.",
+        stitcher.num_vertices(),
+        stitcher.num_triangles()
+    );
+
+    Ok(StreamingReconstruction {
+        grid,
+        num_batches: batches.len(),
+        num_vertices: stitcher.num_vertices(),
+        num_triangles: stitcher.num_triangles(),
+    })
+}
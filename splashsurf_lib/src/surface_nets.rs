@@ -0,0 +1,299 @@
+//! Extraction of iso-surfaces from [`DensityMap`]s using surface nets
+//!
+//! In contrast to marching cubes, surface nets place exactly one vertex in every cell of the
+//! background grid that is crossed by the iso-surface (at the mean of the iso-surface crossings on
+//! the edges of the cell). For every grid edge crossed by the iso-surface, a quad connecting the
+//! vertices of the four cells around this edge is generated. This results in quad meshes with
+//! significantly fewer and better shaped elements than the triangle meshes of marching cubes.
+//!
+//! The quads can be split into triangles along their shorter diagonal using [`triangulate_quads`].
+
+use crate::mesh::{MixedTriQuadMesh3d, TriangleOrQuadCell};
+use crate::topology::Axis;
+use crate::uniform_grid::UniformGrid;
+use crate::{new_map, profile, DensityMap, Index, MapType, Real};
+use nalgebra::Vector3;
+
+/// Extracts a quad mesh of the iso-surface of a density map on the given background grid using surface nets
+///
+/// Points of the background grid without a value in the density map are considered to have a density of zero.
+/// The quads are oriented such that their normals point to the outside of the fluid (i.e. to lower densities).
+pub fn extract_density_map<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+    iso_surface_threshold: R,
+) -> MixedTriQuadMesh3d<R> {
+    profile!("surface_nets::extract_density_map");
+
+    let mut vertices = Vec::new();
+    let mut quads = Vec::new();
+    extract_density_map_append(
+        grid,
+        density_map,
+        iso_surface_threshold,
+        &mut vertices,
+        &mut quads,
+    );
+
+    MixedTriQuadMesh3d {
+        vertices,
+        cells: quads.into_iter().map(TriangleOrQuadCell::Quad).collect(),
+    }
+}
+
+/// Extracts a quad mesh of the iso-surface of a density map using surface nets, appends the vertices and quads to the given vectors
+pub fn extract_density_map_append<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+    iso_surface_threshold: R,
+    vertices: &mut Vec<Vector3<R>>,
+    quads: &mut Vec<[usize; 4]>,
+) {
+    profile!("surface_nets::extract_density_map_append");
+
+    let value_at = |ijk: &[I; 3]| -> R {
+        density_map
+            .get(grid.flatten_point_index_array(ijk))
+            .unwrap_or_else(R::zero)
+    };
+    let is_inside = |value: R| value > iso_surface_threshold;
+    let missing_value_inside = is_inside(R::zero());
+
+    // Every crossed edge has at least one end point with a value in the density map
+    let mut crossed_edges = Vec::new();
+    {
+        profile!("find crossed edges");
+        density_map.for_each(|flat_point_index, value| {
+            let point = grid
+                .try_unflatten_point_index(flat_point_index)
+                .expect("density map contains a point that is not part of the grid");
+            let point_ijk = *point.index();
+            let point_inside = is_inside(value);
+
+            for &axis in Axis::all_possible() {
+                let dim = axis.dim();
+
+                // Edge starting at this point
+                let mut target_ijk = point_ijk;
+                target_ijk[dim] += I::one();
+                if grid.point_exists(&target_ijk)
+                    && point_inside != is_inside(value_at(&target_ijk))
+                {
+                    crossed_edges.push((point_ijk, axis, point_inside));
+                }
+
+                // Edge ending at this point, only visited from here if its origin has no density value
+                if point_ijk[dim] > I::zero() {
+                    let mut origin_ijk = point_ijk;
+                    origin_ijk[dim] -= I::one();
+                    if missing_value_inside != point_inside
+                        && density_map
+                            .get(grid.flatten_point_index_array(&origin_ijk))
+                            .is_none()
+                    {
+                        crossed_edges.push((origin_ijk, axis, missing_value_inside));
+                    }
+                }
+            }
+        });
+        // The iteration order of the density map is not deterministic
+        crossed_edges.sort_unstable();
+    }
+
+    profile!("generate quads");
+    let mut cell_to_vertex: MapType<I, usize> = new_map();
+    for (origin_ijk, axis, origin_inside) in crossed_edges {
+        // Skip edges on the boundary of the grid
+        let Some(cells) = cells_around_edge(&origin_ijk, axis) else {
+            continue;
+        };
+        if !cells.iter().all(|cell_ijk| grid.cell_exists(cell_ijk)) {
+            continue;
+        }
+
+        let quad = cells.map(|cell_ijk| {
+            *cell_to_vertex
+                .entry(grid.flatten_cell_index_array(&cell_ijk))
+                .or_insert_with(|| {
+                    let mut corner_values = [R::zero(); 8];
+                    for (corner, corner_value) in corner_values.iter_mut().enumerate() {
+                        let mut corner_ijk = cell_ijk;
+                        for (dim, index) in corner_ijk.iter_mut().enumerate() {
+                            if corner & (1 << dim) != 0 {
+                                *index += I::one();
+                            }
+                        }
+                        *corner_value = value_at(&corner_ijk);
+                    }
+
+                    let offset = cell_vertex_offset(&corner_values, iso_surface_threshold).expect(
+                        "a cell around a crossed edge has to be crossed by the iso-surface",
+                    );
+                    vertices
+                        .push(grid.point_coordinates_array(&cell_ijk) + offset * grid.cell_size());
+                    vertices.len() - 1
+                })
+        });
+
+        quads.push(orient_quad(quad, origin_inside));
+    }
+}
+
+/// Splits all quads into two triangles each along their shorter diagonal (see [`triangulate_quad`])
+pub fn triangulate_quads<R: Real>(
+    vertices: &[Vector3<R>],
+    quads: &[[usize; 4]],
+) -> Vec<[usize; 3]> {
+    quads
+        .iter()
+        .flat_map(|quad| triangulate_quad(vertices, quad))
+        .collect()
+}
+
+/// Splits a quad into two triangles along its shorter diagonal
+///
+/// The triangles have the same orientation as the quad. They are returned such that
+/// `[t0[0], t0[1], t0[2], t1[2]]` is a cyclic rotation of the input quad.
+pub fn triangulate_quad<R: Real>(vertices: &[Vector3<R>], quad: &[usize; 4]) -> [[usize; 3]; 2] {
+    let [a, b, c, d] = *quad;
+    let diagonal_ac = (vertices[c] - vertices[a]).norm_squared();
+    let diagonal_bd = (vertices[d] - vertices[b]).norm_squared();
+    if diagonal_ac <= diagonal_bd {
+        [[a, b, c], [a, c, d]]
+    } else {
+        [[b, c, d], [b, d, a]]
+    }
+}
+
+/// Reassembles the quads from pairs of consecutive triangles generated by [`triangulate_quad`]
+pub(crate) fn quads_from_triangle_pairs(triangles: &[[usize; 3]]) -> Vec<[usize; 4]> {
+    let pairs = triangles.chunks_exact(2);
+    assert!(
+        pairs.remainder().is_empty(),
+        "number of triangles has to be even to be reassembled to quads"
+    );
+    pairs
+        .map(|pair| [pair[0][0], pair[0][1], pair[0][2], pair[1][2]])
+        .collect()
+}
+
+/// Returns the minimum corner point indices of the four cells around the edge with the given origin point along the given axis
+///
+/// The cells are returned in counter-clockwise order when looking from the positive side of the
+/// edge axis onto the edge. Returns `None` if one of the cells would have a negative index.
+pub(crate) fn cells_around_edge<I: Index>(origin_ijk: &[I; 3], axis: Axis) -> Option<[[I; 3]; 4]> {
+    let [b, c] = axis.orthogonal_axes().map(|axis| axis.dim());
+    if origin_ijk[b] == I::zero() || origin_ijk[c] == I::zero() {
+        return None;
+    }
+
+    let step_back = |ijk: [I; 3], dim: usize| {
+        let mut ijk = ijk;
+        ijk[dim] -= I::one();
+        ijk
+    };
+
+    Some([
+        step_back(step_back(*origin_ijk, b), c),
+        step_back(*origin_ijk, c),
+        *origin_ijk,
+        step_back(*origin_ijk, b),
+    ])
+}
+
+/// Orients a quad of the cells around a crossed edge (in the order of [`cells_around_edge`]) such that its normal points to the outside of the fluid
+pub(crate) fn orient_quad(quad: [usize; 4], origin_inside: bool) -> [usize; 4] {
+    if origin_inside {
+        // The outside is in positive direction of the edge axis
+        quad
+    } else {
+        let [a, b, c, d] = quad;
+        [a, d, c, b]
+    }
+}
+
+/// Computes the position of the surface nets vertex of a cell relative to its minimum corner (in multiples of the cell size)
+///
+/// The corner values have to be ordered by `i + 2 * j + 4 * k` where `i, j, k ∈ {0, 1}` are the
+/// offsets of the corner from the minimum corner of the cell. The vertex is placed at the mean of
+/// the linearly interpolated iso-surface crossings on the edges of the cell.
+/// Returns `None` if the cell is not crossed by the iso-surface.
+pub(crate) fn cell_vertex_offset<R: Real>(
+    corner_values: &[R; 8],
+    iso_surface_threshold: R,
+) -> Option<Vector3<R>> {
+    let mut crossing_sum = Vector3::zeros();
+    let mut crossing_count = 0;
+
+    for (corner, &origin_value) in corner_values.iter().enumerate() {
+        for dim in 0..3 {
+            // Visit every edge only from its origin corner
+            if corner & (1 << dim) != 0 {
+                continue;
+            }
+
+            let target_value = corner_values[corner | (1 << dim)];
+            if (origin_value > iso_surface_threshold) != (target_value > iso_surface_threshold) {
+                let alpha = (iso_surface_threshold - origin_value) / (target_value - origin_value);
+
+                let mut crossing = Vector3::from_fn(|d, _| {
+                    if corner & (1 << d) != 0 {
+                        R::one()
+                    } else {
+                        R::zero()
+                    }
+                });
+                crossing[dim] = alpha;
+
+                crossing_sum += crossing;
+                crossing_count += 1;
+            }
+        }
+    }
+
+    (crossing_count > 0).then(|| crossing_sum / R::from_usize(crossing_count).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_vertex_offset() {
+        // No crossing
+        assert_eq!(cell_vertex_offset(&[1.0; 8], 0.5), None);
+        assert_eq!(cell_vertex_offset(&[0.0; 8], 0.5), None);
+
+        // Plane orthogonal to the z-axis at the center of the cell
+        let values = [1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+        let offset = cell_vertex_offset(&values, 0.5).unwrap();
+        assert!((offset - Vector3::new(0.5, 0.5, 0.5)).norm() < 1e-12);
+
+        // Single inside corner
+        let mut values = [0.0; 8];
+        values[0] = 1.0;
+        let offset = cell_vertex_offset(&values, 0.75).unwrap();
+        assert!((offset - Vector3::new(0.25, 0.25, 0.25) / 3.0).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_triangulate_quad() {
+        // A rhombus where the diagonal between the vertices 1 and 3 is shorter
+        let vertices = vec![
+            Vector3::new(-2.0, 0.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ];
+
+        let triangles = triangulate_quad(&vertices, &[0, 1, 2, 3]);
+        assert_eq!(triangles, [[1, 2, 3], [1, 3, 0]]);
+        assert_eq!(quads_from_triangle_pairs(&triangles), vec![[1, 2, 3, 0]]);
+
+        // Both triangles keep the orientation of the quad
+        for tri in triangles {
+            let [a, b, c] = tri.map(|v| vertices[v]);
+            assert!((b - a).cross(&(c - a)).z > 0.0);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! Workspace for reusing allocated memory between multiple surface reconstructions
 
+use crate::anisotropic_kernel::AnisotropicKernel;
 use crate::Real;
 use nalgebra::Vector3;
 use std::cell::RefCell;
@@ -51,6 +52,8 @@ pub(crate) struct LocalReconstructionWorkspace<R: Real> {
     pub particle_neighbor_lists: Vec<Vec<usize>>,
     /// Storage for per particle densities
     pub particle_densities: Vec<R>,
+    /// Storage for per particle anisotropic kernels (only used if anisotropic kernels are enabled)
+    pub anisotropic_kernels: Vec<AnisotropicKernel<R>>,
 }
 
 impl<R: Real> Default for LocalReconstructionWorkspace<R> {
@@ -66,6 +69,7 @@ impl<R: Real> LocalReconstructionWorkspace<R> {
         Self {
             particle_neighbor_lists: Default::default(),
             particle_densities: Default::default(),
+            anisotropic_kernels: Default::default(),
         }
     }
 
@@ -74,6 +78,7 @@ impl<R: Real> LocalReconstructionWorkspace<R> {
         Self {
             particle_neighbor_lists: Vec::with_capacity(capacity),
             particle_densities: Vec::with_capacity(capacity),
+            anisotropic_kernels: Default::default(),
         }
     }
 }
@@ -0,0 +1,181 @@
+//! Helper functions shared by the integration tests
+
+use nalgebra::Vector3;
+use parking_lot::Mutex;
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::postprocessing::loop_subdivision;
+use splashsurf_lib::{
+    reconstruct_surface, CancellationToken, GridDecompositionParameters, KernelType, Parameters,
+    ReconstructionObserver, ReconstructionStage, SpatialDecomposition, SurfaceExtractionMethod,
+};
+
+/// Generates a ball of particles on a regular lattice with the given spacing
+pub fn particle_ball(center: Vector3<f64>, radius: f64, spacing: f64) -> Vec<Vector3<f64>> {
+    let n = (radius / spacing).ceil() as i32;
+    let mut particles = Vec::new();
+    for i in -n..=n {
+        for j in -n..=n {
+            for k in -n..=n {
+                let p = Vector3::new(i as f64, j as f64, k as f64) * spacing;
+                if p.norm() <= radius {
+                    particles.push(center + p);
+                }
+            }
+        }
+    }
+    particles
+}
+
+/// Generates a cuboid block of particles on a regular lattice with the given spacing
+pub fn particle_block(n: [usize; 3], spacing: f64) -> Vec<Vector3<f64>> {
+    let mut particles = Vec::with_capacity(n[0] * n[1] * n[2]);
+    for i in 0..n[0] {
+        for j in 0..n[1] {
+            for k in 0..n[2] {
+                particles.push(Vector3::new(i as f64, j as f64, k as f64) * spacing);
+            }
+        }
+    }
+    particles
+}
+
+/// Returns single-threaded parameters for the given particle radius, optionally with a subdomain grid of 32 cubes per subdomain
+pub fn block_parameters(particle_radius: f64, subdomain_grid: bool) -> Parameters<f64> {
+    Parameters {
+        enable_multi_threading: false,
+        spatial_decomposition: subdomain_grid.then(|| {
+            SpatialDecomposition::UniformGrid(GridDecompositionParameters {
+                subdomain_num_cubes_per_dim: 32,
+            })
+        }),
+        ..Parameters::new(
+            particle_radius,
+            4.0 * particle_radius,
+            0.75 * particle_radius,
+        )
+    }
+}
+
+/// Returns the difference of two counts relative to the larger one
+pub fn relative_difference(a: usize, b: usize) -> f64 {
+    (a as f64 - b as f64).abs() / (a.max(b) as f64)
+}
+
+/// Returns marching cubes parameters for particles with a radius of 0.025 without domain decomposition
+pub fn default_parameters() -> Parameters<f64> {
+    let particle_radius = 0.025;
+    Parameters {
+        particle_radius,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * particle_radius,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::MarchingCubes,
+        cube_size: 0.75 * particle_radius,
+        iso_surface_threshold: 0.6,
+        particle_aabb: None,
+        enable_multi_threading: true,
+        spatial_decomposition: None,
+        anisotropic_kernel: None,
+        global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
+    }
+}
+
+/// Reconstructs the marching cubes surface of a ball of particles with a radius of 0.4
+pub fn ball_mesh() -> TriMesh3d<f64> {
+    let parameters = default_parameters();
+    let parameters = Parameters {
+        cube_size: 0.5 * parameters.particle_radius,
+        ..parameters
+    };
+
+    let particles = particle_ball(Vector3::zeros(), 0.4, 0.04);
+    reconstruct_surface::<i64, _>(&particles, &parameters)
+        .unwrap()
+        .mesh()
+        .clone()
+}
+
+/// Regular triangulation of the unit square in the xy-plane with `n x n` quads
+pub fn plane_mesh(n: usize) -> TriMesh3d<f64> {
+    let mut mesh = TriMesh3d::default();
+    for i in 0..=n {
+        for j in 0..=n {
+            mesh.vertices
+                .push(Vector3::new(i as f64, j as f64, 0.0) / n as f64);
+        }
+    }
+    let idx = |i: usize, j: usize| i * (n + 1) + j;
+    for i in 0..n {
+        for j in 0..n {
+            mesh.triangles
+                .push([idx(i, j), idx(i + 1, j), idx(i + 1, j + 1)]);
+            mesh.triangles
+                .push([idx(i, j), idx(i + 1, j + 1), idx(i, j + 1)]);
+        }
+    }
+    mesh
+}
+
+/// Closed triangulation of a sphere around the origin with outward facing triangles, obtained by
+/// subdividing an octahedron
+pub fn sphere_mesh(radius: f64, subdivisions: usize) -> TriMesh3d<f64> {
+    let octahedron = TriMesh3d {
+        vertices: vec![
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        ],
+        triangles: vec![
+            [0, 2, 4],
+            [2, 1, 4],
+            [1, 3, 4],
+            [3, 0, 4],
+            [2, 0, 5],
+            [1, 2, 5],
+            [3, 1, 5],
+            [0, 3, 5],
+        ],
+    };
+    let mut mesh = loop_subdivision(&octahedron, subdivisions, false);
+    mesh.vertices
+        .iter_mut()
+        .for_each(|v| *v = v.normalize() * radius);
+    mesh
+}
+
+/// Observer that records all notifications and optionally cancels the reconstruction in the given stage
+#[derive(Default)]
+pub struct RecordingObserver {
+    pub stages: Mutex<Vec<ReconstructionStage>>,
+    /// Largest number of processed subdomains and total number of subdomains per stage
+    pub progress: Mutex<Vec<(ReconstructionStage, usize, usize)>>,
+    pub cancel_in_stage: Option<(ReconstructionStage, CancellationToken)>,
+}
+
+impl ReconstructionObserver for RecordingObserver {
+    fn stage_started(&self, stage: ReconstructionStage) {
+        self.stages.lock().push(stage);
+    }
+
+    fn subdomains_processed(&self, stage: ReconstructionStage, processed: usize, total: usize) {
+        assert!(processed <= total);
+        let mut progress = self.progress.lock();
+        if let Some(entry) = progress.iter_mut().find(|(s, _, _)| *s == stage) {
+            assert_eq!(entry.2, total);
+            entry.1 = entry.1.max(processed);
+        } else {
+            progress.push((stage, processed, total));
+        }
+
+        if let Some((cancel_stage, token)) = &self.cancel_in_stage {
+            if *cancel_stage == stage {
+                token.cancel();
+            }
+        }
+    }
+}
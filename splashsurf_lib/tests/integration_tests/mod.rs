@@ -1,5 +1,28 @@
+pub mod common;
+pub mod test_anisotropic_kernel;
+pub mod test_boundary;
+pub mod test_bvh;
+pub mod test_context;
+pub mod test_curvature;
+pub mod test_decimation;
+pub mod test_density_grid;
 #[cfg(feature = "io")]
 pub mod test_full;
+pub mod test_hole_filling;
+pub mod test_index_type;
+pub mod test_kernel_types;
+pub mod test_level_set;
 #[cfg(feature = "io")]
 pub mod test_mesh;
+pub mod test_multiphase;
 pub mod test_neighborhood_search;
+pub mod test_octree;
+pub mod test_particle_properties;
+pub mod test_progress;
+pub mod test_remeshing;
+pub mod test_sequential;
+pub mod test_signed_distance;
+pub mod test_smoothing;
+pub mod test_streaming;
+pub mod test_subdivision;
+pub mod test_surface_nets;
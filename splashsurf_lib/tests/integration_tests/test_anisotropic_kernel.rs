@@ -0,0 +1,82 @@
+use super::common::{block_parameters, particle_block, relative_difference};
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::{
+    reconstruct_surface, reconstruct_surface_with_properties, AnisotropicKernelParameters,
+    Parameters, ParticleProperties, SurfaceReconstruction,
+};
+
+fn parameters(anisotropic: bool, subdomain_grid: bool) -> Parameters<f64> {
+    Parameters {
+        anisotropic_kernel: anisotropic.then(AnisotropicKernelParameters::default),
+        ..block_parameters(0.025, subdomain_grid)
+    }
+}
+
+/// Returns the extent of the AABB of the mesh vertices along the given axis
+fn mesh_extent(reconstruction: &SurfaceReconstruction<i64, f64>, axis: usize) -> f64 {
+    let (min, max) = reconstruction
+        .mesh()
+        .vertices
+        .iter()
+        .map(|v| v[axis])
+        .fold((f64::MAX, f64::MIN), |(min, max), x| {
+            (min.min(x), max.max(x))
+        });
+    max - min
+}
+
+#[test]
+fn test_anisotropic_kernel_thin_sheet() {
+    // A thin sheet of three particle layers
+    let particles = particle_block([24, 24, 3], 0.05);
+
+    let isotropic = reconstruct_surface::<i64, _>(&particles, &parameters(false, false)).unwrap();
+    let global = reconstruct_surface::<i64, _>(&particles, &parameters(true, false)).unwrap();
+    let grid = reconstruct_surface::<i64, _>(&particles, &parameters(true, true)).unwrap();
+
+    for reconstruction in [&global, &grid] {
+        assert!(!reconstruction.mesh().triangles.is_empty());
+        check_mesh_consistency(
+            reconstruction.grid(),
+            reconstruction.mesh(),
+            true,
+            true,
+            false,
+        )
+        .unwrap();
+
+        // The anisotropic kernels are compressed orthogonal to the sheet, so it should get thinner
+        assert!(mesh_extent(reconstruction, 2) < mesh_extent(&isotropic, 2));
+    }
+
+    // Both spatial decompositions should produce roughly the same surface
+    assert!(relative_difference(global.mesh().triangles.len(), grid.mesh().triangles.len()) < 2e-2);
+}
+
+#[test]
+fn test_anisotropic_kernel_with_particle_properties() {
+    let particle_radius = 0.025;
+    let particles = particle_block([16, 16, 4], 2.0 * particle_radius);
+    let radii = vec![particle_radius; particles.len()];
+    let properties = ParticleProperties {
+        radii: Some(&radii),
+        ..Default::default()
+    };
+
+    for subdomain_grid in [false, true] {
+        let parameters = parameters(true, subdomain_grid);
+        let reference = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+
+        let reconstruction =
+            reconstruct_surface_with_properties::<i64, _>(&particles, &properties, &parameters)
+                .unwrap();
+
+        // Per-particle properties that match the global parameters should reproduce the same surface
+        assert!(
+            relative_difference(
+                reference.mesh().triangles.len(),
+                reconstruction.mesh().triangles.len()
+            ) < 1e-2
+        );
+    }
+}
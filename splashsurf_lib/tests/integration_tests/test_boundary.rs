@@ -0,0 +1,239 @@
+use nalgebra::Vector3;
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::streaming::{
+    reconstruct_surface_streaming, SliceParticleSource, StreamingParameters,
+};
+use splashsurf_lib::{
+    reconstruct_surface, BoundaryParticles, GridDecompositionParameters, KernelType, Parameters,
+    SolidBoundary, SpatialDecomposition, SurfaceExtractionMethod,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const PARTICLE_RADIUS: f64 = 0.025;
+
+/// Generates a box of fluid particles (with approximately the rest density) resting on the floor `z = 0`
+fn fluid_particles() -> Vec<Vector3<f64>> {
+    let spacing = (4.0 / 3.0 * std::f64::consts::PI).cbrt() * PARTICLE_RADIUS;
+    let mut particles = Vec::new();
+    for i in 0..12 {
+        for j in 0..12 {
+            for k in 0..6 {
+                particles.push(
+                    Vector3::new(i as f64, j as f64, k as f64) * spacing
+                        + Vector3::repeat(PARTICLE_RADIUS),
+                );
+            }
+        }
+    }
+    particles
+}
+
+/// Returns a mesh of the floor `z = 0` below the fluid
+fn floor_mesh() -> TriMesh3d<f64> {
+    let (min, max) = (-0.2, 0.8);
+    TriMesh3d {
+        vertices: vec![
+            Vector3::new(min, min, 0.0),
+            Vector3::new(max, min, 0.0),
+            Vector3::new(max, max, 0.0),
+            Vector3::new(min, max, 0.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 2, 3]],
+    }
+}
+
+fn parameters(
+    spatial_decomposition: Option<SpatialDecomposition>,
+    solid_boundary: Option<SolidBoundary<f64>>,
+) -> Parameters<f64> {
+    Parameters {
+        particle_radius: PARTICLE_RADIUS,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * PARTICLE_RADIUS,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::MarchingCubes,
+        cube_size: 0.5 * PARTICLE_RADIUS,
+        iso_surface_threshold: 0.6,
+        particle_aabb: None,
+        enable_multi_threading: true,
+        spatial_decomposition,
+        anisotropic_kernel: None,
+        global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary,
+    }
+}
+
+fn subdomain_grid() -> Option<SpatialDecomposition> {
+    Some(SpatialDecomposition::UniformGrid(
+        GridDecompositionParameters {
+            subdomain_num_cubes_per_dim: 24,
+        },
+    ))
+}
+
+/// Returns the largest z-coordinate of all vertices of the lower half of the mesh below the interior of the fluid
+fn max_bottom_vertex_height(mesh: &TriMesh3d<f64>) -> f64 {
+    mesh.vertices
+        .iter()
+        .filter(|v| v.x > 0.1 && v.x < 0.4 && v.y > 0.1 && v.y < 0.4 && v.z < 0.1)
+        .map(|v| v.z)
+        .fold(f64::MIN, f64::max)
+}
+
+#[test]
+fn test_boundary_floor() {
+    let particles = fluid_particles();
+    // Index of a particle of the lowest layer in the interior of the fluid
+    let bottom_particle = 6 * 12 * 6 + 6 * 6;
+
+    for spatial_decomposition in [None, subdomain_grid()] {
+        for enable_multi_threading in [true, false] {
+            let mut params = parameters(spatial_decomposition.clone(), None);
+            params.enable_multi_threading = enable_multi_threading;
+
+            let reference = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+            // Without boundary, the surface closes off above the floor
+            assert!(max_bottom_vertex_height(reference.mesh()) > 0.0);
+            let reference_density = reference.particle_densities().unwrap()[bottom_particle];
+            assert!(reference_density < 900.0);
+
+            for solid_boundary in [
+                SolidBoundary::from_signed_distance(|p: &Vector3<f64>| p.z),
+                SolidBoundary::from_particles(BoundaryParticles::from_mesh(
+                    &floor_mesh(),
+                    PARTICLE_RADIUS,
+                )),
+            ] {
+                let mut params = params.clone();
+                params.solid_boundary = Some(solid_boundary.clone());
+                let reconstruction = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+
+                // The surface has to continue into the floor instead of closing off above of it
+                let max_height = max_bottom_vertex_height(reconstruction.mesh());
+                assert!(
+                    max_height < 0.0,
+                    "surface closes off at z = {} with boundary {:?}",
+                    max_height,
+                    solid_boundary
+                );
+
+                // The boundary compensates the missing neighbors of the particles at the floor
+                let density = reconstruction.particle_densities().unwrap()[bottom_particle];
+                assert!(density > reference_density);
+                if let SolidBoundary::SignedDistance(_) = solid_boundary {
+                    assert!((density - 1000.0).abs() < 50.0);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_boundary_far_away() {
+    let particles = fluid_particles();
+
+    for spatial_decomposition in [None, subdomain_grid()] {
+        let reference = reconstruct_surface::<i64, _>(
+            &particles,
+            &parameters(spatial_decomposition.clone(), None),
+        )
+        .unwrap();
+
+        // A boundary outside of the kernel support of all particles must not change the surface
+        let evaluations = Arc::new(AtomicUsize::new(0));
+        let sdf_evaluations = evaluations.clone();
+        let reconstruction = reconstruct_surface::<i64, _>(
+            &particles,
+            &parameters(
+                spatial_decomposition,
+                Some(SolidBoundary::from_signed_distance(
+                    move |p: &Vector3<f64>| {
+                        sdf_evaluations.fetch_add(1, Ordering::Relaxed);
+                        p.z + 10.0
+                    },
+                )),
+            ),
+        )
+        .unwrap();
+        assert_eq!(reconstruction.mesh().vertices, reference.mesh().vertices);
+        assert_eq!(reconstruction.mesh().triangles, reference.mesh().triangles);
+        assert_eq!(
+            reconstruction.particle_densities().unwrap(),
+            reference.particle_densities().unwrap()
+        );
+
+        // The boundary must not be evaluated for every particle
+        assert!(evaluations.load(Ordering::Relaxed) < particles.len());
+    }
+}
+
+#[test]
+fn test_boundary_translated() {
+    // Far away from the origin, the subdomains are located at a large offset in the global grid
+    let offset = Vector3::new(13.7, -21.3, 42.1);
+    let particles: Vec<_> = fluid_particles().iter().map(|p| p + offset).collect();
+
+    for spatial_decomposition in [None, subdomain_grid()] {
+        let params = parameters(
+            spatial_decomposition,
+            Some(SolidBoundary::from_signed_distance(
+                move |p: &Vector3<f64>| p.z - offset.z,
+            )),
+        );
+        let reconstruction = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+
+        let mesh = TriMesh3d {
+            vertices: reconstruction
+                .mesh()
+                .vertices
+                .iter()
+                .map(|v| v - offset)
+                .collect(),
+            triangles: reconstruction.mesh().triangles.clone(),
+        };
+        assert!(max_bottom_vertex_height(&mesh) < 0.0);
+    }
+}
+
+#[test]
+fn test_boundary_convert() {
+    let particles = fluid_particles();
+    let params = parameters(
+        subdomain_grid(),
+        Some(SolidBoundary::from_signed_distance(|p: &Vector3<f64>| p.z)),
+    );
+
+    let params_f32 = params.try_convert::<f32>().unwrap();
+    let particles_f32: Vec<_> = particles.iter().map(|p| p.cast::<f32>()).collect();
+    let reconstruction = reconstruct_surface::<i64, f32>(&particles_f32, &params_f32).unwrap();
+    let mesh = TriMesh3d {
+        vertices: reconstruction
+            .mesh()
+            .vertices
+            .iter()
+            .map(|v| v.cast::<f64>())
+            .collect(),
+        triangles: reconstruction.mesh().triangles.clone(),
+    };
+    assert!(max_bottom_vertex_height(&mesh) < 0.0);
+}
+
+#[test]
+fn test_boundary_streaming_unsupported() {
+    let particles = fluid_particles();
+    let params = parameters(
+        subdomain_grid(),
+        Some(SolidBoundary::from_signed_distance(|p: &Vector3<f64>| p.z)),
+    );
+
+    let mut mesh = TriMesh3d::default();
+    assert!(reconstruct_surface_streaming::<i64, _, _, _>(
+        &mut SliceParticleSource::new(&particles, 100),
+        &params,
+        &StreamingParameters::default(),
+        &mut mesh,
+    )
+    .is_err());
+}
@@ -0,0 +1,128 @@
+use super::common::sphere_mesh;
+use nalgebra::Vector3;
+use splashsurf_lib::bvh::TriMeshBvh;
+use splashsurf_lib::mesh::{tri_closest_point_barycentric, TriMesh3d};
+
+/// Deterministic pseudo-random points in the cube `[-extent, extent]^3`
+fn sample_points(n: usize, extent: f64) -> Vec<Vector3<f64>> {
+    let mut state = 0x2545f4914f6cdd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    };
+    (0..n)
+        .map(|_| Vector3::new(next(), next(), next()).map(|x| (2.0 * x - 1.0) * extent))
+        .collect()
+}
+
+#[test]
+fn test_bvh_closest_point() {
+    let mesh = sphere_mesh(1.0, 3);
+    let bvh = TriMeshBvh::par_new(&mesh);
+    assert_eq!(
+        bvh.aabb().unwrap(),
+        &TriMeshBvh::new(&mesh).aabb().unwrap().clone()
+    );
+
+    for p in sample_points(200, 2.0) {
+        let closest = bvh.closest_point(&p).unwrap();
+
+        // Compare with a brute force search over all triangles
+        let brute_force_distance = mesh
+            .triangles
+            .iter()
+            .map(|tri| {
+                let [a, b, c] = tri.map(|v| mesh.vertices[v]);
+                let bary = tri_closest_point_barycentric(&p, &a, &b, &c);
+                (a * bary.x + b * bary.y + c * bary.z - p).norm()
+            })
+            .fold(f64::MAX, f64::min);
+        assert!((closest.distance - brute_force_distance).abs() < 1e-12);
+
+        let [a, b, c] = mesh.triangles[closest.triangle].map(|v| mesh.vertices[v]);
+        let bary = closest.barycentric;
+        assert!((bary.sum() - 1.0).abs() < 1e-12);
+        assert!(bary.iter().all(|x| *x >= 0.0));
+        assert!((a * bary.x + b * bary.y + c * bary.z - closest.point).norm() < 1e-12);
+        assert!((closest.distance - (p.norm() - 1.0).abs()).abs() < 0.05);
+    }
+
+    assert!(TriMeshBvh::new(&TriMesh3d::<f64>::default())
+        .closest_point(&Vector3::zeros())
+        .is_none());
+}
+
+#[test]
+fn test_bvh_ray_intersection() {
+    let mesh = sphere_mesh(1.0, 3);
+    let bvh = TriMeshBvh::par_new(&mesh);
+
+    // Rays from outside towards the origin hit the front of the sphere
+    for p in sample_points(100, 1.0) {
+        let origin = p.normalize() * 3.0;
+        let direction = -p.normalize();
+        let hit = bvh.intersect_ray(&origin, &direction, f64::MAX).unwrap();
+        assert!(hit.t >= 2.0 - 1e-12 && hit.t < 2.1);
+        assert!((hit.point - (origin + direction * hit.t)).norm() < 1e-12);
+        assert!(hit.point.dot(&origin) > 0.0);
+
+        let [a, b, c] = mesh.triangles[hit.triangle].map(|v| mesh.vertices[v]);
+        let bary = hit.barycentric;
+        assert!((a * bary.x + b * bary.y + c * bary.z - hit.point).norm() < 1e-12);
+
+        // The ray does not reach the sphere if it is too short
+        assert!(bvh.intersect_ray(&origin, &direction, 1.5).is_none());
+    }
+
+    // Rays from inside hit the sphere exactly once
+    let origin = Vector3::new(0.1, -0.2, 0.3);
+    for p in sample_points(100, 1.0) {
+        let direction = p.normalize();
+        let hit = bvh.intersect_ray(&origin, &direction, f64::MAX).unwrap();
+        assert!((hit.point.norm() - 1.0).abs() < 0.05);
+        assert!(hit.point.dot(&direction) > 0.0);
+    }
+
+    // Rays pointing away from the sphere miss it
+    let miss = bvh.intersect_ray(
+        &Vector3::new(2.0, 0.0, 0.0),
+        &Vector3::new(1.0, 0.0, 0.0),
+        f64::MAX,
+    );
+    assert!(miss.is_none());
+}
+
+#[test]
+fn test_bvh_winding_number() {
+    let mesh = sphere_mesh(1.0, 4);
+    let bvh = TriMeshBvh::par_new(&mesh);
+
+    for p in sample_points(500, 2.0) {
+        // Skip points too close to the surface where the piecewise linear mesh deviates from the sphere
+        if (p.norm() - 1.0).abs() < 0.05 {
+            continue;
+        }
+
+        let inside = p.norm() < 1.0;
+        let winding_number = bvh.winding_number(&p);
+        let expected = if inside { 1.0 } else { 0.0 };
+        assert!(
+            (winding_number - expected).abs() < 0.05,
+            "winding number {} at {:?}",
+            winding_number,
+            p
+        );
+        assert_eq!(bvh.is_inside(&p), inside);
+    }
+
+    // Removing triangles results in fractional winding numbers
+    let mut open_mesh = mesh.clone();
+    open_mesh
+        .triangles
+        .retain(|tri| mesh.vertices[tri[0]].z < 0.5);
+    let open_bvh = TriMeshBvh::new(&open_mesh);
+    let winding_number = open_bvh.winding_number(&Vector3::zeros());
+    assert!(winding_number > 0.5 && winding_number < 0.95);
+}
@@ -0,0 +1,211 @@
+use super::common::{default_parameters, particle_ball, RecordingObserver};
+use nalgebra::Vector3;
+use parking_lot::Mutex;
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::multiphase::{
+    reconstruct_surface_multiphase, reconstruct_surface_multiphase_with_context,
+};
+use splashsurf_lib::streaming::{
+    reconstruct_surface_streaming_with_context, SliceParticleSource, StreamingParameters,
+};
+use splashsurf_lib::{
+    reconstruct_surface, reconstruct_surface_inplace_with_context, CancellationToken,
+    GridDecompositionParameters, Parameters, ReconstructionContext, ReconstructionError,
+    ReconstructionObserver, ReconstructionStage, SpatialDecomposition, SurfaceReconstruction,
+};
+
+fn subdomain_grid_parameters() -> Parameters<f64> {
+    Parameters {
+        spatial_decomposition: Some(SpatialDecomposition::UniformGrid(
+            GridDecompositionParameters {
+                subdomain_num_cubes_per_dim: 16,
+            },
+        )),
+        ..default_parameters()
+    }
+}
+
+/// Thread pool with three named threads to check that all work is executed in it
+fn custom_thread_pool() -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(3)
+        .thread_name(|i| format!("custom-pool-{}", i))
+        .build()
+        .unwrap()
+}
+
+/// Observer that records the names of all threads it is called from
+#[derive(Default)]
+struct ThreadNameObserver {
+    thread_names: Mutex<Vec<Option<String>>>,
+}
+
+impl ThreadNameObserver {
+    fn record(&self) {
+        let name = std::thread::current().name().map(str::to_string);
+        self.thread_names.lock().push(name);
+    }
+}
+
+impl ReconstructionObserver for ThreadNameObserver {
+    fn stage_started(&self, _stage: ReconstructionStage) {
+        self.record();
+    }
+
+    fn subdomains_processed(&self, _stage: ReconstructionStage, _processed: usize, _total: usize) {
+        self.record();
+    }
+}
+
+#[test]
+fn test_custom_thread_pool() {
+    let particles = particle_ball(Vector3::zeros(), 0.5, 0.05);
+    let thread_pool = custom_thread_pool();
+
+    for params in [default_parameters(), subdomain_grid_parameters()] {
+        let observer = ThreadNameObserver::default();
+        let context = ReconstructionContext::new()
+            .with_observer(&observer)
+            .with_thread_pool(&thread_pool);
+        let mut reconstruction = SurfaceReconstruction::<i64, f64>::default();
+        reconstruct_surface_inplace_with_context(
+            &particles,
+            None,
+            &params,
+            &context,
+            &mut reconstruction,
+        )
+        .unwrap();
+
+        // All notifications have to come from the threads of the custom thread pool
+        let thread_names = observer.thread_names.lock();
+        assert!(!thread_names.is_empty());
+        for name in thread_names.iter() {
+            assert!(
+                name.as_deref()
+                    .is_some_and(|name| name.starts_with("custom-pool-")),
+                "observer was called from thread {:?} outside of the custom thread pool",
+                name
+            );
+        }
+
+        // The thread pool must not change the result of the reconstruction
+        let reference = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+        assert_eq!(
+            reconstruction.mesh().triangles.len(),
+            reference.mesh().triangles.len()
+        );
+    }
+
+    // Other functions can be executed in the thread pool of the context
+    let context = ReconstructionContext::new().with_thread_pool(&thread_pool);
+    assert_eq!(context.install(rayon::current_num_threads), 3);
+    assert!(context.install(|| rayon::current_thread_index().is_some()));
+    // Without a thread pool, the operation is executed on the calling thread
+    assert!(ReconstructionContext::new().install(|| rayon::current_thread_index().is_none()));
+}
+
+#[test]
+fn test_streaming_with_context() {
+    let particles = particle_ball(Vector3::zeros(), 0.5, 0.05);
+    let params = subdomain_grid_parameters();
+    // Budget for a few thousand particles per batch
+    let streaming_parameters = StreamingParameters {
+        memory_budget: 500_000,
+    };
+    let thread_pool = custom_thread_pool();
+
+    let observer = ThreadNameObserver::default();
+    let context = ReconstructionContext::new()
+        .with_observer(&observer)
+        .with_thread_pool(&thread_pool);
+    let mut source = SliceParticleSource::new(&particles, 1000);
+    let mut mesh = TriMesh3d::default();
+    let streaming = reconstruct_surface_streaming_with_context::<i64, _, _, _>(
+        &mut source,
+        &params,
+        &streaming_parameters,
+        &context,
+        &mut mesh,
+    )
+    .unwrap();
+    assert!(streaming.num_batches() > 1);
+
+    let thread_names = observer.thread_names.lock();
+    assert!(!thread_names.is_empty());
+    assert!(thread_names.iter().all(|name| name
+        .as_deref()
+        .is_some_and(|name| name.starts_with("custom-pool-"))));
+
+    let reference = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+    assert_eq!(mesh.triangles.len(), reference.mesh().triangles.len());
+
+    // Cancelling in the first batch aborts the remaining batches
+    let token = CancellationToken::new();
+    let observer = RecordingObserver {
+        cancel_in_stage: Some((ReconstructionStage::SurfaceExtraction, token.clone())),
+        ..Default::default()
+    };
+    let mut source = SliceParticleSource::new(&particles, 1000);
+    let result = reconstruct_surface_streaming_with_context::<i64, _, _, _>(
+        &mut source,
+        &params,
+        &streaming_parameters,
+        &ReconstructionContext::new()
+            .with_observer(&observer)
+            .with_cancellation_token(token),
+        &mut TriMesh3d::default(),
+    );
+    assert!(matches!(result, Err(ReconstructionError::Cancelled)));
+    assert_eq!(
+        observer.stages.lock().as_slice(),
+        &[
+            ReconstructionStage::Decomposition,
+            ReconstructionStage::ParticleDensities,
+            ReconstructionStage::SurfaceExtraction,
+        ]
+    );
+}
+
+#[test]
+fn test_multiphase_with_context() {
+    let particles = particle_ball(Vector3::zeros(), 0.3, 0.05);
+    let phase_ids: Vec<u64> = particles.iter().map(|p| (p.x > 0.0) as u64).collect();
+    let params = default_parameters();
+    let thread_pool = custom_thread_pool();
+
+    let observer = ThreadNameObserver::default();
+    let context = ReconstructionContext::new()
+        .with_observer(&observer)
+        .with_thread_pool(&thread_pool);
+    let reconstruction = reconstruct_surface_multiphase_with_context::<i64, _>(
+        &particles, &phase_ids, &params, &context,
+    )
+    .unwrap();
+
+    let thread_names = observer.thread_names.lock();
+    assert!(!thread_names.is_empty());
+    assert!(thread_names.iter().all(|name| name
+        .as_deref()
+        .is_some_and(|name| name.starts_with("custom-pool-"))));
+
+    let reference =
+        reconstruct_surface_multiphase::<i64, _>(&particles, &phase_ids, &params).unwrap();
+    assert_eq!(reconstruction.phases().len(), 2);
+    for (surface, reference_surface) in reconstruction.phases().iter().zip(reference.phases()) {
+        assert_eq!(
+            surface.mesh.triangles.len(),
+            reference_surface.mesh.triangles.len()
+        );
+    }
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let result = reconstruct_surface_multiphase_with_context::<i64, _>(
+        &particles,
+        &phase_ids,
+        &params,
+        &ReconstructionContext::new().with_cancellation_token(token),
+    );
+    assert!(matches!(result, Err(ReconstructionError::Cancelled)));
+}
@@ -0,0 +1,168 @@
+use nalgebra::Vector3;
+use splashsurf_lib::curvature::par_curvature;
+use splashsurf_lib::mesh::{AttributeData, TriMesh3d};
+use std::f64::consts::PI;
+
+/// Triangulated UV sphere with outward facing triangles
+fn uv_sphere(radius: f64, n_theta: usize, n_phi: usize) -> TriMesh3d<f64> {
+    let mut mesh = TriMesh3d::default();
+    mesh.vertices.push(Vector3::new(0.0, 0.0, radius));
+    for i in 1..n_theta {
+        let theta = PI * i as f64 / n_theta as f64;
+        for j in 0..n_phi {
+            let phi = 2.0 * PI * j as f64 / n_phi as f64;
+            mesh.vertices.push(
+                Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                ) * radius,
+            );
+        }
+    }
+    mesh.vertices.push(Vector3::new(0.0, 0.0, -radius));
+
+    let ring = |i: usize, j: usize| 1 + (i - 1) * n_phi + j % n_phi;
+    let south_pole = mesh.vertices.len() - 1;
+    for j in 0..n_phi {
+        mesh.triangles.push([0, ring(1, j), ring(1, j + 1)]);
+        mesh.triangles
+            .push([south_pole, ring(n_theta - 1, j + 1), ring(n_theta - 1, j)]);
+    }
+    for i in 1..n_theta - 1 {
+        for j in 0..n_phi {
+            mesh.triangles
+                .push([ring(i, j), ring(i + 1, j), ring(i + 1, j + 1)]);
+            mesh.triangles
+                .push([ring(i, j), ring(i + 1, j + 1), ring(i, j + 1)]);
+        }
+    }
+    mesh
+}
+
+/// Open cylinder around the z-axis with outward facing triangles
+fn cylinder(radius: f64, n_phi: usize, n_z: usize) -> TriMesh3d<f64> {
+    let dz = 2.0 * PI * radius / n_phi as f64;
+    let mut mesh = TriMesh3d::default();
+    for i in 0..=n_z {
+        for j in 0..n_phi {
+            let phi = 2.0 * PI * j as f64 / n_phi as f64;
+            mesh.vertices.push(Vector3::new(
+                radius * phi.cos(),
+                radius * phi.sin(),
+                i as f64 * dz,
+            ));
+        }
+    }
+
+    let idx = |i: usize, j: usize| i * n_phi + j % n_phi;
+    for i in 0..n_z {
+        for j in 0..n_phi {
+            mesh.triangles
+                .push([idx(i, j), idx(i, j + 1), idx(i + 1, j + 1)]);
+            mesh.triangles
+                .push([idx(i, j), idx(i + 1, j + 1), idx(i + 1, j)]);
+        }
+    }
+    mesh
+}
+
+#[test]
+fn test_curvature_sphere() {
+    let radius = 2.0;
+    let mesh = uv_sphere(radius, 32, 64);
+    let curvature = par_curvature(&mesh);
+
+    // The integrated Gaussian curvature of a closed genus zero surface is exactly 4π (Gauss-Bonnet)
+    let total_gaussian_curvature = curvature
+        .gaussian_curvature
+        .iter()
+        .zip(curvature.vertex_area.iter())
+        .map(|(k, a)| k * a)
+        .sum::<f64>();
+    assert!((total_gaussian_curvature - 4.0 * PI).abs() < 1e-9);
+
+    // The mixed Voronoi areas tile the surface
+    let vertex_area = curvature.vertex_area.iter().sum::<f64>();
+    assert!((vertex_area - mesh.area()).abs() < 1e-9);
+
+    // Area weighted averages match the curvature of the sphere
+    let average = |values: &[f64]| {
+        values
+            .iter()
+            .zip(curvature.vertex_area.iter())
+            .map(|(v, a)| v * a)
+            .sum::<f64>()
+            / vertex_area
+    };
+    let mean_curvature = average(&curvature.mean_curvature);
+    assert!(
+        (mean_curvature - 1.0 / radius).abs() < 0.01 / radius,
+        "mean curvature {}",
+        mean_curvature
+    );
+    let gaussian_curvature = average(&curvature.gaussian_curvature);
+    assert!((gaussian_curvature - 1.0 / (radius * radius)).abs() < 0.01 / (radius * radius));
+    for (k_max, k_min) in curvature
+        .max_principal_curvature
+        .iter()
+        .zip(curvature.min_principal_curvature.iter())
+    {
+        assert!(k_max >= k_min);
+    }
+
+    // Sequential and parallel computation give the same results
+    let sequential_curvature = splashsurf_lib::curvature::curvature(&mesh);
+    assert_eq!(
+        curvature.mean_curvature,
+        sequential_curvature.mean_curvature
+    );
+    assert_eq!(
+        curvature.max_principal_direction,
+        sequential_curvature.max_principal_direction
+    );
+}
+
+#[test]
+fn test_curvature_cylinder() {
+    let radius = 0.5;
+    let n_phi = 64;
+    let n_z = 16;
+    let mesh = cylinder(radius, n_phi, n_z);
+    let curvature = par_curvature(&mesh);
+
+    for (v, pos) in mesh.vertices.iter().enumerate() {
+        let on_boundary = v < n_phi || v >= n_z * n_phi;
+        if on_boundary {
+            assert_eq!(curvature.mean_curvature[v], 0.0);
+            assert_eq!(curvature.max_principal_direction[v], Vector3::zeros());
+            continue;
+        }
+
+        assert!((curvature.mean_curvature[v] - 0.5 / radius).abs() < 0.01 / radius);
+        assert!(curvature.gaussian_curvature[v].abs() < 0.01 / (radius * radius));
+        assert!((curvature.max_principal_curvature[v] - 1.0 / radius).abs() < 0.1 / radius);
+        assert!(curvature.min_principal_curvature[v].abs() < 0.1 / radius);
+
+        // The maximum curvature is in circumferential and the minimum curvature in axial direction
+        let circumferential = Vector3::new(-pos.y, pos.x, 0.0).normalize();
+        assert!(
+            curvature.max_principal_direction[v]
+                .dot(&circumferential)
+                .abs()
+                > 0.99
+        );
+        assert!(curvature.min_principal_direction[v].z.abs() > 0.99);
+    }
+
+    let attributes = curvature.into_attributes();
+    assert_eq!(attributes.len(), 6);
+    assert_eq!(attributes[0].name, "mean_curvature");
+    for attribute in &attributes {
+        match &attribute.data {
+            AttributeData::ScalarReal(values) => assert_eq!(values.len(), mesh.vertices.len()),
+            AttributeData::Vector3Real(values) => assert_eq!(values.len(), mesh.vertices.len()),
+            AttributeData::ScalarU64(_) => panic!("unexpected attribute type"),
+        }
+    }
+}
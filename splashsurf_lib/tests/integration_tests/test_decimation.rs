@@ -0,0 +1,94 @@
+use super::common::{ball_mesh, plane_mesh};
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::postprocessing::{qem_decimation, QemDecimationParameters};
+
+#[test]
+fn test_qem_decimation_target_triangle_count() {
+    let original = ball_mesh();
+    let original_volume = original.enclosed_volume();
+    let target = original.triangles.len() / 4;
+
+    let mut mesh = original.clone();
+    let vertex_connectivity = qem_decimation(
+        &mut mesh,
+        &QemDecimationParameters {
+            target_triangle_count: Some(target),
+            ..Default::default()
+        },
+        false,
+    );
+
+    assert!(mesh.triangles.len() <= target);
+    assert!(mesh.triangles.len() + 2 >= target);
+    assert_eq!(vertex_connectivity.len(), mesh.vertices.len());
+
+    let info = mesh.compute_manifold_information();
+    assert!(info.is_closed());
+    assert!(info.is_manifold());
+
+    // The shape of the ball is preserved
+    let relative_volume_change = (mesh.enclosed_volume() - original_volume).abs() / original_volume;
+    assert!(
+        relative_volume_change < 0.02,
+        "volume changed by {:.2}%",
+        relative_volume_change * 100.0
+    );
+}
+
+#[test]
+fn test_qem_decimation_error_bound() {
+    let original = plane_mesh(8);
+
+    // All interior vertices of a plane can be removed without any error
+    let mut mesh = original.clone();
+    qem_decimation(
+        &mut mesh,
+        &QemDecimationParameters {
+            max_error: Some(1e-12),
+            ..Default::default()
+        },
+        false,
+    );
+    assert!(mesh.triangles.len() < original.triangles.len() / 4);
+    assert!(mesh.vertices.iter().all(|v| v.z == 0.0));
+    assert!((mesh.area() - 1.0).abs() < 1e-12);
+
+    // Boundary vertices are not removed
+    let num_boundary_vertices = |mesh: &TriMesh3d<f64>| {
+        mesh.vertices
+            .iter()
+            .filter(|v| v.x == 0.0 || v.x == 1.0 || v.y == 0.0 || v.y == 1.0)
+            .count()
+    };
+    assert_eq!(
+        num_boundary_vertices(&mesh),
+        num_boundary_vertices(&original)
+    );
+}
+
+#[test]
+fn test_qem_decimation_guards() {
+    let original = ball_mesh();
+
+    // Without target and error bound, the mesh is not modified
+    let mut mesh = original.clone();
+    qem_decimation(&mut mesh, &QemDecimationParameters::default(), false);
+    assert_eq!(mesh.triangles, original.triangles);
+
+    // Strict guards prevent most of the collapses
+    let target = original.triangles.len() / 4;
+    let mut guarded_mesh = original.clone();
+    qem_decimation(
+        &mut guarded_mesh,
+        &QemDecimationParameters {
+            target_triangle_count: Some(target),
+            max_error: None,
+            max_normal_change: Some(1.0f64.to_radians()),
+            max_aspect_ratio_change: Some(1.0),
+        },
+        false,
+    );
+    assert!(guarded_mesh.triangles.len() > target);
+    assert!(guarded_mesh.triangles.len() < original.triangles.len());
+    assert!(guarded_mesh.compute_manifold_information().is_manifold());
+}
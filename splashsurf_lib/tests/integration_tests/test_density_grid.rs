@@ -0,0 +1,238 @@
+use super::common::{default_parameters, particle_ball};
+use nalgebra::Vector3;
+use splashsurf_lib::{
+    reconstruct_surface, GridDecompositionParameters, OctreeDecompositionParameters, Parameters,
+    SparseDensityGrid, SpatialDecomposition, SurfaceExtractionMethod,
+};
+
+fn particles() -> Vec<Vector3<f64>> {
+    let spacing = 0.05;
+    let mut particles = particle_ball(Vector3::zeros(), 0.4, spacing);
+    particles.extend(particle_ball(Vector3::new(0.7, 0.0, 0.1), 0.15, spacing));
+    particles
+}
+
+fn parameters(
+    extraction_method: SurfaceExtractionMethod,
+    spatial_decomposition: Option<SpatialDecomposition>,
+) -> Parameters<f64> {
+    Parameters {
+        extraction_method,
+        spatial_decomposition,
+        density_grid: true,
+        ..default_parameters()
+    }
+}
+
+/// Asserts that all points that are stored in multiple blocks have the same value in every block
+fn assert_consistent_block_boundaries(density_grid: &SparseDensityGrid<i64, f64>) {
+    let n = density_grid.block_cubes() as usize + 1;
+    for block in density_grid.blocks() {
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    let point_ijk = [
+                        block.block_ijk[0] * density_grid.block_cubes() + i as i64,
+                        block.block_ijk[1] * density_grid.block_cubes() + j as i64,
+                        block.block_ijk[2] * density_grid.block_cubes() + k as i64,
+                    ];
+                    let value = block.values[(i * n + j) * n + k];
+                    let other_value = density_grid.get(&point_ijk).unwrap();
+                    assert!(
+                        (value - other_value).abs() < 1e-10,
+                        "point {:?} has value {} in block {:?} but {} in another block",
+                        point_ijk,
+                        value,
+                        block.block_ijk,
+                        other_value
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_density_grid_subdomain_grid() {
+    let particles = particles();
+
+    for extraction_method in [
+        SurfaceExtractionMethod::MarchingCubes,
+        SurfaceExtractionMethod::SurfaceNets,
+    ] {
+        let reconstruction = reconstruct_surface::<i64, _>(
+            &particles,
+            &parameters(
+                extraction_method,
+                Some(SpatialDecomposition::UniformGrid(
+                    GridDecompositionParameters {
+                        subdomain_num_cubes_per_dim: 16,
+                    },
+                )),
+            ),
+        )
+        .unwrap();
+        let density_grid = reconstruction.density_grid().unwrap();
+
+        assert_eq!(density_grid.grid(), reconstruction.grid());
+        assert_eq!(density_grid.block_cubes(), 16);
+        assert!(density_grid.num_blocks() > 0);
+        assert_consistent_block_boundaries(density_grid);
+
+        // The density has to be above the threshold at all particles deep inside of the fluid
+        let grid = density_grid.grid();
+        let center = grid.enclosing_cell(&Vector3::zeros());
+        assert!(density_grid.get(&center).unwrap() > 0.6);
+
+        // The corners of the background grid are outside of the kernel support of all particles
+        let dense_values = density_grid.to_dense(-1.0);
+        assert_eq!(
+            dense_values.len(),
+            grid.points_per_dim().iter().product::<i64>() as usize
+        );
+        assert!(dense_values[0] == 0.0 || dense_values[0] == -1.0);
+        let flat_center = grid.flatten_point_index_array(&center) as usize;
+        assert_eq!(
+            dense_values[flat_center],
+            density_grid.get(&center).unwrap()
+        );
+
+        // The octree leaves have to result in the same blocks as the uniform subdomain grid
+        let octree_reconstruction = reconstruct_surface::<i64, _>(
+            &particles,
+            &parameters(
+                extraction_method,
+                Some(SpatialDecomposition::Octree(
+                    OctreeDecompositionParameters {
+                        subdomain_num_cubes_per_dim: 16,
+                        max_particles_per_subdomain: 5000,
+                        max_depth: 2,
+                    },
+                )),
+            ),
+        )
+        .unwrap();
+        let octree_density_grid = octree_reconstruction.density_grid().unwrap();
+        assert_consistent_block_boundaries(octree_density_grid);
+        for block in density_grid.blocks() {
+            // The octree additionally contains empty blocks of large leaves
+            let octree_block = octree_density_grid.get_block(&block.block_ijk).unwrap();
+            for (value, octree_value) in block.values.iter().zip(octree_block.values.iter()) {
+                assert!((value - octree_value).abs() < 1e-10);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_density_grid_global() {
+    let particles = particles();
+
+    let global_reconstruction = reconstruct_surface::<i64, _>(
+        &particles,
+        &parameters(SurfaceExtractionMethod::MarchingCubes, None),
+    )
+    .unwrap();
+    let global_density_grid = global_reconstruction.density_grid().unwrap();
+    assert_consistent_block_boundaries(global_density_grid);
+
+    let reconstruction = reconstruct_surface::<i64, _>(
+        &particles,
+        &parameters(
+            SurfaceExtractionMethod::MarchingCubes,
+            Some(SpatialDecomposition::UniformGrid(
+                GridDecompositionParameters {
+                    subdomain_num_cubes_per_dim: 16,
+                },
+            )),
+        ),
+    )
+    .unwrap();
+    let density_grid = reconstruction.density_grid().unwrap();
+
+    // The subdomain grid only extends the global background grid at its upper boundary
+    let global_grid = global_density_grid.grid();
+    assert_eq!(global_grid.aabb().min(), density_grid.grid().aabb().min());
+
+    // Both approaches have to result in (approximately) the same density field
+    let n = density_grid.block_cubes() + 1;
+    let mut max_difference: f64 = 0.0;
+    for block in density_grid.blocks() {
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    let point_ijk = [
+                        block.block_ijk[0] * density_grid.block_cubes() + i,
+                        block.block_ijk[1] * density_grid.block_cubes() + j,
+                        block.block_ijk[2] * density_grid.block_cubes() + k,
+                    ];
+                    let value = block.values[((i * n + j) * n + k) as usize];
+                    // Points outside of the global grid are outside of the kernel support of all particles
+                    let global_value = if global_grid.point_exists(&point_ijk) {
+                        global_density_grid.get(&point_ijk).unwrap_or(0.0)
+                    } else {
+                        0.0
+                    };
+                    max_difference = max_difference.max((value - global_value).abs());
+                }
+            }
+        }
+    }
+    assert!(
+        max_difference < 1e-2,
+        "maximum difference of the density fields is {}",
+        max_difference
+    );
+}
+
+#[test]
+fn test_density_grid_disabled() {
+    let particles = particles();
+    for spatial_decomposition in [
+        None,
+        Some(SpatialDecomposition::UniformGrid(
+            GridDecompositionParameters {
+                subdomain_num_cubes_per_dim: 16,
+            },
+        )),
+    ] {
+        let mut params = parameters(
+            SurfaceExtractionMethod::MarchingCubes,
+            spatial_decomposition,
+        );
+        params.density_grid = false;
+        let reconstruction = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+        assert!(reconstruction.density_grid().is_none());
+    }
+}
+
+#[cfg(feature = "io")]
+#[test]
+fn test_density_grid_io() {
+    use splashsurf_lib::io::sdg_format::{density_grid_from_sdg, density_grid_to_sdg};
+    use splashsurf_lib::io::vtk_format::density_grid_to_vti;
+
+    let particles = particles();
+    let reconstruction = reconstruct_surface::<i64, _>(
+        &particles,
+        &parameters(
+            SurfaceExtractionMethod::MarchingCubes,
+            Some(SpatialDecomposition::UniformGrid(
+                GridDecompositionParameters {
+                    subdomain_num_cubes_per_dim: 16,
+                },
+            )),
+        ),
+    )
+    .unwrap();
+    let density_grid = reconstruction.density_grid().unwrap();
+
+    density_grid_to_vti(density_grid, "../out/density_grid_balls.vti").unwrap();
+
+    let output_file = "../out/density_grid_balls.sdg";
+    density_grid_to_sdg(density_grid, output_file).unwrap();
+    let sdg_density_grid = density_grid_from_sdg::<i64, f64, _>(output_file).unwrap();
+    assert_eq!(sdg_density_grid.grid(), density_grid.grid());
+    assert_eq!(sdg_density_grid.block_cubes(), density_grid.block_cubes());
+    assert_eq!(sdg_density_grid.blocks(), density_grid.blocks());
+}
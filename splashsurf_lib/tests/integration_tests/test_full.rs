@@ -3,8 +3,8 @@ use splashsurf_lib::io::particles_from_file;
 use splashsurf_lib::io::vtk_format::write_vtk;
 use splashsurf_lib::marching_cubes::check_mesh_consistency;
 use splashsurf_lib::{
-    reconstruct_surface, Aabb3d, GridDecompositionParameters, Parameters, Real,
-    SpatialDecomposition,
+    reconstruct_surface, Aabb3d, GridDecompositionParameters, KernelType, Parameters, Real,
+    SpatialDecomposition, SurfaceExtractionMethod,
 };
 use std::path::Path;
 
@@ -31,12 +31,17 @@ fn params_with_aabb<R: Real>(
         particle_radius,
         rest_density: R::from_f64(1000.0).unwrap(),
         compact_support_radius,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::default(),
         cube_size,
         iso_surface_threshold,
         particle_aabb: domain_aabb,
         enable_multi_threading: false,
         spatial_decomposition: None,
+        anisotropic_kernel: None,
         global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
     };
 
     match strategy {
@@ -0,0 +1,309 @@
+use super::common::{ball_mesh, plane_mesh, sphere_mesh};
+use nalgebra::Vector3;
+use splashsurf_lib::mesh::{Mesh3d, TriMesh3d, TriMesh3dExt};
+use splashsurf_lib::postprocessing::{
+    cap_aabb_holes, fill_holes, HoleFillingParameters, HoleTriangulation,
+};
+use splashsurf_lib::Aabb3d;
+
+/// Removes all triangles with a centroid inside of the given square in the xy-plane
+fn remove_square(mesh: &TriMesh3d<f64>, min: f64, max: f64) -> TriMesh3d<f64> {
+    let triangles_to_keep = (0..mesh.triangles.len())
+        .filter(|&f| {
+            let centroid = mesh.triangles[f]
+                .iter()
+                .map(|&i| mesh.vertices[i])
+                .sum::<Vector3<f64>>()
+                / 3.0;
+            !(centroid.x > min && centroid.x < max && centroid.y > min && centroid.y < max)
+        })
+        .collect::<Vec<_>>();
+    mesh.keep_cells(&triangles_to_keep, true)
+}
+
+#[test]
+fn test_fill_holes_plane() {
+    let original = remove_square(&plane_mesh(8), 0.25, 0.75);
+    assert!((original.area() - 0.75).abs() < 1e-12);
+    // The outer boundary and the hole
+    assert_eq!(
+        original
+            .compute_manifold_information()
+            .boundary_loops()
+            .len(),
+        2
+    );
+
+    let fill = |parameters: &HoleFillingParameters| {
+        let mut mesh = original.clone();
+        let loops = fill_holes(&mut mesh, parameters);
+        assert_eq!(loops.len(), 2);
+        assert!(mesh.vertices.iter().all(|v| v.z.abs() < 1e-12));
+        mesh
+    };
+
+    // The holes are filled without overlapping triangles (the outer boundary is filled as well)
+    let mesh = fill(&HoleFillingParameters::default());
+    assert_eq!(mesh.vertices.len(), original.vertices.len());
+    assert!((mesh.area() - 2.0).abs() < 1e-12);
+    assert!(mesh.compute_manifold_information().is_manifold());
+
+    let mesh = fill(&HoleFillingParameters {
+        triangulation: HoleTriangulation::Fan,
+        ..Default::default()
+    });
+    assert_eq!(mesh.vertices.len(), original.vertices.len() + 2);
+    assert!((mesh.area() - 2.0).abs() < 1e-12);
+
+    // Only the hole with at most 16 boundary edges is filled
+    let mut mesh = original.clone();
+    let loops = fill_holes(
+        &mut mesh,
+        &HoleFillingParameters {
+            max_boundary_edges: Some(16),
+            ..Default::default()
+        },
+    );
+    assert_eq!(loops.len(), 1);
+    assert_eq!(loops[0].len(), 16);
+    assert!((mesh.area() - 1.0).abs() < 1e-12);
+    assert_eq!(mesh.compute_manifold_information().boundary_edges.len(), 32);
+
+    // Fairing refines the patches to the resolution of the mesh
+    let mesh = fill(&HoleFillingParameters {
+        fairing_iterations: Some(10),
+        ..Default::default()
+    });
+    assert!(mesh.vertices.len() > original.vertices.len());
+    assert!((mesh.area() - 2.0).abs() < 1e-12);
+    assert!(mesh.compute_manifold_information().is_manifold());
+}
+
+#[test]
+fn test_fill_holes_large_hole() {
+    // The outer boundary has more edges than supported by the minimum area triangulation
+    let original = remove_square(&plane_mesh(128), 0.25, 0.75);
+    assert!(original
+        .compute_manifold_information()
+        .boundary_loops()
+        .iter()
+        .any(|l| l.len() > HoleTriangulation::MAX_MINIMUM_AREA_BOUNDARY_EDGES));
+
+    let mut mesh = original.clone();
+    let loops = fill_holes(&mut mesh, &HoleFillingParameters::default());
+    assert_eq!(loops.len(), 2);
+    assert_eq!(mesh.vertices.len(), original.vertices.len());
+    assert!((mesh.area() - 2.0).abs() < 1e-10);
+    assert!(mesh.compute_manifold_information().is_closed());
+}
+
+#[test]
+fn test_fill_holes_ball() {
+    let original = ball_mesh();
+
+    // Cut off the cap of the ball
+    let triangles_to_keep = (0..original.triangles.len())
+        .filter(|&f| {
+            original.triangles[f]
+                .iter()
+                .any(|&i| original.vertices[i].z < 0.3)
+        })
+        .collect::<Vec<_>>();
+    let open_mesh = original.keep_cells(&triangles_to_keep, false);
+    assert!(!open_mesh.compute_manifold_information().is_closed());
+
+    for triangulation in [HoleTriangulation::Fan, HoleTriangulation::MinimumArea] {
+        for fairing_iterations in [None, Some(20)] {
+            let mut mesh = open_mesh.clone();
+            let loops = fill_holes(
+                &mut mesh,
+                &HoleFillingParameters {
+                    triangulation,
+                    max_boundary_edges: None,
+                    fairing_iterations,
+                },
+            );
+            assert_eq!(loops.len(), 1);
+
+            let info = mesh.compute_manifold_information();
+            assert!(info.is_closed());
+            assert!(info.is_manifold());
+
+            // The volume enclosed by the open mesh is closed by a fan as well
+            let relative_volume_difference = (mesh.enclosed_volume() - open_mesh.enclosed_volume())
+                .abs()
+                / open_mesh.enclosed_volume();
+            if triangulation == HoleTriangulation::Fan && fairing_iterations.is_none() {
+                assert!(relative_volume_difference < 1e-12);
+            } else {
+                assert!(relative_volume_difference < 0.01);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fill_holes_clipped_ball() {
+    let original = ball_mesh();
+
+    // Clip the ball with an AABB cutting off two sides
+    let aabb = Aabb3d::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(0.3, 1.0, 0.3));
+    let clipped = original.par_clamp_with_aabb(&aabb, true, false);
+    let volume = clipped.enclosed_volume();
+
+    let mut mesh = clipped.clone();
+    fill_holes(
+        &mut mesh,
+        &HoleFillingParameters {
+            triangulation: HoleTriangulation::Fan,
+            ..Default::default()
+        },
+    );
+
+    let info = mesh.compute_manifold_information();
+    assert!(info.is_closed());
+    assert!(info.is_manifold());
+    assert!((mesh.enclosed_volume() - volume).abs() < 1e-12 * volume);
+}
+
+/// Extrudes a non-convex U-shaped polygon in the yz-plane along the x-axis from -1 to 1 to a tube with open ends
+fn u_tube_mesh() -> TriMesh3d<f64> {
+    // Counter-clockwise when viewed from the positive x-axis with an area of 0.07
+    let polygon = [
+        (0.0, 0.0),
+        (3.0, 0.0),
+        (3.0, 3.0),
+        (2.0, 3.0),
+        (2.0, 1.0),
+        (1.0, 1.0),
+        (1.0, 3.0),
+        (0.0, 3.0),
+    ];
+    let n = polygon.len();
+    let rings = [-1.0, 0.0, 1.0];
+
+    let mut mesh = TriMesh3d::default();
+    for x in rings {
+        for (y, z) in polygon {
+            mesh.vertices
+                .push(Vector3::new(x, 0.1 * y - 0.15, 0.1 * z - 0.15));
+        }
+    }
+    for r in 0..rings.len() - 1 {
+        for k in 0..n {
+            let [a, b, c, d] = [
+                r * n + k,
+                r * n + (k + 1) % n,
+                (r + 1) * n + (k + 1) % n,
+                (r + 1) * n + k,
+            ];
+            mesh.triangles.push([a, b, c]);
+            mesh.triangles.push([a, c, d]);
+        }
+    }
+    mesh
+}
+
+#[test]
+fn test_cap_aabb_holes_non_convex() {
+    let aabb = Aabb3d::new(Vector3::new(-0.5, -1.0, -1.0), Vector3::new(0.5, 1.0, 1.0));
+    let clipped = u_tube_mesh().par_clamp_with_aabb(&aabb, true, false);
+    assert_eq!(
+        clipped
+            .compute_manifold_information()
+            .boundary_loops()
+            .len(),
+        2
+    );
+
+    let mut mesh = clipped.clone();
+    let source_triangles = cap_aabb_holes(&mut mesh, &aabb);
+    assert_eq!(mesh.vertices.len(), clipped.vertices.len());
+    assert_eq!(
+        source_triangles.len(),
+        mesh.triangles.len() - clipped.triangles.len()
+    );
+
+    let info = mesh.compute_manifold_information();
+    assert!(info.is_closed());
+    assert!(info.is_manifold());
+
+    // The caps do not overlap and face outwards although the centroids of the loops are outside of the U
+    let caps = TriMesh3d {
+        vertices: mesh.vertices.clone(),
+        triangles: mesh.triangles[clipped.triangles.len()..].to_vec(),
+    };
+    assert!((caps.area() - 2.0 * 0.07).abs() < 1e-12);
+    assert!((mesh.enclosed_volume() - 0.07).abs() < 1e-12);
+    for (tri, &source) in caps.triangles.iter().zip(source_triangles.iter()) {
+        let normal = mesh.tri_normal_ijk::<f64>(tri);
+        let x = mesh.vertices[tri[0]].x;
+        assert!(normal.x * x > 0.0);
+        assert!(clipped.triangles[source]
+            .iter()
+            .any(|&v| clipped.vertices[v].x == x));
+    }
+}
+
+#[test]
+fn test_cap_aabb_holes_annulus() {
+    // Shell between two spheres, the inner sphere is oriented inwards
+    let mut shell = sphere_mesh(0.5, 4);
+    let inner = sphere_mesh(0.3, 4);
+    let offset = shell.vertices.len();
+    shell.vertices.extend(inner.vertices);
+    shell.triangles.extend(
+        inner
+            .triangles
+            .iter()
+            .map(|tri| [tri[0] + offset, tri[2] + offset, tri[1] + offset]),
+    );
+
+    // The cut through the shell is an annulus
+    let aabb = Aabb3d::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(0.1, 1.0, 1.0));
+    let clipped = shell.par_clamp_with_aabb(&aabb, true, false);
+    let loops = clipped.compute_manifold_information().boundary_loops();
+    assert_eq!(loops.len(), 2);
+
+    let mut mesh = clipped.clone();
+    let source_triangles = cap_aabb_holes(&mut mesh, &aabb);
+    assert_eq!(mesh.vertices.len(), clipped.vertices.len());
+
+    // A single cap with the inner loop as hole instead of two overlapping caps
+    let num_cap_triangles = mesh.triangles.len() - clipped.triangles.len();
+    assert_eq!(num_cap_triangles, loops[0].len() + loops[1].len());
+    assert_eq!(source_triangles.len(), num_cap_triangles);
+
+    let info = mesh.compute_manifold_information();
+    assert!(info.is_closed());
+    assert!(info.is_manifold());
+
+    let caps = TriMesh3d {
+        vertices: mesh.vertices.clone(),
+        triangles: mesh.triangles[clipped.triangles.len()..].to_vec(),
+    };
+    let annulus_area = std::f64::consts::PI * (0.5f64.powi(2) - 0.3f64.powi(2));
+    assert!((caps.area() - annulus_area).abs() < 0.05 * annulus_area);
+    assert!(caps
+        .triangles
+        .iter()
+        .all(|tri| mesh.tri_normal_ijk::<f64>(tri).x > 0.0));
+}
+
+#[test]
+fn test_cap_aabb_holes_clipped_ball() {
+    // The boundary loop wraps around an edge of the AABB
+    let aabb = Aabb3d::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(0.3, 1.0, 0.3));
+    let clipped = ball_mesh().par_clamp_with_aabb(&aabb, true, false);
+
+    let mut mesh = clipped.clone();
+    let source_triangles = cap_aabb_holes(&mut mesh, &aabb);
+    assert_eq!(
+        source_triangles.len(),
+        mesh.triangles.len() - clipped.triangles.len()
+    );
+
+    let info = mesh.compute_manifold_information();
+    assert!(info.is_closed());
+    assert!(info.is_manifold());
+}
@@ -0,0 +1,124 @@
+use nalgebra::Vector3;
+use splashsurf_lib::{
+    grid_for_reconstruction, reconstruct_surface, reconstruct_surface_with_properties,
+    required_index_range, KernelType, Parameters, ParticleProperties, SpatialDecomposition,
+    SurfaceExtractionMethod,
+};
+
+/// Generates a box of particles on a regular lattice
+fn particle_box(n: usize, spacing: f64) -> Vec<Vector3<f64>> {
+    let mut particles = Vec::new();
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                particles.push(Vector3::new(i as f64, j as f64, k as f64) * spacing);
+            }
+        }
+    }
+    particles
+}
+
+fn parameters(spatial_decomposition: Option<SpatialDecomposition>) -> Parameters<f64> {
+    let particle_radius = 0.025;
+    Parameters {
+        particle_radius,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * particle_radius,
+        kernel_type: KernelType::default(),
+        extraction_method: SurfaceExtractionMethod::MarchingCubes,
+        cube_size: 0.5 * particle_radius,
+        iso_surface_threshold: 0.6,
+        particle_aabb: None,
+        enable_multi_threading: true,
+        spatial_decomposition,
+        anisotropic_kernel: None,
+        global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
+    }
+}
+
+#[test]
+fn test_required_index_range() {
+    let particles = particle_box(12, 0.04);
+    let params = parameters(None);
+
+    let range = required_index_range(&particles, None, &params).unwrap();
+    let grid = grid_for_reconstruction::<i64, _>(
+        &particles,
+        params.particle_radius,
+        params.compact_support_radius,
+        params.cube_size,
+        None,
+        true,
+    )
+    .unwrap();
+
+    assert_eq!(&range.points_per_dim, grid.points_per_dim());
+    assert_eq!(
+        range.num_points,
+        range.points_per_dim.iter().product::<i64>()
+    );
+    assert!(range.fits::<i32>());
+    assert!(range.fits::<i64>());
+    // The grid has more than 32767 points
+    assert!(range.num_points > i64::from(i16::MAX));
+    assert!(!range.fits::<i16>());
+}
+
+#[test]
+fn test_reconstruction_with_i32_indices() {
+    let particles = particle_box(12, 0.04);
+
+    for spatial_decomposition in [None, Some(SpatialDecomposition::default())] {
+        let params = parameters(spatial_decomposition);
+        assert!(required_index_range(&particles, None, &params)
+            .unwrap()
+            .fits::<i32>());
+
+        let reconstruction_i32 = reconstruct_surface::<i32, _>(&particles, &params).unwrap();
+        let reconstruction_i64 = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+
+        assert_eq!(
+            reconstruction_i32.grid().points_per_dim().map(i64::from),
+            *reconstruction_i64.grid().points_per_dim()
+        );
+        assert_eq!(
+            reconstruction_i32.mesh().vertices.len(),
+            reconstruction_i64.mesh().vertices.len()
+        );
+        assert_eq!(
+            reconstruction_i32.mesh().triangles.len(),
+            reconstruction_i64.mesh().triangles.len()
+        );
+    }
+}
+
+#[test]
+fn test_required_index_range_close_to_i32_limit() {
+    // Two particles spanning a background grid that barely fits into the range of i32
+    let particles = vec![Vector3::zeros(), Vector3::repeat(15.8)];
+
+    let range = required_index_range(&particles, None, &parameters(None)).unwrap();
+    assert!(range.fits::<i32>());
+
+    // The subdomain grid extends the background grid to whole subdomains which exceeds the range of i32
+    let params = parameters(Some(SpatialDecomposition::default()));
+    let range = required_index_range(&particles, None, &params).unwrap();
+    assert!(range.points_per_dim.iter().all(|n| (n - 1) % 64 == 0));
+    assert!(!range.fits::<i32>());
+    assert!(reconstruct_surface::<i32, _>(&particles, &params).is_err());
+
+    // Larger per-particle compact support radii enlarge the margin of the background grid
+    let params = parameters(None);
+    let compact_support_radii = vec![8.0 * params.particle_radius; particles.len()];
+    let properties = ParticleProperties {
+        compact_support_radii: Some(&compact_support_radii),
+        ..Default::default()
+    };
+    let range = required_index_range(&particles, Some(&properties), &params).unwrap();
+    assert!(!range.fits::<i32>());
+    assert!(
+        reconstruct_surface_with_properties::<i32, _>(&particles, &properties, &params).is_err()
+    );
+}
@@ -0,0 +1,94 @@
+use super::common::{block_parameters, particle_block};
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::sph_interpolation::SphInterpolator;
+use splashsurf_lib::{reconstruct_surface, KernelType, Parameters};
+
+fn parameters(kernel_type: KernelType, subdomain_grid: bool) -> Parameters<f64> {
+    Parameters {
+        kernel_type,
+        ..block_parameters(0.025, subdomain_grid)
+    }
+}
+
+#[test]
+fn test_reconstruction_with_kernel_types() {
+    let particles = particle_block([10, 10, 10], 0.05);
+
+    for &kernel_type in KernelType::all() {
+        let global = reconstruct_surface::<i64, _>(&particles, &parameters(kernel_type, false))
+            .unwrap_or_else(|e| {
+                panic!("global reconstruction with {:?} failed: {}", kernel_type, e)
+            });
+        let grid = reconstruct_surface::<i64, _>(&particles, &parameters(kernel_type, true))
+            .unwrap_or_else(|e| panic!("grid reconstruction with {:?} failed: {}", kernel_type, e));
+
+        for reconstruction in [&global, &grid] {
+            assert!(!reconstruction.mesh().triangles.is_empty());
+            check_mesh_consistency(
+                reconstruction.grid(),
+                reconstruction.mesh(),
+                true,
+                true,
+                false,
+            )
+            .unwrap_or_else(|e| panic!("mesh with {:?} is inconsistent: {}", kernel_type, e));
+        }
+
+        // Both approaches have to evaluate the densities with the same kernel
+        for (rho_global, rho_grid) in global
+            .particle_densities()
+            .unwrap()
+            .iter()
+            .zip(grid.particle_densities().unwrap().iter())
+        {
+            assert!((rho_global - rho_grid).abs() / rho_global < 1e-2);
+        }
+
+        let (n_global, n_grid) = (global.mesh().triangles.len(), grid.mesh().triangles.len());
+        assert!(
+            (n_global as f64 - n_grid as f64).abs() / (n_global.max(n_grid) as f64) < 2e-2,
+            "{:?}: global mesh has {} triangles, grid mesh has {} triangles",
+            kernel_type,
+            n_global,
+            n_grid
+        );
+    }
+}
+
+#[test]
+fn test_interpolation_with_kernel_types() {
+    let particles = particle_block([10, 10, 10], 0.05);
+    // A particle in the interior of the block with a full neighborhood
+    let interior_particle = particles[5 * 100 + 5 * 10 + 5];
+
+    for &kernel_type in KernelType::all() {
+        let parameters = parameters(kernel_type, false);
+        let reconstruction = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+        let densities = reconstruction.particle_densities().unwrap();
+
+        let particle_rest_mass = (4.0 / 3.0)
+            * std::f64::consts::PI
+            * parameters.particle_radius.powi(3)
+            * parameters.rest_density;
+        let interpolator = SphInterpolator::new(
+            &particles,
+            densities,
+            particle_rest_mass,
+            parameters.compact_support_radius,
+        )
+        .with_kernel_type(kernel_type);
+        assert_eq!(interpolator.kernel_type(), kernel_type);
+
+        // Without correction, the interpolation of a constant one is the Shepard sum of the particle volumes,
+        // which is only close to one in the interior if the interpolator uses the same kernel as the densities
+        let ones = vec![1.0; particles.len()];
+        let shepard =
+            interpolator.interpolate_scalar_quantity(&ones, &[interior_particle], false)[0];
+        assert!(
+            (shepard - 1.0).abs() < 1e-2,
+            "{:?}: Shepard sum in the interior is {}",
+            kernel_type,
+            shepard
+        );
+    }
+}
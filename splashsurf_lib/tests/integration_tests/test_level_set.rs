@@ -0,0 +1,136 @@
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::{
+    check_mesh_consistency, triangulate_level_set, MarchingCubesLevelSet,
+};
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::{Aabb3d, GridDecompositionParameters, UniformGrid};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Signed distance function of a sphere that counts its evaluations
+struct SphereLevelSet {
+    center: Vector3<f64>,
+    radius: f64,
+    enable_culling: bool,
+    evaluations: AtomicUsize,
+}
+
+impl SphereLevelSet {
+    fn new(radius: f64, enable_culling: bool) -> Self {
+        Self {
+            center: Vector3::new(0.1, -0.05, 0.02),
+            radius,
+            enable_culling,
+            evaluations: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl MarchingCubesLevelSet<f64> for SphereLevelSet {
+    fn is_region_supported(&self, aabb: &Aabb3d<f64>) -> bool {
+        if !self.enable_culling {
+            return true;
+        }
+
+        // Distance from the center of the sphere to the closest point of the AABB
+        let closest = self
+            .center
+            .zip_zip_map(aabb.min(), aabb.max(), |c, min, max| c.max(min).min(max));
+        (closest - self.center).norm() <= self.radius
+    }
+
+    fn evaluate(&self, coordinate: &Vector3<f64>) -> f64 {
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+        (coordinate - self.center).norm() - self.radius
+    }
+}
+
+fn grid(cube_size: f64) -> UniformGrid<i64, f64> {
+    let domain = Aabb3d::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+    UniformGrid::from_aabb(&domain, cube_size).unwrap()
+}
+
+fn triangulate(
+    grid: &UniformGrid<i64, f64>,
+    level_set: &SphereLevelSet,
+    subdomain_num_cubes_per_dim: u32,
+    enable_multi_threading: bool,
+) -> TriMesh3d<f64> {
+    triangulate_level_set(
+        grid,
+        level_set,
+        &GridDecompositionParameters {
+            subdomain_num_cubes_per_dim,
+        },
+        enable_multi_threading,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_level_set_sphere() {
+    let grid = grid(0.05);
+    let radius = 0.6;
+
+    let reference = triangulate(&grid, &SphereLevelSet::new(radius, false), 1000, false);
+    check_mesh_consistency(&grid, &reference, true, true, false).unwrap();
+
+    // The triangles are oriented to the outside, so the volume has to be positive
+    let expected_volume = 4.0 / 3.0 * std::f64::consts::PI * radius.powi(3);
+    let volume = reference.enclosed_volume();
+    assert!(
+        (volume - expected_volume).abs() / expected_volume < 1e-2,
+        "volume of sphere mesh is {}, expected {}",
+        volume,
+        expected_volume
+    );
+
+    // Subdomain decomposition, culling and multi-threading must not change the triangulation
+    for subdomain_cubes in [4, 7, 16, 64] {
+        for enable_multi_threading in [false, true] {
+            let mesh = triangulate(
+                &grid,
+                &SphereLevelSet::new(radius, true),
+                subdomain_cubes,
+                enable_multi_threading,
+            );
+            check_mesh_consistency(&grid, &mesh, true, true, false).unwrap_or_else(|e| {
+                panic!(
+                    "mesh with {} cubes per subdomain is inconsistent: {}",
+                    subdomain_cubes, e
+                )
+            });
+            assert_eq!(mesh.vertices.len(), reference.vertices.len());
+            assert_eq!(mesh.triangles.len(), reference.triangles.len());
+            assert!((mesh.enclosed_volume() - volume).abs() < 1e-10);
+        }
+    }
+}
+
+#[test]
+fn test_level_set_culling() {
+    let grid = grid(0.025);
+    let num_points = grid.points_per_dim().iter().product::<i64>() as usize;
+
+    let level_set = SphereLevelSet::new(0.2, true);
+    let mesh = triangulate(&grid, &level_set, 16, true);
+    check_mesh_consistency(&grid, &mesh, true, true, false).unwrap();
+
+    // Only a small fraction of the grid is close to the sphere
+    let evaluations = level_set.evaluations.load(Ordering::Relaxed);
+    assert!(
+        evaluations < num_points / 10,
+        "level set was evaluated {} times on a grid with {} points",
+        evaluations,
+        num_points
+    );
+}
+
+#[test]
+fn test_level_set_cut_by_domain() {
+    let grid = grid(0.05);
+
+    // The sphere is larger than the domain, the mesh should still be closed
+    let mesh = triangulate(&grid, &SphereLevelSet::new(1.2, true), 16, true);
+    assert!(!mesh.triangles.is_empty());
+    check_mesh_consistency(&grid, &mesh, true, true, false).unwrap();
+}
@@ -0,0 +1,229 @@
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::mesh::{AttributeData, TriMesh3d};
+use splashsurf_lib::multiphase::{reconstruct_surface_multiphase, PHASE_ATTRIBUTE_NAME};
+use splashsurf_lib::{
+    reconstruct_surface, KernelType, Parameters, ReconstructionError, SolidBoundary,
+    SpatialDecomposition, SurfaceExtractionMethod,
+};
+
+const PARTICLE_RADIUS: f64 = 0.025;
+
+/// Generates two stacked boxes of particles with the phase ids 3 (bottom) and 7 (top)
+///
+/// Returns the particles, their phase ids and the height of the interface between both phases.
+fn layered_particles() -> (Vec<Vector3<f64>>, Vec<u64>, f64) {
+    let spacing = (4.0 / 3.0 * std::f64::consts::PI).cbrt() * PARTICLE_RADIUS;
+    let mut particles = Vec::new();
+    let mut phase_ids = Vec::new();
+    for i in 0..10 {
+        for j in 0..10 {
+            for k in 0..12 {
+                particles.push(Vector3::new(i as f64, j as f64, k as f64) * spacing);
+                phase_ids.push(if k < 6 { 3 } else { 7 });
+            }
+        }
+    }
+    (particles, phase_ids, 5.5 * spacing)
+}
+
+fn parameters(extraction_method: SurfaceExtractionMethod) -> Parameters<f64> {
+    Parameters {
+        particle_radius: PARTICLE_RADIUS,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * PARTICLE_RADIUS,
+        kernel_type: KernelType::default(),
+        extraction_method,
+        cube_size: 0.5 * PARTICLE_RADIUS,
+        iso_surface_threshold: 0.6,
+        particle_aabb: None,
+        enable_multi_threading: true,
+        spatial_decomposition: None,
+        anisotropic_kernel: None,
+        global_neighborhood_list: false,
+        density_grid: false,
+        solid_boundary: None,
+    }
+}
+
+/// Returns the vertices of the mesh close to the interface plane in the interior of the boxes
+fn interface_vertices(mesh: &TriMesh3d<f64>, interface_height: f64) -> Vec<Vector3<f64>> {
+    mesh.vertices
+        .iter()
+        .filter(|v| v.x > 0.1 && v.x < 0.3 && v.y > 0.1 && v.y < 0.3)
+        .filter(|v| (v.z - interface_height).abs() < 2.0 * PARTICLE_RADIUS)
+        .copied()
+        .collect()
+}
+
+#[test]
+fn test_multiphase_layers() {
+    let (particles, phase_ids, interface_height) = layered_particles();
+
+    for enable_multi_threading in [true, false] {
+        let mut params = parameters(SurfaceExtractionMethod::MarchingCubes);
+        params.enable_multi_threading = enable_multi_threading;
+        let reconstruction =
+            reconstruct_surface_multiphase::<i64, _>(&particles, &phase_ids, &params).unwrap();
+
+        let phases = reconstruction.phases();
+        assert_eq!(phases.len(), 2);
+        assert_eq!(phases[0].phase_id, 3);
+        assert_eq!(phases[1].phase_id, 7);
+
+        for phase in phases {
+            assert!(!phase.mesh.triangles.is_empty());
+            check_mesh_consistency(reconstruction.grid(), &phase.mesh, true, true, false).unwrap();
+        }
+
+        // Both phases have a surface at the interface that consists of the same vertices
+        let bottom = interface_vertices(&phases[0].mesh, interface_height);
+        let top = interface_vertices(&phases[1].mesh, interface_height);
+        assert!(!bottom.is_empty());
+        assert!(!top.is_empty());
+        for (vertices, other_mesh) in [(&bottom, &phases[1].mesh), (&top, &phases[0].mesh)] {
+            for v in vertices {
+                assert!(
+                    other_mesh.vertices.iter().any(|w| (v - w).norm() < 1e-10),
+                    "interface vertex {:?} is missing in the surface of the other phase",
+                    v
+                );
+            }
+        }
+
+        // The bottom phase ends at the interface instead of enclosing the particles of the top phase
+        let bottom_max_z = phases[0]
+            .mesh
+            .vertices
+            .iter()
+            .map(|v| v.z)
+            .fold(f64::MIN, f64::max);
+        assert!(bottom_max_z < interface_height + PARTICLE_RADIUS);
+    }
+}
+
+#[test]
+fn test_multiphase_single_phase() {
+    let (particles, _, _) = layered_particles();
+    let phase_ids = vec![1; particles.len()];
+
+    // With a single phase, the result is identical to the global reconstruction
+    let params = parameters(SurfaceExtractionMethod::MarchingCubes);
+    let reconstruction =
+        reconstruct_surface_multiphase::<i64, _>(&particles, &phase_ids, &params).unwrap();
+    let reference = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+
+    assert_eq!(reconstruction.phases().len(), 1);
+    let mesh = &reconstruction.phase(1).unwrap().mesh;
+    assert_eq!(mesh.vertices.len(), reference.mesh().vertices.len());
+    assert_eq!(mesh.triangles.len(), reference.mesh().triangles.len());
+}
+
+#[test]
+fn test_multiphase_merged_mesh() {
+    let (particles, phase_ids, _) = layered_particles();
+
+    for extraction_method in [
+        SurfaceExtractionMethod::MarchingCubes,
+        SurfaceExtractionMethod::SurfaceNets,
+    ] {
+        let mut params = parameters(extraction_method);
+        // The spatial decomposition is ignored
+        params.spatial_decomposition = Some(SpatialDecomposition::default());
+        let reconstruction =
+            reconstruct_surface_multiphase::<i64, _>(&particles, &phase_ids, &params).unwrap();
+        let merged = reconstruction.to_merged_mesh();
+
+        let num_triangles: Vec<_> = reconstruction
+            .phases()
+            .iter()
+            .map(|phase| phase.mesh.triangles.len())
+            .collect();
+        assert_eq!(
+            merged.mesh.triangles.len(),
+            num_triangles.iter().sum::<usize>()
+        );
+
+        let attribute = merged
+            .cell_attributes
+            .iter()
+            .find(|attribute| attribute.name == PHASE_ATTRIBUTE_NAME)
+            .unwrap();
+        match &attribute.data {
+            AttributeData::ScalarU64(ids) => {
+                assert_eq!(ids.len(), merged.mesh.triangles.len());
+                assert!(ids[..num_triangles[0]].iter().all(|id| *id == 3));
+                assert!(ids[num_triangles[0]..].iter().all(|id| *id == 7));
+            }
+            _ => panic!("phase attribute has to contain integer ids"),
+        }
+    }
+}
+
+#[test]
+fn test_multiphase_length_mismatch() {
+    let (particles, phase_ids, _) = layered_particles();
+    let result = reconstruct_surface_multiphase::<i64, _>(
+        &particles,
+        &phase_ids[1..],
+        &parameters(SurfaceExtractionMethod::MarchingCubes),
+    );
+    assert!(matches!(
+        result,
+        Err(ReconstructionError::ParticlePropertiesLengthMismatch {
+            property: "phase_ids",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_multiphase_boundary() {
+    // Two phases side by side resting on the floor `z = 0`
+    let spacing = (4.0 / 3.0 * std::f64::consts::PI).cbrt() * PARTICLE_RADIUS;
+    let (particles, _, _) = layered_particles();
+    let particles: Vec<_> = particles
+        .iter()
+        .map(|p| p + Vector3::repeat(PARTICLE_RADIUS))
+        .collect();
+    let interface_x = PARTICLE_RADIUS + 2.5 * spacing;
+    let phase_ids: Vec<u64> = particles
+        .iter()
+        .map(|p| if p.x < interface_x { 1 } else { 2 })
+        .collect();
+
+    let mut params = parameters(SurfaceExtractionMethod::MarchingCubes);
+    params.solid_boundary = Some(SolidBoundary::from_signed_distance(|p: &Vector3<f64>| p.z));
+    let reconstruction =
+        reconstruct_surface_multiphase::<i64, _>(&particles, &phase_ids, &params).unwrap();
+    let reference = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+
+    // Away from the interface, the free surfaces of both phases close to the floor are identical to
+    // the surface of the whole mixture, i.e. the boundary is not added once per phase
+    let is_free_surface_close_to_floor = |v: &&Vector3<f64>| {
+        v.z < 4.0 * PARTICLE_RADIUS
+            && (v.y < PARTICLE_RADIUS || v.y > PARTICLE_RADIUS + 9.0 * spacing)
+            && (v.x - interface_x).abs() > 2.0 * PARTICLE_RADIUS
+    };
+    for phase in reconstruction.phases() {
+        let vertices: Vec<_> = phase
+            .mesh
+            .vertices
+            .iter()
+            .filter(is_free_surface_close_to_floor)
+            .collect();
+        assert!(!vertices.is_empty());
+        for v in vertices {
+            assert!(
+                reference
+                    .mesh()
+                    .vertices
+                    .iter()
+                    .any(|w| (v - w).norm() < 1e-10),
+                "vertex {:?} of phase {} is missing in the surface of the mixture",
+                v,
+                phase.phase_id
+            );
+        }
+    }
+}
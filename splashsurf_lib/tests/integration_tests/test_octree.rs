@@ -0,0 +1,106 @@
+use super::common::{default_parameters, particle_ball};
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::{
+    reconstruct_surface, GridDecompositionParameters, OctreeDecompositionParameters, Parameters,
+    SpatialDecomposition, SurfaceExtractionMethod, SurfaceReconstruction,
+};
+
+/// A large ball of particles with a few small droplets around it
+fn particles() -> Vec<Vector3<f64>> {
+    let spacing = 0.05;
+    let mut particles = particle_ball(Vector3::zeros(), 0.6, spacing);
+    particles.extend(particle_ball(Vector3::new(1.0, 0.0, 0.0), 0.1, spacing));
+    particles.extend(particle_ball(Vector3::new(0.0, -0.9, 0.5), 0.1, spacing));
+    particles.extend(particle_ball(Vector3::new(-0.8, 0.7, -0.8), 0.15, spacing));
+    particles
+}
+
+fn parameters(
+    extraction_method: SurfaceExtractionMethod,
+    spatial_decomposition: SpatialDecomposition,
+) -> Parameters<f64> {
+    Parameters {
+        extraction_method,
+        spatial_decomposition: Some(spatial_decomposition),
+        ..default_parameters()
+    }
+}
+
+fn assert_same_surface(
+    reference: &SurfaceReconstruction<i64, f64>,
+    reconstruction: &SurfaceReconstruction<i64, f64>,
+) {
+    let (reference_mesh, mesh) = (reference.mesh(), reconstruction.mesh());
+    assert_eq!(reference_mesh.vertices.len(), mesh.vertices.len());
+    assert_eq!(reference_mesh.triangles.len(), mesh.triangles.len());
+
+    let (reference_volume, volume) = (reference_mesh.enclosed_volume(), mesh.enclosed_volume());
+    assert!(
+        (reference_volume - volume).abs() / reference_volume < 1e-10,
+        "volume of octree reconstruction is {}, uniform grid volume is {}",
+        volume,
+        reference_volume
+    );
+
+    assert_eq!(
+        reference.particle_densities().unwrap(),
+        reconstruction.particle_densities().unwrap()
+    );
+}
+
+#[test]
+fn test_octree_decomposition() {
+    let particles = particles();
+
+    for extraction_method in [
+        SurfaceExtractionMethod::MarchingCubes,
+        SurfaceExtractionMethod::SurfaceNets,
+    ] {
+        let reference = reconstruct_surface::<i64, _>(
+            &particles,
+            &parameters(
+                extraction_method,
+                SpatialDecomposition::UniformGrid(GridDecompositionParameters {
+                    subdomain_num_cubes_per_dim: 16,
+                }),
+            ),
+        )
+        .unwrap();
+
+        // Only the largest leaves, mixed leaf sizes and only the smallest leaves
+        for max_particles_per_subdomain in [usize::MAX, 5000, 1] {
+            let reconstruction = reconstruct_surface::<i64, _>(
+                &particles,
+                &parameters(
+                    extraction_method,
+                    SpatialDecomposition::Octree(OctreeDecompositionParameters {
+                        subdomain_num_cubes_per_dim: 16,
+                        max_particles_per_subdomain,
+                        max_depth: 2,
+                    }),
+                ),
+            )
+            .unwrap();
+
+            // The patches of the octree leaves have to be stitched seamlessly
+            check_mesh_consistency(
+                reconstruction.grid(),
+                reconstruction.mesh(),
+                true,
+                true,
+                false,
+            )
+            .unwrap();
+            assert_same_surface(&reference, &reconstruction);
+
+            if extraction_method == SurfaceExtractionMethod::SurfaceNets {
+                let quad_mesh = reconstruction.quad_mesh().unwrap();
+                assert_eq!(
+                    2 * quad_mesh.cells.len(),
+                    reconstruction.mesh().triangles.len()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use super::common::{block_parameters, particle_block, relative_difference};
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::{
+    reconstruct_surface, reconstruct_surface_with_properties,
+    reconstruct_surface_with_properties_inplace, Parameters, ParticleProperties,
+    ReconstructionError, SurfaceReconstruction,
+};
+
+fn reconstruct_with_properties(
+    particles: &[Vector3<f64>],
+    properties: &ParticleProperties<f64>,
+    parameters: &Parameters<f64>,
+) -> SurfaceReconstruction<i64, f64> {
+    reconstruct_surface_with_properties(particles, properties, parameters).unwrap()
+}
+
+#[test]
+fn test_uniform_particle_properties() {
+    let particle_radius = 0.025;
+    let particles = particle_block([12, 10, 8], 2.0 * particle_radius);
+    let radii = vec![particle_radius; particles.len()];
+
+    for subdomain_grid in [false, true] {
+        let parameters = block_parameters(particle_radius, subdomain_grid);
+
+        let reference = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+        let reconstruction = reconstruct_with_properties(
+            &particles,
+            &ParticleProperties {
+                radii: Some(&radii),
+                ..Default::default()
+            },
+            &parameters,
+        );
+
+        // Per-particle properties that match the global parameters should reproduce the same surface
+        let reference_densities = reference.particle_densities().unwrap();
+        let densities = reconstruction.particle_densities().unwrap();
+        for (rho_ref, rho) in reference_densities.iter().zip(densities.iter()) {
+            assert!((rho_ref - rho).abs() / rho_ref < 1e-2);
+        }
+
+        assert!(
+            relative_difference(
+                reference.mesh().triangles.len(),
+                reconstruction.mesh().triangles.len()
+            ) < 1e-2
+        );
+
+        check_mesh_consistency(
+            reconstruction.grid(),
+            reconstruction.mesh(),
+            true,
+            true,
+            false,
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_varying_particle_radii() {
+    let small_radius = 0.025;
+    let large_radius = 2.0 * small_radius;
+
+    // A block of small particles and a separate block of large particles
+    let small_particles = particle_block([8, 8, 8], 2.0 * small_radius);
+    let large_particles = particle_block([6, 6, 6], 2.0 * large_radius)
+        .into_iter()
+        .map(|p| p + Vector3::new(24.0 * small_radius, 0.0, 0.0))
+        .collect::<Vec<_>>();
+
+    let mut particles = small_particles.clone();
+    particles.extend(large_particles.iter().cloned());
+
+    let mut radii = vec![small_radius; small_particles.len()];
+    radii.extend(std::iter::repeat(large_radius).take(large_particles.len()));
+
+    let properties = ParticleProperties {
+        radii: Some(&radii),
+        ..Default::default()
+    };
+
+    let global = reconstruct_with_properties(
+        &particles,
+        &properties,
+        &block_parameters(small_radius, false),
+    );
+    let grid = reconstruct_with_properties(
+        &particles,
+        &properties,
+        &block_parameters(small_radius, true),
+    );
+
+    // Both blocks have the same packing, so their interior particles should have the same density
+    let max_density = |densities: &[f64]| densities.iter().copied().fold(0.0, f64::max);
+    for reconstruction in [&global, &grid] {
+        let densities = reconstruction.particle_densities().unwrap();
+        let (small_densities, large_densities) = densities.split_at(small_particles.len());
+        let (small_max, large_max) = (max_density(small_densities), max_density(large_densities));
+        assert!((small_max - large_max).abs() / small_max < 5e-2);
+
+        check_mesh_consistency(
+            reconstruction.grid(),
+            reconstruction.mesh(),
+            true,
+            true,
+            false,
+        )
+        .unwrap();
+    }
+
+    // Both spatial decompositions should produce roughly the same surface
+    assert!(relative_difference(global.mesh().triangles.len(), grid.mesh().triangles.len()) < 2e-2);
+}
+
+#[test]
+fn test_large_per_particle_support_radius() {
+    let particle_radius = 0.025;
+    let particles = particle_block([12, 10, 8], 2.0 * particle_radius);
+
+    // A single particle whose kernel support requires a ghost margin thicker than half of a subdomain
+    let mut compact_support_radii = vec![4.0 * particle_radius; particles.len()];
+    compact_support_radii[0] = 16.0 * particle_radius;
+
+    let properties = ParticleProperties {
+        compact_support_radii: Some(&compact_support_radii),
+        ..Default::default()
+    };
+
+    let global = reconstruct_with_properties(
+        &particles,
+        &properties,
+        &block_parameters(particle_radius, false),
+    );
+    // The subdomains are enlarged instead of failing the reconstruction
+    let grid = reconstruct_with_properties(
+        &particles,
+        &properties,
+        &block_parameters(particle_radius, true),
+    );
+
+    check_mesh_consistency(grid.grid(), grid.mesh(), true, true, false).unwrap();
+    assert!(relative_difference(global.mesh().triangles.len(), grid.mesh().triangles.len()) < 2e-2);
+}
+
+#[test]
+fn test_particle_properties_length_mismatch() {
+    let particle_radius = 0.025;
+    let particles = particle_block([4, 4, 4], 2.0 * particle_radius);
+    let masses = vec![1.0; particles.len() - 1];
+
+    let mut reconstruction = SurfaceReconstruction::<i64, f64>::default();
+    let result = reconstruct_surface_with_properties_inplace(
+        &particles,
+        &ParticleProperties {
+            masses: Some(&masses),
+            ..Default::default()
+        },
+        &block_parameters(particle_radius, false),
+        &mut reconstruction,
+    );
+
+    assert!(matches!(
+        result,
+        Err(ReconstructionError::ParticlePropertiesLengthMismatch {
+            property: "masses",
+            ..
+        })
+    ));
+}
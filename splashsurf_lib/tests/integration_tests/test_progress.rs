@@ -0,0 +1,167 @@
+use super::common::{default_parameters, particle_ball, RecordingObserver};
+use nalgebra::Vector3;
+use splashsurf_lib::{
+    reconstruct_surface, reconstruct_surface_inplace_with_context, CancellationToken,
+    GridDecompositionParameters, OctreeDecompositionParameters, Parameters, ReconstructionContext,
+    ReconstructionError, ReconstructionStage, SpatialDecomposition, SurfaceReconstruction,
+};
+
+fn parameters(spatial_decomposition: Option<SpatialDecomposition>) -> Parameters<f64> {
+    Parameters {
+        spatial_decomposition,
+        ..default_parameters()
+    }
+}
+
+fn subdomain_grid() -> Option<SpatialDecomposition> {
+    Some(SpatialDecomposition::UniformGrid(
+        GridDecompositionParameters {
+            subdomain_num_cubes_per_dim: 16,
+        },
+    ))
+}
+
+#[test]
+fn test_progress_subdomain_grid() {
+    let particles = particle_ball(Vector3::zeros(), 0.5, 0.05);
+
+    for spatial_decomposition in [
+        subdomain_grid(),
+        Some(SpatialDecomposition::Octree(
+            OctreeDecompositionParameters::default(),
+        )),
+    ] {
+        for enable_multi_threading in [true, false] {
+            let mut params = parameters(spatial_decomposition.clone());
+            params.enable_multi_threading = enable_multi_threading;
+
+            let observer = RecordingObserver::default();
+            let mut reconstruction = SurfaceReconstruction::<i64, f64>::default();
+            reconstruct_surface_inplace_with_context(
+                &particles,
+                None,
+                &params,
+                &ReconstructionContext::new().with_observer(&observer),
+                &mut reconstruction,
+            )
+            .unwrap();
+
+            assert_eq!(
+                observer.stages.lock().as_slice(),
+                &[
+                    ReconstructionStage::Decomposition,
+                    ReconstructionStage::ParticleDensities,
+                    ReconstructionStage::SurfaceExtraction,
+                    ReconstructionStage::Stitching,
+                ]
+            );
+
+            // All subdomains of both stages have to be reported as processed
+            let progress = observer.progress.lock();
+            assert_eq!(progress.len(), 2);
+            for (_, processed, total) in progress.iter() {
+                assert!(*total > 0);
+                assert_eq!(processed, total);
+            }
+
+            // The observer must not change the result of the reconstruction
+            let reference = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+            assert_eq!(
+                reconstruction.mesh().triangles.len(),
+                reference.mesh().triangles.len()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_progress_global() {
+    let particles = particle_ball(Vector3::zeros(), 0.3, 0.05);
+
+    let observer = RecordingObserver::default();
+    let mut reconstruction = SurfaceReconstruction::<i64, f64>::default();
+    reconstruct_surface_inplace_with_context(
+        &particles,
+        None,
+        &parameters(None),
+        &ReconstructionContext::new().with_observer(&observer),
+        &mut reconstruction,
+    )
+    .unwrap();
+
+    assert_eq!(
+        observer.stages.lock().as_slice(),
+        &[
+            ReconstructionStage::ParticleDensities,
+            ReconstructionStage::SurfaceExtraction,
+        ]
+    );
+    assert!(observer.progress.lock().is_empty());
+    assert!(!reconstruction.mesh().triangles.is_empty());
+}
+
+#[test]
+fn test_cancellation_before_reconstruction() {
+    let particles = particle_ball(Vector3::zeros(), 0.3, 0.05);
+
+    for spatial_decomposition in [None, subdomain_grid()] {
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let observer = RecordingObserver::default();
+        let mut reconstruction = SurfaceReconstruction::<i64, f64>::default();
+        let result = reconstruct_surface_inplace_with_context(
+            &particles,
+            None,
+            &parameters(spatial_decomposition),
+            &ReconstructionContext::new()
+                .with_observer(&observer)
+                .with_cancellation_token(token.clone()),
+            &mut reconstruction,
+        );
+
+        assert!(matches!(result, Err(ReconstructionError::Cancelled)));
+        assert!(observer.stages.lock().is_empty());
+    }
+}
+
+#[test]
+fn test_cancellation_during_reconstruction() {
+    let particles = particle_ball(Vector3::zeros(), 0.5, 0.05);
+
+    for cancel_stage in [
+        ReconstructionStage::ParticleDensities,
+        ReconstructionStage::SurfaceExtraction,
+    ] {
+        for enable_multi_threading in [true, false] {
+            let mut params = parameters(subdomain_grid());
+            params.enable_multi_threading = enable_multi_threading;
+
+            let token = CancellationToken::new();
+            let observer = RecordingObserver {
+                cancel_in_stage: Some((cancel_stage, token.clone())),
+                ..Default::default()
+            };
+            let mut reconstruction = SurfaceReconstruction::<i64, f64>::default();
+            let result = reconstruct_surface_inplace_with_context(
+                &particles,
+                None,
+                &params,
+                &ReconstructionContext::new()
+                    .with_observer(&observer)
+                    .with_cancellation_token(token),
+                &mut reconstruction,
+            );
+
+            assert!(matches!(result, Err(ReconstructionError::Cancelled)));
+            // No further stage is started after the cancellation
+            assert_eq!(observer.stages.lock().last(), Some(&cancel_stage));
+            // The remaining subdomains of the stage are skipped (in parallel, subdomains that were
+            // already started by other threads are still completed)
+            if !enable_multi_threading {
+                let progress = observer.progress.lock();
+                assert_eq!(progress.last().unwrap().1, 1);
+            }
+        }
+    }
+}
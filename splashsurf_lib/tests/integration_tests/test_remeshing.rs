@@ -0,0 +1,102 @@
+use super::common::{ball_mesh, plane_mesh};
+use splashsurf_lib::mesh::{TriMesh3d, TriMesh3dExt};
+use splashsurf_lib::postprocessing::{
+    isotropic_remeshing, par_isotropic_remeshing, IsotropicRemeshingParameters,
+};
+
+/// Returns the lengths of all edges of the mesh (inner edges are counted twice)
+fn edge_lengths(mesh: &TriMesh3d<f64>) -> Vec<f64> {
+    mesh.triangles
+        .iter()
+        .flat_map(|tri| {
+            (0..3).map(|i| (mesh.vertices[tri[(i + 1) % 3]] - mesh.vertices[tri[i]]).norm())
+        })
+        .collect()
+}
+
+#[test]
+fn test_isotropic_remeshing() {
+    let original = ball_mesh();
+    let original_volume = original.enclosed_volume();
+
+    let target_edge_length = 0.03;
+    let mut mesh = original.clone();
+    let vertex_connectivity = isotropic_remeshing(
+        &mut mesh,
+        &IsotropicRemeshingParameters::new(target_edge_length),
+        false,
+    );
+    assert_eq!(vertex_connectivity.len(), mesh.vertices.len());
+
+    let info = mesh.compute_manifold_information();
+    assert!(info.is_closed());
+    assert!(info.is_manifold());
+
+    // The edge lengths are close to the target edge length
+    let lengths = edge_lengths(&mesh);
+    let mean_length = lengths.iter().sum::<f64>() / lengths.len() as f64;
+    assert!(
+        (mean_length - target_edge_length).abs() < 0.15 * target_edge_length,
+        "mean edge length: {mean_length}"
+    );
+    assert!(lengths
+        .iter()
+        .all(|&l| l <= 4.0 / 3.0 * target_edge_length + 1e-12));
+
+    // All triangles are well shaped in contrast to the marching cubes triangles
+    let max_aspect_ratio = |mesh: &TriMesh3d<f64>| {
+        mesh.triangles
+            .iter()
+            .map(|tri| mesh.tri_aspect_ratio::<f64>(tri))
+            .fold(0.0, f64::max)
+    };
+    assert!(max_aspect_ratio(&original) > 10.0);
+    assert!(max_aspect_ratio(&mesh) < 2.0);
+
+    // The shape of the ball is preserved
+    let relative_volume_change = (mesh.enclosed_volume() - original_volume).abs() / original_volume;
+    assert!(
+        relative_volume_change < 0.01,
+        "volume changed by {:.2}%",
+        relative_volume_change * 100.0
+    );
+
+    // The parallel relaxation gives the same result
+    let mut par_mesh = original.clone();
+    par_isotropic_remeshing(
+        &mut par_mesh,
+        &IsotropicRemeshingParameters::new(target_edge_length),
+        false,
+    );
+    assert_eq!(par_mesh.vertices, mesh.vertices);
+    assert_eq!(par_mesh.triangles, mesh.triangles);
+}
+
+#[test]
+fn test_isotropic_remeshing_boundary() {
+    let original = plane_mesh(8);
+
+    let mut mesh = original.clone();
+    isotropic_remeshing(&mut mesh, &IsotropicRemeshingParameters::new(0.05), false);
+
+    // The mesh stays in the plane and covers the same area
+    assert!(mesh.triangles.len() > original.triangles.len());
+    assert!(mesh.vertices.iter().all(|v| v.z.abs() < 1e-12));
+    assert!((mesh.area() - 1.0).abs() < 1e-12);
+
+    // The boundary vertices are kept
+    for v in original
+        .vertices
+        .iter()
+        .filter(|v| v.x == 0.0 || v.x == 1.0 || v.y == 0.0 || v.y == 1.0)
+    {
+        assert!(mesh.vertices.contains(v));
+    }
+}
+
+#[test]
+#[should_panic(expected = "positive and finite")]
+fn test_isotropic_remeshing_invalid_edge_length() {
+    let mut mesh = plane_mesh(2);
+    isotropic_remeshing(&mut mesh, &IsotropicRemeshingParameters::new(0.0), false);
+}
@@ -0,0 +1,197 @@
+use super::common::{default_parameters, particle_ball};
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::mesh::Mesh3d;
+use splashsurf_lib::postprocessing::{
+    hc_smoothing_inplace, laplacian_smoothing_inplace, laplacian_smoothing_normals_inplace,
+    par_hc_smoothing_inplace, par_laplacian_smoothing_inplace,
+    par_laplacian_smoothing_normals_inplace, par_taubin_smoothing_inplace,
+    taubin_smoothing_inplace,
+};
+use splashsurf_lib::sph_interpolation::SphInterpolator;
+use splashsurf_lib::{
+    reconstruct_surface, GridDecompositionParameters, OctreeDecompositionParameters, Parameters,
+    SpatialDecomposition, SurfaceExtractionMethod,
+};
+
+fn particles() -> Vec<Vector3<f64>> {
+    let spacing = 0.05;
+    let mut particles = particle_ball(Vector3::zeros(), 0.5, spacing);
+    particles.extend(particle_ball(Vector3::new(0.8, 0.0, 0.0), 0.15, spacing));
+    particles
+}
+
+fn parameters(
+    extraction_method: SurfaceExtractionMethod,
+    spatial_decomposition: Option<SpatialDecomposition>,
+    enable_multi_threading: bool,
+) -> Parameters<f64> {
+    Parameters {
+        extraction_method,
+        enable_multi_threading,
+        spatial_decomposition,
+        ..default_parameters()
+    }
+}
+
+#[test]
+fn test_sequential_reconstruction() {
+    let particles = particles();
+
+    let spatial_decompositions = [
+        None,
+        Some(SpatialDecomposition::UniformGrid(
+            GridDecompositionParameters {
+                subdomain_num_cubes_per_dim: 16,
+            },
+        )),
+        Some(SpatialDecomposition::Octree(
+            OctreeDecompositionParameters {
+                subdomain_num_cubes_per_dim: 16,
+                max_particles_per_subdomain: 5000,
+                max_depth: 2,
+            },
+        )),
+    ];
+
+    for extraction_method in [
+        SurfaceExtractionMethod::MarchingCubes,
+        SurfaceExtractionMethod::SurfaceNets,
+    ] {
+        for spatial_decomposition in spatial_decompositions.iter().cloned() {
+            let parallel = reconstruct_surface::<i64, _>(
+                &particles,
+                &parameters(extraction_method, spatial_decomposition.clone(), true),
+            )
+            .unwrap();
+            let sequential = reconstruct_surface::<i64, _>(
+                &particles,
+                &parameters(extraction_method, spatial_decomposition.clone(), false),
+            )
+            .unwrap();
+
+            check_mesh_consistency(sequential.grid(), sequential.mesh(), true, true, false)
+                .unwrap();
+
+            if spatial_decomposition.is_some() {
+                // The subdomains are processed and stitched in a deterministic order, therefore
+                // the sequential code path has to produce exactly the same mesh
+                assert_eq!(sequential.mesh().vertices, parallel.mesh().vertices);
+                assert_eq!(sequential.mesh().triangles, parallel.mesh().triangles);
+            } else {
+                assert_eq!(
+                    sequential.mesh().vertices.len(),
+                    parallel.mesh().vertices.len()
+                );
+                assert_eq!(
+                    sequential.mesh().triangles.len(),
+                    parallel.mesh().triangles.len()
+                );
+            }
+            assert_eq!(
+                sequential.particle_densities().unwrap(),
+                parallel.particle_densities().unwrap()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_sequential_postprocessing() {
+    let particles = particles();
+    let params = parameters(SurfaceExtractionMethod::MarchingCubes, None, true);
+    let reconstruction = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+    let mesh = reconstruction.mesh();
+    let vertex_connectivity = mesh.vertex_vertex_connectivity();
+
+    // Mesh smoothing
+    let weights = vec![0.5; mesh.vertices.len()];
+    let mut parallel_mesh = mesh.clone();
+    par_laplacian_smoothing_inplace(&mut parallel_mesh, &vertex_connectivity, 5, 1.0, &weights);
+    let mut sequential_mesh = mesh.clone();
+    laplacian_smoothing_inplace(&mut sequential_mesh, &vertex_connectivity, 5, 1.0, &weights);
+    assert_eq!(sequential_mesh.vertices, parallel_mesh.vertices);
+
+    // Volume-preserving mesh smoothing
+    let mut parallel_mesh = mesh.clone();
+    par_taubin_smoothing_inplace(
+        &mut parallel_mesh,
+        &vertex_connectivity,
+        5,
+        0.5,
+        -0.53,
+        &weights,
+    );
+    let mut sequential_mesh = mesh.clone();
+    taubin_smoothing_inplace(
+        &mut sequential_mesh,
+        &vertex_connectivity,
+        5,
+        0.5,
+        -0.53,
+        &weights,
+    );
+    assert_eq!(sequential_mesh.vertices, parallel_mesh.vertices);
+
+    let mut parallel_mesh = mesh.clone();
+    par_hc_smoothing_inplace(
+        &mut parallel_mesh,
+        &vertex_connectivity,
+        5,
+        0.1,
+        0.6,
+        &weights,
+    );
+    let mut sequential_mesh = mesh.clone();
+    hc_smoothing_inplace(
+        &mut sequential_mesh,
+        &vertex_connectivity,
+        5,
+        0.1,
+        0.6,
+        &weights,
+    );
+    assert_eq!(sequential_mesh.vertices, parallel_mesh.vertices);
+
+    // Normal smoothing
+    let normals = mesh
+        .vertex_normals()
+        .into_iter()
+        .map(|n| n.into_inner())
+        .collect::<Vec<_>>();
+    let mut parallel_normals = normals.clone();
+    par_laplacian_smoothing_normals_inplace(&mut parallel_normals, &vertex_connectivity, 5);
+    let mut sequential_normals = normals;
+    laplacian_smoothing_normals_inplace(&mut sequential_normals, &vertex_connectivity, 5);
+    assert_eq!(sequential_normals, parallel_normals);
+
+    // SPH interpolation
+    let particle_rest_mass = params.rest_density * (2.0 * params.particle_radius).powi(3);
+    let interpolator = SphInterpolator::new(
+        &particles,
+        reconstruction.particle_densities().unwrap(),
+        particle_rest_mass,
+        params.compact_support_radius,
+    );
+    assert!(interpolator.multi_threading());
+    let parallel_normals = interpolator.interpolate_normals(&mesh.vertices);
+    let densities = interpolator.interpolate_scalar_quantity(
+        reconstruction.particle_densities().unwrap(),
+        &mesh.vertices,
+        true,
+    );
+
+    let interpolator = interpolator.with_multi_threading(false);
+    assert_eq!(
+        interpolator.interpolate_normals(&mesh.vertices),
+        parallel_normals
+    );
+    assert_eq!(
+        interpolator.interpolate_scalar_quantity(
+            reconstruction.particle_densities().unwrap(),
+            &mesh.vertices,
+            true,
+        ),
+        densities
+    );
+}
@@ -0,0 +1,83 @@
+use super::common::sphere_mesh;
+use nalgebra::Vector3;
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::signed_distance::{par_signed_distance_grid, signed_distance_grid};
+use splashsurf_lib::{Aabb3d, UniformGrid};
+
+fn test_grid() -> UniformGrid<i64, f64> {
+    let aabb = Aabb3d::new(Vector3::repeat(-2.0), Vector3::repeat(2.0));
+    UniformGrid::from_aabb(&aabb, 0.2).unwrap()
+}
+
+#[test]
+fn test_signed_distance_grid_full() {
+    let radius = 1.0;
+    let mesh = sphere_mesh(radius, 4);
+    let grid = test_grid();
+    let sdf = par_signed_distance_grid(&mesh, &grid, None);
+
+    assert_eq!(sdf.narrow_band_width(), None);
+    assert_eq!(
+        sdf.values().len(),
+        grid.points_per_dim().iter().product::<i64>() as usize
+    );
+
+    // The distance to the inscribed mesh deviates from the sphere by at most the chord height
+    for i in 0..grid.points_per_dim()[0] {
+        for j in 0..grid.points_per_dim()[1] {
+            for k in 0..grid.points_per_dim()[2] {
+                let x = grid.point_coordinates_indices(i, j, k);
+                let value = sdf.get(&[i, j, k]).unwrap();
+                assert!(
+                    (value - (x.norm() - radius)).abs() < 0.01,
+                    "signed distance {} at {:?}",
+                    value,
+                    x
+                );
+            }
+        }
+    }
+    assert!(sdf.get(&[100, 0, 0]).is_none());
+
+    let sequential_sdf = signed_distance_grid(&mesh, &grid, None);
+    assert_eq!(sdf.values(), sequential_sdf.values());
+}
+
+#[test]
+fn test_signed_distance_grid_narrow_band() {
+    let radius = 1.0;
+    let grid = test_grid();
+
+    // Spherical shell with a hollow core, the core is an outside region enclosed by the band
+    let mut shell = sphere_mesh(radius, 4);
+    let mut core = sphere_mesh(0.4, 3);
+    core.triangles.iter_mut().for_each(|tri| tri.swap(1, 2));
+    shell.append(&mut core);
+
+    for (mesh, center_inside) in [(sphere_mesh(radius, 4), true), (shell, false)] {
+        let full_sdf = par_signed_distance_grid(&mesh, &grid, None);
+        assert_eq!(full_sdf.get(&[10, 10, 10]).unwrap() < 0.0, center_inside);
+
+        // The signs are propagated for widths larger than half the cell size and queried for every point otherwise
+        for width in [0.3, 0.05] {
+            let sdf = par_signed_distance_grid(&mesh, &grid, Some(width));
+            assert_eq!(sdf.narrow_band_width(), Some(width));
+
+            // Values inside of the band are exact, values outside are clamped to the band width
+            for (value, full_value) in sdf.values().iter().zip(full_sdf.values()) {
+                if full_value.abs() < width {
+                    assert_eq!(value, full_value);
+                } else {
+                    assert_eq!(*value, width.copysign(*full_value));
+                }
+            }
+
+            let sequential_sdf = signed_distance_grid(&mesh, &grid, Some(width));
+            assert_eq!(sdf.values(), sequential_sdf.values());
+        }
+    }
+
+    // Without any triangles, all points are outside
+    let empty_sdf = par_signed_distance_grid(&TriMesh3d::default(), &grid, Some(0.3));
+    assert!(empty_sdf.into_values().iter().all(|v| *v == 0.3));
+}
@@ -0,0 +1,96 @@
+use super::common::ball_mesh;
+use nalgebra::Vector3;
+use splashsurf_lib::mesh::{Mesh3d, TriMesh3d};
+use splashsurf_lib::postprocessing::{
+    par_hc_smoothing_inplace, par_laplacian_smoothing_inplace, par_taubin_smoothing_inplace,
+};
+
+/// Mean distance of the vertices to the mean position of their neighbors
+fn roughness(mesh: &TriMesh3d<f64>, vertex_connectivity: &[Vec<usize>]) -> f64 {
+    mesh.vertices
+        .iter()
+        .zip(vertex_connectivity)
+        .map(|(v, neighbors)| {
+            let mean = neighbors
+                .iter()
+                .map(|&j| mesh.vertices[j])
+                .sum::<Vector3<f64>>()
+                / neighbors.len() as f64;
+            (mean - v).norm()
+        })
+        .sum::<f64>()
+        / mesh.vertices.len() as f64
+}
+
+#[test]
+fn test_volume_preserving_smoothing() {
+    let original = ball_mesh();
+    let original_volume = original.enclosed_volume();
+    let vertex_connectivity = original.vertex_vertex_connectivity();
+    let weights = vec![1.0; original.vertices.len()];
+    let iterations = 50;
+
+    let mut laplacian = original.clone();
+    par_laplacian_smoothing_inplace(
+        &mut laplacian,
+        &vertex_connectivity,
+        iterations,
+        1.0,
+        &weights,
+    );
+
+    let mut taubin = original.clone();
+    par_taubin_smoothing_inplace(
+        &mut taubin,
+        &vertex_connectivity,
+        iterations,
+        0.5,
+        -0.53,
+        &weights,
+    );
+
+    let mut hc = original.clone();
+    par_hc_smoothing_inplace(
+        &mut hc,
+        &vertex_connectivity,
+        iterations,
+        0.1,
+        0.6,
+        &weights,
+    );
+
+    let relative_volume_change =
+        |mesh: &TriMesh3d<f64>| (mesh.enclosed_volume() - original_volume).abs() / original_volume;
+
+    let laplacian_change = relative_volume_change(&laplacian);
+    let taubin_change = relative_volume_change(&taubin);
+    let hc_change = relative_volume_change(&hc);
+
+    assert!(
+        taubin_change < 0.25 * laplacian_change,
+        "taubin: {taubin_change}, laplacian: {laplacian_change}"
+    );
+    assert!(
+        hc_change < 0.25 * laplacian_change,
+        "hc: {hc_change}, laplacian: {laplacian_change}"
+    );
+
+    // Both variants still smooth the staircase artifacts of the marching cubes mesh
+    let original_roughness = roughness(&original, &vertex_connectivity);
+    assert!(roughness(&taubin, &vertex_connectivity) < 0.5 * original_roughness);
+    assert!(roughness(&hc, &vertex_connectivity) < 0.5 * original_roughness);
+
+    // Zero weights keep all vertices in place
+    let zero_weights = vec![0.0; original.vertices.len()];
+    let mut fixed = original.clone();
+    par_taubin_smoothing_inplace(
+        &mut fixed,
+        &vertex_connectivity,
+        5,
+        0.5,
+        -0.53,
+        &zero_weights,
+    );
+    par_hc_smoothing_inplace(&mut fixed, &vertex_connectivity, 5, 0.1, 0.6, &zero_weights);
+    assert_eq!(fixed.vertices, original.vertices);
+}
@@ -0,0 +1,145 @@
+use super::common::{default_parameters, particle_ball};
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::streaming::{
+    reconstruct_surface_streaming, SliceParticleSource, StreamingParameters,
+};
+use splashsurf_lib::{
+    reconstruct_surface, GridDecompositionParameters, OctreeDecompositionParameters, Parameters,
+    SpatialDecomposition, SurfaceExtractionMethod,
+};
+
+fn particles() -> Vec<Vector3<f64>> {
+    let spacing = 0.05;
+    let mut particles = particle_ball(Vector3::zeros(), 0.5, spacing);
+    particles.extend(particle_ball(Vector3::new(0.8, 0.1, 0.0), 0.15, spacing));
+    particles.extend(particle_ball(Vector3::new(-0.2, -0.7, 0.3), 0.1, spacing));
+    particles
+}
+
+fn parameters(extraction_method: SurfaceExtractionMethod) -> Parameters<f64> {
+    Parameters {
+        extraction_method,
+        spatial_decomposition: Some(SpatialDecomposition::UniformGrid(
+            GridDecompositionParameters {
+                subdomain_num_cubes_per_dim: 16,
+            },
+        )),
+        ..default_parameters()
+    }
+}
+
+#[test]
+fn test_streaming_reconstruction() {
+    let particles = particles();
+
+    for extraction_method in [
+        SurfaceExtractionMethod::MarchingCubes,
+        SurfaceExtractionMethod::SurfaceNets,
+    ] {
+        let params = parameters(extraction_method);
+        let reference = reconstruct_surface::<i64, _>(&particles, &params).unwrap();
+
+        for enable_multi_threading in [true, false] {
+            let mut params = params.clone();
+            params.enable_multi_threading = enable_multi_threading;
+
+            let mut source = SliceParticleSource::new(&particles, 1000);
+            let mut mesh = TriMesh3d::default();
+            let streaming = reconstruct_surface_streaming::<i64, _, _, _>(
+                &mut source,
+                &params,
+                // Budget for a few thousand particles per batch
+                &StreamingParameters {
+                    memory_budget: 500_000,
+                },
+                &mut mesh,
+            )
+            .unwrap();
+
+            assert!(streaming.num_batches() > 1);
+            assert_eq!(streaming.num_vertices(), mesh.vertices.len());
+            assert_eq!(streaming.num_triangles(), mesh.triangles.len());
+            assert_eq!(streaming.grid(), reference.grid());
+
+            // The patches of all batches have to be stitched seamlessly
+            check_mesh_consistency(streaming.grid(), &mesh, true, true, false).unwrap();
+
+            // Only the order of the vertices and triangles differs
+            assert_eq!(mesh.vertices.len(), reference.mesh().vertices.len());
+            assert_eq!(mesh.triangles.len(), reference.mesh().triangles.len());
+            let (reference_volume, volume) =
+                (reference.mesh().enclosed_volume(), mesh.enclosed_volume());
+            assert!(
+                (reference_volume - volume).abs() / reference_volume < 1e-10,
+                "volume of streamed mesh is {}, reference volume is {}",
+                volume,
+                reference_volume
+            );
+        }
+    }
+}
+
+#[test]
+fn test_streaming_requires_uniform_grid() {
+    let particles = particles();
+
+    for spatial_decomposition in [
+        None,
+        Some(SpatialDecomposition::Octree(
+            OctreeDecompositionParameters::default(),
+        )),
+    ] {
+        let mut params = parameters(SurfaceExtractionMethod::MarchingCubes);
+        params.spatial_decomposition = spatial_decomposition;
+
+        let mut source = SliceParticleSource::new(&particles, 1000);
+        let mut mesh = TriMesh3d::default();
+        assert!(reconstruct_surface_streaming::<i64, _, _, _>(
+            &mut source,
+            &params,
+            &StreamingParameters::default(),
+            &mut mesh,
+        )
+        .is_err());
+    }
+}
+
+#[cfg(feature = "io")]
+#[test]
+fn test_streaming_obj_sink() {
+    use splashsurf_lib::io::obj_format::{surface_mesh_from_obj, ObjMeshSink};
+
+    let particles = particles();
+    let params = parameters(SurfaceExtractionMethod::MarchingCubes);
+    let streaming_parameters = StreamingParameters {
+        memory_budget: 500_000,
+    };
+
+    let mut mesh = TriMesh3d::default();
+    reconstruct_surface_streaming::<i64, _, _, _>(
+        &mut SliceParticleSource::new(&particles, 1000),
+        &params,
+        &streaming_parameters,
+        &mut mesh,
+    )
+    .unwrap();
+
+    let output_file = "../out/streaming_balls.obj";
+    let mut obj_sink = ObjMeshSink::create(output_file).unwrap();
+    reconstruct_surface_streaming::<i64, _, _, _>(
+        &mut SliceParticleSource::new(&particles, 1000),
+        &params,
+        &streaming_parameters,
+        &mut obj_sink,
+    )
+    .unwrap();
+    obj_sink.finish().unwrap();
+
+    let obj_mesh = surface_mesh_from_obj::<f64, _>(output_file).unwrap().mesh;
+    assert_eq!(obj_mesh.vertices.len(), mesh.vertices.len());
+    assert_eq!(obj_mesh.triangles.len(), mesh.triangles.len());
+    let (obj_volume, volume) = (obj_mesh.enclosed_volume(), mesh.enclosed_volume());
+    assert!((obj_volume - volume).abs() / volume < 1e-6);
+}
@@ -0,0 +1,110 @@
+use super::common::plane_mesh;
+use nalgebra::Vector3;
+use splashsurf_lib::mesh::{AttributeData, MeshAttribute, MeshWithData, TriMesh3d};
+use splashsurf_lib::postprocessing::{loop_subdivision, loop_subdivision_with_data};
+
+/// Closed octahedron with outward facing triangles
+fn octahedron() -> TriMesh3d<f64> {
+    TriMesh3d {
+        vertices: vec![
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        ],
+        triangles: vec![
+            [0, 2, 4],
+            [2, 1, 4],
+            [1, 3, 4],
+            [3, 0, 4],
+            [2, 0, 5],
+            [1, 2, 5],
+            [3, 1, 5],
+            [0, 3, 5],
+        ],
+    }
+}
+
+#[test]
+fn test_loop_subdivision_closed_mesh() {
+    let original = octahedron();
+    let mesh = loop_subdivision(&original, 3, false);
+
+    // Every step splits each triangle into four and inserts one vertex per edge
+    assert_eq!(mesh.triangles.len(), 8 * 4 * 4 * 4);
+    assert_eq!(mesh.vertices.len(), mesh.triangles.len() / 2 + 2);
+
+    let info = mesh.compute_manifold_information();
+    assert!(info.is_closed());
+    assert!(info.is_manifold());
+
+    // The subdivision surface is smooth and lies inside of the control mesh
+    assert!(mesh.enclosed_volume() > 0.0);
+    assert!(mesh.enclosed_volume() < original.enclosed_volume());
+    for v in &mesh.vertices {
+        assert!(v.norm() < 1.0);
+        assert!(v.norm() > 0.4);
+    }
+
+    // The limit positions of the original vertices do not depend on the number of steps
+    let limit_1 = loop_subdivision(&original, 1, true);
+    let limit_2 = loop_subdivision(&original, 2, true);
+    for v in 0..original.vertices.len() {
+        assert!((limit_1.vertices[v] - limit_2.vertices[v]).norm() < 1e-12);
+        assert!((limit_1.vertices[v] - mesh.vertices[v]).norm() < 0.05);
+    }
+}
+
+#[test]
+fn test_loop_subdivision_with_data() {
+    let original = plane_mesh(4);
+    let num_triangles = original.triangles.len();
+    let mesh_with_data = MeshWithData::new(original.clone())
+        .with_point_data(MeshAttribute::new_real_vector3(
+            "position",
+            original.vertices.clone(),
+        ))
+        .with_cell_data(MeshAttribute::new(
+            "triangle",
+            (0..num_triangles as u64).collect::<Vec<_>>(),
+        ));
+
+    let subdivided = loop_subdivision_with_data(&mesh_with_data, 2, false);
+    let mesh = &subdivided.mesh;
+    assert_eq!(mesh.triangles.len(), num_triangles * 16);
+    assert_eq!(
+        loop_subdivision(&original, 2, false).vertices,
+        mesh.vertices
+    );
+    assert!(mesh.vertices.iter().all(|v| v.z == 0.0));
+    assert!(mesh.compute_manifold_information().is_manifold());
+
+    // In a regular planar triangulation, the interior vertices are not moved and new vertices are
+    // inserted at the edge midpoints, i.e. they coincide with the linearly subdivided positions
+    match &subdivided.point_attributes[0].data {
+        AttributeData::Vector3Real(positions) => {
+            assert_eq!(positions.len(), mesh.vertices.len());
+            assert_eq!(&positions[..original.vertices.len()], &original.vertices);
+            for (position, vertex) in positions.iter().zip(mesh.vertices.iter()) {
+                // Skip vertices close to the corners of the plane which are rounded off by the subdivision
+                if vertex.iter().take(2).all(|x| *x > 0.2 && *x < 0.8) {
+                    assert!((position - vertex).norm() < 1e-12);
+                }
+            }
+        }
+        _ => panic!("unexpected attribute type"),
+    }
+
+    // Cell attributes are copied to the children of each triangle
+    match &subdivided.cell_attributes[0].data {
+        AttributeData::ScalarU64(triangles) => {
+            assert_eq!(triangles.len(), mesh.triangles.len());
+            for (i, parent) in triangles.iter().enumerate() {
+                assert_eq!(*parent, (i / 16) as u64);
+            }
+        }
+        _ => panic!("unexpected attribute type"),
+    }
+}
@@ -0,0 +1,94 @@
+use super::common::{default_parameters, particle_ball};
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::mesh::{tri_min_max_angles, TriMesh3d};
+use splashsurf_lib::{
+    reconstruct_surface, GridDecompositionParameters, Parameters, SpatialDecomposition,
+    SurfaceExtractionMethod,
+};
+
+fn parameters(extraction_method: SurfaceExtractionMethod, subdomain_grid: bool) -> Parameters<f64> {
+    Parameters {
+        extraction_method,
+        spatial_decomposition: subdomain_grid.then(|| {
+            SpatialDecomposition::UniformGrid(GridDecompositionParameters {
+                subdomain_num_cubes_per_dim: 32,
+            })
+        }),
+        ..default_parameters()
+    }
+}
+
+/// Returns the fraction of triangles with a minimum angle below the given limit
+fn sliver_fraction(mesh: &TriMesh3d<f64>, min_angle_deg: f64) -> f64 {
+    let slivers = mesh
+        .triangles
+        .iter()
+        .filter(|tri| {
+            let [a, b, c] = tri.map(|v| mesh.vertices[v]);
+            let (min_angle, _): (f64, f64) = tri_min_max_angles(&a, &b, &c);
+            min_angle < min_angle_deg.to_radians()
+        })
+        .count();
+    slivers as f64 / mesh.triangles.len() as f64
+}
+
+#[test]
+fn test_surface_nets_reconstruction() {
+    let particles = particle_ball(Vector3::zeros(), 0.5, 0.05);
+
+    let marching_cubes = reconstruct_surface::<i64, _>(
+        &particles,
+        &parameters(SurfaceExtractionMethod::MarchingCubes, false),
+    )
+    .unwrap();
+    assert!(marching_cubes.quad_mesh().is_none());
+    let marching_cubes_volume = marching_cubes.mesh().enclosed_volume();
+
+    let global = reconstruct_surface::<i64, _>(
+        &particles,
+        &parameters(SurfaceExtractionMethod::SurfaceNets, false),
+    )
+    .unwrap();
+    let grid = reconstruct_surface::<i64, _>(
+        &particles,
+        &parameters(SurfaceExtractionMethod::SurfaceNets, true),
+    )
+    .unwrap();
+
+    for reconstruction in [&global, &grid] {
+        let mesh = reconstruction.mesh();
+        assert!(!mesh.triangles.is_empty());
+        check_mesh_consistency(reconstruction.grid(), mesh, true, true, false).unwrap();
+
+        // Every quad is split into two triangles sharing the vertices of the triangle mesh
+        let quad_mesh = reconstruction.quad_mesh().unwrap();
+        assert_eq!(quad_mesh.vertices.len(), mesh.vertices.len());
+        assert_eq!(2 * quad_mesh.cells.len(), mesh.triangles.len());
+        assert!(mesh
+            .triangles
+            .chunks_exact(2)
+            .all(|pair| pair[0][0] == pair[1][0] && pair[0][2] == pair[1][1]));
+
+        // The triangles are oriented to the outside and enclose roughly the same volume as with marching cubes
+        let volume = mesh.enclosed_volume();
+        assert!(
+            (volume - marching_cubes_volume).abs() / marching_cubes_volume < 2e-2,
+            "volume of surface nets mesh is {}, marching cubes volume is {}",
+            volume,
+            marching_cubes_volume
+        );
+
+        // Surface nets should produce far less badly shaped triangles
+        assert!(sliver_fraction(mesh, 10.0) < 0.5 * sliver_fraction(marching_cubes.mesh(), 10.0));
+    }
+
+    // The subdomain decomposition should result in the same surface
+    assert_eq!(global.mesh().vertices.len(), grid.mesh().vertices.len());
+    assert_eq!(global.mesh().triangles.len(), grid.mesh().triangles.len());
+    let (global_volume, grid_volume) = (
+        global.mesh().enclosed_volume(),
+        grid.mesh().enclosed_volume(),
+    );
+    assert!((global_volume - grid_volume).abs() / global_volume < 1e-3);
+}